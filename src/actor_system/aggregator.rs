@@ -0,0 +1,187 @@
+// src/actor_system/aggregator.rs
+
+//! # Aggregator Actor
+//!
+//! `AggregatorActor` collects the messages it receives over a window and folds each
+//! closed window into a single result that is sent to a downstream actor.
+//!
+//! A window closes either after a fixed number of messages (`Window::Count`) or a
+//! fixed time after its first message (`Window::Duration`). Empty windows are never
+//! emitted, and a partial window still buffered when the actor shuts down is flushed.
+//!
+//! The timer closing a time-based window is a task of the target's `ActorSystem`:
+//! it stops when the system's cancellation token is cancelled, and
+//! `wait_until_stopped` waits for it.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use astra::actor_system::{ActorSystem, AggregatorActor, Window};
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), String> {
+//! let mut system: ActorSystem<u64> = ActorSystem::new();
+//! # struct Sink;
+//! # #[async_trait::async_trait]
+//! # impl astra::actor_system::Actor for Sink {
+//! #     type Message = u64;
+//! #     type Error = String;
+//! #     async fn receive(&mut self, _: astra::actor_system::Message<u64>) -> Result<(), String> { Ok(()) }
+//! # }
+//! system.add_actor("sums".to_string(), Sink);
+//!
+//! // Emit the sum of every 10 numbers to the "sums" actor
+//! let target = system.actor_ref("sums").unwrap();
+//! let summer = AggregatorActor::new(target, Window::Count(10), |batch: Vec<u64>| batch.iter().sum());
+//! system.add_actor("summer".to_string(), summer);
+//!
+//! for n in 1..=10 {
+//!     system.send_message("summer", n).await?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{Actor, ActorRef, Message};
+use crate::clock::{Clock, TokioClock};
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// When an `AggregatorActor` closes its current window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Window {
+    /// After this many messages.
+    Count(usize),
+    /// This long after the first message of the window arrived.
+    Duration(Duration),
+}
+
+type FoldFn<M, R> = Arc<dyn Fn(Vec<M>) -> R + Send + Sync>;
+
+pub struct AggregatorActor<M, R> {
+    target: ActorRef<R>,
+    window: Window,
+    fold: FoldFn<M, R>,
+    buffer: Arc<Mutex<Vec<M>>>,
+    timer: Option<JoinHandle<()>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl<M, R> AggregatorActor<M, R>
+where
+    M: Send + 'static,
+    R: Send + std::fmt::Debug + 'static,
+{
+    /// Creates an aggregator folding each window with `fold` and sending the
+    /// result to `target`.
+    pub fn new<F>(target: ActorRef<R>, window: Window, fold: F) -> Self
+    where
+        F: Fn(Vec<M>) -> R + Send + Sync + 'static,
+    {
+        AggregatorActor {
+            target,
+            window,
+            fold: Arc::new(fold),
+            buffer: Arc::new(Mutex::new(Vec::new())),
+            timer: None,
+            clock: Arc::new(TokioClock),
+        }
+    }
+
+    /// Sets the clock used to close time-based windows.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    // Close the current window, emitting its result unless it is empty
+    async fn flush(&mut self) -> Result<(), String> {
+        if let Some(timer) = self.timer.take() {
+            timer.abort();
+        }
+        emit(&self.buffer, &self.fold, &self.target).await
+    }
+
+    // Start the timer closing a time-based window, unless one is already running
+    fn start_timer(&mut self, duration: Duration) {
+        if self
+            .timer
+            .as_ref()
+            .is_some_and(|timer| !timer.is_finished())
+        {
+            return;
+        }
+        let buffer = Arc::clone(&self.buffer);
+        let fold = Arc::clone(&self.fold);
+        let target = self.target.clone();
+        let clock = Arc::clone(&self.clock);
+        let shared = &self.target.shared;
+        let cancel = shared.cancel.clone();
+        self.timer = Some(shared.spawn(async move {
+            tokio::select! {
+                _ = clock.sleep(duration) => {}
+                _ = cancel.cancelled() => return,
+            }
+            if let Err(e) = emit(&buffer, &fold, &target).await {
+                println!("Failed to emit aggregated window: {}", e);
+            }
+        }));
+    }
+}
+
+// Take whatever is buffered and send its folded result downstream
+async fn emit<M, R>(
+    buffer: &Mutex<Vec<M>>,
+    fold: &FoldFn<M, R>,
+    target: &ActorRef<R>,
+) -> Result<(), String>
+where
+    R: Send + std::fmt::Debug,
+{
+    let batch = std::mem::take(&mut *buffer.lock().unwrap());
+    if batch.is_empty() {
+        return Ok(());
+    }
+    target.send(fold(batch)).await
+}
+
+#[async_trait]
+impl<M, R> Actor for AggregatorActor<M, R>
+where
+    M: Send + std::fmt::Debug + 'static,
+    R: Send + std::fmt::Debug + 'static,
+{
+    type Message = M;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error> {
+        match message {
+            Message::Regular(msg) => {
+                let buffered = {
+                    let mut buffer = self.buffer.lock().unwrap();
+                    buffer.push(msg);
+                    buffer.len()
+                };
+                match self.window {
+                    Window::Count(count) if buffered >= count => self.flush().await,
+                    Window::Count(_) => Ok(()),
+                    Window::Duration(duration) => {
+                        self.start_timer(duration);
+                        Ok(())
+                    }
+                }
+            }
+            // Don't lose a partially filled window on shutdown
+            Message::Shutdown => self.flush().await,
+            Message::System(_) => Ok(()),
+        }
+    }
+
+    async fn cleanup(&mut self) {
+        if let Err(e) = self.flush().await {
+            println!("Failed to flush aggregated window: {}", e);
+        }
+    }
+}
@@ -0,0 +1,341 @@
+// src/actor_system/autoscale.rs
+
+//! # Autoscaling
+//!
+//! An `Autoscaler` grows and shrinks a pool of identical workers with the load.
+//! The workers are actors of one `ActorSystem`, built by a factory. Messages
+//! go to the pool through a `PoolHandle` (see `handle`), which picks a worker
+//! with a `ConsistentHashRouter`, so they are spread over whichever workers
+//! are in the pool at the time. The workers are added to the autoscaler's own
+//! clone of the system, which other clones don't see, so send through the
+//! handle rather than `ActorSystem::dispatch`.
+//!
+//! Each `sample` reads the mailbox depth of every worker and compares the
+//! average with the thresholds of the `ScalingPolicy`:
+//!
+//! - above `scale_up_depth` for `sustain` samples in a row, a worker is added,
+//!   unless the pool is at `max_workers`;
+//! - at or below `scale_down_depth` for `sustain` samples in a row, the worker
+//!   with the shortest queue leaves the pool, unless it is at `min_workers`. It
+//!   is taken off the router first, then stopped with `stop_actor`, so it
+//!   handles what it has queued (under the default `StopMode::Drain`) before it
+//!   goes.
+//!
+//! After either action the autoscaler waits out the `cooldown` before scaling
+//! again, giving the pool time to settle. `run` samples on a fixed period until
+//! the system's cancellation token is cancelled. A message routed to a worker
+//! just as it leaves the pool fails with `SendError::Closing`, as any message
+//! to a stopping actor does.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use astra::actor_system::{
+//!     Actor, ActorSystem, Autoscaler, ConsistentHashRouter, Message, ScalingPolicy,
+//! };
+//! use async_trait::async_trait;
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//!
+//! struct Resizer;
+//!
+//! #[async_trait]
+//! impl Actor for Resizer {
+//!     type Message = u32;
+//!     type Error = String;
+//!
+//!     async fn receive(&mut self, _message: Message<u32>) -> Result<(), String> {
+//!         Ok(())
+//!     }
+//! }
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), String> {
+//!     let system = ActorSystem::new();
+//!     let router = Arc::new(ConsistentHashRouter::new(|image: &u32| *image));
+//!     let policy = ScalingPolicy::new(2, 8)
+//!         .with_thresholds(100, 10)
+//!         .with_cooldown(Duration::from_secs(30));
+//!     let autoscaler = Autoscaler::new(system.clone(), router, "resizer", || Resizer, policy);
+//!     assert_eq!(autoscaler.workers().len(), 2);
+//!
+//!     let pool = autoscaler.handle();
+//!     let sampling = tokio::spawn(autoscaler.run(Duration::from_secs(1)));
+//!     pool.send(7).await?;
+//!     system.shutdown_timeout(Duration::from_secs(5)).await?;
+//!     sampling.await.unwrap();
+//!     Ok(())
+//! }
+//! ```
+
+use super::{Actor, ActorRef, ActorSystem, ConsistentHashRouter, Router};
+use crate::clock::{self, Clock, TokioClock};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+/// When an `Autoscaler` resizes its pool, see the module docs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScalingPolicy {
+    pub min_workers: usize,
+    pub max_workers: usize,
+    /// The average mailbox depth above which the pool grows.
+    pub scale_up_depth: usize,
+    /// The average mailbox depth at or below which the pool shrinks.
+    pub scale_down_depth: usize,
+    /// How many samples in a row must cross a threshold before scaling.
+    pub sustain: u32,
+    /// The least time between two scaling actions.
+    pub cooldown: Duration,
+}
+
+impl ScalingPolicy {
+    /// A policy keeping between `min_workers` (at least 1) and `max_workers`,
+    /// growing above an average depth of 16 and shrinking when the mailboxes are
+    /// empty, after 3 samples in a row, at most every 10 seconds.
+    pub fn new(min_workers: usize, max_workers: usize) -> Self {
+        let min_workers = min_workers.max(1);
+        ScalingPolicy {
+            min_workers,
+            max_workers: max_workers.max(min_workers),
+            scale_up_depth: 16,
+            scale_down_depth: 0,
+            sustain: 3,
+            cooldown: Duration::from_secs(10),
+        }
+    }
+
+    /// Grows the pool above an average depth of `up` and shrinks it at or below
+    /// `down`.
+    pub fn with_thresholds(mut self, up: usize, down: usize) -> Self {
+        self.scale_up_depth = up;
+        self.scale_down_depth = down.min(up);
+        self
+    }
+
+    /// Scales only after `samples` samples in a row crossed a threshold.
+    pub fn with_sustain(mut self, samples: u32) -> Self {
+        self.sustain = samples.max(1);
+        self
+    }
+
+    /// Waits at least `cooldown` between two scaling actions.
+    pub fn with_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+}
+
+/// What a `sample` did to the pool.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScaleAction {
+    /// Added the named worker.
+    Grew(String),
+    /// Drained and removed the named worker.
+    Shrank(String),
+    /// Left the pool as it was.
+    Held,
+}
+
+// The workers in the pool, oldest first
+type Workers<M, E> = Arc<RwLock<Vec<ActorRef<M, E>>>>;
+
+/// Sends messages to the workers of an `Autoscaler`'s pool. Clones send to the
+/// same pool.
+pub struct PoolHandle<M, E = String> {
+    router: Arc<ConsistentHashRouter<M>>,
+    workers: Workers<M, E>,
+}
+
+impl<M, E> Clone for PoolHandle<M, E> {
+    fn clone(&self) -> Self {
+        PoolHandle {
+            router: Arc::clone(&self.router),
+            workers: Arc::clone(&self.workers),
+        }
+    }
+}
+
+impl<M: Send + std::fmt::Debug, E> PoolHandle<M, E> {
+    /// Sends a message to the worker the router picks for it.
+    pub async fn send(&self, message: M) -> Result<(), String> {
+        let name = self.router.route(&message).ok_or("No worker in the pool")?;
+        let worker = self
+            .workers
+            .read()
+            .unwrap()
+            .iter()
+            .find(|worker| worker.name() == name)
+            .cloned()
+            .ok_or_else(|| format!("Worker {} left the pool", name))?;
+        worker.send(message).await
+    }
+}
+
+/// Resizes a pool of workers with the load, see the module docs.
+pub struct Autoscaler<M, E, F> {
+    system: ActorSystem<M, E>,
+    router: Arc<ConsistentHashRouter<M>>,
+    // Workers are named `{prefix}-{n}`
+    prefix: String,
+    factory: F,
+    policy: ScalingPolicy,
+    clock: Arc<dyn Clock>,
+    workers: Workers<M, E>,
+    next_worker: usize,
+    // Consecutive samples above the scale-up and at or below the scale-down
+    // threshold
+    above: u32,
+    below: u32,
+    last_scaled: Option<Instant>,
+}
+
+impl<M, E, A, F> Autoscaler<M, E, F>
+where
+    M: Send + 'static + std::fmt::Debug,
+    E: Send + 'static + std::fmt::Debug,
+    A: Actor<Message = M, Error = E> + Send + 'static,
+    F: FnMut() -> A + Send,
+{
+    /// Starts a pool of `policy.min_workers` workers built by `factory`, added
+    /// to `system` and to `router`, which should start out empty.
+    pub fn new(
+        system: ActorSystem<M, E>,
+        router: Arc<ConsistentHashRouter<M>>,
+        prefix: &str,
+        factory: F,
+        policy: ScalingPolicy,
+    ) -> Self {
+        let mut autoscaler = Autoscaler {
+            system,
+            router,
+            prefix: prefix.to_string(),
+            factory,
+            policy,
+            clock: Arc::new(TokioClock),
+            workers: Arc::default(),
+            next_worker: 0,
+            above: 0,
+            below: 0,
+            last_scaled: None,
+        };
+        for _ in 0..autoscaler.policy.min_workers {
+            autoscaler.grow();
+        }
+        autoscaler
+    }
+
+    /// Sets the clock timing the cooldown and `run`, e.g. a `MockClock` in
+    /// tests.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Sends messages to the pool.
+    pub fn handle(&self) -> PoolHandle<M, E> {
+        PoolHandle {
+            router: Arc::clone(&self.router),
+            workers: Arc::clone(&self.workers),
+        }
+    }
+
+    /// The names of the workers in the pool, oldest first.
+    pub fn workers(&self) -> Vec<String> {
+        let workers = self.workers.read().unwrap();
+        workers
+            .iter()
+            .map(|worker| worker.name().to_string())
+            .collect()
+    }
+
+    /// The average mailbox depth of the workers.
+    pub fn average_depth(&self) -> usize {
+        let workers = self.workers.read().unwrap();
+        let total: usize = workers.iter().map(|worker| worker.mailbox_depth()).sum();
+        total / workers.len().max(1)
+    }
+
+    /// Samples the load once, resizing the pool if the policy says so.
+    pub async fn sample(&mut self) -> ScaleAction {
+        let depth = self.average_depth();
+        if depth > self.policy.scale_up_depth {
+            self.above += 1;
+            self.below = 0;
+        } else if depth <= self.policy.scale_down_depth {
+            self.below += 1;
+            self.above = 0;
+        } else {
+            self.above = 0;
+            self.below = 0;
+        }
+        let cooling = self
+            .last_scaled
+            .is_some_and(|at| self.clock.now() < at + self.policy.cooldown);
+        if cooling {
+            return ScaleAction::Held;
+        }
+        let size = self.workers.read().unwrap().len();
+        let action = if self.above >= self.policy.sustain && size < self.policy.max_workers {
+            ScaleAction::Grew(self.grow())
+        } else if self.below >= self.policy.sustain && size > self.policy.min_workers {
+            ScaleAction::Shrank(self.shrink().await)
+        } else {
+            return ScaleAction::Held;
+        };
+        self.above = 0;
+        self.below = 0;
+        self.last_scaled = Some(self.clock.now());
+        action
+    }
+
+    /// Samples every `period` until the system's cancellation token is
+    /// cancelled.
+    pub async fn run(mut self, period: Duration) {
+        let shutdown = self.system.cancellation_token();
+        let mut ticks = clock::interval(Arc::clone(&self.clock), period);
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => return,
+                _ = ticks.tick() => {
+                    self.sample().await;
+                }
+            }
+        }
+    }
+
+    // Start a worker under the next free name and route to it
+    fn grow(&mut self) -> String {
+        let name = loop {
+            let name = format!("{}-{}", self.prefix, self.next_worker);
+            self.next_worker += 1;
+            if self.system.actor_ref(&name).is_none() {
+                break name;
+            }
+        };
+        self.system.add_actor(name.clone(), (self.factory)());
+        if let Some(worker) = self.system.actor_ref(&name) {
+            self.workers.write().unwrap().push(worker);
+        }
+        self.router.add_worker(&name);
+        name
+    }
+
+    // Take the worker with the shortest queue off the router, then stop it once
+    // it has handled that queue
+    async fn shrink(&mut self) -> String {
+        let name = {
+            let mut workers = self.workers.write().unwrap();
+            let (index, _) = workers
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, worker)| worker.mailbox_depth())
+                .expect("the pool is above its minimum of one worker");
+            workers.remove(index).name().to_string()
+        };
+        self.router.remove_worker(&name);
+        if let Err(e) = self.system.stop_actor(&name).await {
+            eprintln!("Failed to stop worker {}: {}", name, e);
+        }
+        name
+    }
+}
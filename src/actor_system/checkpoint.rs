@@ -0,0 +1,50 @@
+// src/actor_system/checkpoint.rs
+
+//! # Checkpoints
+//!
+//! `ActorSystem::checkpoint_all` takes a consistent checkpoint across every
+//! participant registered with `ActorSystem::add_checkpoint_participant`, in two
+//! phases:
+//!
+//! 1. every participant is asked to `prepare_checkpoint`, making all its writes so
+//!    far durable and returning a `CheckpointToken`;
+//! 2. only once all of them have prepared, each one is asked to
+//!    `commit_checkpoint` with its token.
+//!
+//! Participants stay locked from the first prepare to the last commit, so no
+//! writes slip in between and the checkpoint reflects a single logical point. If
+//! any participant fails to prepare, those already prepared are told to
+//! `abort_checkpoint` and the whole checkpoint fails.
+
+use async_trait::async_trait;
+
+/// Proof that a participant prepared a given checkpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CheckpointToken {
+    checkpoint_id: u64,
+}
+
+impl CheckpointToken {
+    pub fn new(checkpoint_id: u64) -> Self {
+        CheckpointToken { checkpoint_id }
+    }
+
+    /// The checkpoint this token was issued for.
+    pub fn checkpoint_id(&self) -> u64 {
+        self.checkpoint_id
+    }
+}
+
+/// Something that takes part in system-wide checkpoints, such as a `DataActor`.
+#[async_trait]
+pub trait Checkpoint: Send {
+    /// Makes every write so far durable and returns a token for the checkpoint.
+    async fn prepare_checkpoint(&mut self, checkpoint_id: u64) -> Result<CheckpointToken, String>;
+
+    /// Marks the prepared checkpoint as taken.
+    async fn commit_checkpoint(&mut self, token: CheckpointToken) -> Result<(), String>;
+
+    /// Called instead of `commit_checkpoint` when another participant failed to
+    /// prepare. Nothing to undo by default.
+    async fn abort_checkpoint(&mut self, _token: CheckpointToken) {}
+}
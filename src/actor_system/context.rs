@@ -0,0 +1,151 @@
+// src/actor_system/context.rs
+
+//! # Actor hierarchies
+//!
+//! Every actor gets a `Context` through `Actor::receive_with_context`. With it the
+//! actor can spawn children with `spawn_child`: they run under the parent's
+//! supervision, so the failures the system detects in a child, such as a receive
+//! timeout, go to the supervisor the parent was added under and are handled by
+//! its strategy. Children are named `"{parent}/{child}"` and stop along with
+//! their parent: when the parent leaves its loop, its children are shut down, in
+//! reverse order of spawning, before the parent's own `cleanup` runs.
+//!
+//! Children are not registered with the system itself: reach them through
+//! `Context::child`, or keep the `ActorRef` `spawn_child` returns.
+//!
+//! ```rust
+//! use astra::actor_system::{Actor, ActorSystem, Context, Message};
+//! use async_trait::async_trait;
+//!
+//! struct Worker;
+//!
+//! #[async_trait]
+//! impl Actor for Worker {
+//!     type Message = String;
+//!     type Error = String;
+//!
+//!     async fn receive(&mut self, message: Message<String>) -> Result<(), String> {
+//!         if let Message::Regular(job) = message {
+//!             println!("working on {}", job);
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//!
+//! // Spawns a worker on first use and hands it every job
+//! struct Manager;
+//!
+//! #[async_trait]
+//! impl Actor for Manager {
+//!     type Message = String;
+//!     type Error = String;
+//!
+//!     async fn receive(&mut self, _message: Message<String>) -> Result<(), String> {
+//!         Ok(())
+//!     }
+//!
+//!     async fn receive_with_context(
+//!         &mut self,
+//!         context: &Context<String>,
+//!         message: Message<String>,
+//!     ) -> Result<(), String> {
+//!         if let Message::Regular(job) = message {
+//!             let worker = match context.child("worker") {
+//!                 Some(worker) => worker,
+//!                 None => context.spawn_child("worker", Worker),
+//!             };
+//!             worker.send(job).await?;
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), String> {
+//!     let mut system = ActorSystem::new();
+//!     system.add_actor("manager".to_string(), Manager);
+//!     system.send_message("manager", "report".to_string()).await?;
+//!     // Stops the worker too
+//!     system.shutdown().await;
+//!     Ok(())
+//! }
+//! ```
+
+use super::{Actor, ActorOptions, ActorRef, ActorSystem};
+use std::sync::Mutex;
+
+/// What an actor can reach while handling a message: its name and its children.
+pub struct Context<M, E = String> {
+    name: String,
+    children: Mutex<ActorSystem<M, E>>,
+}
+
+impl<M, E> Context<M, E>
+where
+    M: Send + 'static + std::fmt::Debug,
+    E: Send + 'static + std::fmt::Debug,
+{
+    // `children` is the empty system the children are added to
+    pub(super) fn new(name: String, children: ActorSystem<M, E>) -> Self {
+        Context {
+            name,
+            children: Mutex::new(children),
+        }
+    }
+
+    /// The name of the actor handling the message.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Starts `actor` as a child named `"{parent}/{name}"`, replacing any child of
+    /// that name, and returns a handle to it.
+    pub fn spawn_child<A>(&self, name: &str, actor: A) -> ActorRef<M, E>
+    where
+        A: Actor<Message = M, Error = E> + Send + 'static,
+    {
+        self.spawn_child_with_options(name, actor, ActorOptions::default())
+    }
+
+    /// Starts a child configured with the given `ActorOptions`.
+    pub fn spawn_child_with_options<A>(
+        &self,
+        name: &str,
+        actor: A,
+        options: ActorOptions,
+    ) -> ActorRef<M, E>
+    where
+        A: Actor<Message = M, Error = E> + Send + 'static,
+    {
+        let name = self.child_name(name);
+        let mut children = self.children.lock().unwrap();
+        children.add_actor_with_options(name.clone(), actor, options);
+        children
+            .actor_ref(&name)
+            .expect("a child is registered once added")
+    }
+
+    /// Returns a handle to the child spawned as `name`.
+    pub fn child(&self, name: &str) -> Option<ActorRef<M, E>> {
+        self.children
+            .lock()
+            .unwrap()
+            .actor_ref(&self.child_name(name))
+    }
+
+    /// The full names of the children, in the order they were spawned.
+    pub fn children(&self) -> Vec<String> {
+        self.children.lock().unwrap().actor_names()
+    }
+
+    // Shut every child down, the last spawned first
+    pub(super) async fn stop_children(&self) {
+        // A snapshot, so the lock isn't held across the shutdown
+        let children = self.children.lock().unwrap().clone();
+        children.shutdown().await;
+    }
+
+    fn child_name(&self, name: &str) -> String {
+        format!("{}/{}", self.name, name)
+    }
+}
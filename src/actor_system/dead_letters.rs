@@ -0,0 +1,229 @@
+// src/actor_system/dead_letters.rs
+
+//! # Dead letters
+//!
+//! When enabled with `ActorSystem::with_dead_letters`, messages that can't be
+//! delivered because their target actor doesn't exist or has stopped are kept in a
+//! bounded dead-letter queue instead of being lost. Once the problem is fixed (e.g.
+//! the missing actor is registered), `ActorSystem::reprocess_dead_letters` tries to
+//! deliver them again.
+//!
+//! With `ActorSystem::with_persistent_dead_letters` the queue is also kept in a
+//! `StorageBackend`, so dead letters survive a restart; this requires the
+//! messages to be serializable. Every change to the queue is written in the
+//! background, and once more by `ActorSystem::shutdown`. A restarted system
+//! picks the persisted dead letters up with `ActorSystem::load_dead_letters`,
+//! or on its first `reprocess_dead_letters`, ahead of any dead-lettered since.
+//! Until then nothing is written, so the persisted ones can't be overwritten.
+//!
+//! Every undeliverable message is also announced as a `DeadLetterEvent` on the
+//! system's event bus (see `ActorSystem::event_bus`), queue or not.
+
+use crate::backends::boxed::BoxedBackend;
+use crate::backends::storage::StorageBackend;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// A message that could not be delivered.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeadLetter<M> {
+    /// The actor the message was sent to.
+    pub target: String,
+    /// The undelivered message.
+    pub message: M,
+    /// Why delivery failed.
+    pub reason: String,
+}
+
+/// A message that could not be delivered, as published on the event bus. The
+/// message itself is kept by the dead-letter queue, the event only describes it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeadLetterEvent {
+    /// The actor the message was sent to.
+    pub target: String,
+    /// The undelivered message, `Debug` formatted.
+    pub message: String,
+    /// Why delivery failed.
+    pub reason: String,
+}
+
+/// The result of `ActorSystem::reprocess_dead_letters`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ReprocessReport {
+    /// Dead letters delivered and removed from the queue.
+    pub redelivered: usize,
+    /// Dead letters that still couldn't be delivered and remain queued.
+    pub still_failing: usize,
+}
+
+// Bounded queue of dead letters, dropping the oldest once full
+pub(crate) struct DeadLetterQueue<M> {
+    capacity: usize,
+    letters: VecDeque<DeadLetter<M>>,
+    persistence: Option<Persistence<M>>,
+}
+
+// Where a persistent queue is kept, and how its messages are (de)serialized
+struct Persistence<M> {
+    store: Arc<DeadLetterStore>,
+    encode: Encode<M>,
+    decode: Decode<M>,
+}
+
+type Encode<M> = fn(&VecDeque<DeadLetter<M>>) -> Result<Vec<u8>, String>;
+type Decode<M> = fn(&[u8]) -> Result<Vec<DeadLetter<M>>, String>;
+
+fn encode<M: Serialize>(letters: &VecDeque<DeadLetter<M>>) -> Result<Vec<u8>, String> {
+    serde_json::to_vec(letters).map_err(|e| e.to_string())
+}
+
+fn decode<M: DeserializeOwned>(data: &[u8]) -> Result<Vec<DeadLetter<M>>, String> {
+    if data.is_empty() {
+        return Ok(Vec::new());
+    }
+    serde_json::from_slice(data).map_err(|e| format!("Failed to read dead letters: {}", e))
+}
+
+pub(crate) struct DeadLetterStore {
+    saved: tokio::sync::Mutex<Saved>,
+    // Numbers the writes, so an older one finishing late can't undo a newer one
+    versions: AtomicU64,
+}
+
+struct Saved {
+    backend: BoxedBackend,
+    // Set once the letters of a previous run are merged into the queue
+    loaded: bool,
+    version: u64,
+}
+
+// A write of the queue's content to its store
+pub(crate) struct Save {
+    store: Arc<DeadLetterStore>,
+    version: u64,
+    data: Result<Vec<u8>, String>,
+}
+
+impl Save {
+    pub(crate) async fn run(self) -> Result<(), String> {
+        let mut saved = self.store.saved.lock().await;
+        if !saved.loaded || saved.version >= self.version {
+            return Ok(());
+        }
+        Self::write(&mut saved, self.version, self.data).await
+    }
+
+    async fn write(
+        saved: &mut Saved,
+        version: u64,
+        data: Result<Vec<u8>, String>,
+    ) -> Result<(), String> {
+        // The error isn't `Send` and this runs in spawned tasks, hence the `String`
+        saved
+            .backend
+            .write_bytes(&data?)
+            .await
+            .map_err(|e| format!("Failed to persist dead letters: {}", e))?;
+        saved.version = version;
+        Ok(())
+    }
+}
+
+impl<M> DeadLetterQueue<M> {
+    pub(crate) fn new(capacity: usize) -> Self {
+        DeadLetterQueue {
+            capacity: capacity.max(1),
+            letters: VecDeque::new(),
+            persistence: None,
+        }
+    }
+
+    pub(crate) fn persistent<B>(capacity: usize, backend: B) -> Self
+    where
+        M: Serialize + DeserializeOwned,
+        B: StorageBackend + 'static,
+    {
+        let mut queue = Self::new(capacity);
+        queue.persistence = Some(Persistence {
+            store: Arc::new(DeadLetterStore {
+                saved: tokio::sync::Mutex::new(Saved {
+                    backend: BoxedBackend::new(backend),
+                    loaded: false,
+                    version: 0,
+                }),
+                versions: AtomicU64::new(0),
+            }),
+            encode: encode::<M>,
+            decode: decode::<M>,
+        });
+        queue
+    }
+
+    // The write bringing the store up to date, `None` unless the queue is persistent
+    pub(crate) fn save(&self) -> Option<Save> {
+        self.persistence.as_ref().map(|persistence| Save {
+            store: Arc::clone(&persistence.store),
+            version: persistence.store.versions.fetch_add(1, Ordering::SeqCst) + 1,
+            data: (persistence.encode)(&self.letters),
+        })
+    }
+
+    pub(crate) fn push(&mut self, letter: DeadLetter<M>) {
+        if self.letters.len() == self.capacity {
+            self.letters.pop_front();
+        }
+        self.letters.push_back(letter);
+    }
+
+    pub(crate) fn take_all(&mut self) -> Vec<DeadLetter<M>> {
+        self.letters.drain(..).collect()
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &DeadLetter<M>> {
+        self.letters.iter()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.letters.len()
+    }
+}
+
+// Merge the letters a previous run persisted into the queue, the first time,
+// then write the queue to its store; nothing to do unless it is persistent
+pub(crate) async fn sync<M>(queue: &Mutex<Option<DeadLetterQueue<M>>>) -> Result<(), String> {
+    let (store, decode) = match queue.lock().unwrap().as_ref() {
+        Some(DeadLetterQueue {
+            persistence: Some(persistence),
+            ..
+        }) => (Arc::clone(&persistence.store), persistence.decode),
+        _ => return Ok(()),
+    };
+    let mut saved = store.saved.lock().await;
+    if !saved.loaded {
+        let data = saved
+            .backend
+            .read_bytes()
+            .await
+            .map_err(|e| format!("Failed to read dead letters: {}", e))?;
+        let persisted = decode(&data)?;
+        if let Some(queue) = queue.lock().unwrap().as_mut() {
+            let newer = queue.take_all();
+            for letter in persisted.into_iter().chain(newer) {
+                queue.push(letter);
+            }
+        }
+        saved.loaded = true;
+    }
+    let save = queue
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(DeadLetterQueue::save);
+    match save {
+        Some(save) => Save::write(&mut saved, save.version, save.data).await,
+        None => Ok(()),
+    }
+}
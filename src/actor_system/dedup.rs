@@ -0,0 +1,103 @@
+// src/actor_system/dedup.rs
+
+//! # Deduplication
+//!
+//! `DedupActor` collapses back-to-back duplicates: a `Message::Regular` equal to
+//! the message handled just before it is skipped. This only looks at adjacent
+//! messages, so `["a", "a", "b", "a"]` is handled as `["a", "b", "a"]`; it is an
+//! optimization for idempotent control messages (e.g. repeated "reload config"),
+//! not a general idempotency guarantee.
+//!
+//! ```rust
+//! use astra::actor_system::{Actor, ActorSystem, Message};
+//! use async_trait::async_trait;
+//!
+//! struct Reloader;
+//!
+//! #[async_trait]
+//! impl Actor for Reloader {
+//!     type Message = String;
+//!     type Error = String;
+//!
+//!     async fn receive(&mut self, message: Message<String>) -> Result<(), String> {
+//!         if let Message::Regular(msg) = message {
+//!             println!("handling {}", msg);
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), String> {
+//!     let mut system = ActorSystem::new();
+//!     system.add_dedup_actor("reloader".to_string(), Reloader);
+//!     system.send_message("reloader", "reload".to_string()).await?;
+//!     // Skipped, it repeats the previous message
+//!     system.send_message("reloader", "reload".to_string()).await?;
+//!     system.shutdown().await;
+//!     Ok(())
+//! }
+//! ```
+
+use super::{Actor, Message};
+use async_trait::async_trait;
+
+/// Wraps an actor, skipping messages equal to the one handled right before.
+/// Usually created through `ActorSystem::add_dedup_actor`.
+pub struct DedupActor<A: Actor> {
+    actor: A,
+    // The last message the actor handled successfully
+    last: Option<A::Message>,
+}
+
+impl<A: Actor> DedupActor<A> {
+    pub fn new(actor: A) -> Self {
+        DedupActor { actor, last: None }
+    }
+}
+
+#[async_trait]
+impl<A> Actor for DedupActor<A>
+where
+    A: Actor + Send,
+    A::Message: PartialEq + Clone + Send,
+{
+    type Message = A::Message;
+    type Error = A::Error;
+
+    async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error> {
+        match message {
+            Message::Regular(msg) => {
+                if self.last.as_ref() == Some(&msg) {
+                    return Ok(());
+                }
+                // A failed message is not remembered, so a repeat of it is retried
+                self.last = None;
+                self.actor.receive(Message::Regular(msg.clone())).await?;
+                self.last = Some(msg);
+                Ok(())
+            }
+            Message::Shutdown => self.actor.receive(Message::Shutdown).await,
+            Message::System(message) => self.actor.receive(Message::System(message)).await,
+        }
+    }
+
+    async fn cleanup(&mut self) {
+        self.actor.cleanup().await;
+    }
+
+    async fn try_cleanup(&mut self) -> Result<(), Self::Error>
+    where
+        Self::Error: Send,
+    {
+        self.actor.try_cleanup().await
+    }
+
+    fn receives_system_messages(&self) -> bool {
+        self.actor.receives_system_messages()
+    }
+
+    fn inspect_state(&self) -> Option<String> {
+        self.actor.inspect_state()
+    }
+}
@@ -0,0 +1,168 @@
+// src/actor_system/federation.rs
+
+//! # Federations
+//!
+//! A process can run several `ActorSystem`s side by side, e.g. one per
+//! tenant or subsystem, each with its own supervisor, limits and shutdown. A
+//! `Federation` puts them under one roof: the systems are added under names,
+//! `send` delivers to an actor of a named system, and `actor_names` lists the
+//! actors of every system as `system/actor`. The systems themselves are left
+//! as they are; the federation only holds handles on them.
+//!
+//! `send_to` takes an actor name alone and delivers it to the first system
+//! running an actor of that name: the default system set with `with_default`,
+//! then the others in the order they were added.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use astra::actor_system::{Actor, ActorSystem, Federation, Message};
+//! use async_trait::async_trait;
+//!
+//! struct Printer;
+//!
+//! #[async_trait]
+//! impl Actor for Printer {
+//!     type Message = String;
+//!     type Error = String;
+//!
+//!     async fn receive(&mut self, message: Message<String>) -> Result<(), String> {
+//!         if let Message::Regular(text) = message {
+//!             println!("{}", text);
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), String> {
+//!     let mut billing = ActorSystem::new();
+//!     billing.add_actor("invoices".to_string(), Printer);
+//!     let mut shipping = ActorSystem::new();
+//!     shipping.add_actor("parcels".to_string(), Printer);
+//!
+//!     let mut federation = Federation::new();
+//!     federation.add_system("billing", billing)?;
+//!     federation.add_system("shipping", shipping)?;
+//!     assert_eq!(
+//!         federation.actor_names(),
+//!         vec!["billing/invoices", "shipping/parcels"]
+//!     );
+//!
+//!     federation.send("billing", "invoices", "invoice #1".to_string()).await?;
+//!     federation.send_to("parcels", "parcel #1".to_string()).await?;
+//!     federation.shutdown().await;
+//!     Ok(())
+//! }
+//! ```
+
+use super::{ActorSystem, ShutdownReport};
+
+/// Named `ActorSystem`s messages can be routed across, see the module docs.
+pub struct Federation<M, E = String> {
+    // In the order they were added, which is the order `send_to` searches
+    systems: Vec<(String, ActorSystem<M, E>)>,
+    default: Option<String>,
+}
+
+impl<M, E> Default for Federation<M, E> {
+    fn default() -> Self {
+        Federation {
+            systems: Vec::new(),
+            default: None,
+        }
+    }
+}
+
+impl<M, E> Federation<M, E>
+where
+    M: Send + 'static + std::fmt::Debug,
+    E: Send + 'static + std::fmt::Debug,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes `send_to` look in the system named `name` before the others.
+    pub fn with_default(mut self, name: &str) -> Self {
+        self.default = Some(name.to_string());
+        self
+    }
+
+    /// Adds `system` under `name`, failing if a system of that name is already
+    /// federated.
+    pub fn add_system(&mut self, name: &str, system: ActorSystem<M, E>) -> Result<(), String> {
+        if self.system(name).is_some() {
+            return Err(format!("System {} is already federated", name));
+        }
+        self.systems.push((name.to_string(), system));
+        Ok(())
+    }
+
+    /// The system added under `name`.
+    pub fn system(&self, name: &str) -> Option<&ActorSystem<M, E>> {
+        self.systems
+            .iter()
+            .find(|(system_name, _)| system_name == name)
+            .map(|(_, system)| system)
+    }
+
+    /// The names of the systems, in the order they were added.
+    pub fn system_names(&self) -> Vec<String> {
+        self.systems.iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    /// The actors of every system as `system/actor`, system by system in the
+    /// order they were added.
+    pub fn actor_names(&self) -> Vec<String> {
+        self.systems
+            .iter()
+            .flat_map(|(name, system)| {
+                system
+                    .actor_names()
+                    .into_iter()
+                    .map(move |actor| format!("{}/{}", name, actor))
+            })
+            .collect()
+    }
+
+    /// Sends a message to the actor `actor` of the system named `system`.
+    pub async fn send(&self, system: &str, actor: &str, message: M) -> Result<(), String> {
+        self.system(system)
+            .ok_or_else(|| format!("System {} not found", system))?
+            .send_message(actor, message)
+            .await
+    }
+
+    /// The name of the system `send_to` delivers to `actor`: the default system
+    /// if it runs the actor, else the first other system that does.
+    pub fn resolve(&self, actor: &str) -> Option<&str> {
+        let default = self
+            .default
+            .as_deref()
+            .and_then(|name| self.systems.iter().find(|(system, _)| system == name));
+        default
+            .into_iter()
+            .chain(&self.systems)
+            .find(|(_, system)| system.actor_ref(actor).is_some())
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Sends a message to `actor` in whichever system runs it (see `resolve`).
+    pub async fn send_to(&self, actor: &str, message: M) -> Result<(), String> {
+        let system = self
+            .resolve(actor)
+            .ok_or_else(|| format!("Actor {} not found in any system", actor))?;
+        self.send(system, actor, message).await
+    }
+
+    /// Shuts every system down, one after the other in the order they were
+    /// added, returning the report of each.
+    pub async fn shutdown(&self) -> Vec<(String, ShutdownReport)> {
+        let mut reports = Vec::new();
+        for (name, system) in &self.systems {
+            reports.push((name.clone(), system.shutdown().await));
+        }
+        reports
+    }
+}
@@ -0,0 +1,58 @@
+// src/actor_system/high_water.rs
+
+//! # Mailbox high-water mark
+//!
+//! An actor added with `ActorOptions::with_high_water_mark(percent)` warns before
+//! its mailbox saturates: when a send brings the mailbox to `percent`% of its
+//! capacity or more, a `LogLevel::Warn` line naming the actor and its depth goes
+//! to the system's logger (see `ActorSystem::with_logger`). The warning fires on
+//! crossing the mark, not on every send above it; once the mailbox is seen below
+//! the mark again, the next crossing warns again. `ActorRef::high_water_crossings`
+//! counts them. Each crossing is also published as a `HighWaterEvent` on the
+//! system's event bus (see `ActorSystem::event_bus`).
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// A mailbox that crossed its high-water mark, as published on the event bus.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HighWaterEvent {
+    pub actor: String,
+    /// Messages in the mailbox when the mark was crossed.
+    pub depth: usize,
+    pub capacity: usize,
+}
+
+#[derive(Debug)]
+pub(crate) struct HighWaterMark {
+    // Mailbox depth, in messages, at which the mark is crossed
+    threshold: usize,
+    above: AtomicBool,
+    crossings: AtomicU64,
+}
+
+impl HighWaterMark {
+    pub(crate) fn new(capacity: usize, percent: u8) -> Self {
+        // Round up, so 80% of 5 messages is 4 and the mark is never 0
+        let threshold = (capacity * usize::from(percent)).div_ceil(100).max(1);
+        HighWaterMark {
+            threshold,
+            above: AtomicBool::new(false),
+            crossings: AtomicU64::new(0),
+        }
+    }
+
+    // Record the mailbox depth after a send, returning whether it just crossed
+    pub(crate) fn observe(&self, depth: usize) -> bool {
+        let above = depth >= self.threshold;
+        let was_above = self.above.swap(above, Ordering::SeqCst);
+        if above && !was_above {
+            self.crossings.fetch_add(1, Ordering::SeqCst);
+            return true;
+        }
+        false
+    }
+
+    pub(crate) fn crossings(&self) -> u64 {
+        self.crossings.load(Ordering::SeqCst)
+    }
+}
@@ -0,0 +1,84 @@
+// src/actor_system/ids.rs
+
+//! # Id generators
+//!
+//! `ActorSystem::add_actor_auto` names actors with an `IdGenerator`, for systems
+//! that spawn actors on the fly, e.g. one per request. `SequentialGen`, the
+//! default, counts up (`actor-1`, `actor-2`, ...), which keeps logs readable;
+//! `UuidGen` makes random version 4 UUIDs, for names that must not collide with
+//! those of another process.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Makes a fresh id on every call.
+pub trait IdGenerator: Send + Sync {
+    fn next(&self) -> String;
+}
+
+/// Ids made of a prefix and a counter starting at 1, like `actor-1`.
+#[derive(Debug)]
+pub struct SequentialGen {
+    prefix: String,
+    counter: AtomicU64,
+}
+
+impl SequentialGen {
+    pub fn new(prefix: &str) -> Self {
+        SequentialGen {
+            prefix: prefix.to_string(),
+            counter: AtomicU64::new(0),
+        }
+    }
+}
+
+impl Default for SequentialGen {
+    fn default() -> Self {
+        SequentialGen::new("actor")
+    }
+}
+
+impl IdGenerator for SequentialGen {
+    fn next(&self) -> String {
+        let n = self.counter.fetch_add(1, Ordering::Relaxed) + 1;
+        format!("{}-{}", self.prefix, n)
+    }
+}
+
+/// Random version 4 UUIDs, like `8c4f2e1a-93b7-4d0c-a5e2-61f0b9d37c48`. The bits
+/// come from the standard library's randomly keyed hasher: unpredictable enough
+/// for names, but not meant for secrets.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct UuidGen;
+
+// Makes every hash unique even when the hasher keys repeat
+static UUID_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+fn random_u64() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(UUID_COUNTER.fetch_add(1, Ordering::Relaxed));
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    hasher.write_u128(now.as_nanos());
+    hasher.finish()
+}
+
+impl IdGenerator for UuidGen {
+    fn next(&self) -> String {
+        let bits = (u128::from(random_u64()) << 64) | u128::from(random_u64());
+        // Set the version (4) and variant (RFC 4122) bits
+        let bits = (bits & !(0xF << 76)) | (0x4 << 76);
+        let bits = (bits & !(0x3 << 62)) | (0x2 << 62);
+        format!(
+            "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+            bits >> 96,
+            (bits >> 80) & 0xFFFF,
+            (bits >> 64) & 0xFFFF,
+            (bits >> 48) & 0xFFFF,
+            bits & 0xFFFF_FFFF_FFFF
+        )
+    }
+}
@@ -0,0 +1,125 @@
+// src/actor_system/inspect.rs
+
+//! # Inspection
+//!
+//! `ActorSystem::inspect` asks a live actor to describe its internal state, for
+//! debugging without a bespoke message per actor. Actors opt in by implementing
+//! `Debuggable` and being added with `ActorSystem::add_debuggable_actor`; for
+//! any other actor `inspect` returns `None`.
+//!
+//! The request travels beside the mailbox and is answered between two messages,
+//! so it never waits behind a long queue, but it does wait for the message being
+//! handled to finish.
+//!
+//! ```rust
+//! use astra::actor_system::{Actor, ActorSystem, Debuggable, Message};
+//! use async_trait::async_trait;
+//!
+//! struct Counter {
+//!     count: u32,
+//! }
+//!
+//! #[async_trait]
+//! impl Actor for Counter {
+//!     type Message = String;
+//!     type Error = String;
+//!
+//!     async fn receive(&mut self, message: Message<String>) -> Result<(), String> {
+//!         if let Message::Regular(_) = message {
+//!             self.count += 1;
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//!
+//! impl Debuggable for Counter {
+//!     fn debug_state(&self) -> String {
+//!         format!("count={}", self.count)
+//!     }
+//! }
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), String> {
+//!     let mut system = ActorSystem::new();
+//!     system.add_debuggable_actor("counter".to_string(), Counter { count: 0 });
+//!     system.send_message("counter", "tick".to_string()).await?;
+//!     // The request would be answered ahead of the queued tick otherwise
+//!     system.wait_quiesced().await;
+//!     assert_eq!(system.inspect("counter").await.as_deref(), Some("count=1"));
+//!     system.shutdown().await;
+//!     Ok(())
+//! }
+//! ```
+
+use super::{Actor, Context, Message};
+use async_trait::async_trait;
+
+/// An actor that can describe its internal state to `ActorSystem::inspect`.
+pub trait Debuggable {
+    fn debug_state(&self) -> String;
+}
+
+/// Wraps a `Debuggable` actor so `ActorSystem::inspect` can reach its state.
+/// Usually created through `ActorSystem::add_debuggable_actor`.
+pub struct DebuggableActor<A> {
+    actor: A,
+}
+
+impl<A> DebuggableActor<A> {
+    pub fn new(actor: A) -> Self {
+        DebuggableActor { actor }
+    }
+}
+
+#[async_trait]
+impl<A> Actor for DebuggableActor<A>
+where
+    A: Actor + Debuggable + Send,
+    A::Message: Send,
+{
+    type Message = A::Message;
+    type Error = A::Error;
+
+    async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error> {
+        self.actor.receive(message).await
+    }
+
+    async fn receive_with_context(
+        &mut self,
+        context: &Context<Self::Message, Self::Error>,
+        message: Message<Self::Message>,
+    ) -> Result<(), Self::Error>
+    where
+        Self::Message: Send,
+        Self::Error: Send,
+    {
+        self.actor.receive_with_context(context, message).await
+    }
+
+    async fn receive_batch(&mut self, messages: Vec<Self::Message>) -> Result<(), Self::Error>
+    where
+        Self::Message: Send,
+        Self::Error: Send,
+    {
+        self.actor.receive_batch(messages).await
+    }
+
+    async fn cleanup(&mut self) {
+        self.actor.cleanup().await;
+    }
+
+    async fn try_cleanup(&mut self) -> Result<(), Self::Error>
+    where
+        Self::Error: Send,
+    {
+        self.actor.try_cleanup().await
+    }
+
+    fn receives_system_messages(&self) -> bool {
+        self.actor.receives_system_messages()
+    }
+
+    fn inspect_state(&self) -> Option<String> {
+        Some(self.actor.debug_state())
+    }
+}
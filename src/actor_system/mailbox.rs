@@ -0,0 +1,662 @@
+// src/actor_system/mailbox.rs
+
+//! # Mailboxes
+//!
+//! By default an actor's mailbox is bounded: once it is full, `send_message`
+//! waits for room and `try_send_message` fails with `SendError::MailboxFull`.
+//! That protects the queued messages, which is wrong for telemetry where only
+//! the latest readings matter. In a mailbox of an actor added with
+//! `ActorOptions::with_overflow_policy(OverflowPolicy::DropOldest)` a send to a
+//! full mailbox never waits or fails, it discards the oldest queued message to
+//! make room. `ActorRef::dropped_messages` counts the discarded ones.
+//!
+//! Whatever the policy, `ActorSystem::on_overflow` installs a hook that sees
+//! each message sent to a full mailbox before the policy handles it.
+//!
+//! A `Shutdown` is never discarded, so an actor always gets to clean up.
+//!
+//! The receiving half is shared with the actor's `ActorRef`s, so
+//! `ActorSystem::drain_mailbox` can take the queued regular messages out from
+//! under a busy actor. `Shutdown` and system messages stay queued, in order.
+//!
+//! `ActorSystem::peek_mailbox` copies queued regular messages without taking
+//! them, so they still count against the capacity.
+//!
+//! ## Priority lane
+//!
+//! An actor added with `ActorOptions::with_priority_lane` has a second lane in
+//! its mailbox for the messages sent with `ActorRef::send_priority` (or
+//! `ActorSystem::send_priority_message`), which it handles before the others.
+//! Both lanes share the capacity and the overflow policy. So that a steady
+//! stream of priority messages can't starve the rest, every message is
+//! timestamped as it is queued: once the oldest regular message has waited
+//! longer than the aging threshold it is handled next, priority messages or
+//! not. Drains and peeks list the priority lane first.
+
+use super::Message;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::error::{SendError, TrySendError};
+use tokio::sync::Notify;
+use tokio::time::{sleep_until, Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+/// What happens to a message sent to a full mailbox.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// Keep the queued messages: `send_message` waits for room and
+    /// `try_send_message` fails with `SendError::MailboxFull`.
+    #[default]
+    Block,
+    /// Discard the oldest queued message to make room for the new one.
+    DropOldest,
+}
+
+// Create a mailbox holding up to `capacity` messages, with a priority lane
+// aging its regular messages after `priority_aging` if set
+pub(crate) fn mailbox<M>(
+    capacity: usize,
+    policy: OverflowPolicy,
+    priority_aging: Option<Duration>,
+) -> (MailboxSender<M>, MailboxReceiver<M>) {
+    if let Some(aging) = priority_aging {
+        let lanes = Arc::new(Lanes {
+            capacity,
+            policy,
+            aging,
+            state: Mutex::new(LanesState {
+                regular: VecDeque::new(),
+                priority: VecDeque::new(),
+                closed: false,
+            }),
+            senders: AtomicUsize::new(1),
+            dropped: AtomicU64::new(0),
+            closing: AtomicBool::new(false),
+            readable: Notify::new(),
+            writable: Notify::new(),
+            closed: Notify::new(),
+        });
+        return (
+            MailboxSender::Lanes(Arc::clone(&lanes)),
+            MailboxReceiver::Lanes(lanes),
+        );
+    }
+    let queue = Arc::new(Queue {
+        capacity,
+        policy,
+        state: Mutex::new(QueueState {
+            queue: VecDeque::new(),
+            closed: false,
+        }),
+        senders: AtomicUsize::new(1),
+        dropped: AtomicU64::new(0),
+        closing: AtomicBool::new(false),
+        readable: Notify::new(),
+        writable: Notify::new(),
+        closed: Notify::new(),
+    });
+    (
+        MailboxSender::Queue(Arc::clone(&queue)),
+        MailboxReceiver::Queue(queue),
+    )
+}
+
+// A mailbox without a priority lane
+pub(crate) struct Queue<M> {
+    capacity: usize,
+    policy: OverflowPolicy,
+    state: Mutex<QueueState<M>>,
+    // Like a channel, the mailbox closes once every sender is gone
+    senders: AtomicUsize,
+    dropped: AtomicU64,
+    // Set once the actor is stopping: regular messages are refused, checked
+    // under the state lock so none lands behind the `Shutdown`
+    closing: AtomicBool,
+    // Wakes the receiver when a message arrives or the mailbox closes
+    readable: Notify,
+    // Wakes the senders waiting for room in a blocking mailbox
+    writable: Notify,
+    // Wakes `closed` waiters once the receiver is gone
+    closed: Notify,
+}
+
+struct QueueState<M> {
+    queue: VecDeque<Message<M>>,
+    closed: bool,
+}
+
+// A mailbox with a priority lane, see the module docs
+pub(crate) struct Lanes<M> {
+    capacity: usize,
+    policy: OverflowPolicy,
+    // How long a regular message may wait behind priority ones
+    aging: Duration,
+    state: Mutex<LanesState<M>>,
+    senders: AtomicUsize,
+    dropped: AtomicU64,
+    closing: AtomicBool,
+    readable: Notify,
+    // Wakes the senders waiting for room in a blocking mailbox
+    writable: Notify,
+    closed: Notify,
+}
+
+struct LanesState<M> {
+    // Everything but priority messages, with when each was queued
+    regular: VecDeque<(Instant, Message<M>)>,
+    priority: VecDeque<M>,
+    closed: bool,
+}
+
+impl<M> LanesState<M> {
+    fn len(&self) -> usize {
+        self.regular.len() + self.priority.len()
+    }
+
+    // The next message for the actor: priority first, unless the oldest regular
+    // one has waited past `aging`
+    fn pop(&mut self, aging: Duration) -> Option<Message<M>> {
+        let aged = self
+            .regular
+            .front()
+            .is_some_and(|(queued, _)| queued.elapsed() >= aging);
+        if aged || self.priority.is_empty() {
+            self.regular.pop_front().map(|(_, message)| message)
+        } else {
+            self.priority.pop_front().map(Message::Regular)
+        }
+    }
+
+    // Discard the oldest regular message, from the regular lane if it holds
+    // one
+    fn evict(&mut self) -> Option<M> {
+        let oldest = self
+            .regular
+            .iter()
+            .position(|(_, queued)| matches!(queued, Message::Regular(_)));
+        match oldest.and_then(|i| self.regular.remove(i)) {
+            Some((_, Message::Regular(oldest))) => Some(oldest),
+            _ => self.priority.pop_front(),
+        }
+    }
+}
+
+impl<M> Lanes<M> {
+    // Queue a message on its lane, or hand it back if the mailbox is closed or,
+    // when it blocks, full. Returns the regular message pushed out to make
+    // room, if any.
+    fn try_push(
+        &self,
+        message: Message<M>,
+        priority: bool,
+    ) -> Result<Option<M>, TrySendError<Message<M>>> {
+        let mut state = self.state.lock().unwrap();
+        let refused = matches!(message, Message::Regular(_)) && self.closing.load(Ordering::SeqCst);
+        if state.closed || refused {
+            return Err(TrySendError::Closed(message));
+        }
+        let mut evicted = None;
+        if matches!(message, Message::Regular(_)) && state.len() >= self.capacity {
+            if self.policy == OverflowPolicy::Block {
+                return Err(TrySendError::Full(message));
+            }
+            evicted = state.evict();
+            if evicted.is_some() {
+                self.dropped.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+        match message {
+            Message::Regular(message) if priority => state.priority.push_back(message),
+            message => state.regular.push_back((Instant::now(), message)),
+        }
+        drop(state);
+        self.readable.notify_one();
+        Ok(evicted)
+    }
+
+    // Like `try_push`, but wait for room in a full blocking mailbox
+    async fn push(&self, mut message: Message<M>, priority: bool) -> Result<Option<M>, Message<M>> {
+        loop {
+            let writable = self.writable.notified();
+            tokio::pin!(writable);
+            writable.as_mut().enable();
+            match self.try_push(message, priority) {
+                Ok(evicted) => return Ok(evicted),
+                Err(TrySendError::Closed(rejected)) => return Err(rejected),
+                Err(TrySendError::Full(rejected)) => message = rejected,
+            }
+            writable.await;
+        }
+    }
+
+    fn pop(&self) -> Option<Message<M>> {
+        let message = self.state.lock().unwrap().pop(self.aging);
+        if message.is_some() {
+            self.writable.notify_waiters();
+        }
+        message
+    }
+
+    fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.closed.notify_waiters();
+        self.writable.notify_waiters();
+    }
+}
+
+impl<M> Queue<M> {
+    // Queue a message, or hand it back if the mailbox is closed or, when it
+    // blocks, full. Returns the regular message pushed out to make room, if
+    // any.
+    fn try_push(&self, message: Message<M>) -> Result<Option<M>, TrySendError<Message<M>>> {
+        let mut state = self.state.lock().unwrap();
+        let refused = matches!(message, Message::Regular(_)) && self.closing.load(Ordering::SeqCst);
+        if state.closed || refused {
+            return Err(TrySendError::Closed(message));
+        }
+        let mut evicted = None;
+        if matches!(message, Message::Regular(_)) && state.queue.len() >= self.capacity {
+            if self.policy == OverflowPolicy::Block {
+                return Err(TrySendError::Full(message));
+            }
+            let oldest = state
+                .queue
+                .iter()
+                .position(|queued| matches!(queued, Message::Regular(_)));
+            if let Some(Message::Regular(oldest)) = oldest.and_then(|i| state.queue.remove(i)) {
+                self.dropped.fetch_add(1, Ordering::SeqCst);
+                evicted = Some(oldest);
+            }
+        }
+        state.queue.push_back(message);
+        drop(state);
+        self.readable.notify_one();
+        Ok(evicted)
+    }
+
+    // Like `try_push`, but wait for room in a full blocking mailbox
+    async fn push(&self, mut message: Message<M>) -> Result<Option<M>, Message<M>> {
+        loop {
+            let writable = self.writable.notified();
+            tokio::pin!(writable);
+            writable.as_mut().enable();
+            match self.try_push(message) {
+                Ok(evicted) => return Ok(evicted),
+                Err(TrySendError::Closed(rejected)) => return Err(rejected),
+                Err(TrySendError::Full(rejected)) => message = rejected,
+            }
+            writable.await;
+        }
+    }
+
+    fn pop(&self) -> Option<Message<M>> {
+        let message = self.state.lock().unwrap().queue.pop_front();
+        if message.is_some() {
+            self.writable.notify_waiters();
+        }
+        message
+    }
+
+    fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.closed.notify_waiters();
+        self.writable.notify_waiters();
+    }
+}
+
+// The sending half of an actor's mailbox, held by its `ActorRef`s
+pub(crate) enum MailboxSender<M> {
+    Queue(Arc<Queue<M>>),
+    Lanes(Arc<Lanes<M>>),
+}
+
+impl<M> MailboxSender<M> {
+    // Queue a message, waiting for room if the mailbox blocks. Returns the
+    // message evicted to make room, if any.
+    pub(crate) async fn send(
+        &self,
+        message: Message<M>,
+    ) -> Result<Option<M>, SendError<Message<M>>> {
+        match self {
+            MailboxSender::Queue(queue) => queue.push(message).await.map_err(SendError),
+            MailboxSender::Lanes(lanes) => lanes.push(message, false).await.map_err(SendError),
+        }
+    }
+
+    // Like `send`, but on the priority lane if the mailbox has one
+    pub(crate) async fn send_priority(
+        &self,
+        message: Message<M>,
+    ) -> Result<Option<M>, SendError<Message<M>>> {
+        match self {
+            MailboxSender::Lanes(lanes) => lanes.push(message, true).await.map_err(SendError),
+            _ => self.send(message).await,
+        }
+    }
+
+    pub(crate) fn try_send(
+        &self,
+        message: Message<M>,
+    ) -> Result<Option<M>, TrySendError<Message<M>>> {
+        match self {
+            MailboxSender::Queue(queue) => queue.try_push(message),
+            MailboxSender::Lanes(lanes) => lanes.try_push(message, false),
+        }
+    }
+
+    // Number of messages waiting
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            MailboxSender::Queue(queue) => {
+                let state = queue.state.lock().unwrap();
+                state.queue.len().min(queue.capacity)
+            }
+            MailboxSender::Lanes(lanes) => {
+                let state = lanes.state.lock().unwrap();
+                state.len().min(lanes.capacity)
+            }
+        }
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        match self {
+            MailboxSender::Queue(queue) => queue.capacity,
+            MailboxSender::Lanes(lanes) => lanes.capacity,
+        }
+    }
+
+    // Refuse regular messages, as `Closed`, or accept them again. Senders
+    // waiting for room are woken up to be refused.
+    pub(crate) fn set_closing(&self, closing: bool) {
+        let (flag, writable) = match self {
+            MailboxSender::Queue(queue) => (&queue.closing, &queue.writable),
+            MailboxSender::Lanes(lanes) => (&lanes.closing, &lanes.writable),
+        };
+        flag.store(closing, Ordering::SeqCst);
+        writable.notify_waiters();
+    }
+
+    pub(crate) fn is_closing(&self) -> bool {
+        match self {
+            MailboxSender::Queue(queue) => queue.closing.load(Ordering::SeqCst),
+            MailboxSender::Lanes(lanes) => lanes.closing.load(Ordering::SeqCst),
+        }
+    }
+
+    // Messages discarded to make room, always 0 for a blocking mailbox
+    pub(crate) fn dropped(&self) -> u64 {
+        match self {
+            MailboxSender::Queue(queue) => queue.dropped.load(Ordering::SeqCst),
+            MailboxSender::Lanes(lanes) => lanes.dropped.load(Ordering::SeqCst),
+        }
+    }
+
+    // Wait until the receiving half is gone
+    pub(crate) async fn closed(&self) {
+        match self {
+            MailboxSender::Queue(queue) => loop {
+                let closed = queue.closed.notified();
+                if queue.state.lock().unwrap().closed {
+                    return;
+                }
+                closed.await;
+            },
+            MailboxSender::Lanes(lanes) => loop {
+                let closed = lanes.closed.notified();
+                if lanes.state.lock().unwrap().closed {
+                    return;
+                }
+                closed.await;
+            },
+        }
+    }
+}
+
+impl<M> Clone for MailboxSender<M> {
+    fn clone(&self) -> Self {
+        match self {
+            MailboxSender::Queue(queue) => {
+                queue.senders.fetch_add(1, Ordering::SeqCst);
+                MailboxSender::Queue(Arc::clone(queue))
+            }
+            MailboxSender::Lanes(lanes) => {
+                lanes.senders.fetch_add(1, Ordering::SeqCst);
+                MailboxSender::Lanes(Arc::clone(lanes))
+            }
+        }
+    }
+}
+
+impl<M> Drop for MailboxSender<M> {
+    fn drop(&mut self) {
+        match self {
+            MailboxSender::Queue(queue) => {
+                if queue.senders.fetch_sub(1, Ordering::SeqCst) == 1 {
+                    queue.readable.notify_one();
+                }
+            }
+            MailboxSender::Lanes(lanes) => {
+                if lanes.senders.fetch_sub(1, Ordering::SeqCst) == 1 {
+                    lanes.readable.notify_one();
+                }
+            }
+        }
+    }
+}
+
+impl<M> fmt::Debug for MailboxSender<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MailboxSender::Queue(queue) => f
+                .debug_struct("Mailbox")
+                .field("capacity", &queue.capacity)
+                .field("policy", &queue.policy)
+                .field("dropped", &queue.dropped.load(Ordering::SeqCst))
+                .finish(),
+            MailboxSender::Lanes(lanes) => f
+                .debug_struct("PriorityMailbox")
+                .field("capacity", &lanes.capacity)
+                .field("policy", &lanes.policy)
+                .field("aging", &lanes.aging)
+                .field("dropped", &lanes.dropped.load(Ordering::SeqCst))
+                .finish(),
+        }
+    }
+}
+
+// The receiving half of an actor's mailbox, owned by the actor's task
+pub(crate) enum MailboxReceiver<M> {
+    Queue(Arc<Queue<M>>),
+    Lanes(Arc<Lanes<M>>),
+}
+
+// Takes the queued regular messages out of a mailbox, without its receiver
+pub(crate) enum MailboxDrain<M> {
+    Queue(Arc<Queue<M>>),
+    Lanes(Arc<Lanes<M>>),
+}
+
+impl<M> MailboxDrain<M> {
+    // Remove and return every queued regular message, keeping the others
+    pub(crate) fn drain(&self) -> Vec<M> {
+        let mut drained = Vec::new();
+        let mut keep = |message, kept: &mut VecDeque<Message<M>>| match message {
+            Message::Regular(message) => drained.push(message),
+            other => kept.push_back(other),
+        };
+        match self {
+            MailboxDrain::Queue(queue) => {
+                let mut state = queue.state.lock().unwrap();
+                let mut kept = VecDeque::new();
+                for message in state.queue.drain(..) {
+                    keep(message, &mut kept);
+                }
+                state.queue = kept;
+                drop(state);
+                queue.writable.notify_waiters();
+            }
+            MailboxDrain::Lanes(lanes) => {
+                let mut state = lanes.state.lock().unwrap();
+                let LanesState {
+                    regular, priority, ..
+                } = &mut *state;
+                drained.extend(priority.drain(..));
+                let mut kept = VecDeque::new();
+                for (queued, message) in regular.drain(..) {
+                    match message {
+                        Message::Regular(message) => drained.push(message),
+                        other => kept.push_back((queued, other)),
+                    }
+                }
+                *regular = kept;
+                drop(state);
+                lanes.writable.notify_waiters();
+            }
+        }
+        drained
+    }
+}
+
+impl<M: Clone> MailboxDrain<M> {
+    // Copy up to `max` queued regular messages, oldest first, leaving them queued
+    pub(crate) fn peek(&self, max: usize) -> Vec<M> {
+        let regular = |message: &Message<M>| match message {
+            Message::Regular(message) => Some(message.clone()),
+            _ => None,
+        };
+        match self {
+            MailboxDrain::Queue(queue) => {
+                let state = queue.state.lock().unwrap();
+                state.queue.iter().filter_map(regular).take(max).collect()
+            }
+            MailboxDrain::Lanes(lanes) => {
+                let state = lanes.state.lock().unwrap();
+                let regular = state
+                    .regular
+                    .iter()
+                    .filter_map(|(_, queued)| regular(queued));
+                state
+                    .priority
+                    .iter()
+                    .cloned()
+                    .chain(regular)
+                    .take(max)
+                    .collect()
+            }
+        }
+    }
+}
+
+impl<M> Clone for MailboxDrain<M> {
+    fn clone(&self) -> Self {
+        match self {
+            MailboxDrain::Queue(queue) => MailboxDrain::Queue(Arc::clone(queue)),
+            MailboxDrain::Lanes(lanes) => MailboxDrain::Lanes(Arc::clone(lanes)),
+        }
+    }
+}
+
+impl<M> MailboxReceiver<M> {
+    pub(crate) fn drain_handle(&self) -> MailboxDrain<M> {
+        match self {
+            MailboxReceiver::Queue(queue) => MailboxDrain::Queue(Arc::clone(queue)),
+            MailboxReceiver::Lanes(lanes) => MailboxDrain::Lanes(Arc::clone(lanes)),
+        }
+    }
+
+    // The next message, or `None` once the mailbox is closed and empty, or
+    // every sender is gone
+    pub(crate) async fn recv(&mut self) -> Option<Message<M>> {
+        match self {
+            MailboxReceiver::Queue(queue) => loop {
+                let readable = queue.readable.notified();
+                if let Some(message) = queue.pop() {
+                    return Some(message);
+                }
+                {
+                    let state = queue.state.lock().unwrap();
+                    if state.closed || queue.senders.load(Ordering::SeqCst) == 0 {
+                        return None;
+                    }
+                }
+                readable.await;
+            },
+            MailboxReceiver::Lanes(lanes) => loop {
+                let readable = lanes.readable.notified();
+                if let Some(message) = lanes.pop() {
+                    return Some(message);
+                }
+                {
+                    let state = lanes.state.lock().unwrap();
+                    if state.closed || lanes.senders.load(Ordering::SeqCst) == 0 {
+                        return None;
+                    }
+                }
+                readable.await;
+            },
+        }
+    }
+
+    // Gather up to `max` regular messages starting with `first`, waiting at most
+    // `linger` for more to arrive. A `Shutdown` ends the batch early and is
+    // handed back to be handled after it.
+    pub(crate) async fn recv_batch(
+        &mut self,
+        first: M,
+        max: usize,
+        linger: Duration,
+        cancel: &CancellationToken,
+    ) -> (Vec<M>, Option<Message<M>>) {
+        let mut batch = vec![first];
+        let deadline = Instant::now() + linger;
+        while batch.len() < max {
+            let next = match self.try_recv() {
+                Some(message) => Some(message),
+                None => tokio::select! {
+                    message = self.recv() => message,
+                    _ = sleep_until(deadline) => break,
+                    _ = cancel.cancelled() => break,
+                },
+            };
+            match next {
+                Some(Message::Regular(message)) => batch.push(message),
+                Some(shutdown) => return (batch, Some(shutdown)),
+                None => break,
+            }
+        }
+        (batch, None)
+    }
+
+    pub(crate) fn try_recv(&mut self) -> Option<Message<M>> {
+        match self {
+            MailboxReceiver::Queue(queue) => queue.pop(),
+            MailboxReceiver::Lanes(lanes) => lanes.pop(),
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        match self {
+            MailboxReceiver::Queue(queue) => queue.state.lock().unwrap().queue.is_empty(),
+            MailboxReceiver::Lanes(lanes) => lanes.state.lock().unwrap().len() == 0,
+        }
+    }
+
+    // Refuse new messages, keeping the queued ones for `try_recv`
+    pub(crate) fn close(&mut self) {
+        match self {
+            MailboxReceiver::Queue(queue) => queue.close(),
+            MailboxReceiver::Lanes(lanes) => lanes.close(),
+        }
+    }
+}
+
+impl<M> Drop for MailboxReceiver<M> {
+    fn drop(&mut self) {
+        match self {
+            MailboxReceiver::Queue(queue) => queue.close(),
+            MailboxReceiver::Lanes(lanes) => lanes.close(),
+        }
+    }
+}
@@ -0,0 +1,2795 @@
+// src/actor_system/mod.rs
+
+//! # Asynchronous Actor System
+//!
+//! The `actor` module provides an asynchronous actor system.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use astra::actor_system::{Actor, ActorSystem, Message};
+//! use async_trait::async_trait;
+//! use std::error::Error;
+//!
+//! struct SimpleActor;
+//!
+//! #[async_trait]
+//! impl Actor for SimpleActor {
+//!     type Message = String;
+//!     type Error = String;
+//!
+//!     async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error> {
+//!         match message {
+//!             Message::Regular(msg) => {
+//!                 println!("Received message: {}", msg);
+//!                 Ok(())
+//!             }
+//!             Message::Shutdown => {
+//!                 println!("Shutting down SimpleActor.");
+//!                 Ok(())
+//!             }
+//!             // Only delivered to actors that opt in
+//!             Message::System(_) => Ok(()),
+//!         }
+//!     }
+//! }
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn Error>> {
+//!     let mut system = ActorSystem::new();
+//!     system.add_actor("simple_actor".to_string(), SimpleActor);
+//!     system.send_message("simple_actor", "Hello, actor!".to_string()).await?;
+//!     system.shutdown().await;
+//!     Ok(())
+//! }
+//! ```
+//!
+//! ## Tracing
+//!
+//! With the `tracing` feature, each delivery to an actor is handled inside a
+//! `message` span, so whatever the actor logs through `tracing` while handling
+//! it is tagged with the span's fields:
+//!
+//! - `actor`: the name of the actor;
+//! - `message_id`: the number of the delivery, counting from 1 per actor. A
+//!   batch is one delivery.
+
+use crate::backends::storage::StorageBackend;
+use crate::clock::{self, Clock, TokioClock};
+use crate::event_bus::EventBus;
+use crate::logging::{LogLevel, Logger};
+use crate::supervision::{SupervisionStrategy, Supervisor};
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use futures::{Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tokio::runtime::Handle;
+use tokio::sync::mpsc;
+use tokio::sync::{oneshot, watch, Notify};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+
+mod aggregator;
+mod autoscale;
+mod checkpoint;
+mod context;
+mod dead_letters;
+mod dedup;
+mod federation;
+mod high_water;
+mod ids;
+mod inspect;
+mod mailbox;
+mod persist;
+mod persistent_scheduler;
+mod pipe;
+mod rate_limit;
+mod router;
+mod runtime;
+mod scheduler;
+mod timers;
+mod topology;
+
+pub use aggregator::{AggregatorActor, Window};
+pub use autoscale::{Autoscaler, PoolHandle, ScaleAction, ScalingPolicy};
+pub use checkpoint::{Checkpoint, CheckpointToken};
+pub use context::Context;
+pub use dead_letters::{DeadLetter, DeadLetterEvent, ReprocessReport};
+pub use dedup::DedupActor;
+pub use federation::Federation;
+pub use high_water::HighWaterEvent;
+pub use ids::{IdGenerator, SequentialGen, UuidGen};
+pub use inspect::{Debuggable, DebuggableActor};
+pub use mailbox::OverflowPolicy;
+pub use persist::{from_persist_json, to_persist_json, Persistent, PersistentActor};
+pub use persistent_scheduler::{PendingTimer, PersistentScheduler};
+pub use pipe::PipeHandle;
+pub use rate_limit::RateLimitPolicy;
+pub use router::{ConsistentHashRouter, Router};
+pub use runtime::{RuntimeConfig, DEFAULT_RUNTIME_THREAD_NAME};
+pub use scheduler::DEFAULT_ACTOR_WEIGHT;
+pub use timers::{TimerHandle, TimerInfo, TimerKind};
+pub use topology::{ActorDescription, ActorFactories, SystemSnapshot};
+
+use dead_letters::DeadLetterQueue;
+use high_water::HighWaterMark;
+use mailbox::{MailboxDrain, MailboxReceiver, MailboxSender};
+use rate_limit::TokenBucket;
+use runtime::OwnedRuntime;
+use scheduler::PooledScheduler;
+use timers::TimerRegistry;
+
+/// The `Actor` trait defines the interface for any actor within the actor system.
+/// Implementors of this trait are responsible for processing messages and managing their resources.
+#[async_trait]
+pub trait Actor {
+    /// The type of messages the actor can receive.
+    type Message: std::fmt::Debug;
+
+    /// The type of errors that can occur when processing a message.
+    type Error: std::fmt::Debug;
+
+    /// Processes a message. Implementors should define the logic for handling different messages here.
+    async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error>;
+
+    /// Processes a message with access to the actor's `Context`, e.g. to spawn
+    /// children. This is what the system calls; the default ignores the context
+    /// and hands the message to `receive`. Batches go to `receive_batch` instead.
+    async fn receive_with_context(
+        &mut self,
+        context: &Context<Self::Message, Self::Error>,
+        message: Message<Self::Message>,
+    ) -> Result<(), Self::Error>
+    where
+        Self::Message: Send,
+        Self::Error: Send,
+    {
+        let _ = context;
+        self.receive(message).await
+    }
+
+    /// Processes a group of regular messages, delivered instead of single ones
+    /// to actors added with `ActorOptions::with_batching`. Override it to handle
+    /// them together, e.g. with one bulk insert. The default hands each message to
+    /// `receive` in order and returns the first error, after the whole batch.
+    async fn receive_batch(&mut self, messages: Vec<Self::Message>) -> Result<(), Self::Error>
+    where
+        Self::Message: Send,
+        Self::Error: Send,
+    {
+        let mut result = Ok(());
+        for message in messages {
+            let handled = self.receive(Message::Regular(message)).await;
+            if result.is_ok() {
+                result = handled;
+            }
+        }
+        result
+    }
+
+    /// Cleans up resources used by the actor. This method is called when the actor system shuts down.
+    async fn cleanup(&mut self) {
+        // Default cleanup implementation
+    }
+
+    /// Cleans up like `cleanup`, but reports failure, which `ActorSystem::shutdown`
+    /// lists in its `ShutdownReport`. This is what the system calls; the default
+    /// runs `cleanup` and reports success. Override this instead of `cleanup` when
+    /// cleaning up can fail.
+    async fn try_cleanup(&mut self) -> Result<(), Self::Error>
+    where
+        Self::Error: Send,
+    {
+        self.cleanup().await;
+        Ok(())
+    }
+
+    /// Whether the actor also wants the `SystemMessage`s its task handles, as
+    /// `Message::System`. They are handled by the task either way; `false` by
+    /// default.
+    fn receives_system_messages(&self) -> bool {
+        false
+    }
+
+    /// Reports the actor's state to `ActorSystem::inspect`, `None` by default.
+    /// Rather than overriding this, implement `Debuggable` and add the actor with
+    /// `ActorSystem::add_debuggable_actor`.
+    fn inspect_state(&self) -> Option<String> {
+        None
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Message<M> {
+    Regular(M),
+    Shutdown,
+    /// A framework control message, handled by the actor's task. Only actors whose
+    /// `receives_system_messages` returns `true` see it.
+    System(SystemMessage),
+}
+
+/// Control messages the actor's task handles itself, queued in the mailbox
+/// like any other message.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum SystemMessage {
+    /// Answered once the task reaches it, showing the actor is alive and has
+    /// worked through what was queued before; see `ActorRef::ping`.
+    Ping(Pong),
+}
+
+/// The reply to a `SystemMessage::Ping`. Clones answer the same ping; the first
+/// answer counts.
+#[derive(Debug, Clone)]
+pub struct Pong {
+    reply: Arc<Mutex<Option<oneshot::Sender<()>>>>,
+}
+
+impl Pong {
+    fn new() -> (Self, oneshot::Receiver<()>) {
+        let (reply, answered) = oneshot::channel();
+        let pong = Pong {
+            reply: Arc::new(Mutex::new(Some(reply))),
+        };
+        (pong, answered)
+    }
+
+    fn answer(&self) {
+        if let Some(reply) = self.reply.lock().unwrap().take() {
+            let _ = reply.send(());
+        }
+    }
+}
+
+/// A type-erased message, so actors handling different payload types can live in
+/// the same `ActorSystem<AnyMessage>`.
+///
+/// The price is that message types are only checked at runtime: actors added with
+/// `ActorSystem::add_typed_actor` declare the payload type they expect, and
+/// `send_message` rejects an `AnyMessage` carrying anything else instead of the
+/// compiler catching the mistake. Stick to a regular `ActorSystem<M>` when all
+/// actors share one message type.
+pub struct AnyMessage {
+    payload: Box<dyn Any + Send>,
+    type_name: &'static str,
+}
+
+impl AnyMessage {
+    /// Wraps a payload into a type-erased message.
+    pub fn new<T: Any + Send>(payload: T) -> Self {
+        AnyMessage {
+            payload: Box::new(payload),
+            type_name: std::any::type_name::<T>(),
+        }
+    }
+
+    /// Returns true if the payload is a `T`.
+    pub fn is<T: Any>(&self) -> bool {
+        self.payload.is::<T>()
+    }
+
+    /// Returns a reference to the payload if it is a `T`.
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.payload.downcast_ref::<T>()
+    }
+
+    /// Unwraps the payload, giving the message back if it isn't a `T`.
+    pub fn downcast<T: Any>(self) -> Result<T, AnyMessage> {
+        let type_name = self.type_name;
+        self.payload
+            .downcast::<T>()
+            .map(|payload| *payload)
+            .map_err(|payload| AnyMessage { payload, type_name })
+    }
+
+    /// The type name of the payload, for diagnostics.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+}
+
+impl fmt::Debug for AnyMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "AnyMessage({})", self.type_name)
+    }
+}
+
+/// Adapts an actor with a concrete message type to `AnyMessage`, downcasting each
+/// message before handing it over. Usually created through `add_typed_actor`.
+pub struct TypedActor<A> {
+    actor: A,
+}
+
+impl<A> TypedActor<A> {
+    pub fn new(actor: A) -> Self {
+        TypedActor { actor }
+    }
+}
+
+#[async_trait]
+impl<A> Actor for TypedActor<A>
+where
+    A: Actor + Send,
+    A::Message: Any + Send,
+    A::Error: From<String>,
+{
+    type Message = AnyMessage;
+    type Error = A::Error;
+
+    async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error> {
+        match message {
+            Message::Regular(msg) => match msg.downcast::<A::Message>() {
+                Ok(msg) => self.actor.receive(Message::Regular(msg)).await,
+                Err(msg) => Err(format!(
+                    "Expected message of type {}, got {}",
+                    std::any::type_name::<A::Message>(),
+                    msg.type_name()
+                )
+                .into()),
+            },
+            Message::Shutdown => self.actor.receive(Message::Shutdown).await,
+            Message::System(message) => self.actor.receive(Message::System(message)).await,
+        }
+    }
+
+    async fn cleanup(&mut self) {
+        self.actor.cleanup().await;
+    }
+
+    async fn try_cleanup(&mut self) -> Result<(), Self::Error>
+    where
+        Self::Error: Send,
+    {
+        self.actor.try_cleanup().await
+    }
+
+    fn receives_system_messages(&self) -> bool {
+        self.actor.receives_system_messages()
+    }
+
+    fn inspect_state(&self) -> Option<String> {
+        self.actor.inspect_state()
+    }
+}
+
+// The behavior run by an actor's task, boxed so it can be swapped at runtime
+type BoxedActor<M, E> = Box<dyn Actor<Message = M, Error = E> + Send>;
+
+// Builds a fresh instance of an actor the supervisor restarts, along with how
+// reloading its state went
+type RestartFactory<M, E> =
+    Arc<dyn Fn() -> BoxFuture<'static, (BoxedActor<M, E>, Result<(), String>)> + Send + Sync>;
+
+// What the actor task hands to the actor next
+enum Delivery<M> {
+    One(Message<M>),
+    Batch(Vec<M>),
+}
+
+impl<M> Delivery<M> {
+    // How many regular messages it holds
+    fn regular(&self) -> usize {
+        match self {
+            Delivery::One(Message::Regular(_)) => 1,
+            Delivery::One(_) => 0,
+            Delivery::Batch(messages) => messages.len(),
+        }
+    }
+}
+
+// Optional per-actor check run before a message is enqueued
+type MessageCheck<M> = Arc<dyn Fn(&M) -> Result<(), String> + Send + Sync>;
+
+// Called with the actor's name and a message sent to its full mailbox
+type OverflowHook<M> = Arc<dyn Fn(&str, &M) + Send + Sync>;
+
+/// Reports how many bytes a message's payload takes, so oversized messages can be
+/// rejected by `ActorSystem::with_max_message_size`.
+pub trait MessageSize {
+    fn message_size(&self) -> usize;
+}
+
+impl MessageSize for String {
+    fn message_size(&self) -> usize {
+        self.len()
+    }
+}
+
+impl MessageSize for Vec<u8> {
+    fn message_size(&self) -> usize {
+        self.len()
+    }
+}
+
+struct SizeLimit<M> {
+    max: usize,
+    measure: fn(&M) -> usize,
+}
+
+// State shared between an `ActorSystem` and every `ActorRef` it hands out, so
+// system-wide settings apply no matter how a message is sent
+struct SystemShared<M> {
+    size_limit: RwLock<Option<SizeLimit<M>>>,
+    dead_letters: Mutex<Option<DeadLetterQueue<M>>>,
+    last_checkpoint_id: AtomicU64,
+    // Cancelling it stops every task the system spawned, which `tasks` keeps track of
+    cancel: CancellationToken,
+    tasks: TaskTracker,
+    // The subset of `tasks` running actors
+    actor_tasks: TaskTracker,
+    timers: Arc<TimerRegistry>,
+    // Set by `quiesce`, new messages are refused at the system's entry points
+    quiescing: AtomicBool,
+    // Cancelled once `shutdown` has stopped the actors, or along with `cancel`
+    shut_down: CancellationToken,
+    // Cancelled by `request_shutdown` and by supervisors escalating to shutdown
+    shutdown_requested: CancellationToken,
+    // Regular messages enqueued but not handled yet, across all actors
+    pending: AtomicUsize,
+    // Woken whenever `pending` drops to zero
+    drained: Notify,
+    // The most `pending` may reach, `usize::MAX` for no limit
+    pending_limit: AtomicUsize,
+    // Woken whenever `pending` drops while there is a limit
+    room: Notify,
+    logger: RwLock<Option<SystemLogger>>,
+    events: EventBus,
+    // Where tasks are spawned, when not on the ambient runtime
+    runtime: RwLock<Option<Handle>>,
+    // Set only on the system that built the runtime, which shuts it down
+    owned_runtime: Mutex<OwnedRuntime>,
+}
+
+type SystemLogger = Arc<dyn Logger + Send + Sync>;
+
+impl<M> SystemShared<M> {
+    fn new(cancel: CancellationToken) -> Self {
+        let shut_down = cancel.child_token();
+        SystemShared {
+            size_limit: RwLock::new(None),
+            dead_letters: Mutex::new(None),
+            last_checkpoint_id: AtomicU64::new(0),
+            cancel,
+            tasks: TaskTracker::new(),
+            actor_tasks: TaskTracker::new(),
+            timers: Arc::new(TimerRegistry::default()),
+            quiescing: AtomicBool::new(false),
+            shut_down,
+            shutdown_requested: CancellationToken::new(),
+            pending: AtomicUsize::new(0),
+            drained: Notify::new(),
+            pending_limit: AtomicUsize::new(usize::MAX),
+            room: Notify::new(),
+            logger: RwLock::new(None),
+            events: EventBus::new(),
+            runtime: RwLock::new(None),
+            owned_runtime: Mutex::new(OwnedRuntime::default()),
+        }
+    }
+
+    // Spawn a task the system keeps track of, on its runtime
+    fn spawn<F>(&self, task: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        match self.runtime.read().unwrap().as_ref() {
+            Some(runtime) => self.tasks.spawn_on(task, runtime),
+            None => self.tasks.spawn(task),
+        }
+    }
+
+    // Hand a line to the system's logger, if any, without waiting for it
+    fn log(&self, level: LogLevel, message: String) {
+        if let Some(logger) = self.logger.read().unwrap().clone() {
+            self.spawn(async move { logger.log(level, &message).await });
+        }
+    }
+
+    // Count a message about to be enqueued, unless the system is at its limit;
+    // undone with `handled` if it wasn't enqueued after all
+    fn try_enqueue(&self, actor: &str) -> Result<(), SendError> {
+        let limit = self.pending_limit.load(Ordering::SeqCst);
+        self.pending
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |pending| {
+                (pending < limit).then_some(pending + 1)
+            })
+            .map(|_| ())
+            .map_err(|_| SendError::SystemFull {
+                actor: actor.to_string(),
+                limit,
+            })
+    }
+
+    // Like `try_enqueue`, but waits for room under the limit
+    async fn enqueue(&self, actor: &str) {
+        loop {
+            let room = self.room.notified();
+            tokio::pin!(room);
+            // Registered before checking, so a message handled in between wakes it
+            room.as_mut().enable();
+            if self.try_enqueue(actor).is_ok() {
+                return;
+            }
+            room.await;
+        }
+    }
+
+    fn handled(&self) {
+        self.handled_many(1);
+    }
+
+    fn handled_many(&self, count: usize) {
+        if count == 0 {
+            return;
+        }
+        if self.pending.fetch_sub(count, Ordering::SeqCst) == count {
+            self.drained.notify_waiters();
+        }
+        if self.pending_limit.load(Ordering::SeqCst) != usize::MAX {
+            self.room.notify_waiters();
+        }
+    }
+
+    // Announce an undeliverable message, and keep it if the dead-letter queue
+    // is enabled
+    fn dead_letter(&self, target: &str, message: M, error: &SendError)
+    where
+        M: fmt::Debug,
+    {
+        self.events.publish(DeadLetterEvent {
+            target: target.to_string(),
+            message: format!("{:?}", message),
+            reason: error.to_string(),
+        });
+        let save = self
+            .dead_letters
+            .lock()
+            .unwrap()
+            .as_mut()
+            .and_then(|queue| {
+                queue.push(DeadLetter {
+                    target: target.to_string(),
+                    message,
+                    reason: error.to_string(),
+                });
+                queue.save()
+            });
+        if let Some(save) = save {
+            let logger = self.logger.read().unwrap().clone();
+            self.spawn(async move {
+                if let (Err(e), Some(logger)) = (save.run().await, logger) {
+                    logger.log(LogLevel::Error, &e).await;
+                }
+            });
+        }
+    }
+
+    fn check_size(&self, actor: &str, message: &M) -> Result<(), SendError> {
+        if let Some(limit) = self.size_limit.read().unwrap().as_ref() {
+            let size = (limit.measure)(message);
+            if size > limit.max {
+                return Err(SendError::TooLarge {
+                    actor: actor.to_string(),
+                    size,
+                    max: limit.max,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A cloneable handle to a single actor, for sending it messages directly
+/// (e.g. from another actor) without going through the `ActorSystem`.
+pub struct ActorRef<M, E = String> {
+    name: String,
+    // The type the actor was registered with, for `ActorSystem::describe`
+    actor_type: &'static str,
+    sender: MailboxSender<M>,
+    // Takes queued messages back out, see `drain_mailbox`
+    drain: MailboxDrain<M>,
+    // Hands a replacement behavior to the actor's task
+    behavior: mpsc::UnboundedSender<BoxedActor<M, E>>,
+    // Asks the actor's task for `inspect_state`
+    inspections: mpsc::UnboundedSender<oneshot::Sender<Option<String>>>,
+    check: Option<MessageCheck<M>>,
+    // Only set for rate limits with the `Reject` policy, shared by all clones
+    rate_limit: Option<Arc<Mutex<TokenBucket>>>,
+    high_water: Option<Arc<HighWaterMark>>,
+    // Set by `ActorSystem::on_overflow`, shared by all clones
+    overflow_hook: Arc<RwLock<Option<OverflowHook<M>>>>,
+    stop_mode: StopMode,
+    // Stops the actor's task, with or without `StopMode::Immediate`
+    stop: CancellationToken,
+    // Cuts short the message being handled: `stop` itself under
+    // `StopMode::Immediate`, otherwise only cancelled by `stop_within`
+    abort: CancellationToken,
+    // Set by the actor's task once it has cleaned up
+    stopped: watch::Receiver<Option<Stopped>>,
+    shared: Arc<SystemShared<M>>,
+}
+
+impl<M: Send + std::fmt::Debug, E> ActorRef<M, E> {
+    /// The name the actor was registered under.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Sends a message, waiting for mailbox space if needed, and for room under
+    /// the system's `with_global_mailbox_limit` if it has one.
+    pub async fn send(&self, message: M) -> Result<(), String> {
+        self.send_on_lane(message, false).await
+    }
+
+    /// Like `send`, but on the priority lane of the mailbox, so the actor handles
+    /// the message before the regular ones (see
+    /// `ActorOptions::with_priority_lane`). Without a priority lane it is a
+    /// plain `send`.
+    pub async fn send_priority(&self, message: M) -> Result<(), String> {
+        self.send_on_lane(message, true).await
+    }
+
+    async fn send_on_lane(&self, message: M, priority: bool) -> Result<(), String> {
+        self.deliver(message, priority)
+            .await
+            .map_err(|(e, message)| {
+                if let SendError::Closed(_) | SendError::Closing(_) = e {
+                    self.shared.dead_letter(&self.name, message, &e);
+                }
+                e.to_string()
+            })
+    }
+
+    /// Number of messages waiting in the actor's mailbox.
+    pub fn mailbox_depth(&self) -> usize {
+        self.sender.len()
+    }
+
+    /// Number of messages the actor's mailbox holds.
+    pub fn mailbox_capacity(&self) -> usize {
+        self.sender.capacity()
+    }
+
+    /// How many queued messages were discarded to make room for newer ones (see
+    /// `OverflowPolicy::DropOldest`); always 0 for a blocking mailbox.
+    pub fn dropped_messages(&self) -> u64 {
+        self.sender.dropped()
+    }
+
+    /// How many times a send took the mailbox over its high-water mark (see
+    /// `ActorOptions::with_high_water_mark`); always 0 without one.
+    pub fn high_water_crossings(&self) -> u64 {
+        self.high_water
+            .as_ref()
+            .map_or(0, |high_water| high_water.crossings())
+    }
+
+    /// Whether the actor has been told to shut down. A closing actor refuses new
+    /// messages with `SendError::Closing`.
+    pub fn is_closing(&self) -> bool {
+        self.sender.is_closing()
+    }
+
+    // Refuse new messages, or accept them again, without stopping the actor
+    pub(crate) fn set_closing(&self, closing: bool) {
+        self.sender.set_closing(closing);
+    }
+
+    /// Sends a message without waiting, failing with `SendError::MailboxFull`
+    /// when the actor is saturated, or `SendError::SystemFull` when the system is.
+    pub fn try_send(&self, message: M) -> Result<(), SendError> {
+        if let Err(e) = self.accepts(&message) {
+            if let SendError::Closing(_) = e {
+                self.shared.dead_letter(&self.name, message, &e);
+            }
+            return Err(e);
+        }
+        if let Err(e) = self.shared.try_enqueue(&self.name) {
+            self.refund();
+            return Err(e);
+        }
+        self.check_overflow(&message);
+        let sent = self
+            .sender
+            .try_send(Message::Regular(message))
+            .map(|evicted| self.evicted(evicted))
+            .map_err(|e| {
+                self.shared.handled();
+                self.refund();
+                match e {
+                    mpsc::error::TrySendError::Full(_) => SendError::MailboxFull(self.name.clone()),
+                    mpsc::error::TrySendError::Closed(message) => {
+                        let e = self.refused();
+                        if let Message::Regular(message) = message {
+                            self.shared.dead_letter(&self.name, message, &e);
+                        }
+                        e
+                    }
+                }
+            });
+        self.check_high_water();
+        sent
+    }
+
+    /// Sends the actor a `SystemMessage::Ping` and waits for its task to answer,
+    /// i.e. until the actor has handled everything queued before it. Fails with
+    /// `SendError::Closed` if the actor stopped first.
+    pub async fn ping(&self) -> Result<(), SendError> {
+        let (pong, answered) = Pong::new();
+        let closed = || SendError::Closed(self.name.clone());
+        self.sender
+            .send(Message::System(SystemMessage::Ping(pong)))
+            .await
+            .map_err(|_| closed())?;
+        answered.await.map_err(|_| closed())
+    }
+
+    /// Removes and returns the regular messages waiting in the actor's mailbox,
+    /// oldest first, without the actor handling them. A message the actor is
+    /// handling right now is not queued anymore and stays with it; `Shutdown`
+    /// and system messages stay queued.
+    pub fn drain_mailbox(&self) -> Vec<M> {
+        let drained = self.drain.drain();
+        self.shared.handled_many(drained.len());
+        drained
+    }
+
+    /// Copies up to `max` of the regular messages waiting in the actor's
+    /// mailbox, oldest first, leaving them queued: a look at what a stuck or
+    /// overloaded actor is about to handle.
+    pub fn peek_mailbox(&self, max: usize) -> Vec<M>
+    where
+        M: Clone,
+    {
+        self.drain.peek(max)
+    }
+
+    // Waits for the actor's task to finish cleaning up and says how it stopped
+    async fn stopped(&self) -> Stopped {
+        let mut stopped = self.stopped.clone();
+        let outcome = match stopped.wait_for(Option::is_some).await {
+            Ok(outcome) => outcome.clone(),
+            // The task is gone without reporting, e.g. it panicked
+            Err(_) => None,
+        };
+        outcome.unwrap_or(Stopped::Aborted)
+    }
+
+    // How the actor stopped, or `None` if it hasn't yet
+    fn stopped_now(&self) -> Option<Stopped> {
+        self.stopped.borrow().clone()
+    }
+
+    // Stop the actor according to its `StopMode` and wait until it has
+    async fn stop(&self) -> Stopped {
+        if let Some(stopped) = self.stopped_now() {
+            return stopped;
+        }
+        // Refuse new messages from now on, rather than queueing them behind
+        // `Shutdown` where they would never be processed
+        self.set_closing(true);
+        match self.stop_mode {
+            StopMode::Drain => {
+                if let Err(e) = self.sender.send(Message::Shutdown).await {
+                    println!(
+                        "Failed to send shutdown signal to actor {}: {:?}",
+                        self.name, e
+                    );
+                }
+            }
+            StopMode::Immediate => self.stop.cancel(),
+        }
+        // The mailbox closes once the actor's task has exited
+        self.sender.closed().await;
+        self.stopped().await
+    }
+
+    // Like `stop`, but cancel the actor if it hasn't stopped within `timeout`,
+    // e.g. because it is stuck handling a message, and wait for its task to exit
+    async fn stop_within(&self, timeout: Duration) -> Stopped {
+        match tokio::time::timeout(timeout, self.stop()).await {
+            Ok(stopped) => stopped,
+            Err(_) => {
+                self.abort.cancel();
+                self.stop.cancel();
+                self.stopped().await;
+                Stopped::Aborted
+            }
+        }
+    }
+
+    // Enqueue a message, on the priority lane if `priority`, handing it back
+    // with the error if it can't be delivered
+    async fn deliver(&self, message: M, priority: bool) -> Result<(), (SendError, M)> {
+        if let Err(e) = self.accepts(&message) {
+            return Err((e, message));
+        }
+        self.shared.enqueue(&self.name).await;
+        self.check_overflow(&message);
+        let sent = if priority {
+            self.sender.send_priority(Message::Regular(message)).await
+        } else {
+            self.sender.send(Message::Regular(message)).await
+        };
+        let evicted = sent.map_err(|mpsc::error::SendError(message)| {
+            self.shared.handled();
+            self.refund();
+            match message {
+                Message::Regular(message) => (self.refused(), message),
+                _ => unreachable!("only regular messages are delivered"),
+            }
+        })?;
+        self.evicted(evicted);
+        self.check_high_water();
+        Ok(())
+    }
+
+    // A message pushed out of a drop-oldest mailbox will never be handled
+    fn evicted(&self, evicted: Option<M>) {
+        if evicted.is_some() {
+            self.shared.handled();
+        }
+    }
+
+    // Show the overflow hook a message about to meet a full mailbox, before the
+    // overflow policy makes it wait, fail or push out the oldest message
+    fn check_overflow(&self, message: &M) {
+        if self.mailbox_depth() < self.mailbox_capacity() {
+            return;
+        }
+        let hook = self.overflow_hook.read().unwrap().clone();
+        if let Some(hook) = hook {
+            hook(&self.name, message);
+        }
+    }
+
+    // Warn when a send has just taken the mailbox over its high-water mark
+    fn check_high_water(&self) {
+        if let Some(high_water) = &self.high_water {
+            let depth = self.mailbox_depth();
+            if high_water.observe(depth) {
+                self.shared.events.publish(HighWaterEvent {
+                    actor: self.name.clone(),
+                    depth,
+                    capacity: self.mailbox_capacity(),
+                });
+                self.shared.log(
+                    LogLevel::Warn,
+                    format!(
+                        "Mailbox of actor {} is nearly full: {} of {} messages",
+                        self.name,
+                        depth,
+                        self.mailbox_capacity()
+                    ),
+                );
+            }
+        }
+    }
+
+    // Why the mailbox refused a message: it started closing while the message
+    // was on its way, or it is gone
+    fn refused(&self) -> SendError {
+        if self.is_closing() {
+            SendError::Closing(self.name.clone())
+        } else {
+            SendError::Closed(self.name.clone())
+        }
+    }
+
+    // Make sure the actor accepts the message before enqueueing it
+    fn accepts(&self, message: &M) -> Result<(), SendError> {
+        if self.is_closing() {
+            return Err(SendError::Closing(self.name.clone()));
+        }
+        self.shared.check_size(&self.name, message)?;
+        if let Some(check) = &self.check {
+            check(message).map_err(|reason| SendError::Rejected {
+                actor: self.name.clone(),
+                reason,
+            })?;
+        }
+        if let Some(bucket) = &self.rate_limit {
+            if !bucket.lock().unwrap().try_acquire() {
+                return Err(SendError::RateLimited(self.name.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    // Give back the rate-limit token `accepts` took for a message that then
+    // failed to be enqueued
+    fn refund(&self) {
+        if let Some(bucket) = &self.rate_limit {
+            bucket.lock().unwrap().refund();
+        }
+    }
+}
+
+impl<M, E> Clone for ActorRef<M, E> {
+    fn clone(&self) -> Self {
+        ActorRef {
+            name: self.name.clone(),
+            actor_type: self.actor_type,
+            sender: self.sender.clone(),
+            drain: self.drain.clone(),
+            behavior: self.behavior.clone(),
+            inspections: self.inspections.clone(),
+            check: self.check.clone(),
+            rate_limit: self.rate_limit.clone(),
+            high_water: self.high_water.clone(),
+            overflow_hook: Arc::clone(&self.overflow_hook),
+            stop_mode: self.stop_mode,
+            stop: self.stop.clone(),
+            abort: self.abort.clone(),
+            stopped: self.stopped.clone(),
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<M, E> fmt::Debug for ActorRef<M, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ActorRef")
+            .field("name", &self.name)
+            .field("actor_type", &self.actor_type)
+            .field("sender", &self.sender)
+            .field("checked", &self.check.is_some())
+            .field("rate_limited", &self.rate_limit.is_some())
+            .field("closing", &self.sender.is_closing())
+            .finish()
+    }
+}
+
+/// Number of messages an actor's mailbox holds unless configured otherwise.
+pub const DEFAULT_MAILBOX_CAPACITY: usize = 100;
+
+/// How an actor added with `ActorSystem::add_supervised_actor` comes back from
+/// a restart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RestartPolicy {
+    /// Run the actor's `on_restart` hook, e.g. to load its persisted state,
+    /// before the fresh instance handles any message. Off by default, leaving
+    /// the instance as its factory built it.
+    pub reload_state: bool,
+}
+
+/// How `ActorSystem::shutdown` stops an actor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StopMode {
+    /// Handle everything queued, then `Shutdown`, then clean up. For actors that
+    /// must not lose work, such as writers.
+    #[default]
+    Drain,
+    /// Stop right away, abandoning the message being handled and discarding the
+    /// queued ones, then clean up. For actors whose work is cheap to lose.
+    Immediate,
+}
+
+/// How each actor stopped, as returned by `ActorSystem::shutdown`. Every actor
+/// is listed once, in the order it was stopped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShutdownReport {
+    /// Actors that handled what they were meant to and cleaned up.
+    pub stopped_cleanly: Vec<String>,
+    /// Actors whose `Actor::try_cleanup` failed, with the error.
+    pub cleanup_failed: Vec<(String, String)>,
+    /// Actors cut short by cancellation, with their queue left unhandled: those
+    /// with `StopMode::Immediate`, and those that didn't drain within the
+    /// timeout of `shutdown_timeout` or the shutdown timeout of `shutdown`.
+    pub aborted: Vec<String>,
+}
+
+impl ShutdownReport {
+    /// Whether every actor stopped cleanly.
+    pub fn is_clean(&self) -> bool {
+        self.cleanup_failed.is_empty() && self.aborted.is_empty()
+    }
+
+    fn record(&mut self, actor: &str, stopped: Stopped) {
+        match stopped {
+            Stopped::Cleanly => self.stopped_cleanly.push(actor.to_string()),
+            Stopped::CleanupFailed(e) => self.cleanup_failed.push((actor.to_string(), e)),
+            Stopped::Aborted => self.aborted.push(actor.to_string()),
+        }
+    }
+}
+
+// How an actor's task ended, reported by the task once it is done
+#[derive(Debug, Clone)]
+enum Stopped {
+    Cleanly,
+    CleanupFailed(String),
+    Aborted,
+}
+
+/// Per-actor settings for `ActorSystem::add_actor_with_options`.
+#[derive(Debug, Clone)]
+pub struct ActorOptions {
+    mailbox_capacity: usize,
+    rate_limit: Option<(u32, Duration)>,
+    rate_limit_policy: RateLimitPolicy,
+    weight: u32,
+    receive_timeout: Option<Duration>,
+    high_water_mark: Option<u8>,
+    overflow_policy: OverflowPolicy,
+    // How long a regular message may wait behind priority ones, if the mailbox
+    // has a priority lane
+    priority_aging: Option<Duration>,
+    // The largest batch, and how long to wait for it to fill up
+    batching: Option<(usize, Duration)>,
+    stop_mode: StopMode,
+}
+
+impl ActorOptions {
+    pub fn new() -> Self {
+        ActorOptions {
+            mailbox_capacity: DEFAULT_MAILBOX_CAPACITY,
+            rate_limit: None,
+            rate_limit_policy: RateLimitPolicy::default(),
+            weight: DEFAULT_ACTOR_WEIGHT,
+            receive_timeout: None,
+            high_water_mark: None,
+            overflow_policy: OverflowPolicy::default(),
+            priority_aging: None,
+            batching: None,
+            stop_mode: StopMode::default(),
+        }
+    }
+
+    /// Logs a warning through the system's logger (see `ActorSystem::with_logger`)
+    /// whenever a send fills the mailbox to `percent`% of its capacity or more,
+    /// coming from below: an early signal before senders start blocking. `percent`
+    /// is clamped to 1..=100.
+    pub fn with_high_water_mark(mut self, percent: u8) -> Self {
+        self.high_water_mark = Some(percent.clamp(1, 100));
+        self
+    }
+
+    /// Gives up on any `receive` call that takes longer than `timeout`, as a
+    /// safety net for handlers that get stuck. The abandoned call is dropped, the
+    /// timeout is logged and reported to the system's supervisor (see
+    /// `ActorSystem::with_supervisor`) as a failure, and the actor moves on to its
+    /// next message. If the supervisor's strategy is `Restart` and the actor was
+    /// added with `ActorSystem::add_restartable_actor` or `add_supervised_actor`,
+    /// it moves on as a fresh instance.
+    pub fn with_receive_timeout(mut self, timeout: Duration) -> Self {
+        self.receive_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the actor's share of turns in a system created with
+    /// `ActorSystem::with_pooled_scheduler`: under saturation, an actor with weight
+    /// 3 handles three messages for every one handled by an actor with weight 1.
+    /// Has no effect otherwise.
+    pub fn with_weight(mut self, weight: u32) -> Self {
+        self.weight = weight.max(1);
+        self
+    }
+
+    /// Limits the actor to `messages` messages per `window`. What happens to the
+    /// excess depends on the `RateLimitPolicy`, `Reject` by default.
+    pub fn with_rate_limit(mut self, messages: u32, window: Duration) -> Self {
+        self.rate_limit = Some((messages, window));
+        self
+    }
+
+    /// Chooses how messages over the rate limit are handled.
+    pub fn with_rate_limit_policy(mut self, policy: RateLimitPolicy) -> Self {
+        self.rate_limit_policy = policy;
+        self
+    }
+
+    /// Chooses what happens to messages sent to a full mailbox, see
+    /// `OverflowPolicy`.
+    pub fn with_overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.overflow_policy = policy;
+        self
+    }
+
+    /// Gives the mailbox a priority lane for the messages sent with
+    /// `ActorRef::send_priority`, handled before the others. A regular message
+    /// that has waited longer than `aging` is handled next even while priority
+    /// messages are pending, so they can't starve it. See the `mailbox` module.
+    pub fn with_priority_lane(mut self, aging: Duration) -> Self {
+        self.priority_aging = Some(aging);
+        self
+    }
+
+    /// Delivers regular messages to `Actor::receive_batch` in groups of up to
+    /// `max_batch`: once a message arrives, the actor waits up to `linger` for
+    /// more to join it before handling the batch. A `Shutdown` is delivered by
+    /// itself, after the batch it cut short.
+    pub fn with_batching(mut self, max_batch: usize, linger: Duration) -> Self {
+        self.batching = Some((max_batch.max(1), linger));
+        self
+    }
+
+    /// Chooses how `ActorSystem::shutdown` stops the actor, see `StopMode`.
+    pub fn with_stop_mode(mut self, stop_mode: StopMode) -> Self {
+        self.stop_mode = stop_mode;
+        self
+    }
+
+    /// Sets how many messages can wait in the actor's mailbox before senders
+    /// block (`send_message`) or are turned away (`try_send_message`), or with
+    /// `OverflowPolicy::DropOldest`, before the oldest ones are discarded.
+    pub fn with_mailbox_capacity(mut self, capacity: usize) -> Self {
+        self.mailbox_capacity = capacity.max(1);
+        self
+    }
+}
+
+impl Default for ActorOptions {
+    fn default() -> Self {
+        ActorOptions::new()
+    }
+}
+
+/// Why `try_send_message` could not deliver a message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SendError {
+    /// No actor is registered under this name.
+    NotFound(String),
+    /// The actor's mailbox is at capacity.
+    MailboxFull(String),
+    /// The actor has stopped and no longer receives messages.
+    Closed(String),
+    /// The actor was told to shut down and refuses new messages, even though
+    /// it may still be handling the ones queued before.
+    Closing(String),
+    /// The system is quiescing and refuses new messages from outside.
+    Quiescing(String),
+    /// The actor refused the message before it was enqueued.
+    Rejected { actor: String, reason: String },
+    /// The message is larger than the system's `max_message_size`.
+    TooLarge {
+        actor: String,
+        size: usize,
+        max: usize,
+    },
+    /// The actor's rate limit was exceeded.
+    RateLimited(String),
+    /// The system holds as many queued messages as its
+    /// `with_global_mailbox_limit` allows, across all actors.
+    SystemFull { actor: String, limit: usize },
+}
+
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendError::NotFound(name) => write!(f, "Actor {} not found", name),
+            SendError::MailboxFull(name) => write!(f, "Mailbox of actor {} is full", name),
+            SendError::Closed(name) => write!(f, "Actor {} is closed", name),
+            SendError::Closing(name) => write!(f, "Actor {} is shutting down", name),
+            SendError::Quiescing(name) => {
+                write!(
+                    f,
+                    "System is quiescing, actor {} takes no new messages",
+                    name
+                )
+            }
+            SendError::Rejected { actor, reason } => {
+                write!(f, "Actor {} rejected message: {}", actor, reason)
+            }
+            SendError::RateLimited(name) => write!(f, "Actor {} is rate limited", name),
+            SendError::SystemFull { actor, limit } => write!(
+                f,
+                "System holds its limit of {} queued messages, actor {} takes no more",
+                limit, actor
+            ),
+            SendError::TooLarge { actor, size, max } => write!(
+                f,
+                "Message for actor {} is {} bytes, exceeding the {} byte limit",
+                actor, size, max
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SendError {}
+
+type CheckpointParticipant = Arc<tokio::sync::Mutex<dyn Checkpoint>>;
+
+/// How long `run_until_signal` lets actors drain, and `shutdown` waits for each
+/// actor, unless configured otherwise.
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How many responses an `ask_stream` actor may emit ahead of the caller
+/// reading them before its `send` waits.
+pub const ASK_STREAM_CAPACITY: usize = 32;
+
+/// A set of actors sharing a message type `M` and an error type `E`, which
+/// defaults to `String`. Errors returned by the actors' `receive` are logged by
+/// the task running each actor.
+pub struct ActorSystem<M, E = String> {
+    actors: HashMap<String, ActorRef<M, E>>,
+    // Actor names in registration order, so shutdown can stop them in reverse
+    order: Vec<String>,
+    // Actor name to the actors it depends on, which must outlive it at shutdown
+    dependencies: HashMap<String, Vec<String>>,
+    checkpoints: Vec<(String, CheckpointParticipant)>,
+    shutdown_timeout: Duration,
+    // Shares out processing turns when the system runs in pooled mode
+    scheduler: Option<Arc<PooledScheduler>>,
+    supervisor: Option<Arc<Supervisor>>,
+    // Picks the target of `dispatch`
+    router: Option<Arc<dyn Router<M>>>,
+    // The time source of timers
+    clock: Arc<dyn Clock>,
+    // Names the actors of `add_actor_auto`
+    ids: Arc<dyn IdGenerator>,
+    shared: Arc<SystemShared<M>>,
+}
+
+impl<M, E> Clone for ActorSystem<M, E> {
+    fn clone(&self) -> Self {
+        ActorSystem {
+            actors: self.actors.clone(),
+            order: self.order.clone(),
+            dependencies: self.dependencies.clone(),
+            checkpoints: self.checkpoints.clone(),
+            shutdown_timeout: self.shutdown_timeout,
+            scheduler: self.scheduler.clone(),
+            supervisor: self.supervisor.clone(),
+            router: self.router.clone(),
+            clock: Arc::clone(&self.clock),
+            ids: Arc::clone(&self.ids),
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<M, E> fmt::Debug for ActorSystem<M, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ActorSystem")
+            .field("actors", &self.actors)
+            .finish()
+    }
+}
+
+impl<M, E> ActorSystem<M, E>
+where
+    M: Send + 'static + std::fmt::Debug,
+    E: Send + 'static + std::fmt::Debug,
+{
+    pub fn new() -> Self {
+        ActorSystem::with_cancellation_token(CancellationToken::new())
+    }
+
+    /// Creates a system that stops when `parent` is cancelled, e.g. an
+    /// application-wide token cancelled on SIGTERM.
+    pub fn with_cancellation_token(parent: CancellationToken) -> Self {
+        ActorSystem {
+            actors: HashMap::new(),
+            order: Vec::new(),
+            dependencies: HashMap::new(),
+            checkpoints: Vec::new(),
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+            scheduler: None,
+            supervisor: None,
+            router: None,
+            clock: Arc::new(TokioClock),
+            ids: Arc::new(SequentialGen::default()),
+            shared: Arc::new(SystemShared::new(parent.child_token())),
+        }
+    }
+
+    /// Runs the system in pooled mode: at most `workers` actors handle a message at
+    /// any time, with turns shared out by weighted round-robin (see
+    /// `ActorOptions::with_weight`) so a busy actor can't starve the others.
+    /// Applies to actors added after this call.
+    pub fn with_pooled_scheduler(mut self, workers: usize) -> Self {
+        self.scheduler = Some(Arc::new(PooledScheduler::new(workers)));
+        self
+    }
+
+    /// Reports actor failures detected by the system, such as a `receive` that
+    /// exceeded its `ActorOptions::with_receive_timeout`, to `supervisor`, which
+    /// applies its strategy. Applies to actors added after this call. The
+    /// supervisor's events are published on the system's event bus from now on,
+    /// and with `EscalationPolicy::Shutdown` its escalations request the
+    /// system's shutdown (see `request_shutdown`).
+    pub fn with_supervisor(mut self, supervisor: Arc<Supervisor>) -> Self {
+        supervisor.attach_event_bus(&self.shared.events);
+        supervisor.attach_shutdown(&self.shared.shutdown_requested);
+        self.supervisor = Some(supervisor);
+        self
+    }
+
+    /// The bus the system publishes its events on, supervision decisions, dead
+    /// letters and mailbox high-water crossings, see the `event_bus` module.
+    /// Children spawned with `Context::spawn_child` publish on their parent's bus.
+    pub fn event_bus(&self) -> EventBus {
+        self.shared.events.clone()
+    }
+
+    /// Sends the system's own log lines, such as mailbox high-water warnings (see
+    /// `ActorOptions::with_high_water_mark`), to `logger`.
+    pub fn with_logger(self, logger: Arc<dyn Logger + Send + Sync>) -> Self {
+        *self.shared.logger.write().unwrap() = Some(logger);
+        self
+    }
+
+    /// Installs the router `dispatch` uses to pick the actor a message goes to.
+    pub fn with_router(mut self, router: Arc<dyn Router<M>>) -> Self {
+        self.router = Some(router);
+        self
+    }
+
+    /// Sets how `add_actor_auto` names actors, `SequentialGen::default()` unless
+    /// set.
+    pub fn with_id_generator(mut self, ids: Arc<dyn IdGenerator>) -> Self {
+        self.ids = ids;
+        self
+    }
+
+    /// Sets the clock driving `send_after` and `schedule_recurring`, e.g. a
+    /// `MockClock` in tests.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Runs the system on a tokio runtime of its own, built from `config`,
+    /// instead of the ambient one, so actor work is isolated from the rest of the
+    /// application. Applies to actors, timers and pipes added after this call,
+    /// including the children actors spawn. `shutdown` stops the runtime once the
+    /// actors have stopped, dropping any task still running on it.
+    pub fn with_runtime(self, config: RuntimeConfig) -> Result<Self, String> {
+        let runtime = config
+            .build()
+            .map_err(|e| format!("Failed to build the actor runtime: {}", e))?;
+        *self.shared.runtime.write().unwrap() = Some(runtime.handle().clone());
+        *self.shared.owned_runtime.lock().unwrap() = OwnedRuntime::new(runtime);
+        Ok(self)
+    }
+
+    /// Caps the regular messages queued across all of this system's actors, or
+    /// being handled by them, at `limit`, whatever their mailbox capacities, so
+    /// fan-in overload can't grow memory without bound. Once the cap is reached
+    /// `try_send_message` fails with `SendError::SystemFull` and `send_message`
+    /// waits for a message to be handled. The actors an actor spawns run in a
+    /// system of their own and don't count.
+    pub fn with_global_mailbox_limit(self, limit: usize) -> Self {
+        self.shared.pending_limit.store(limit, Ordering::SeqCst);
+        self
+    }
+
+    /// Number of regular messages queued across all actors or being handled,
+    /// which `with_global_mailbox_limit` caps.
+    pub fn queued_messages(&self) -> usize {
+        self.shared.pending.load(Ordering::SeqCst)
+    }
+
+    /// The token every task spawned by this system watches. Cancelling it stops
+    /// all actors (running their `cleanup`) and stream pipes; pass it to other
+    /// long-running components, like `SnapshotActor::with_cancellation_token`, so
+    /// they stop along with the system.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.shared.cancel.clone()
+    }
+
+    /// A token cancelled once `shutdown` (or `shutdown_timeout`) has stopped
+    /// every actor, or along with the cancellation token. Unlike that one it
+    /// leaves the actors be, so components that outlive them, such as the
+    /// snapshot task of a `SnapshotActor` (see `SnapshotActor::with_system`),
+    /// stop once the actors are done rather than alongside them.
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.shared.shut_down.clone()
+    }
+
+    /// Number of tasks spawned by this system that are still running.
+    pub fn running_tasks(&self) -> usize {
+        self.shared.tasks.len()
+    }
+
+    /// Waits until every task spawned by this system has exited, typically after
+    /// cancelling its token.
+    pub async fn wait_until_stopped(&self) {
+        self.shared.tasks.close();
+        self.shared.tasks.wait().await;
+        // Allow tasks added later to be waited on again
+        self.shared.tasks.reopen();
+    }
+
+    pub fn add_actor<A>(&mut self, name: String, actor: A)
+    where
+        A: Actor<Message = M, Error = E> + Send + 'static,
+        M: std::fmt::Debug,
+    {
+        self.spawn_actor(
+            name,
+            std::any::type_name::<A>(),
+            Box::new(actor),
+            ActorOptions::default(),
+            None,
+            None,
+        );
+    }
+
+    /// Adds an actor under a name made by the system's `IdGenerator` (see
+    /// `with_id_generator`) and returns the name. Names already taken are
+    /// skipped, so the actor never replaces another.
+    pub fn add_actor_auto<A>(&mut self, actor: A) -> String
+    where
+        A: Actor<Message = M, Error = E> + Send + 'static,
+    {
+        let name = loop {
+            let name = self.ids.next();
+            if !self.actors.contains_key(&name) {
+                break name;
+            }
+        };
+        self.add_actor(name.clone(), actor);
+        name
+    }
+
+    /// Adds an actor configured with the given `ActorOptions`.
+    pub fn add_actor_with_options<A>(&mut self, name: String, actor: A, options: ActorOptions)
+    where
+        A: Actor<Message = M, Error = E> + Send + 'static,
+    {
+        self.spawn_actor(
+            name,
+            std::any::type_name::<A>(),
+            Box::new(actor),
+            options,
+            None,
+            None,
+        );
+    }
+
+    /// Adds an actor built by `factory`, which is called again to restart the
+    /// actor when the system's supervisor handles a failure with
+    /// `SupervisionStrategy::Restart`: a receive that ran past the actor's
+    /// `ActorOptions::with_receive_timeout`. The stuck instance is dropped without
+    /// its `cleanup`, and the fresh one takes over the mailbox.
+    pub fn add_restartable_actor<A, F>(&mut self, name: String, factory: F)
+    where
+        A: Actor<Message = M, Error = E> + Send + 'static,
+        F: Fn() -> A + Send + Sync + 'static,
+    {
+        self.add_restartable_actor_with_options(name, factory, ActorOptions::default());
+    }
+
+    /// Like `add_restartable_actor`, configured with the given `ActorOptions`.
+    pub fn add_restartable_actor_with_options<A, F>(
+        &mut self,
+        name: String,
+        factory: F,
+        options: ActorOptions,
+    ) where
+        A: Actor<Message = M, Error = E> + Send + 'static,
+        F: Fn() -> A + Send + Sync + 'static,
+    {
+        let actor = factory();
+        let restart: RestartFactory<M, E> = Arc::new(move || {
+            let actor: BoxedActor<M, E> = Box::new(factory());
+            Box::pin(async move { (actor, Ok(())) })
+        });
+        self.spawn_actor(
+            name,
+            std::any::type_name::<A>(),
+            Box::new(actor),
+            options,
+            None,
+            Some(restart),
+        );
+    }
+
+    /// Like `add_restartable_actor`, for actors with state to recover: when
+    /// `policy.reload_state` is set, each fresh instance is handed to
+    /// `on_restart`, e.g. calling `SnapshotActor::load_state`, before it takes
+    /// over the mailbox. If the hook fails, the failure is printed and the
+    /// instance carries on as the hook left it. The first instance isn't
+    /// handed to the hook.
+    pub fn add_supervised_actor<A, F, H>(
+        &mut self,
+        name: String,
+        factory: F,
+        policy: RestartPolicy,
+        on_restart: H,
+    ) where
+        A: Actor<Message = M, Error = E> + Send + 'static,
+        F: Fn() -> A + Send + Sync + 'static,
+        H: for<'a> Fn(&'a mut A) -> BoxFuture<'a, Result<(), String>> + Send + Sync + 'static,
+    {
+        self.add_supervised_actor_with_options(
+            name,
+            factory,
+            policy,
+            on_restart,
+            ActorOptions::default(),
+        );
+    }
+
+    /// Like `add_supervised_actor`, configured with the given `ActorOptions`.
+    pub fn add_supervised_actor_with_options<A, F, H>(
+        &mut self,
+        name: String,
+        factory: F,
+        policy: RestartPolicy,
+        on_restart: H,
+        options: ActorOptions,
+    ) where
+        A: Actor<Message = M, Error = E> + Send + 'static,
+        F: Fn() -> A + Send + Sync + 'static,
+        H: for<'a> Fn(&'a mut A) -> BoxFuture<'a, Result<(), String>> + Send + Sync + 'static,
+    {
+        let actor = factory();
+        let (factory, on_restart) = (Arc::new(factory), Arc::new(on_restart));
+        let restart: RestartFactory<M, E> = Arc::new(move || {
+            let (factory, on_restart) = (Arc::clone(&factory), Arc::clone(&on_restart));
+            Box::pin(async move {
+                let mut actor = factory();
+                let reloaded = if policy.reload_state {
+                    on_restart(&mut actor).await
+                } else {
+                    Ok(())
+                };
+                let actor: BoxedActor<M, E> = Box::new(actor);
+                (actor, reloaded)
+            })
+        });
+        self.spawn_actor(
+            name,
+            std::any::type_name::<A>(),
+            Box::new(actor),
+            options,
+            None,
+            Some(restart),
+        );
+    }
+
+    fn spawn_actor(
+        &mut self,
+        name: String,
+        actor_type: &'static str,
+        mut actor: BoxedActor<M, E>,
+        options: ActorOptions,
+        check: Option<MessageCheck<M>>,
+        restart: Option<RestartFactory<M, E>>,
+    ) {
+        let (tx, mut rx): (MailboxSender<M>, MailboxReceiver<M>) = mailbox::mailbox(
+            options.mailbox_capacity,
+            options.overflow_policy,
+            options.priority_aging,
+        );
+        let drain = rx.drain_handle();
+
+        let bucket = options
+            .rate_limit
+            .map(|(messages, window)| TokenBucket::new(messages, window));
+        let (mut pacing, rate_limit) = match options.rate_limit_policy {
+            RateLimitPolicy::Delay => (bucket, None),
+            RateLimitPolicy::Reject => (None, bucket.map(|bucket| Arc::new(Mutex::new(bucket)))),
+        };
+
+        let (behavior, mut behaviors) = mpsc::unbounded_channel::<BoxedActor<M, E>>();
+        let (inspections, mut inspection_requests) =
+            mpsc::unbounded_channel::<oneshot::Sender<Option<String>>>();
+        let slot = self
+            .scheduler
+            .as_ref()
+            .map(|scheduler| scheduler.register(options.weight));
+
+        let receive_timeout = options.receive_timeout;
+        let batching = options.batching;
+        let stop_mode = options.stop_mode;
+        let high_water = options
+            .high_water_mark
+            .map(|percent| Arc::new(HighWaterMark::new(options.mailbox_capacity, percent)));
+        let supervisor = self.supervisor.clone();
+        let context = Context::new(name.clone(), self.child_system());
+        let task_name = name.clone();
+        let shared = Arc::clone(&self.shared);
+        // Cancelled with the system, or on its own to stop the actor immediately
+        let cancel = self.shared.cancel.child_token();
+        let stop = cancel.clone();
+        let abort = match stop_mode {
+            StopMode::Drain => CancellationToken::new(),
+            StopMode::Immediate => cancel.clone(),
+        };
+        let interrupt = abort.clone();
+        let (report_stop, stopped) = watch::channel(None);
+        let actor_tasks = self.shared.actor_tasks.clone();
+        self.shared.spawn(actor_tasks.track_future(async move {
+            // A Shutdown or system message that cut a batch short, handled
+            // right after it
+            let mut held = None;
+            // Set when cancellation stops the actor before its `Shutdown`
+            let mut aborted = false;
+            // Numbers the deliveries, for their tracing spans
+            #[cfg(feature = "tracing")]
+            let mut delivered: u64 = 0;
+            loop {
+                let message = if let Some(message) = held.take() {
+                    message
+                } else if let Ok(new_actor) = behaviors.try_recv() {
+                    actor = new_actor;
+                    continue;
+                } else if let Ok(reply) = inspection_requests.try_recv() {
+                    let _ = reply.send(actor.inspect_state());
+                    continue;
+                } else if let Some(message) = rx.try_recv() {
+                    // A busy actor takes its next message without waiting in
+                    // the select below, which checks the same in the same
+                    // order. It still yields now and then, as `recv` would
+                    tokio::task::consume_budget().await;
+                    message
+                } else {
+                    tokio::select! {
+                    // Swap behaviors before handling the next message
+                    biased;
+                    Some(new_actor) = behaviors.recv() => {
+                        actor = new_actor;
+                        continue;
+                    }
+                    Some(reply) = inspection_requests.recv() => {
+                        let _ = reply.send(actor.inspect_state());
+                        continue;
+                    }
+                    message = rx.recv() => match message {
+                        Some(message) => message,
+                        None => break,
+                    },
+                    _ = cancel.cancelled() => {
+                        aborted = true;
+                        break;
+                    }
+                    }
+                };
+                if let Message::System(system) = &message {
+                    match system {
+                        SystemMessage::Ping(pong) => pong.answer(),
+                    }
+                    if !actor.receives_system_messages() {
+                        continue;
+                    }
+                }
+                let delivery = match (batching, message) {
+                    (Some((max_batch, linger)), Message::Regular(first)) => {
+                        let (batch, shutdown) =
+                            rx.recv_batch(first, max_batch, linger, &cancel).await;
+                        held = shutdown;
+                        Delivery::Batch(batch)
+                    }
+                    (_, message) => Delivery::One(message),
+                };
+                let regular = delivery.regular();
+                if let Some(bucket) = pacing.as_mut() {
+                    for _ in 0..regular {
+                        bucket.acquire().await;
+                    }
+                }
+                let stop = matches!(delivery, Delivery::One(Message::Shutdown));
+                if let Some(slot) = &slot {
+                    tokio::select! {
+                        _ = slot.turn() => {}
+                        _ = cancel.cancelled() => {
+                            shared.handled_many(regular);
+                            aborted = true;
+                            break;
+                        }
+                    }
+                }
+                let handled = match delivery {
+                    Delivery::One(message) => actor.receive_with_context(&context, message),
+                    Delivery::Batch(messages) => actor.receive_batch(messages),
+                };
+                let handled = async {
+                    match receive_timeout {
+                        Some(limit) => tokio::time::timeout(limit, handled)
+                            .await
+                            .map_err(|_| limit),
+                        None => Ok(handled.await),
+                    }
+                };
+                #[cfg(feature = "tracing")]
+                let handled = {
+                    delivered += 1;
+                    let span = tracing::info_span!(
+                        "message",
+                        actor = %task_name,
+                        message_id = delivered
+                    );
+                    tracing::Instrument::instrument(handled, span)
+                };
+                let result = tokio::select! {
+                    result = handled => result,
+                    _ = interrupt.cancelled() => {
+                        shared.handled_many(regular);
+                        aborted = true;
+                        break;
+                    }
+                };
+                match result {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => println!("Error processing message: {:?}", e),
+                    Err(limit) => {
+                        let error = format!("receive timed out after {:?}", limit);
+                        println!("Actor {} {}", task_name, error);
+                        if let Some(supervisor) = &supervisor {
+                            supervisor.handle_failure(&task_name, &error);
+                            // The timed out call was dropped along with the old
+                            // instance, so it can't complete and handle its
+                            // message a second time
+                            if let (SupervisionStrategy::Restart, Some(restart)) =
+                                (supervisor.strategy(), &restart)
+                            {
+                                let (fresh, reloaded) = restart().await;
+                                actor = fresh;
+                                supervisor.restarted(&task_name, &error);
+                                if let Err(e) = reloaded {
+                                    println!(
+                                        "Actor {} failed to reload its state: {}",
+                                        task_name, e
+                                    );
+                                }
+                            }
+                        }
+                    }
+                }
+                shared.handled_many(regular);
+                if let Some(slot) = &slot {
+                    slot.finish(!rx.is_empty());
+                }
+                // Messages queued behind Shutdown are not processed
+                if stop {
+                    break;
+                }
+            }
+            // Whatever is left in the mailbox will never be handled
+            rx.close();
+            while let Some(message) = rx.try_recv() {
+                if let Message::Regular(_) = message {
+                    shared.handled();
+                }
+            }
+            context.stop_children().await;
+            let outcome = match actor.try_cleanup().await {
+                Err(e) => Stopped::CleanupFailed(format!("{:?}", e)),
+                Ok(()) if aborted => Stopped::Aborted,
+                Ok(()) => Stopped::Cleanly,
+            };
+            let _ = report_stop.send(Some(outcome));
+        }));
+
+        let actor_ref = ActorRef {
+            name: name.clone(),
+            actor_type,
+            sender: tx,
+            drain,
+            behavior,
+            inspections,
+            check,
+            rate_limit,
+            high_water,
+            overflow_hook: Arc::new(RwLock::new(None)),
+            stop_mode,
+            stop,
+            abort,
+            stopped,
+            shared: Arc::clone(&self.shared),
+        };
+        self.order.retain(|existing| *existing != name);
+        self.order.push(name.clone());
+        self.actors.insert(name, actor_ref);
+    }
+
+    // The system an actor's children run in: stopped along with this one and
+    // supervised by the same supervisor
+    fn child_system(&self) -> ActorSystem<M, E> {
+        let mut children = ActorSystem::with_cancellation_token(self.shared.cancel.clone())
+            .with_clock(Arc::clone(&self.clock));
+        children.supervisor = self.supervisor.clone();
+        Arc::get_mut(&mut children.shared)
+            .expect("a new system isn't shared yet")
+            .events = self.event_bus();
+        if let Some(logger) = self.shared.logger.read().unwrap().clone() {
+            children = children.with_logger(logger);
+        }
+        // Children run where their parent does, but only the parent stops it
+        *children.shared.runtime.write().unwrap() = self.shared.runtime.read().unwrap().clone();
+        children
+    }
+
+    pub async fn send_message(&self, actor_name: &str, message: M) -> Result<(), String> {
+        self.check_quiescing(actor_name)
+            .map_err(|e| e.to_string())?;
+        match self.lookup(actor_name) {
+            Ok(actor) => actor.send(message).await,
+            Err(e) => {
+                self.shared.dead_letter(actor_name, message, &e);
+                Err(e.to_string())
+            }
+        }
+    }
+
+    /// Like `send_message`, but on the priority lane of the actor's mailbox, see
+    /// `ActorRef::send_priority`.
+    pub async fn send_priority_message(&self, actor_name: &str, message: M) -> Result<(), String> {
+        self.check_quiescing(actor_name)
+            .map_err(|e| e.to_string())?;
+        match self.lookup(actor_name) {
+            Ok(actor) => actor.send_priority(message).await,
+            Err(e) => {
+                self.shared.dead_letter(actor_name, message, &e);
+                Err(e.to_string())
+            }
+        }
+    }
+
+    /// Sends a message to the actor the installed router picks for it (see
+    /// `with_router`), like `send_message` to that actor. Fails if no router is
+    /// installed or it has no route for the message.
+    pub async fn dispatch(&self, message: M) -> Result<(), String> {
+        let router = self.router.as_ref().ok_or("No router installed")?;
+        match router.route(&message) {
+            Some(target) => self.send_message(&target, message).await,
+            None => Err(format!("No route for message {:?}", message)),
+        }
+    }
+
+    /// Sends a message without waiting for mailbox space, failing with
+    /// `SendError::MailboxFull` instead when the actor is saturated.
+    pub fn try_send_message(&self, actor_name: &str, message: M) -> Result<(), SendError> {
+        self.check_quiescing(actor_name)?;
+        match self.lookup(actor_name) {
+            Ok(actor) => actor.try_send(message),
+            Err(e) => {
+                self.shared.dead_letter(actor_name, message, &e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Stops new work from entering the system: from now on `send_message` and
+    /// `try_send_message` fail with `SendError::Quiescing`, while actors keep
+    /// handling what is already queued. Messages actors send each other through
+    /// `ActorRef`s are still accepted, so work in progress can complete. Unlike
+    /// `shutdown`, nothing stops; use `wait_quiesced` to know when the system is
+    /// idle and `resume` to accept messages again.
+    pub fn quiesce(&self) {
+        self.shared.quiescing.store(true, Ordering::SeqCst);
+    }
+
+    /// Accepts new messages again after `quiesce`.
+    pub fn resume(&self) {
+        self.shared.quiescing.store(false, Ordering::SeqCst);
+    }
+
+    /// Whether `quiesce` was called (and not undone by `resume`).
+    pub fn is_quiescing(&self) -> bool {
+        self.shared.quiescing.load(Ordering::SeqCst)
+    }
+
+    /// Whether every message enqueued so far has been handled, so all
+    /// mailboxes are empty and no actor is in the middle of a message.
+    pub fn quiesced(&self) -> bool {
+        self.shared.pending.load(Ordering::SeqCst) == 0
+    }
+
+    /// Waits until `quiesced` is true.
+    pub async fn wait_quiesced(&self) {
+        loop {
+            // Register before checking, so a drain in between isn't missed
+            let drained = self.shared.drained.notified();
+            if self.quiesced() {
+                return;
+            }
+            drained.await;
+        }
+    }
+
+    fn check_quiescing(&self, actor_name: &str) -> Result<(), SendError> {
+        if self.is_quiescing() {
+            return Err(SendError::Quiescing(actor_name.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Enables the dead-letter queue, keeping up to `capacity` messages that could
+    /// not be delivered because their actor is missing or stopped. Once full, the
+    /// oldest dead letters are dropped.
+    pub fn with_dead_letters(self, capacity: usize) -> Self {
+        *self.shared.dead_letters.lock().unwrap() = Some(DeadLetterQueue::new(capacity));
+        self
+    }
+
+    /// Like `with_dead_letters`, but also keeps the queue in `backend`, so dead
+    /// letters survive a restart. Call `load_dead_letters` on the restarted
+    /// system to bring them back, see the `dead_letters` module docs. Failures to
+    /// write the queue go to the system's logger.
+    pub fn with_persistent_dead_letters<B>(self, backend: B, capacity: usize) -> Self
+    where
+        M: Serialize + DeserializeOwned,
+        B: StorageBackend + 'static,
+    {
+        *self.shared.dead_letters.lock().unwrap() =
+            Some(DeadLetterQueue::persistent(capacity, backend));
+        self
+    }
+
+    /// Brings back the dead letters persisted by a previous run of a system
+    /// built with `with_persistent_dead_letters`, ahead of those queued since,
+    /// and returns how many dead letters are queued now. Only the first call
+    /// loads anything; later ones just write the queue out.
+    pub async fn load_dead_letters(&self) -> Result<usize, String> {
+        dead_letters::sync(&self.shared.dead_letters).await?;
+        Ok(self.dead_letter_count())
+    }
+
+    /// Number of messages currently in the dead-letter queue.
+    pub fn dead_letter_count(&self) -> usize {
+        self.shared
+            .dead_letters
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map_or(0, DeadLetterQueue::len)
+    }
+
+    /// Tries to deliver every dead letter to its original target again. Delivered
+    /// messages are removed from the queue, the others stay for a later attempt.
+    /// A persistent queue first loads what a previous run left in it.
+    pub async fn reprocess_dead_letters(&self) -> Result<ReprocessReport, String> {
+        dead_letters::sync(&self.shared.dead_letters).await?;
+        let letters = match self.shared.dead_letters.lock().unwrap().as_mut() {
+            Some(queue) => queue.take_all(),
+            None => return Err("Dead-letter queue is not enabled".to_string()),
+        };
+
+        let mut report = ReprocessReport::default();
+        let mut failed = Vec::new();
+        for letter in letters {
+            let result = match self.lookup(&letter.target) {
+                Ok(actor) => actor.deliver(letter.message, false).await,
+                Err(e) => Err((e, letter.message)),
+            };
+            match result {
+                Ok(()) => report.redelivered += 1,
+                Err((e, message)) => failed.push(DeadLetter {
+                    target: letter.target,
+                    message,
+                    reason: e.to_string(),
+                }),
+            }
+        }
+        report.still_failing = failed.len();
+
+        // Put back what still fails, ahead of anything dead-lettered meanwhile
+        if let Some(queue) = self.shared.dead_letters.lock().unwrap().as_mut() {
+            let newer = queue.take_all();
+            for letter in failed.into_iter().chain(newer) {
+                queue.push(letter);
+            }
+        }
+        dead_letters::sync(&self.shared.dead_letters).await?;
+        Ok(report)
+    }
+
+    /// Registers a participant in the checkpoints taken by `checkpoint_all`. The
+    /// caller keeps its own handle on the participant to keep using it.
+    pub fn add_checkpoint_participant<P>(
+        &mut self,
+        name: &str,
+        participant: Arc<tokio::sync::Mutex<P>>,
+    ) where
+        P: Checkpoint + 'static,
+    {
+        self.checkpoints.push((name.to_string(), participant));
+    }
+
+    /// Takes a two-phase checkpoint across all registered participants and returns
+    /// its id. If any participant fails to prepare, the checkpoint is aborted on
+    /// all of them and nothing is committed.
+    pub async fn checkpoint_all(&self) -> Result<u64, String> {
+        let checkpoint_id = self
+            .shared
+            .last_checkpoint_id
+            .fetch_add(1, Ordering::SeqCst)
+            + 1;
+
+        // Hold every participant until the end so they checkpoint the same point
+        let mut participants = Vec::with_capacity(self.checkpoints.len());
+        for (name, participant) in &self.checkpoints {
+            participants.push((name, participant.lock().await));
+        }
+
+        let mut tokens = Vec::with_capacity(participants.len());
+        let mut prepare_error = None;
+        for (name, participant) in participants.iter_mut() {
+            match participant.prepare_checkpoint(checkpoint_id).await {
+                Ok(token) => tokens.push(token),
+                Err(e) => {
+                    prepare_error = Some(format!("{} failed to prepare: {}", name, e));
+                    break;
+                }
+            }
+        }
+        if let Some(e) = prepare_error {
+            for ((_, prepared), token) in participants.iter_mut().zip(tokens) {
+                prepared.abort_checkpoint(token).await;
+            }
+            return Err(format!("Checkpoint {} aborted, {}", checkpoint_id, e));
+        }
+
+        let mut failures = Vec::new();
+        for ((name, participant), token) in participants.iter_mut().zip(tokens) {
+            if let Err(e) = participant.commit_checkpoint(token).await {
+                failures.push(format!("{}: {}", name, e));
+            }
+        }
+        if failures.is_empty() {
+            Ok(checkpoint_id)
+        } else {
+            Err(format!(
+                "Checkpoint {} failed to commit on {}",
+                checkpoint_id,
+                failures.join(", ")
+            ))
+        }
+    }
+
+    /// Replaces the named actor's behavior, keeping its mailbox: every message
+    /// handled from now on, including those already queued, goes to `new_actor`.
+    /// A message being handled while swapping finishes with the old behavior.
+    ///
+    /// The old behavior is dropped without calling its `cleanup`, since the actor
+    /// keeps running; resources it owns should be handed over to `new_actor`.
+    pub fn swap_behavior<A>(&self, actor_name: &str, new_actor: A) -> Result<(), String>
+    where
+        A: Actor<Message = M, Error = E> + Send + 'static,
+    {
+        let actor = self.lookup(actor_name).map_err(|e| e.to_string())?;
+        actor
+            .behavior
+            .send(Box::new(new_actor))
+            .map_err(|_| SendError::Closed(actor_name.to_string()).to_string())
+    }
+
+    /// Calls `hook` with the actor's name and the message whenever a send finds
+    /// the named actor's mailbox full, before its `OverflowPolicy` handles the
+    /// message: a chance to shed load or add workers. The hook runs on the
+    /// sender's task, so it should be quick. Replaces any earlier hook.
+    pub fn on_overflow<F>(&self, actor_name: &str, hook: F) -> Result<(), String>
+    where
+        F: Fn(&str, &M) + Send + Sync + 'static,
+    {
+        let actor = self.lookup(actor_name).map_err(|e| e.to_string())?;
+        *actor.overflow_hook.write().unwrap() = Some(Arc::new(hook));
+        Ok(())
+    }
+
+    /// Pings the named actor, see `ActorRef::ping`.
+    pub async fn ping(&self, actor_name: &str) -> Result<(), SendError> {
+        self.lookup(actor_name)?.ping().await
+    }
+
+    /// Takes the queued messages out of the named actor's mailbox, e.g. to keep
+    /// the pending work before a forced restart; see `ActorRef::drain_mailbox`.
+    /// Returns nothing if the actor is unknown.
+    pub fn drain_mailbox(&self, actor_name: &str) -> Vec<M> {
+        self.lookup(actor_name)
+            .map(ActorRef::drain_mailbox)
+            .unwrap_or_default()
+    }
+
+    /// Copies up to `max` messages queued for the named actor without taking
+    /// them; see `ActorRef::peek_mailbox`. Returns nothing if the actor is
+    /// unknown.
+    pub fn peek_mailbox(&self, actor_name: &str, max: usize) -> Vec<M>
+    where
+        M: Clone,
+    {
+        self.lookup(actor_name)
+            .map(|actor| actor.peek_mailbox(max))
+            .unwrap_or_default()
+    }
+
+    /// Asks the named actor to report its state, see `Debuggable`. Returns `None`
+    /// if the actor is unknown, has stopped or isn't debuggable.
+    pub async fn inspect(&self, actor_name: &str) -> Option<String> {
+        let actor = self.lookup(actor_name).ok()?;
+        let (reply, state) = oneshot::channel();
+        actor.inspections.send(reply).ok()?;
+        state.await.ok().flatten()
+    }
+
+    /// Adds an actor whose state `inspect` can report, see `Debuggable`.
+    pub fn add_debuggable_actor<A>(&mut self, name: String, actor: A)
+    where
+        A: Actor<Message = M, Error = E> + Debuggable + Send + 'static,
+    {
+        self.add_debuggable_actor_with_options(name, actor, ActorOptions::default());
+    }
+
+    /// Like `add_debuggable_actor`, configured with the given `ActorOptions`.
+    pub fn add_debuggable_actor_with_options<A>(
+        &mut self,
+        name: String,
+        actor: A,
+        options: ActorOptions,
+    ) where
+        A: Actor<Message = M, Error = E> + Debuggable + Send + 'static,
+    {
+        self.spawn_actor(
+            name,
+            std::any::type_name::<A>(),
+            Box::new(DebuggableActor::new(actor)),
+            options,
+            None,
+            None,
+        );
+    }
+
+    /// Delivers every item of `stream` to the named actor from a background task,
+    /// waiting for mailbox space as needed. Stops when the stream ends or the actor
+    /// stops accepting messages; the returned handle can also cancel it.
+    pub fn pipe_stream<S>(&self, actor_name: &str, stream: S) -> Result<PipeHandle, String>
+    where
+        S: Stream<Item = M> + Send + 'static,
+    {
+        let actor = self.lookup(actor_name).map_err(|e| e.to_string())?.clone();
+        let cancel = self.shared.cancel.clone();
+        let task = self.shared.spawn(async move {
+            let mut stream = Box::pin(stream);
+            let mut delivered = 0;
+            loop {
+                let item = tokio::select! {
+                    item = stream.next() => match item {
+                        Some(item) => item,
+                        None => break,
+                    },
+                    _ = cancel.cancelled() => break,
+                };
+                if let Err(e) = actor.send(item).await {
+                    println!("Stopping pipe to actor {}: {}", actor.name(), e);
+                    break;
+                }
+                delivered += 1;
+            }
+            delivered
+        });
+        Ok(PipeHandle { task })
+    }
+
+    /// Asks the named actor for a stream of responses. `request` builds the
+    /// message around the sender the actor emits responses on, and the stream
+    /// ends once the actor drops that sender. Dropping the stream closes the
+    /// channel, so the actor's next `send` fails and it can stop producing.
+    pub async fn ask_stream<R, F>(
+        &self,
+        actor_name: &str,
+        request: F,
+    ) -> Result<impl Stream<Item = R>, String>
+    where
+        R: Send + 'static,
+        F: FnOnce(mpsc::Sender<R>) -> M,
+    {
+        let (sender, receiver) = mpsc::channel(ASK_STREAM_CAPACITY);
+        self.send_message(actor_name, request(sender)).await?;
+        Ok(futures::stream::unfold(
+            receiver,
+            |mut receiver| async move { receiver.recv().await.map(|response| (response, receiver)) },
+        ))
+    }
+
+    /// Describes which actors are registered, in registration order, with their
+    /// type and how many messages are waiting in their mailbox. This is the
+    /// system's wiring only; actor state is snapshotted separately.
+    pub fn describe(&self) -> SystemSnapshot {
+        let actors = self
+            .order
+            .iter()
+            .filter_map(|name| self.actors.get(name))
+            .map(|actor| ActorDescription {
+                name: actor.name.clone(),
+                actor_type: actor.actor_type.to_string(),
+                mailbox_depth: actor.sender.len(),
+                mailbox_capacity: actor.sender.capacity(),
+            })
+            .collect();
+        SystemSnapshot { actors }
+    }
+
+    /// Re-registers the actors listed in `snapshot` that `factories` can build,
+    /// with the mailbox capacity they had. Returns the names of the actors that
+    /// were restored; actors without a factory are skipped, and messages that
+    /// were queued when the snapshot was taken are not replayed.
+    pub fn restore(
+        &mut self,
+        snapshot: &SystemSnapshot,
+        factories: &ActorFactories<M, E>,
+    ) -> Vec<String> {
+        let mut restored = Vec::new();
+        for description in &snapshot.actors {
+            if let Some((actor_type, actor)) = factories.build(&description.name) {
+                let options =
+                    ActorOptions::new().with_mailbox_capacity(description.mailbox_capacity.max(1));
+                self.spawn_actor(
+                    description.name.clone(),
+                    actor_type,
+                    actor,
+                    options,
+                    None,
+                    None,
+                );
+                restored.push(description.name.clone());
+            }
+        }
+        restored
+    }
+
+    /// Delivers `message` to the named actor once `delay` has passed.
+    pub fn send_after(
+        &self,
+        actor_name: &str,
+        message: M,
+        delay: Duration,
+    ) -> Result<TimerHandle, String> {
+        let actor = self.lookup(actor_name).map_err(|e| e.to_string())?.clone();
+        let clock = Arc::clone(&self.clock);
+        let cancel = self.shared.cancel.child_token();
+        let timers = Arc::clone(&self.shared.timers);
+        let handle = timers.register(
+            actor_name,
+            TimerKind::Once,
+            clock.now() + delay,
+            cancel.clone(),
+        );
+        let id = handle.id();
+        self.shared.spawn(async move {
+            tokio::select! {
+                _ = clock.sleep(delay) => {
+                    if let Err(e) = actor.send(message).await {
+                        println!("Timer {} could not deliver to actor {}: {}", id, actor.name(), e);
+                    }
+                }
+                _ = cancel.cancelled() => {}
+            }
+            timers.remove(id);
+        });
+        Ok(handle)
+    }
+
+    /// Delivers a message built by `message` to the named actor every `period`,
+    /// starting one period from now, until cancelled or the actor stops.
+    pub fn schedule_recurring<F>(
+        &self,
+        actor_name: &str,
+        period: Duration,
+        mut message: F,
+    ) -> Result<TimerHandle, String>
+    where
+        F: FnMut() -> M + Send + 'static,
+    {
+        let actor = self.lookup(actor_name).map_err(|e| e.to_string())?.clone();
+        let clock = Arc::clone(&self.clock);
+        let cancel = self.shared.cancel.child_token();
+        let timers = Arc::clone(&self.shared.timers);
+        let handle = timers.register(
+            actor_name,
+            TimerKind::Recurring(period),
+            clock.now() + period,
+            cancel.clone(),
+        );
+        let id = handle.id();
+        self.shared.spawn(async move {
+            let mut ticks = clock::interval(Arc::clone(&clock), period);
+            // The first tick is immediate
+            ticks.tick().await;
+            loop {
+                tokio::select! {
+                    tick = ticks.tick() => {
+                        timers.reschedule(id, tick + period);
+                        if let Err(e) = actor.send(message()).await {
+                            println!("Stopping timer {} to actor {}: {}", id, actor.name(), e);
+                            break;
+                        }
+                    }
+                    _ = cancel.cancelled() => break,
+                }
+            }
+            timers.remove(id);
+        });
+        Ok(handle)
+    }
+
+    /// Lists the timers that are still pending, oldest first.
+    pub fn scheduled_timers(&self) -> Vec<TimerInfo> {
+        self.shared.timers.list()
+    }
+
+    /// Cancels every pending timer.
+    pub fn cancel_all_timers(&self) {
+        self.shared.timers.cancel_all();
+    }
+
+    /// The names of the registered actors, in registration order.
+    pub fn actor_names(&self) -> Vec<String> {
+        self.order.clone()
+    }
+
+    /// Returns a handle to the named actor.
+    pub fn actor_ref(&self, actor_name: &str) -> Option<ActorRef<M, E>> {
+        self.actors.get(actor_name).cloned()
+    }
+
+    fn lookup(&self, actor_name: &str) -> Result<&ActorRef<M, E>, SendError> {
+        self.actors
+            .get(actor_name)
+            .ok_or_else(|| SendError::NotFound(actor_name.to_string()))
+    }
+
+    /// Declares that `actor` depends on `dependency`, e.g. because it writes
+    /// through it: `shutdown` then only stops `dependency` once `actor` has fully
+    /// stopped, so whatever `actor` flushes on its way out is still handled. Fails
+    /// if either actor is unknown or the dependency would close a cycle.
+    pub fn depends_on(&mut self, actor: &str, dependency: &str) -> Result<(), String> {
+        for name in [actor, dependency] {
+            self.lookup(name).map_err(|e| e.to_string())?;
+        }
+        if self.reaches(dependency, actor) {
+            return Err(format!(
+                "Actor {} depending on {} would create a dependency cycle",
+                actor, dependency
+            ));
+        }
+        let dependencies = self.dependencies.entry(actor.to_string()).or_default();
+        if !dependencies.iter().any(|existing| existing == dependency) {
+            dependencies.push(dependency.to_string());
+        }
+        Ok(())
+    }
+
+    // Whether `to` can be reached from `from` by following dependencies
+    fn reaches(&self, from: &str, to: &str) -> bool {
+        let mut stack = vec![from];
+        let mut seen = Vec::new();
+        while let Some(name) = stack.pop() {
+            if name == to {
+                return true;
+            }
+            if seen.contains(&name) {
+                continue;
+            }
+            seen.push(name);
+            if let Some(dependencies) = self.dependencies.get(name) {
+                stack.extend(dependencies.iter().map(String::as_str));
+            }
+        }
+        false
+    }
+
+    /// The order `shutdown` stops the actors in: every actor comes after the
+    /// actors that depend on it (see `depends_on`), otherwise actors registered
+    /// later come first.
+    pub fn shutdown_order(&self) -> Vec<String> {
+        let mut remaining: Vec<&String> = self.order.iter().rev().collect();
+        let mut stop_order = Vec::with_capacity(remaining.len());
+        while !remaining.is_empty() {
+            // `depends_on` refuses cycles, so some actor has no dependents left
+            let next = remaining
+                .iter()
+                .position(|candidate| {
+                    !remaining.iter().any(|other| {
+                        self.dependencies
+                            .get(*other)
+                            .is_some_and(|dependencies| dependencies.contains(candidate))
+                    })
+                })
+                .expect("actor dependencies are acyclic");
+            stop_order.push(remaining.remove(next).clone());
+        }
+        stop_order
+    }
+
+    /// Stops every actor: each one handles what is queued before its `Shutdown`,
+    /// then cleans up, unless it was added with `StopMode::Immediate`, in which
+    /// case it gets no `Shutdown` and leaves its queue unhandled. Actors are
+    /// stopped one at a time in `shutdown_order`, waiting for each to finish: by
+    /// default in reverse registration order, so an actor can still flush into
+    /// the actors registered before it (e.g. an aggregator into its target), and
+    /// always after the actors depending on it.
+    ///
+    /// An actor that hasn't stopped within the shutdown timeout (see
+    /// `with_shutdown_timeout`) is cancelled, cutting short the message it is
+    /// handling, and listed as aborted once its task has exited, so one stuck
+    /// actor doesn't hold up the others nor outlive the actors it depends on.
+    ///
+    /// Once an actor is told to shut down, sending it a message fails right away
+    /// with `SendError::Closing` (the message goes to the dead-letter queue if
+    /// enabled) instead of being queued behind `Shutdown` and silently dropped.
+    /// Pending timers are cancelled before any actor is stopped, and a runtime
+    /// set with `with_runtime` is shut down after the last one. A persistent
+    /// dead-letter queue is written out once the actors have stopped.
+    ///
+    /// Returns how each actor stopped: cleanly, with a failed `try_cleanup`, or
+    /// aborted without handling its queue.
+    pub async fn shutdown(&self) -> ShutdownReport {
+        // No timer may fire into actors that are stopping
+        self.cancel_all_timers();
+        let mut report = ShutdownReport::default();
+        for name in self.shutdown_order() {
+            let stopped = self.actors[&name].stop_within(self.shutdown_timeout).await;
+            report.record(&name, stopped);
+        }
+        self.shared.shut_down.cancel();
+        if let Err(e) = dead_letters::sync(&self.shared.dead_letters).await {
+            self.shared.log(LogLevel::Error, e);
+        }
+        self.shared.owned_runtime.lock().unwrap().shutdown();
+        report
+    }
+
+    /// Stops the named actor the way `shutdown` stops each one, leaving the others
+    /// running, and returns how it stopped. The actor stays registered, refusing
+    /// messages with `SendError::Closing`; `shutdown` lists it as it stopped here.
+    pub async fn stop_actor(&self, actor_name: &str) -> Result<ShutdownReport, String> {
+        let actor = self.lookup(actor_name).map_err(|e| e.to_string())?;
+        let mut report = ShutdownReport::default();
+        report.record(actor_name, actor.stop_within(self.shutdown_timeout).await);
+        Ok(report)
+    }
+
+    /// Asks the system to shut down: `run_until_shutdown` and
+    /// `run_until_signal` return after shutting it down with `shutdown_timeout`,
+    /// and `shutdown_requested` wakes up. A supervisor escalating with
+    /// `EscalationPolicy::Shutdown` calls this. Nothing stops by itself, since
+    /// only the code running the system holds every actor it added.
+    pub fn request_shutdown(&self) {
+        self.shared.shutdown_requested.cancel();
+    }
+
+    /// Whether `request_shutdown` was called, by any clone.
+    pub fn is_shutdown_requested(&self) -> bool {
+        self.shared.shutdown_requested.is_cancelled()
+    }
+
+    /// Waits until a shutdown is requested (see `request_shutdown`).
+    pub async fn shutdown_requested(&self) {
+        self.shared.shutdown_requested.cancelled().await;
+    }
+
+    /// Runs until a shutdown is requested (see `request_shutdown`) or the
+    /// system's cancellation token is cancelled, then shuts down with
+    /// `shutdown_timeout` (`DEFAULT_SHUTDOWN_TIMEOUT` unless set with
+    /// `with_shutdown_timeout`) and returns the `ShutdownReport`. Call it on the
+    /// system the actors were added to, or a clone made after.
+    pub async fn run_until_shutdown(self) -> Result<ShutdownReport, String> {
+        tokio::select! {
+            _ = self.shutdown_requested() => {}
+            _ = self.shared.cancel.cancelled() => {}
+        }
+        self.shutdown_timeout(self.shutdown_timeout).await
+    }
+
+    /// Sets the timeout `run_until_signal` and `run_until_shutdown` pass to
+    /// `shutdown_timeout`, which is also how long `shutdown` and `stop_actor`
+    /// wait for each actor.
+    pub fn with_shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = timeout;
+        self
+    }
+
+    /// Shuts the system down gracefully, giving actors up to `timeout` to process
+    /// what is queued before their `Shutdown` and clean up.
+    ///
+    /// Afterwards the system's cancellation token is cancelled, stopping everything
+    /// still running: actors that did not drain in time, stream pipes and any
+    /// component watching the token. Those get another `timeout` to exit.
+    ///
+    /// Returns how each actor stopped, listing the ones that did not drain in
+    /// time as aborted, or an error if some tasks never exited.
+    pub async fn shutdown_timeout(&self, timeout: Duration) -> Result<ShutdownReport, String> {
+        let drained = tokio::time::timeout(timeout, async {
+            let report = self.shutdown().await;
+            self.shared.actor_tasks.close();
+            self.shared.actor_tasks.wait().await;
+            report
+        })
+        .await;
+        self.shared.actor_tasks.reopen();
+
+        self.shared.cancel.cancel();
+        let stopped = tokio::time::timeout(timeout, self.wait_until_stopped())
+            .await
+            .is_ok();
+        // In case the actors did not drain and `shutdown` never got to it
+        self.shared.owned_runtime.lock().unwrap().shutdown();
+
+        if !stopped {
+            return Err(format!(
+                "{} tasks still running after cancellation",
+                self.running_tasks()
+            ));
+        }
+        Ok(drained.unwrap_or_else(|_| {
+            let mut report = ShutdownReport::default();
+            for name in self.shutdown_order() {
+                let stopped = self.actors[&name].stopped_now();
+                report.record(&name, stopped.unwrap_or(Stopped::Aborted));
+            }
+            report
+        }))
+    }
+
+    /// Runs until the process receives SIGINT (Ctrl-C) or, on Unix, SIGTERM, then
+    /// shuts down with `shutdown_timeout` (`DEFAULT_SHUTDOWN_TIMEOUT` unless set with
+    /// `with_shutdown_timeout`). Components given the system's cancellation token,
+    /// such as `SnapshotActor`s, stop along with it.
+    ///
+    /// Also returns, after the same shutdown, if the system's cancellation token is
+    /// cancelled first, e.g. through a parent token, or a shutdown is requested
+    /// (see `request_shutdown`). Returns the `ShutdownReport`.
+    #[cfg(feature = "signal")]
+    pub async fn run_until_signal(self) -> Result<ShutdownReport, String> {
+        tokio::select! {
+            result = wait_for_signal() => result?,
+            _ = self.shared.cancel.cancelled() => {}
+            _ = self.shutdown_requested() => {}
+        }
+        self.shutdown_timeout(self.shutdown_timeout).await
+    }
+}
+
+// Match `name` against a glob where `*` is any run of characters and `?` any one
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Where the last `*` was, and how much of the name it has swallowed so far
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                // Let the last `*` swallow one more character and retry
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(all(feature = "signal", unix))]
+async fn wait_for_signal() -> Result<(), String> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut terminate = signal(SignalKind::terminate())
+        .map_err(|e| format!("Failed to listen for SIGTERM: {}", e))?;
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => {
+            result.map_err(|e| format!("Failed to listen for SIGINT: {}", e))
+        }
+        _ = terminate.recv() => Ok(()),
+    }
+}
+
+#[cfg(all(feature = "signal", not(unix)))]
+async fn wait_for_signal() -> Result<(), String> {
+    tokio::signal::ctrl_c()
+        .await
+        .map_err(|e| format!("Failed to listen for Ctrl-C: {}", e))
+}
+
+impl<M, E> ActorSystem<M, E>
+where
+    M: Clone + Send + 'static + std::fmt::Debug,
+    E: Send + 'static + std::fmt::Debug,
+{
+    /// Sends a copy of `message` to every actor whose name matches `pattern`, in
+    /// registration order, returning each actor's name with the outcome. The
+    /// pattern is a glob where `*` matches any run of characters and `?` any
+    /// single one, e.g. `worker-*`; without wildcards it must match exactly.
+    pub async fn send_matching(
+        &self,
+        pattern: &str,
+        message: M,
+    ) -> Vec<(String, Result<(), String>)> {
+        let mut results = Vec::new();
+        for name in self.actor_names() {
+            if glob_matches(pattern, &name) {
+                let result = self.send_message(&name, message.clone()).await;
+                results.push((name, result));
+            }
+        }
+        results
+    }
+
+    /// Like `send_matching`, but with `try_send_message`: an actor whose mailbox is
+    /// full gets `SendError::MailboxFull` instead of holding up the others.
+    pub fn try_send_matching(
+        &self,
+        pattern: &str,
+        message: M,
+    ) -> Vec<(String, Result<(), SendError>)> {
+        self.actor_names()
+            .into_iter()
+            .filter(|name| glob_matches(pattern, name))
+            .map(|name| {
+                let result = self.try_send_message(&name, message.clone());
+                (name, result)
+            })
+            .collect()
+    }
+
+    /// Sends a copy of `message` to every actor without waiting on any mailbox,
+    /// returning each actor's name with the outcome, so a control message gets
+    /// through to the actors that have room even while others are saturated.
+    pub fn broadcast_try(&self, message: M) -> Vec<(String, Result<(), SendError>)> {
+        self.try_send_matching("*", message)
+    }
+
+    /// Returns a copy of the messages currently in the dead-letter queue.
+    pub fn dead_letters(&self) -> Vec<DeadLetter<M>> {
+        self.shared
+            .dead_letters
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map_or_else(Vec::new, |queue| queue.iter().cloned().collect())
+    }
+}
+
+impl<M, E> ActorSystem<M, E>
+where
+    M: PartialEq + Clone + Send + 'static + std::fmt::Debug,
+    E: Send + 'static + std::fmt::Debug,
+{
+    /// Adds an actor that skips any message equal to the one it handled just
+    /// before. Only adjacent duplicates are collapsed, see `DedupActor`.
+    pub fn add_dedup_actor<A>(&mut self, name: String, actor: A)
+    where
+        A: Actor<Message = M, Error = E> + Send + 'static,
+    {
+        self.add_dedup_actor_with_options(name, actor, ActorOptions::default());
+    }
+
+    /// Like `add_dedup_actor`, configured with the given `ActorOptions`.
+    pub fn add_dedup_actor_with_options<A>(&mut self, name: String, actor: A, options: ActorOptions)
+    where
+        A: Actor<Message = M, Error = E> + Send + 'static,
+    {
+        self.spawn_actor(
+            name,
+            std::any::type_name::<A>(),
+            Box::new(DedupActor::new(actor)),
+            options,
+            None,
+            None,
+        );
+    }
+}
+
+impl<M, E> ActorSystem<M, E>
+where
+    M: MessageSize + Send + 'static + std::fmt::Debug,
+    E: Send + 'static + std::fmt::Debug,
+{
+    /// Rejects messages larger than `max` bytes with `SendError::TooLarge` before
+    /// they are enqueued. Applies to every actor, including through `ActorRef`s.
+    pub fn with_max_message_size(self, max: usize) -> Self {
+        *self.shared.size_limit.write().unwrap() = Some(SizeLimit {
+            max,
+            measure: M::message_size,
+        });
+        self
+    }
+}
+
+impl<M, E> ActorSystem<M, E>
+where
+    M: FromStr + Send + 'static + std::fmt::Debug,
+    M::Err: fmt::Display,
+    E: Send + 'static + std::fmt::Debug,
+{
+    /// Rebuilds an event-sourced actor's state from an append log: reads every
+    /// record in `backend`, one per line, e.g. appended by a `FileBackend` created
+    /// with `FileBackend::new_append`, parses it and sends it to the named
+    /// actor, in order. Returns once the actor handled them all, with how many
+    /// there were; an empty log replays nothing. Blank lines are skipped.
+    ///
+    /// Call it before the actor gets other messages. Replay goes through the
+    /// actor's `ActorRef`, so `quiesce` keeps other senders out meanwhile.
+    pub async fn replay_from_backend<B: StorageBackend>(
+        &self,
+        actor_name: &str,
+        mut backend: B,
+    ) -> Result<usize, String> {
+        let actor = self.lookup(actor_name).map_err(|e| e.to_string())?;
+        let log = backend
+            .read()
+            .await
+            .map_err(|e| format!("Failed to read the log for actor {}: {}", actor_name, e))?;
+        let mut replayed = 0;
+        for (line, record) in log.lines().enumerate() {
+            if record.trim().is_empty() {
+                continue;
+            }
+            let message = record.parse().map_err(|e| {
+                format!(
+                    "Failed to parse record {} for actor {}: {}",
+                    line + 1,
+                    actor_name,
+                    e
+                )
+            })?;
+            actor.send(message).await?;
+            replayed += 1;
+        }
+        actor.ping().await.map_err(|e| e.to_string())?;
+        Ok(replayed)
+    }
+}
+
+impl<E> ActorSystem<AnyMessage, E>
+where
+    E: From<String> + Send + 'static + std::fmt::Debug,
+{
+    /// Adds an actor with its own message type to a type-erased system.
+    ///
+    /// `send_message` checks that every `AnyMessage` sent to this actor carries an
+    /// `A::Message` and returns an error otherwise.
+    pub fn add_typed_actor<A>(&mut self, name: String, actor: A)
+    where
+        A: Actor<Error = E> + Send + 'static,
+        A::Message: Any + Send,
+    {
+        let expected = TypeId::of::<A::Message>();
+        let check: MessageCheck<AnyMessage> = Arc::new(move |message| {
+            if message.payload.as_ref().type_id() == expected {
+                Ok(())
+            } else {
+                Err(format!(
+                    "expected message of type {}, got {}",
+                    std::any::type_name::<A::Message>(),
+                    message.type_name()
+                ))
+            }
+        });
+        self.spawn_actor(
+            name,
+            std::any::type_name::<A>(),
+            Box::new(TypedActor::new(actor)),
+            ActorOptions::default(),
+            Some(check),
+            None,
+        );
+    }
+}
+
+impl<M, E> Default for ActorSystem<M, E>
+where
+    M: Send + 'static + std::fmt::Debug,
+    E: Send + 'static + std::fmt::Debug,
+{
+    fn default() -> Self {
+        ActorSystem::new()
+    }
+}
@@ -0,0 +1,257 @@
+// src/actor_system/persist.rs
+
+//! # Persistent actors
+//!
+//! A `PersistentActor` records every regular message it receives in a log
+//! backend before handing it to the wrapped actor, so the actor's state can be
+//! rebuilt by replaying the commands it handled: `PersistentActor::open` feeds
+//! the logged messages back through the actor before it is added to a system.
+//!
+//! Actors opt in by implementing `Persistent`, which turns their messages into
+//! bytes and back. Messages that implement serde's traits can use
+//! `to_persist_json` and `from_persist_json`.
+//!
+//! A message is logged before it is handled, so a message the actor failed to
+//! handle is replayed as well. Like the system does for a live actor, replay
+//! reports the failure and carries on with the next message. The log
+//! backend must append with `extend_bytes`; a `WalBackend` keeps it from being
+//! torn by a crash.
+//!
+//! ```rust
+//! use astra::actor_system::{from_persist_json, to_persist_json, Actor, Message};
+//! use astra::actor_system::{Persistent, PersistentActor};
+//! use astra::backends::memory::MemoryBackend;
+//! use async_trait::async_trait;
+//!
+//! #[derive(Default)]
+//! struct Balance {
+//!     total: i64,
+//! }
+//!
+//! #[async_trait]
+//! impl Actor for Balance {
+//!     type Message = i64;
+//!     type Error = String;
+//!
+//!     async fn receive(&mut self, message: Message<i64>) -> Result<(), String> {
+//!         if let Message::Regular(amount) = message {
+//!             self.total += amount;
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//!
+//! impl Persistent for Balance {
+//!     fn persist_repr(message: &i64) -> Vec<u8> {
+//!         to_persist_json(message)
+//!     }
+//!
+//!     fn from_persist(bytes: &[u8]) -> Result<i64, String> {
+//!         from_persist_json(bytes)
+//!     }
+//! }
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let log = MemoryBackend::new();
+//!     let mut balance = PersistentActor::open(Balance::default(), log.clone()).await?;
+//!     balance.receive(Message::Regular(40)).await?;
+//!     balance.receive(Message::Regular(2)).await?;
+//!
+//!     // A fresh actor catches up from the log
+//!     let restored = PersistentActor::open(Balance::default(), log).await?;
+//!     assert_eq!(restored.replayed(), 2);
+//!     assert_eq!(restored.actor().total, 42);
+//!     Ok(())
+//! }
+//! ```
+
+use super::{Actor, Context, Message};
+use crate::backends::storage::StorageBackend;
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::error::Error;
+
+/// An actor whose messages can be persisted and replayed.
+pub trait Persistent: Actor {
+    /// The bytes persisted for `message`.
+    fn persist_repr(message: &Self::Message) -> Vec<u8>;
+
+    /// Rebuilds a message from the bytes `persist_repr` returned.
+    fn from_persist(bytes: &[u8]) -> Result<Self::Message, String>;
+}
+
+/// Encodes a message as JSON, for `Persistent::persist_repr`.
+pub fn to_persist_json<M: Serialize>(message: &M) -> Vec<u8> {
+    serde_json::to_vec(message).expect("a persisted message serializes to JSON")
+}
+
+/// Decodes a message encoded with `to_persist_json`, for `Persistent::from_persist`.
+pub fn from_persist_json<M: DeserializeOwned>(bytes: &[u8]) -> Result<M, String> {
+    serde_json::from_slice(bytes).map_err(|e| e.to_string())
+}
+
+// Frame a message as `{len}:{bytes}\n`, so its bytes can be anything
+fn frame(bytes: &[u8]) -> Vec<u8> {
+    let mut framed = format!("{}:", bytes.len()).into_bytes();
+    framed.extend_from_slice(bytes);
+    framed.push(b'\n');
+    framed
+}
+
+// Split a log into the messages it frames
+fn unframe(mut log: &[u8]) -> Result<Vec<&[u8]>, String> {
+    let mut messages = Vec::new();
+    while !log.is_empty() {
+        let colon = log
+            .iter()
+            .position(|b| *b == b':')
+            .ok_or("Persisted message without a length")?;
+        let len: usize = std::str::from_utf8(&log[..colon])
+            .ok()
+            .and_then(|len| len.parse().ok())
+            .ok_or("Persisted message with an unreadable length")?;
+        let rest = &log[colon + 1..];
+        if rest.len() <= len || rest[len] != b'\n' {
+            return Err("Persisted message is truncated".to_string());
+        }
+        messages.push(&rest[..len]);
+        log = &rest[len + 1..];
+    }
+    Ok(messages)
+}
+
+/// Wraps a `Persistent` actor, logging each regular message it receives to a
+/// backend (see the module docs).
+#[derive(Debug)]
+pub struct PersistentActor<A, B> {
+    actor: A,
+    log: B,
+    replayed: usize,
+}
+
+impl<A, B> PersistentActor<A, B>
+where
+    A: Persistent + Send,
+    A::Message: Send,
+    A::Error: Send,
+    B: StorageBackend,
+{
+    /// Replays the messages in `log` through `actor`, in the order they were
+    /// logged, then keeps logging to it. Fails if the log can't be read or
+    /// decoded.
+    pub async fn open(mut actor: A, mut log: B) -> Result<Self, Box<dyn Error>> {
+        let raw = log.read_bytes().await?;
+        let messages = unframe(&raw)?;
+        for bytes in &messages {
+            let message = A::from_persist(bytes)?;
+            if let Err(e) = actor.receive(Message::Regular(message)).await {
+                println!("Error replaying persisted message: {:?}", e);
+            }
+        }
+        Ok(PersistentActor {
+            actor,
+            log,
+            replayed: messages.len(),
+        })
+    }
+
+    /// How many messages `open` replayed.
+    pub fn replayed(&self) -> usize {
+        self.replayed
+    }
+
+    /// Returns a reference to the wrapped actor.
+    pub fn actor(&self) -> &A {
+        &self.actor
+    }
+
+    // Append the framed messages to the log in one write
+    async fn persist(&mut self, framed: Vec<u8>) -> Result<(), String> {
+        self.log
+            .extend_bytes(&framed)
+            .await
+            .map_err(|e| format!("Failed to persist a message: {}", e))
+    }
+
+    // Push buffered log writes down to storage
+    async fn flush_log(&mut self) -> Result<(), String> {
+        self.log
+            .flush()
+            .await
+            .map_err(|e| format!("Failed to flush the message log: {}", e))
+    }
+}
+
+#[async_trait]
+impl<A, B> Actor for PersistentActor<A, B>
+where
+    A: Persistent + Send,
+    A::Message: Send,
+    A::Error: From<String> + Send,
+    B: StorageBackend,
+{
+    type Message = A::Message;
+    type Error = A::Error;
+
+    async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error> {
+        if let Message::Regular(msg) = &message {
+            self.persist(frame(&A::persist_repr(msg))).await?;
+        }
+        self.actor.receive(message).await
+    }
+
+    async fn receive_with_context(
+        &mut self,
+        context: &Context<Self::Message, Self::Error>,
+        message: Message<Self::Message>,
+    ) -> Result<(), Self::Error>
+    where
+        Self::Message: Send,
+        Self::Error: Send,
+    {
+        if let Message::Regular(msg) = &message {
+            self.persist(frame(&A::persist_repr(msg))).await?;
+        }
+        self.actor.receive_with_context(context, message).await
+    }
+
+    async fn receive_batch(&mut self, messages: Vec<Self::Message>) -> Result<(), Self::Error>
+    where
+        Self::Message: Send,
+        Self::Error: Send,
+    {
+        let framed = messages
+            .iter()
+            .flat_map(|message| frame(&A::persist_repr(message)))
+            .collect();
+        self.persist(framed).await?;
+        self.actor.receive_batch(messages).await
+    }
+
+    async fn cleanup(&mut self) {
+        self.actor.cleanup().await;
+        if let Err(e) = self.flush_log().await {
+            eprintln!("{}", e);
+        }
+    }
+
+    async fn try_cleanup(&mut self) -> Result<(), Self::Error>
+    where
+        Self::Error: Send,
+    {
+        let cleaned = self.actor.try_cleanup().await;
+        let flushed = self.flush_log().await;
+        cleaned?;
+        Ok(flushed?)
+    }
+
+    fn receives_system_messages(&self) -> bool {
+        self.actor.receives_system_messages()
+    }
+
+    fn inspect_state(&self) -> Option<String> {
+        self.actor.inspect_state()
+    }
+}
@@ -0,0 +1,363 @@
+// src/actor_system/persistent_scheduler.rs
+
+//! # Persistent scheduler
+//!
+//! Timers started with `send_after` and `schedule_recurring` live in memory and
+//! are lost on restart. A `PersistentScheduler` records each pending timer, its
+//! target actor, message and fire time, in a `StorageBackend`, and removes it
+//! once it fired. `PersistentScheduler::open` re-arms whatever a previous run
+//! left pending, so reminders and deadlines fire across restarts.
+//!
+//! Timers are identified by a key chosen by the caller. Scheduling under a key
+//! that is already pending replaces that timer, so a restarted process that
+//! schedules its timers again doesn't end up with duplicates.
+//!
+//! A timer that became due while the process was down fires as soon as it is
+//! re-armed. A recurring timer that missed several periods fires once, not once
+//! per missed period, and then keeps its original cadence.
+//!
+//! A timer is removed from the store after its message was delivered, so a
+//! crash in between delivers it again on the next run: delivery is at least
+//! once. Fire times are stored as wall-clock times; waiting for them goes
+//! through the system's `Clock`. Shutting the system down stops the timers, as
+//! it does the in-memory ones, but leaves them in the store.
+//!
+//! ```rust
+//! use astra::actor_system::{Actor, ActorSystem, Message, PersistentScheduler};
+//! use astra::backends::memory::MemoryBackend;
+//! use async_trait::async_trait;
+//! use std::time::Duration;
+//!
+//! struct Reminder;
+//!
+//! #[async_trait]
+//! impl Actor for Reminder {
+//!     type Message = String;
+//!     type Error = String;
+//!
+//!     async fn receive(&mut self, message: Message<String>) -> Result<(), String> {
+//!         if let Message::Regular(text) = message {
+//!             println!("reminder: {}", text);
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let mut system = ActorSystem::new();
+//!     system.add_actor("reminder".to_string(), Reminder);
+//!
+//!     let scheduler = PersistentScheduler::open(&system, MemoryBackend::new()).await?;
+//!     scheduler
+//!         .send_after(
+//!             "invoice-42",
+//!             "reminder",
+//!             "invoice 42 is due".to_string(),
+//!             Duration::from_secs(86_400),
+//!         )
+//!         .await?;
+//!     assert_eq!(scheduler.pending().await.len(), 1);
+//!
+//!     // The timer stays in the store, for the next run to pick up
+//!     system.shutdown().await;
+//!     Ok(())
+//! }
+//! ```
+
+use super::{ActorSystem, TimerHandle, TimerKind};
+use crate::backends::storage::StorageBackend;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::Mutex;
+
+/// A timer recorded by a `PersistentScheduler`, as listed by `pending`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingTimer {
+    pub key: String,
+    /// The actor the timer delivers to.
+    pub actor: String,
+    pub kind: TimerKind,
+    /// When the timer fires next.
+    pub fire_at: SystemTime,
+}
+
+// A timer as persisted, with times in milliseconds since the Unix epoch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredTimer {
+    actor: String,
+    fire_at_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    period_ms: Option<u64>,
+    message: serde_json::Value,
+}
+
+fn to_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+fn from_millis(ms: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_millis(ms)
+}
+
+// The first fire time of a recurring timer after `now`, skipping missed periods
+fn next_fire_ms(fire_at_ms: u64, period_ms: u64, now_ms: u64) -> u64 {
+    let period_ms = period_ms.max(1);
+    if fire_at_ms > now_ms {
+        return fire_at_ms;
+    }
+    let missed = (now_ms - fire_at_ms) / period_ms + 1;
+    fire_at_ms + missed * period_ms
+}
+
+// The persisted timers and the running ones, updated together
+struct Store<B> {
+    backend: B,
+    timers: BTreeMap<String, StoredTimer>,
+    running: HashMap<String, TimerHandle>,
+}
+
+impl<B: StorageBackend> Store<B> {
+    // The error isn't `Send` and this runs in timer tasks, hence the `String`
+    async fn save(&mut self) -> Result<(), String> {
+        let data = serde_json::to_vec(&self.timers).map_err(|e| e.to_string())?;
+        self.backend
+            .write_bytes(&data)
+            .await
+            .map_err(|e| format!("Failed to persist timers: {}", e))
+    }
+}
+
+/// Timers that survive restarts, see the module docs.
+pub struct PersistentScheduler<M, B, E = String> {
+    system: ActorSystem<M, E>,
+    store: Arc<Mutex<Store<B>>>,
+    // Wall-clock time and clock reading taken together, so the system clock can
+    // be turned into wall-clock time
+    anchor: (SystemTime, Instant),
+}
+
+impl<M, B, E> Clone for PersistentScheduler<M, B, E> {
+    fn clone(&self) -> Self {
+        PersistentScheduler {
+            system: self.system.clone(),
+            store: Arc::clone(&self.store),
+            anchor: self.anchor,
+        }
+    }
+}
+
+impl<M, B, E> PersistentScheduler<M, B, E>
+where
+    M: Serialize + DeserializeOwned + Clone + Send + Sync + 'static + std::fmt::Debug,
+    B: StorageBackend + 'static,
+    E: Send + 'static + std::fmt::Debug,
+{
+    /// Loads the timers stored in `backend` and arms them against `system`'s
+    /// actors; those that are overdue fire right away. Add the actors first.
+    pub async fn open(system: &ActorSystem<M, E>, mut backend: B) -> Result<Self, Box<dyn Error>> {
+        let raw = backend.read_bytes().await?;
+        let timers: BTreeMap<String, StoredTimer> = if raw.is_empty() {
+            BTreeMap::new()
+        } else {
+            serde_json::from_slice(&raw)?
+        };
+        let scheduler = PersistentScheduler {
+            system: system.clone(),
+            store: Arc::new(Mutex::new(Store {
+                backend,
+                timers: timers.clone(),
+                running: HashMap::new(),
+            })),
+            anchor: (SystemTime::now(), system.clock.now()),
+        };
+        let mut store = scheduler.store.lock().await;
+        for (key, timer) in timers {
+            let message = serde_json::from_value(timer.message.clone())?;
+            let handle = scheduler.arm(&key, &timer, message);
+            store.running.insert(key, handle);
+        }
+        drop(store);
+        Ok(scheduler)
+    }
+
+    /// Delivers `message` to the named actor once `delay` has passed, replacing
+    /// any timer pending under `key`.
+    pub async fn send_after(
+        &self,
+        key: &str,
+        actor_name: &str,
+        message: M,
+        delay: Duration,
+    ) -> Result<(), String> {
+        self.schedule(key, actor_name, message, delay, None).await
+    }
+
+    /// Delivers a copy of `message` to the named actor every `period`, starting one
+    /// period from now, replacing any timer pending under `key`.
+    pub async fn schedule_recurring(
+        &self,
+        key: &str,
+        actor_name: &str,
+        period: Duration,
+        message: M,
+    ) -> Result<(), String> {
+        self.schedule(key, actor_name, message, period, Some(period))
+            .await
+    }
+
+    /// Cancels and forgets the timer pending under `key`. Returns `false` if
+    /// there was none.
+    pub async fn cancel(&self, key: &str) -> Result<bool, String> {
+        let mut store = self.store.lock().await;
+        if let Some(handle) = store.running.remove(key) {
+            handle.cancel();
+        }
+        if store.timers.remove(key).is_none() {
+            return Ok(false);
+        }
+        store.save().await?;
+        Ok(true)
+    }
+
+    /// The timers still pending, by key.
+    pub async fn pending(&self) -> Vec<PendingTimer> {
+        let store = self.store.lock().await;
+        store
+            .timers
+            .iter()
+            .map(|(key, timer)| PendingTimer {
+                key: key.clone(),
+                actor: timer.actor.clone(),
+                kind: match timer.period_ms {
+                    Some(period_ms) => TimerKind::Recurring(Duration::from_millis(period_ms)),
+                    None => TimerKind::Once,
+                },
+                fire_at: from_millis(timer.fire_at_ms),
+            })
+            .collect()
+    }
+
+    async fn schedule(
+        &self,
+        key: &str,
+        actor_name: &str,
+        message: M,
+        delay: Duration,
+        period: Option<Duration>,
+    ) -> Result<(), String> {
+        if self.system.actor_ref(actor_name).is_none() {
+            return Err(format!("Actor {} not found", actor_name));
+        }
+        let timer = StoredTimer {
+            actor: actor_name.to_string(),
+            fire_at_ms: to_millis(self.now() + delay),
+            period_ms: period.map(|period| period.as_millis() as u64),
+            message: serde_json::to_value(&message).map_err(|e| e.to_string())?,
+        };
+        let mut store = self.store.lock().await;
+        if let Some(replaced) = store.running.remove(key) {
+            replaced.cancel();
+        }
+        // Persisted before it is armed, so it can't fire without being recorded
+        store.timers.insert(key.to_string(), timer.clone());
+        store.save().await?;
+        let handle = self.arm(key, &timer, message);
+        store.running.insert(key.to_string(), handle);
+        Ok(())
+    }
+
+    // The current wall-clock time, as measured by the system's clock
+    fn now(&self) -> SystemTime {
+        let (wall, instant) = self.anchor;
+        wall + self.system.clock.now().saturating_duration_since(instant)
+    }
+
+    // Start the task that fires the timer
+    fn arm(&self, key: &str, timer: &StoredTimer, message: M) -> TimerHandle {
+        let delay = |fire_at_ms: u64, now: SystemTime| {
+            from_millis(fire_at_ms)
+                .duration_since(now)
+                .unwrap_or(Duration::ZERO)
+        };
+        let kind = match timer.period_ms {
+            Some(period_ms) => TimerKind::Recurring(Duration::from_millis(period_ms)),
+            None => TimerKind::Once,
+        };
+        let clock = Arc::clone(&self.system.clock);
+        let cancel = self.system.shared.cancel.child_token();
+        let timers = Arc::clone(&self.system.shared.timers);
+        let handle = timers.register(
+            &timer.actor,
+            kind,
+            clock.now() + delay(timer.fire_at_ms, self.now()),
+            cancel.clone(),
+        );
+        let id = handle.id();
+        let scheduler = self.clone();
+        let (key, actor_name, mut fire_at_ms, period_ms) = (
+            key.to_string(),
+            timer.actor.clone(),
+            timer.fire_at_ms,
+            timer.period_ms,
+        );
+        self.system.shared.spawn(async move {
+            loop {
+                let wait = delay(fire_at_ms, scheduler.now());
+                tokio::select! {
+                    _ = clock.sleep(wait) => {}
+                    _ = cancel.cancelled() => break,
+                }
+                match scheduler.system.actor_ref(&actor_name) {
+                    Some(actor) => {
+                        if let Err(e) = actor.send(message.clone()).await {
+                            println!(
+                                "Timer {} could not deliver to actor {}: {}",
+                                key, actor_name, e
+                            );
+                        }
+                    }
+                    // Left in the store, for a run that has the actor
+                    None => {
+                        println!("Timer {} found no actor {}", key, actor_name);
+                        break;
+                    }
+                }
+                let mut store = scheduler.store.lock().await;
+                // Replaced or cancelled while delivering
+                if store.running.get(&key).map(TimerHandle::id) != Some(id) {
+                    break;
+                }
+                let saved = match period_ms {
+                    Some(period_ms) => {
+                        fire_at_ms =
+                            next_fire_ms(fire_at_ms, period_ms, to_millis(scheduler.now()));
+                        if let Some(timer) = store.timers.get_mut(&key) {
+                            timer.fire_at_ms = fire_at_ms;
+                        }
+                        timers.reschedule(id, clock.now() + delay(fire_at_ms, scheduler.now()));
+                        store.save().await
+                    }
+                    None => {
+                        store.timers.remove(&key);
+                        store.running.remove(&key);
+                        store.save().await
+                    }
+                };
+                if let Err(e) = saved {
+                    println!("Timer {}: {}", key, e);
+                }
+                if period_ms.is_none() {
+                    break;
+                }
+            }
+            timers.remove(id);
+        });
+        handle
+    }
+}
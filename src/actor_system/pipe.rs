@@ -0,0 +1,37 @@
+// src/actor_system/pipe.rs
+
+//! # Stream pipes
+//!
+//! `ActorSystem::pipe_stream` forwards every item of a `futures::Stream` to an
+//! actor from a background task. Items are sent one at a time and the pipe waits
+//! whenever the actor's mailbox is full, so a fast stream can't outrun the actor.
+//! The pipe stops once the stream ends, when the actor stops, when the system's
+//! cancellation token is cancelled, or when cancelled through its `PipeHandle`.
+
+use tokio::task::JoinHandle;
+
+/// A running stream pipe started by `ActorSystem::pipe_stream`.
+#[derive(Debug)]
+pub struct PipeHandle {
+    pub(crate) task: JoinHandle<usize>,
+}
+
+impl PipeHandle {
+    /// Stops forwarding. Items already delivered stay in the actor's mailbox.
+    pub fn cancel(&self) {
+        self.task.abort();
+    }
+
+    /// Whether the pipe has stopped.
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    /// Waits for the pipe to stop and returns how many items it delivered, or an
+    /// error if it was cancelled.
+    pub async fn join(self) -> Result<usize, String> {
+        self.task
+            .await
+            .map_err(|e| format!("Pipe did not complete: {}", e))
+    }
+}
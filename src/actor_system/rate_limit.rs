@@ -0,0 +1,76 @@
+// src/actor_system/rate_limit.rs
+
+//! # Rate limiting
+//!
+//! An actor added with `ActorOptions::with_rate_limit(n, window)` handles at most
+//! `n` messages per `window`, with bursts of up to `n`. Excess messages are handled
+//! according to the `RateLimitPolicy`: rejected with `SendError::RateLimited` before
+//! they are enqueued, or delayed by pacing the actor until the rate allows them.
+//!
+//! The limit is a token bucket holding `n` tokens that refills continuously at
+//! `n` tokens per `window`; each message takes one token.
+
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// What happens to messages that exceed an actor's rate limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RateLimitPolicy {
+    /// Refuse the message, the sender gets `SendError::RateLimited`.
+    #[default]
+    Reject,
+    /// Accept the message but hold the actor back until it is within the rate
+    /// again. Senders feel it as backpressure once the mailbox fills up.
+    Delay,
+}
+
+#[derive(Debug)]
+pub(crate) struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    pub(crate) fn new(messages: u32, window: Duration) -> Self {
+        let capacity = f64::from(messages.max(1));
+        TokenBucket {
+            capacity,
+            tokens: capacity,
+            refill_per_sec: capacity / window.as_secs_f64().max(f64::EPSILON),
+            last_refill: Instant::now(),
+        }
+    }
+
+    // Take a token if one is available
+    pub(crate) fn try_acquire(&mut self) -> bool {
+        self.refill();
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    // Give back a token taken for a message that was never enqueued
+    pub(crate) fn refund(&mut self) {
+        self.tokens = (self.tokens + 1.0).min(self.capacity);
+    }
+
+    // Wait until a token is available, then take it
+    pub(crate) async fn acquire(&mut self) {
+        while !self.try_acquire() {
+            let missing = 1.0 - self.tokens;
+            tokio::time::sleep(Duration::from_secs_f64(missing / self.refill_per_sec)).await;
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+    }
+}
@@ -0,0 +1,180 @@
+// src/actor_system/router.rs
+
+//! # Routing
+//!
+//! `send_message` delivers to the actor named by the caller. A system built
+//! `with_router` can also pick the actor from the message itself:
+//! `ActorSystem::dispatch` asks the installed `Router` for a target name and
+//! sends the message there. Routers can route by a field of the message, hash a
+//! key across a pool of workers, keep a key sticky to one actor, and so on. Any
+//! `Fn(&M) -> Option<String>` closure is a router.
+//!
+//! `ConsistentHashRouter` spreads messages over a pool of workers by a key
+//! taken from each message: the same key always goes to the same worker, and
+//! workers can join or leave the pool while it routes. It places every worker
+//! at many points of a hash ring and sends a key to the first worker point at
+//! or after the key's hash, so adding a worker only takes keys over from its
+//! neighbours and removing one only moves the keys it had.
+//!
+//! ```rust
+//! use astra::actor_system::{Actor, ActorSystem, Message};
+//! use async_trait::async_trait;
+//! use std::sync::Arc;
+//!
+//! struct Printer;
+//!
+//! #[async_trait]
+//! impl Actor for Printer {
+//!     type Message = u32;
+//!     type Error = String;
+//!
+//!     async fn receive(&mut self, message: Message<u32>) -> Result<(), String> {
+//!         if let Message::Regular(n) = message {
+//!             println!("got {}", n);
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), String> {
+//!     // Spread numbers over two workers by their remainder
+//!     let mut system = ActorSystem::new()
+//!         .with_router(Arc::new(|n: &u32| Some(format!("worker{}", n % 2))));
+//!     system.add_actor("worker0".to_string(), Printer);
+//!     system.add_actor("worker1".to_string(), Printer);
+//!
+//!     system.dispatch(7).await?;
+//!     system.shutdown().await;
+//!     Ok(())
+//! }
+//! ```
+//!
+//! ## Consistent hashing
+//!
+//! ```rust
+//! use astra::actor_system::{ConsistentHashRouter, Router};
+//!
+//! // Orders keyed by customer, so each customer is handled by one worker
+//! struct Order {
+//!     customer: String,
+//! }
+//!
+//! let router = ConsistentHashRouter::new(|order: &Order| order.customer.clone())
+//!     .with_worker("worker0")
+//!     .with_worker("worker1");
+//! let order = Order { customer: "ada".to_string() };
+//! let worker = router.route(&order).unwrap();
+//! assert_eq!(router.route(&order).unwrap(), worker);
+//!
+//! router.add_worker("worker2");
+//! router.remove_worker(&worker);
+//! assert_ne!(router.route(&order).unwrap(), worker);
+//! ```
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+use std::sync::RwLock;
+
+// How many points of the ring each worker gets; more spread keys more evenly
+const POINTS_PER_WORKER: usize = 128;
+
+/// Picks the actor a message is dispatched to.
+pub trait Router<M>: Send + Sync {
+    /// The name of the actor that should receive `message`, or `None` if it has
+    /// nowhere to go.
+    fn route(&self, message: &M) -> Option<String>;
+}
+
+impl<M, F> Router<M> for F
+where
+    F: Fn(&M) -> Option<String> + Send + Sync,
+{
+    fn route(&self, message: &M) -> Option<String> {
+        self(message)
+    }
+}
+
+/// Routes messages to a pool of workers by consistent hashing of a key, see
+/// the module docs. Messages go nowhere while the pool is empty.
+pub struct ConsistentHashRouter<M> {
+    key_hash: Box<dyn Fn(&M) -> u64 + Send + Sync>,
+    // Each worker's points, by their position on the ring
+    ring: RwLock<BTreeMap<u64, String>>,
+}
+
+impl<M> ConsistentHashRouter<M> {
+    /// Creates a router with no workers, keying each message by
+    /// `key_extractor`.
+    pub fn new<K, F>(key_extractor: F) -> Self
+    where
+        K: Hash,
+        F: Fn(&M) -> K + Send + Sync + 'static,
+    {
+        ConsistentHashRouter {
+            key_hash: Box::new(move |message| hash_of(&key_extractor(message))),
+            ring: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// Adds the worker named `name` to the pool.
+    pub fn with_worker(self, name: &str) -> Self {
+        self.add_worker(name);
+        self
+    }
+
+    /// Adds the worker named `name` to the pool, taking over its share of the
+    /// keys. Adding a worker that is already there changes nothing.
+    pub fn add_worker(&self, name: &str) {
+        let mut ring = self.ring.write().unwrap();
+        for point in 0..POINTS_PER_WORKER {
+            ring.insert(hash_of(&(name, point)), name.to_string());
+        }
+    }
+
+    /// Removes the worker named `name` from the pool, handing its keys to the
+    /// remaining workers. Returns whether it was in the pool.
+    pub fn remove_worker(&self, name: &str) -> bool {
+        let mut ring = self.ring.write().unwrap();
+        let before = ring.len();
+        ring.retain(|_, worker| worker != name);
+        ring.len() != before
+    }
+
+    /// The names of the workers in the pool, sorted.
+    pub fn workers(&self) -> Vec<String> {
+        let mut workers: Vec<String> = self.ring.read().unwrap().values().cloned().collect();
+        workers.sort();
+        workers.dedup();
+        workers
+    }
+
+    /// The worker a message with key `key` is routed to.
+    pub fn worker_for<K: Hash>(&self, key: &K) -> Option<String> {
+        self.worker_at(hash_of(key))
+    }
+
+    // The owner of the first point at or after `hash`, wrapping around the ring
+    fn worker_at(&self, hash: u64) -> Option<String> {
+        let ring = self.ring.read().unwrap();
+        ring.range(hash..)
+            .next()
+            .or_else(|| ring.iter().next())
+            .map(|(_, worker)| worker.clone())
+    }
+}
+
+impl<M> Router<M> for ConsistentHashRouter<M> {
+    fn route(&self, message: &M) -> Option<String> {
+        self.worker_at((self.key_hash)(message))
+    }
+}
+
+// `DefaultHasher::new` always uses the same keys, so a key hashes the same way
+// in every process
+fn hash_of<K: Hash + ?Sized>(key: &K) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
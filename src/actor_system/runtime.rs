@@ -0,0 +1,120 @@
+// src/actor_system/runtime.rs
+
+//! # Dedicated runtime
+//!
+//! By default an `ActorSystem` spawns its tasks on the ambient tokio runtime,
+//! next to whatever else the application runs there. `ActorSystem::with_runtime`
+//! gives it a runtime of its own instead, built from a `RuntimeConfig`: actors,
+//! timers and stream pipes all run on its threads, so a busy web server can't
+//! delay them and they can't delay it. The system shuts the runtime down along
+//! with itself.
+//!
+//! With the `core-affinity` feature, `RuntimeConfig::with_core_pinning` pins
+//! each of the runtime's threads to a CPU core, handing the cores out in turn.
+
+use std::io;
+#[cfg(feature = "core-affinity")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature = "core-affinity")]
+use std::sync::Arc;
+use tokio::runtime::{Builder, Runtime};
+
+/// The thread name used unless `RuntimeConfig::with_thread_name` says otherwise.
+pub const DEFAULT_RUNTIME_THREAD_NAME: &str = "astra-actor";
+
+/// How `ActorSystem::with_runtime` builds the system's runtime: a multi-thread
+/// runtime with the given number of worker threads and thread name.
+#[derive(Debug, Clone)]
+pub struct RuntimeConfig {
+    worker_threads: Option<usize>,
+    thread_name: String,
+    #[cfg(feature = "core-affinity")]
+    core_pinning: bool,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        RuntimeConfig {
+            worker_threads: None,
+            thread_name: DEFAULT_RUNTIME_THREAD_NAME.to_string(),
+            #[cfg(feature = "core-affinity")]
+            core_pinning: false,
+        }
+    }
+}
+
+impl RuntimeConfig {
+    /// A multi-thread runtime with one worker per core, named
+    /// `DEFAULT_RUNTIME_THREAD_NAME`.
+    pub fn new() -> Self {
+        RuntimeConfig::default()
+    }
+
+    /// Runs the runtime on `threads` worker threads (at least one).
+    pub fn with_worker_threads(mut self, threads: usize) -> Self {
+        self.worker_threads = Some(threads.max(1));
+        self
+    }
+
+    /// Names the runtime's threads `name`, e.g. to tell them apart in a profiler.
+    pub fn with_thread_name(mut self, name: impl Into<String>) -> Self {
+        self.thread_name = name.into();
+        self
+    }
+
+    /// Pins each of the runtime's threads to a core, round-robin over the cores
+    /// the process may run on.
+    #[cfg(feature = "core-affinity")]
+    pub fn with_core_pinning(mut self) -> Self {
+        self.core_pinning = true;
+        self
+    }
+
+    pub(crate) fn build(&self) -> io::Result<Runtime> {
+        let mut builder = Builder::new_multi_thread();
+        builder.enable_all().thread_name(self.thread_name.clone());
+        if let Some(threads) = self.worker_threads {
+            builder.worker_threads(threads);
+        }
+        #[cfg(feature = "core-affinity")]
+        if self.core_pinning {
+            let cores = core_affinity::get_core_ids().unwrap_or_default();
+            let next = Arc::new(AtomicUsize::new(0));
+            builder.on_thread_start(move || {
+                if !cores.is_empty() {
+                    let core = cores[next.fetch_add(1, Ordering::Relaxed) % cores.len()];
+                    core_affinity::set_for_current(core);
+                }
+            });
+        }
+        builder.build()
+    }
+}
+
+// The runtime an `ActorSystem` owns. A runtime can't be dropped from async code,
+// so it is shut down in the background instead, whenever that happens.
+#[derive(Debug, Default)]
+pub(crate) struct OwnedRuntime {
+    runtime: Option<Runtime>,
+}
+
+impl OwnedRuntime {
+    pub(crate) fn new(runtime: Runtime) -> Self {
+        OwnedRuntime {
+            runtime: Some(runtime),
+        }
+    }
+
+    // Stop the runtime, dropping every task still on it
+    pub(crate) fn shutdown(&mut self) {
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+impl Drop for OwnedRuntime {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
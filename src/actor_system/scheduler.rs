@@ -0,0 +1,180 @@
+// src/actor_system/scheduler.rs
+
+//! # Pooled scheduling
+//!
+//! By default every actor handles its messages as soon as its task gets to run, so
+//! a chatty actor competes with all the others for the runtime's workers. A system
+//! created with `ActorSystem::with_pooled_scheduler(workers)` instead lets at most
+//! `workers` actors handle a message at the same time, and hands out those turns
+//! by weighted round-robin over the actors that have messages waiting.
+//!
+//! An actor added with `ActorOptions::with_weight(w)` gets up to `w` turns in a
+//! row each time its turn comes around, so under saturation actors process
+//! messages in proportion to their weights, and no actor with messages waiting is
+//! ever starved. Actors default to a weight of `DEFAULT_ACTOR_WEIGHT`.
+
+use std::sync::{Arc, Mutex};
+use tokio::sync::Semaphore;
+
+/// Weight of an actor unless set with `ActorOptions::with_weight`.
+pub const DEFAULT_ACTOR_WEIGHT: u32 = 1;
+
+#[derive(Debug)]
+pub(crate) struct PooledScheduler {
+    state: Mutex<PoolState>,
+}
+
+#[derive(Debug)]
+struct PoolState {
+    // Turns that can still be handed out
+    free: usize,
+    // Indexed by slot id, `None` once the actor has stopped
+    entries: Vec<Option<Entry>>,
+    // The entry whose turn it is
+    cursor: usize,
+}
+
+#[derive(Debug)]
+struct Entry {
+    weight: u32,
+    // Turns left for this entry before the cursor moves on
+    credits: u32,
+    state: TurnState,
+    grant: Arc<Semaphore>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TurnState {
+    // No message waiting
+    Idle,
+    // Waiting for a turn
+    Ready,
+    // Handling a message
+    Running,
+}
+
+impl PooledScheduler {
+    pub(crate) fn new(workers: usize) -> Self {
+        PooledScheduler {
+            state: Mutex::new(PoolState {
+                free: workers.max(1),
+                entries: Vec::new(),
+                cursor: 0,
+            }),
+        }
+    }
+
+    // Add an actor to the rotation
+    pub(crate) fn register(self: &Arc<Self>, weight: u32) -> SchedulerSlot {
+        let weight = weight.max(1);
+        let grant = Arc::new(Semaphore::new(0));
+        let mut state = self.state.lock().unwrap();
+        state.entries.push(Some(Entry {
+            weight,
+            credits: weight,
+            state: TurnState::Idle,
+            grant: Arc::clone(&grant),
+        }));
+        SchedulerSlot {
+            scheduler: Arc::clone(self),
+            id: state.entries.len() - 1,
+            grant,
+        }
+    }
+
+    fn update(&self, id: usize, f: impl FnOnce(&mut PoolState, &mut Entry)) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(mut entry) = state.entries[id].take() {
+            f(&mut state, &mut entry);
+            state.entries[id] = Some(entry);
+        }
+        state.dispatch();
+    }
+}
+
+impl PoolState {
+    // Hand out free turns, starting with the entry under the cursor
+    fn dispatch(&mut self) {
+        let len = self.entries.len();
+        let mut skipped = 0;
+        while self.free > 0 && skipped < len {
+            let cursor = self.cursor;
+            let mut advance = true;
+            match &mut self.entries[cursor] {
+                Some(entry) if entry.state == TurnState::Ready => {
+                    entry.state = TurnState::Running;
+                    entry.grant.add_permits(1);
+                    entry.credits -= 1;
+                    self.free -= 1;
+                    skipped = 0;
+                    advance = entry.credits == 0;
+                    if advance {
+                        entry.credits = entry.weight;
+                    }
+                }
+                Some(entry) => {
+                    entry.credits = entry.weight;
+                    skipped += 1;
+                }
+                None => skipped += 1,
+            }
+            if advance {
+                self.cursor = (cursor + 1) % len;
+            }
+        }
+    }
+}
+
+/// An actor's place in a `PooledScheduler`; dropping it leaves the rotation.
+#[derive(Debug)]
+pub(crate) struct SchedulerSlot {
+    scheduler: Arc<PooledScheduler>,
+    id: usize,
+    grant: Arc<Semaphore>,
+}
+
+impl SchedulerSlot {
+    // Wait for a turn to handle a message
+    pub(crate) async fn turn(&self) {
+        self.scheduler.update(self.id, |_, entry| {
+            if entry.state == TurnState::Idle {
+                entry.state = TurnState::Ready;
+            }
+        });
+        self.grant
+            .acquire()
+            .await
+            .expect("the grant semaphore is never closed")
+            .forget();
+    }
+
+    // Give the turn back. With `more` messages waiting the actor stays in line
+    // rather than waiting to ask again, so it doesn't lose its remaining credits.
+    pub(crate) fn finish(&self, more: bool) {
+        self.scheduler.update(self.id, |state, entry| {
+            if entry.state == TurnState::Running {
+                state.free += 1;
+            }
+            entry.state = if more {
+                TurnState::Ready
+            } else {
+                TurnState::Idle
+            };
+        });
+    }
+}
+
+impl Drop for SchedulerSlot {
+    fn drop(&mut self) {
+        let mut state = self.scheduler.state.lock().unwrap();
+        // A turn granted to a stopped actor goes back to the pool
+        if let Some(Entry {
+            state: TurnState::Running,
+            ..
+        }) = state.entries[self.id].take()
+        {
+            state.free += 1;
+        }
+        state.dispatch();
+    }
+}
@@ -0,0 +1,131 @@
+// src/actor_system/timers.rs
+
+//! # Timers
+//!
+//! `ActorSystem::send_after` delivers a message once a delay has passed and
+//! `ActorSystem::schedule_recurring` delivers one every period. Each timer runs
+//! in its own task and is tracked by the system until it finishes:
+//! `scheduled_timers` lists those still pending and `cancel_all_timers` stops
+//! them. Shutting the system down cancels every timer first, so none fires into
+//! actors that are going away. Timers read time through the system's `Clock`
+//! (see `ActorSystem::with_clock`).
+//!
+//! A recurring timer, or a one-shot timer that has not fired yet, also stops by
+//! itself once its target actor no longer accepts messages.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+/// Whether a timer fires once or keeps firing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimerKind {
+    Once,
+    Recurring(Duration),
+}
+
+/// A pending timer, as listed by `ActorSystem::scheduled_timers`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimerInfo {
+    pub id: u64,
+    /// The actor the timer delivers to.
+    pub actor: String,
+    pub kind: TimerKind,
+    /// When the timer fires next, on the system's clock.
+    pub next_fire: Instant,
+}
+
+#[derive(Debug)]
+struct TimerEntry {
+    info: TimerInfo,
+    cancel: CancellationToken,
+}
+
+// The timers of a system that haven't finished yet
+#[derive(Debug, Default)]
+pub(crate) struct TimerRegistry {
+    next_id: AtomicU64,
+    entries: Mutex<BTreeMap<u64, TimerEntry>>,
+}
+
+impl TimerRegistry {
+    // Record a new timer, returning its handle
+    pub(crate) fn register(
+        self: &Arc<Self>,
+        actor: &str,
+        kind: TimerKind,
+        next_fire: Instant,
+        cancel: CancellationToken,
+    ) -> TimerHandle {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+        let info = TimerInfo {
+            id,
+            actor: actor.to_string(),
+            kind,
+            next_fire,
+        };
+        self.entries.lock().unwrap().insert(
+            id,
+            TimerEntry {
+                info,
+                cancel: cancel.clone(),
+            },
+        );
+        TimerHandle {
+            id,
+            cancel,
+            registry: Arc::clone(self),
+        }
+    }
+
+    pub(crate) fn reschedule(&self, id: u64, next_fire: Instant) {
+        if let Some(entry) = self.entries.lock().unwrap().get_mut(&id) {
+            entry.info.next_fire = next_fire;
+        }
+    }
+
+    pub(crate) fn remove(&self, id: u64) {
+        self.entries.lock().unwrap().remove(&id);
+    }
+
+    pub(crate) fn list(&self) -> Vec<TimerInfo> {
+        let entries = self.entries.lock().unwrap();
+        entries.values().map(|entry| entry.info.clone()).collect()
+    }
+
+    pub(crate) fn cancel_all(&self) {
+        let entries = std::mem::take(&mut *self.entries.lock().unwrap());
+        for entry in entries.into_values() {
+            entry.cancel.cancel();
+        }
+    }
+}
+
+/// A timer started by `send_after` or `schedule_recurring`. Dropping the handle
+/// leaves the timer running.
+#[derive(Debug, Clone)]
+pub struct TimerHandle {
+    id: u64,
+    cancel: CancellationToken,
+    registry: Arc<TimerRegistry>,
+}
+
+impl TimerHandle {
+    /// The id the timer is listed under in `scheduled_timers`.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Stops the timer; a message it already delivered stays in the mailbox.
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+        self.registry.remove(self.id);
+    }
+
+    /// Whether the timer was cancelled, directly or with the whole system.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+}
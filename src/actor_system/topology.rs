@@ -0,0 +1,117 @@
+// src/actor_system/topology.rs
+
+//! # Topology snapshots
+//!
+//! `ActorSystem::describe` captures the wiring of a system (which actors are
+//! registered, their types and mailboxes) as a serializable `SystemSnapshot`,
+//! useful for debugging or to carry a deployment over a restart. After the
+//! restart, `ActorSystem::restore` re-registers the actors it has factories for.
+//!
+//! ```rust
+//! use astra::actor_system::{Actor, ActorFactories, ActorSystem, Message};
+//! use async_trait::async_trait;
+//!
+//! struct Worker;
+//!
+//! #[async_trait]
+//! impl Actor for Worker {
+//!     type Message = String;
+//!     type Error = String;
+//!
+//!     async fn receive(&mut self, _message: Message<String>) -> Result<(), String> {
+//!         Ok(())
+//!     }
+//! }
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let mut system = ActorSystem::new();
+//!     system.add_actor("worker".to_string(), Worker);
+//!     let snapshot = system.describe();
+//!     system.shutdown().await;
+//!
+//!     let mut restarted = ActorSystem::new();
+//!     let factories = ActorFactories::new().with_factory("worker", || Worker);
+//!     assert_eq!(restarted.restore(&snapshot, &factories), vec!["worker"]);
+//!     restarted.shutdown().await;
+//! }
+//! ```
+
+use super::{Actor, BoxedActor};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// One registered actor, as seen by `ActorSystem::describe`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActorDescription {
+    pub name: String,
+    /// The Rust type the actor was registered with.
+    pub actor_type: String,
+    /// Messages waiting in the mailbox when the snapshot was taken.
+    pub mailbox_depth: usize,
+    pub mailbox_capacity: usize,
+}
+
+/// The actors registered with an `ActorSystem`, in registration order.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SystemSnapshot {
+    pub actors: Vec<ActorDescription>,
+}
+
+impl SystemSnapshot {
+    /// Returns the description of the named actor, if it was registered.
+    pub fn actor(&self, name: &str) -> Option<&ActorDescription> {
+        self.actors.iter().find(|actor| actor.name == name)
+    }
+}
+
+type Factory<M, E> = Box<dyn Fn() -> (&'static str, BoxedActor<M, E>) + Send + Sync>;
+
+/// Builds actors by name for `ActorSystem::restore`.
+pub struct ActorFactories<M, E = String> {
+    factories: HashMap<String, Factory<M, E>>,
+}
+
+impl<M, E> ActorFactories<M, E> {
+    pub fn new() -> Self {
+        ActorFactories {
+            factories: HashMap::new(),
+        }
+    }
+
+    /// Registers the factory used to recreate the actor named `name`.
+    pub fn with_factory<A, F>(mut self, name: &str, factory: F) -> Self
+    where
+        A: Actor<Message = M, Error = E> + Send + 'static,
+        F: Fn() -> A + Send + Sync + 'static,
+    {
+        self.factories.insert(
+            name.to_string(),
+            Box::new(move || {
+                let actor: BoxedActor<M, E> = Box::new(factory());
+                (std::any::type_name::<A>(), actor)
+            }),
+        );
+        self
+    }
+
+    // Build a fresh actor for `name`, along with its type name
+    pub(crate) fn build(&self, name: &str) -> Option<(&'static str, BoxedActor<M, E>)> {
+        self.factories.get(name).map(|factory| factory())
+    }
+}
+
+impl<M, E> Default for ActorFactories<M, E> {
+    fn default() -> Self {
+        ActorFactories::new()
+    }
+}
+
+impl<M, E> fmt::Debug for ActorFactories<M, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ActorFactories")
+            .field("actors", &self.factories.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
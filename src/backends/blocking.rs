@@ -0,0 +1,127 @@
+// src/backends/blocking.rs
+
+//! # Blocking Backend
+//!
+//! `BlockingBackend` turns a synchronous storage library, e.g. a blocking SQLite
+//! driver, into a `StorageBackend`. It is made of three closures reading,
+//! writing and cleaning up the stored data, each run on tokio's blocking thread
+//! pool with `spawn_blocking`, so a slow call doesn't stall the worker threads
+//! other actors run on.
+//!
+//! The closures deal in bytes; `read` fails if what they return isn't valid
+//! UTF-8. Appending, ranges and `compare_and_swap` are built on them as the
+//! trait's defaults, so they aren't atomic. A closure that panics fails the
+//! operation instead of the actor.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use astra::backends::blocking::BlockingBackend;
+//! use astra::backends::storage::StorageBackend;
+//! use std::sync::{Arc, Mutex};
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     // Stands in for a synchronous driver
+//!     let stored = Arc::new(Mutex::new(Vec::new()));
+//!     let (read, write, cleanup) = (stored.clone(), stored.clone(), stored.clone());
+//!     let mut backend = BlockingBackend::new(
+//!         move || Ok(read.lock().unwrap().clone()),
+//!         move |data: &[u8]| {
+//!             *write.lock().unwrap() = data.to_vec();
+//!             Ok(())
+//!         },
+//!         move || {
+//!             cleanup.lock().unwrap().clear();
+//!             Ok(())
+//!         },
+//!     );
+//!
+//!     backend.write("balance=10").await?;
+//!     assert_eq!(backend.read().await?, "balance=10");
+//!     Ok(())
+//! }
+//! ```
+
+use super::storage::StorageBackend;
+use async_trait::async_trait;
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+/// The error of a blocking call, which has to cross threads.
+pub type BlockingError = Box<dyn Error + Send + Sync>;
+
+type BlockingRead = Arc<dyn Fn() -> Result<Vec<u8>, BlockingError> + Send + Sync>;
+type BlockingWrite = Arc<dyn Fn(&[u8]) -> Result<(), BlockingError> + Send + Sync>;
+type BlockingCleanup = Arc<dyn Fn() -> Result<(), BlockingError> + Send + Sync>;
+
+/// Runs synchronous storage calls off the async worker threads, see the module
+/// docs. Clones share the closures.
+#[derive(Clone)]
+pub struct BlockingBackend {
+    read: BlockingRead,
+    write: BlockingWrite,
+    cleanup: BlockingCleanup,
+}
+
+impl BlockingBackend {
+    // Create a BlockingBackend storing data with `write`, fetching it with
+    // `read` and removing it with `cleanup`
+    pub fn new<R, W, C>(read: R, write: W, cleanup: C) -> Self
+    where
+        R: Fn() -> Result<Vec<u8>, BlockingError> + Send + Sync + 'static,
+        W: Fn(&[u8]) -> Result<(), BlockingError> + Send + Sync + 'static,
+        C: Fn() -> Result<(), BlockingError> + Send + Sync + 'static,
+    {
+        BlockingBackend {
+            read: Arc::new(read),
+            write: Arc::new(write),
+            cleanup: Arc::new(cleanup),
+        }
+    }
+}
+
+impl fmt::Debug for BlockingBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlockingBackend").finish_non_exhaustive()
+    }
+}
+
+// Run a blocking call on the blocking thread pool
+async fn run_blocking<T, F>(call: F) -> Result<T, Box<dyn Error>>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, BlockingError> + Send + 'static,
+{
+    match tokio::task::spawn_blocking(call).await {
+        Ok(result) => result.map_err(|e| e as Box<dyn Error>),
+        Err(e) => Err(format!("Blocking storage call failed: {}", e).into()),
+    }
+}
+
+#[async_trait]
+impl StorageBackend for BlockingBackend {
+    async fn write(&mut self, data: &str) -> Result<(), Box<dyn Error>> {
+        self.write_bytes(data.as_bytes()).await
+    }
+
+    async fn read(&mut self) -> Result<String, Box<dyn Error>> {
+        Ok(String::from_utf8(self.read_bytes().await?)?)
+    }
+
+    async fn cleanup(&mut self) -> Result<(), Box<dyn Error>> {
+        let cleanup = Arc::clone(&self.cleanup);
+        run_blocking(move || cleanup()).await
+    }
+
+    async fn write_bytes(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        let (write, data) = (Arc::clone(&self.write), data.to_vec());
+        run_blocking(move || write(&data)).await
+    }
+
+    async fn read_bytes(&mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+        let read = Arc::clone(&self.read);
+        run_blocking(move || read()).await
+    }
+}
@@ -0,0 +1,166 @@
+// src/backends/boxed.rs
+
+//! # Boxed Backend
+//!
+//! `StorageBackend` requires `Clone`, so it can't be used as a trait object.
+//! `BoxedBackend` erases the type of any backend behind a single concrete type,
+//! for when the backend is only known at runtime or a stack of decorators gets
+//! too long to spell out (see `BackendBuilder`).
+
+use super::storage::StorageBackend;
+use async_trait::async_trait;
+use std::error::Error;
+use std::fmt;
+use std::time::SystemTime;
+
+// The object-safe twin of `StorageBackend`, implemented for every backend
+#[async_trait]
+trait DynBackend: Send + Sync {
+    async fn write(&mut self, data: &str) -> Result<(), Box<dyn Error>>;
+    async fn read(&mut self) -> Result<String, Box<dyn Error>>;
+    async fn cleanup(&mut self) -> Result<(), Box<dyn Error>>;
+    async fn write_bytes(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>>;
+    async fn extend_bytes(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>>;
+    async fn read_bytes(&mut self) -> Result<Vec<u8>, Box<dyn Error>>;
+    async fn read_range(&mut self, start: usize, len: usize) -> Result<String, Box<dyn Error>>;
+    async fn flush(&mut self) -> Result<(), Box<dyn Error>>;
+    async fn compare_and_swap(&mut self, expected: &str, new: &str)
+        -> Result<bool, Box<dyn Error>>;
+    async fn last_modified(&mut self) -> Result<Option<SystemTime>, Box<dyn Error>>;
+    async fn health_check(&mut self) -> Result<(), Box<dyn Error>>;
+    fn clone_box(&self) -> Box<dyn DynBackend>;
+}
+
+#[async_trait]
+impl<B: StorageBackend + 'static> DynBackend for B {
+    async fn write(&mut self, data: &str) -> Result<(), Box<dyn Error>> {
+        StorageBackend::write(self, data).await
+    }
+
+    async fn read(&mut self) -> Result<String, Box<dyn Error>> {
+        StorageBackend::read(self).await
+    }
+
+    async fn cleanup(&mut self) -> Result<(), Box<dyn Error>> {
+        StorageBackend::cleanup(self).await
+    }
+
+    async fn write_bytes(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        StorageBackend::write_bytes(self, data).await
+    }
+
+    async fn extend_bytes(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        StorageBackend::extend_bytes(self, data).await
+    }
+
+    async fn read_bytes(&mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+        StorageBackend::read_bytes(self).await
+    }
+
+    async fn read_range(&mut self, start: usize, len: usize) -> Result<String, Box<dyn Error>> {
+        StorageBackend::read_range(self, start, len).await
+    }
+
+    async fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        StorageBackend::flush(self).await
+    }
+
+    async fn compare_and_swap(
+        &mut self,
+        expected: &str,
+        new: &str,
+    ) -> Result<bool, Box<dyn Error>> {
+        StorageBackend::compare_and_swap(self, expected, new).await
+    }
+
+    async fn last_modified(&mut self) -> Result<Option<SystemTime>, Box<dyn Error>> {
+        StorageBackend::last_modified(self).await
+    }
+
+    async fn health_check(&mut self) -> Result<(), Box<dyn Error>> {
+        StorageBackend::health_check(self).await
+    }
+
+    fn clone_box(&self) -> Box<dyn DynBackend> {
+        Box::new(self.clone())
+    }
+}
+
+/// Any `StorageBackend`, behind a box.
+pub struct BoxedBackend {
+    inner: Box<dyn DynBackend>,
+}
+
+impl BoxedBackend {
+    // Box up `backend`, forwarding every operation to it
+    pub fn new<B: StorageBackend + 'static>(backend: B) -> Self {
+        BoxedBackend {
+            inner: Box::new(backend),
+        }
+    }
+}
+
+impl Clone for BoxedBackend {
+    fn clone(&self) -> Self {
+        BoxedBackend {
+            inner: self.inner.clone_box(),
+        }
+    }
+}
+
+impl fmt::Debug for BoxedBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoxedBackend").finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl StorageBackend for BoxedBackend {
+    async fn write(&mut self, data: &str) -> Result<(), Box<dyn Error>> {
+        self.inner.write(data).await
+    }
+
+    async fn read(&mut self) -> Result<String, Box<dyn Error>> {
+        self.inner.read().await
+    }
+
+    async fn cleanup(&mut self) -> Result<(), Box<dyn Error>> {
+        self.inner.cleanup().await
+    }
+
+    async fn write_bytes(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        self.inner.write_bytes(data).await
+    }
+
+    async fn extend_bytes(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        self.inner.extend_bytes(data).await
+    }
+
+    async fn read_bytes(&mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.inner.read_bytes().await
+    }
+
+    async fn read_range(&mut self, start: usize, len: usize) -> Result<String, Box<dyn Error>> {
+        self.inner.read_range(start, len).await
+    }
+
+    async fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        self.inner.flush().await
+    }
+
+    async fn compare_and_swap(
+        &mut self,
+        expected: &str,
+        new: &str,
+    ) -> Result<bool, Box<dyn Error>> {
+        self.inner.compare_and_swap(expected, new).await
+    }
+
+    async fn last_modified(&mut self) -> Result<Option<SystemTime>, Box<dyn Error>> {
+        self.inner.last_modified().await
+    }
+
+    async fn health_check(&mut self) -> Result<(), Box<dyn Error>> {
+        self.inner.health_check().await
+    }
+}
@@ -0,0 +1,131 @@
+// src/backends/buffered.rs
+
+//! # Buffered Backend
+//!
+//! `BufferedBackend` wraps another backend and holds writes in memory until
+//! `flush_threshold` writes have been buffered (or `flush` is called), so a burst
+//! of writes costs a single write on the inner backend.
+//!
+//! ## Read consistency
+//!
+//! By default reads are *consistent*: `read` flushes any pending write first, so a
+//! read always reflects the prior writes made through the same handle. The price is
+//! that interleaving reads and writes defeats the buffering, since every read forces
+//! a write to the inner backend. When stale reads are acceptable, disable it with
+//! `with_read_consistent(false)` and reads go straight to the inner backend.
+//!
+//! Buffering is per handle: a cloned `BufferedBackend` carries its own copy of the
+//! pending write, and other handles on the same storage only see it once flushed.
+
+use super::storage::StorageBackend;
+use async_trait::async_trait;
+use std::error::Error;
+use std::time::SystemTime;
+
+#[derive(Debug, Clone)]
+pub struct BufferedBackend<B: StorageBackend> {
+    inner: B,
+    pending: Option<Vec<u8>>,
+    buffered_writes: usize,
+    flush_threshold: usize,
+    read_consistent: bool,
+}
+
+impl<B: StorageBackend> BufferedBackend<B> {
+    // Create a new BufferedBackend that flushes after `flush_threshold` writes
+    pub fn new(inner: B, flush_threshold: usize) -> Self {
+        BufferedBackend {
+            inner,
+            pending: None,
+            buffered_writes: 0,
+            flush_threshold: flush_threshold.max(1),
+            read_consistent: true,
+        }
+    }
+
+    /// Controls whether `read` flushes pending writes before reading (the default).
+    pub fn with_read_consistent(mut self, read_consistent: bool) -> Self {
+        self.read_consistent = read_consistent;
+        self
+    }
+
+    /// Returns true if there is a write that has not reached the inner backend yet.
+    pub fn has_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Returns a reference to the wrapped backend.
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+}
+
+#[async_trait]
+impl<B: StorageBackend> StorageBackend for BufferedBackend<B> {
+    // Buffer the write, flushing once the threshold is reached
+    async fn write(&mut self, data: &str) -> Result<(), Box<dyn Error>> {
+        self.write_bytes(data.as_bytes()).await
+    }
+
+    // Read from the inner backend, flushing first in consistent mode
+    async fn read(&mut self) -> Result<String, Box<dyn Error>> {
+        if self.read_consistent {
+            self.flush().await?;
+        }
+        self.inner.read().await
+    }
+
+    // Buffer raw bytes, flushing once the threshold is reached
+    async fn write_bytes(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        // Each write replaces the stored value, so only the latest one is kept
+        self.pending = Some(data.to_vec());
+        self.buffered_writes += 1;
+        if self.buffered_writes >= self.flush_threshold {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    // Appending builds on the pending value, so write that out first
+    async fn extend_bytes(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        self.flush().await?;
+        self.inner.extend_bytes(data).await
+    }
+
+    // Read raw bytes, flushing first in consistent mode
+    async fn read_bytes(&mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+        if self.read_consistent {
+            self.flush().await?;
+        }
+        self.inner.read_bytes().await
+    }
+
+    // Drop anything still buffered and clean up the inner backend
+    async fn cleanup(&mut self) -> Result<(), Box<dyn Error>> {
+        self.pending = None;
+        self.buffered_writes = 0;
+        self.inner.cleanup().await
+    }
+
+    // Write the pending value (if any) to the inner backend
+    async fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        if let Some(data) = self.pending.take() {
+            if let Err(e) = self.inner.write_bytes(&data).await {
+                // Keep the value buffered so a later flush can retry it
+                self.pending = Some(data);
+                return Err(e);
+            }
+        }
+        self.buffered_writes = 0;
+        self.inner.flush().await
+    }
+
+    // Writes still buffered haven't reached the inner backend, so they don't count
+    async fn last_modified(&mut self) -> Result<Option<SystemTime>, Box<dyn Error>> {
+        self.inner.last_modified().await
+    }
+
+    async fn health_check(&mut self) -> Result<(), Box<dyn Error>> {
+        self.inner.health_check().await
+    }
+}
@@ -0,0 +1,106 @@
+// src/backends/builder.rs
+
+//! # Backend Builder
+//!
+//! `BackendBuilder` stacks decorators on a base backend without spelling out
+//! `CachingBackend::new(BufferedBackend::new(MeteredBackend::new(...), n), ttl)`.
+//! Whatever order the `with_*` methods are called in, the layers are applied
+//! from the base outwards as:
+//!
+//! 1. `MeteredBackend`, closest to the base, so it measures the storage itself
+//! 2. `BufferedBackend`, batching writes before they reach the storage
+//! 3. `CachingBackend`, outermost, so cached reads skip every other layer
+//!
+//! The result is a `BoxedBackend`, whatever the layers are.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use astra::backends::builder::BackendBuilder;
+//! use astra::backends::memory::MemoryBackend;
+//! use astra::backends::storage::StorageBackend;
+//! use std::time::Duration;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let builder = BackendBuilder::new(MemoryBackend::new())
+//!         .with_cache(Duration::from_secs(5))
+//!         .with_metrics();
+//!     // Grab the metrics before the backend's type is erased
+//!     let metrics = builder.metrics().unwrap();
+//!     let mut backend = builder.build();
+//!
+//!     backend.write("hello").await?;
+//!     assert_eq!(backend.read().await?, "hello");
+//!     // The read was served by the cache, only the write reached the storage
+//!     assert_eq!(metrics.stats().count, 1);
+//!     Ok(())
+//! }
+//! ```
+
+use super::boxed::BoxedBackend;
+use super::buffered::BufferedBackend;
+use super::caching::CachingBackend;
+use super::metered::{BackendMetrics, MeteredBackend};
+use super::storage::StorageBackend;
+use std::time::Duration;
+
+/// Collects the decorators to put around a base backend, see the module docs.
+#[derive(Debug, Clone)]
+pub struct BackendBuilder<B: StorageBackend> {
+    base: B,
+    metrics: Option<BackendMetrics>,
+    flush_threshold: Option<usize>,
+    cache_ttl: Option<Duration>,
+}
+
+impl<B: StorageBackend + 'static> BackendBuilder<B> {
+    pub fn new(base: B) -> Self {
+        BackendBuilder {
+            base,
+            metrics: None,
+            flush_threshold: None,
+            cache_ttl: None,
+        }
+    }
+
+    /// Records operation latencies with a `MeteredBackend`; read them through
+    /// `metrics()`.
+    pub fn with_metrics(mut self) -> Self {
+        self.metrics.get_or_insert_with(BackendMetrics::new);
+        self
+    }
+
+    /// Buffers writes with a `BufferedBackend` flushing every `flush_threshold`
+    /// writes.
+    pub fn with_buffering(mut self, flush_threshold: usize) -> Self {
+        self.flush_threshold = Some(flush_threshold);
+        self
+    }
+
+    /// Caches reads for `ttl` with a `CachingBackend`.
+    pub fn with_cache(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = Some(ttl);
+        self
+    }
+
+    /// The metrics the built backend will record into, if `with_metrics` was set.
+    pub fn metrics(&self) -> Option<BackendMetrics> {
+        self.metrics.clone()
+    }
+
+    /// Wraps the base backend in the chosen decorators.
+    pub fn build(self) -> BoxedBackend {
+        let mut backend = match self.metrics {
+            Some(metrics) => BoxedBackend::new(MeteredBackend::with_metrics(self.base, metrics)),
+            None => BoxedBackend::new(self.base),
+        };
+        if let Some(flush_threshold) = self.flush_threshold {
+            backend = BoxedBackend::new(BufferedBackend::new(backend, flush_threshold));
+        }
+        if let Some(ttl) = self.cache_ttl {
+            backend = BoxedBackend::new(CachingBackend::new(backend, ttl));
+        }
+        backend
+    }
+}
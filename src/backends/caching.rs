@@ -0,0 +1,150 @@
+// src/backends/caching.rs
+
+//! # Caching Backend
+//!
+//! `CachingBackend` wraps a slow backend (a database, object storage) and keeps the
+//! last value read or written in memory for `ttl`. Reads within the TTL are served
+//! from the cache without touching the inner backend; once it expires the next
+//! read goes to the inner backend again and refreshes the cache.
+//!
+//! Writes go to the inner backend and, once they succeed, replace the cached value,
+//! so reads through the same handle always see their own writes. Writes made
+//! through other handles (or by other processes) are only seen once the cached
+//! value expires. Like buffering, caching is per handle: a clone gets its own copy
+//! of the cache.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use astra::backends::caching::CachingBackend;
+//! use astra::backends::memory::MemoryBackend;
+//! use astra::backends::storage::StorageBackend;
+//! use std::time::Duration;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let mut backend = CachingBackend::new(MemoryBackend::new(), Duration::from_secs(30));
+//!     backend.write("config").await?;
+//!     // Served from the cache
+//!     assert_eq!(backend.read().await?, "config");
+//!     Ok(())
+//! }
+//! ```
+
+use super::storage::StorageBackend;
+use async_trait::async_trait;
+use std::error::Error;
+use std::time::{Duration, Instant, SystemTime};
+
+#[derive(Debug, Clone)]
+pub struct CachingBackend<B: StorageBackend> {
+    inner: B,
+    ttl: Duration,
+    cached: Option<(Vec<u8>, Instant)>,
+}
+
+impl<B: StorageBackend> CachingBackend<B> {
+    // Create a new CachingBackend keeping values for `ttl`
+    pub fn new(inner: B, ttl: Duration) -> Self {
+        CachingBackend {
+            inner,
+            ttl,
+            cached: None,
+        }
+    }
+
+    /// Returns true if a read right now would be served from the cache.
+    pub fn is_cached(&self) -> bool {
+        self.fresh().is_some()
+    }
+
+    /// Drops the cached value, so the next read goes to the inner backend.
+    pub fn invalidate(&mut self) {
+        self.cached = None;
+    }
+
+    /// Returns a reference to the wrapped backend.
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    // The cached value, unless it has expired
+    fn fresh(&self) -> Option<&[u8]> {
+        match &self.cached {
+            Some((data, cached_at)) if cached_at.elapsed() < self.ttl => Some(data),
+            _ => None,
+        }
+    }
+
+    fn cache(&mut self, data: Vec<u8>) {
+        self.cached = Some((data, Instant::now()));
+    }
+}
+
+#[async_trait]
+impl<B: StorageBackend> StorageBackend for CachingBackend<B> {
+    async fn write(&mut self, data: &str) -> Result<(), Box<dyn Error>> {
+        // Don't serve a value the inner backend may not hold anymore
+        self.invalidate();
+        self.inner.write(data).await?;
+        self.cache(data.as_bytes().to_vec());
+        Ok(())
+    }
+
+    async fn read(&mut self) -> Result<String, Box<dyn Error>> {
+        let data = self.read_bytes().await?;
+        Ok(String::from_utf8(data)?)
+    }
+
+    async fn write_bytes(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        self.invalidate();
+        self.inner.write_bytes(data).await?;
+        self.cache(data.to_vec());
+        Ok(())
+    }
+
+    async fn extend_bytes(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        self.invalidate();
+        self.inner.extend_bytes(data).await
+    }
+
+    // Serve from the cache while it's fresh, otherwise read through
+    async fn read_bytes(&mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+        if let Some(data) = self.fresh() {
+            return Ok(data.to_vec());
+        }
+        let data = self.inner.read_bytes().await?;
+        self.cache(data.clone());
+        Ok(data)
+    }
+
+    async fn cleanup(&mut self) -> Result<(), Box<dyn Error>> {
+        self.invalidate();
+        self.inner.cleanup().await
+    }
+
+    async fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        self.inner.flush().await
+    }
+
+    async fn compare_and_swap(
+        &mut self,
+        expected: &str,
+        new: &str,
+    ) -> Result<bool, Box<dyn Error>> {
+        self.invalidate();
+        let swapped = self.inner.compare_and_swap(expected, new).await?;
+        if swapped {
+            self.cache(new.as_bytes().to_vec());
+        }
+        Ok(swapped)
+    }
+
+    async fn last_modified(&mut self) -> Result<Option<SystemTime>, Box<dyn Error>> {
+        self.inner.last_modified().await
+    }
+
+    async fn health_check(&mut self) -> Result<(), Box<dyn Error>> {
+        self.inner.health_check().await
+    }
+}
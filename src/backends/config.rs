@@ -0,0 +1,131 @@
+// src/backends/config.rs
+
+//! # Backend Configuration
+//!
+//! `BackendConfig` describes a backend as data, so deployment config can pick
+//! one at runtime instead of the code calling a particular constructor. It
+//! parses from a URI:
+//!
+//! - `memory://`: a fresh `MemoryBackend`
+//! - `file:///tmp/state`: a `FileBackend` on `/tmp/state`, created if missing;
+//!   `file://state` names `state` relative to the working directory
+//!
+//! Any other scheme fails with `BackendConfigError::UnknownScheme`; there is no
+//! Redis, S3 or Postgres backend to build yet. `from_uri` parses and builds in
+//! one go, returning a `BoxedBackend` whatever the scheme.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use astra::backends::config::BackendConfig;
+//! use astra::backends::storage::StorageBackend;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let config: BackendConfig = "memory://".parse()?;
+//!     assert_eq!(config, BackendConfig::Memory);
+//!
+//!     let mut backend = astra::backends::from_uri("memory://").await?;
+//!     backend.write("hello").await?;
+//!     assert_eq!(backend.read().await?, "hello");
+//!     Ok(())
+//! }
+//! ```
+
+use super::boxed::BoxedBackend;
+use super::file::FileBackend;
+use super::memory::MemoryBackend;
+use std::fmt;
+use std::str::FromStr;
+
+/// A backend to build, see the module docs for the URIs it parses from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BackendConfig {
+    /// A `MemoryBackend`, from `memory://`.
+    Memory,
+    /// A `FileBackend` on `path`, from `file://<path>`.
+    File { path: String },
+}
+
+/// Why a backend URI could not be parsed or its backend built.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BackendConfigError {
+    /// The URI isn't of the form `<scheme>://<rest>`, or `<rest>` doesn't suit
+    /// the scheme.
+    Malformed { uri: String, reason: String },
+    /// No backend is known by this scheme.
+    UnknownScheme(String),
+    /// The URI parsed, but its backend failed to open.
+    Open { uri: String, reason: String },
+}
+
+impl fmt::Display for BackendConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackendConfigError::Malformed { uri, reason } => {
+                write!(f, "Malformed backend URI {}: {}", uri, reason)
+            }
+            BackendConfigError::UnknownScheme(scheme) => {
+                write!(f, "Unknown backend scheme {}", scheme)
+            }
+            BackendConfigError::Open { uri, reason } => {
+                write!(f, "Failed to open backend {}: {}", uri, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BackendConfigError {}
+
+impl FromStr for BackendConfig {
+    type Err = BackendConfigError;
+
+    fn from_str(uri: &str) -> Result<Self, Self::Err> {
+        let malformed = |reason: &str| BackendConfigError::Malformed {
+            uri: uri.to_string(),
+            reason: reason.to_string(),
+        };
+        let (scheme, rest) = uri
+            .split_once("://")
+            .ok_or_else(|| malformed("expected <scheme>://"))?;
+        match scheme.to_ascii_lowercase().as_str() {
+            "memory" if rest.is_empty() => Ok(BackendConfig::Memory),
+            "memory" => Err(malformed("memory:// takes nothing after the scheme")),
+            "file" if rest.is_empty() => Err(malformed("file:// needs a path")),
+            "file" => Ok(BackendConfig::File {
+                path: rest.to_string(),
+            }),
+            _ => Err(BackendConfigError::UnknownScheme(scheme.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for BackendConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackendConfig::Memory => write!(f, "memory://"),
+            BackendConfig::File { path } => write!(f, "file://{}", path),
+        }
+    }
+}
+
+impl BackendConfig {
+    /// Builds the backend this config describes.
+    pub async fn build(&self) -> Result<BoxedBackend, BackendConfigError> {
+        match self {
+            BackendConfig::Memory => Ok(BoxedBackend::new(MemoryBackend::new())),
+            BackendConfig::File { path } => FileBackend::new(path)
+                .await
+                .map(BoxedBackend::new)
+                .map_err(|e| BackendConfigError::Open {
+                    uri: self.to_string(),
+                    reason: e.to_string(),
+                }),
+        }
+    }
+}
+
+/// Parses `uri` into a `BackendConfig` and builds its backend.
+pub async fn from_uri(uri: &str) -> Result<BoxedBackend, BackendConfigError> {
+    uri.parse::<BackendConfig>()?.build().await
+}
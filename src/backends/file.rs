@@ -2,13 +2,43 @@
 
 use super::storage::StorageBackend;
 use async_trait::async_trait;
+use std::collections::HashMap;
 use std::error::Error;
-use tokio::fs::{self, File};
-use tokio::io::{self, AsyncReadExt, AsyncWriteExt};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+use std::time::SystemTime;
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::{self, AsyncReadExt, AsyncSeekExt, AsyncWriteExt, SeekFrom};
+use tokio::sync::RwLock;
+
+// One lock per file, shared by every FileBackend pointing at it, so readers never
+// observe a file that a concurrent write has truncated but not yet refilled. The
+// backends own the locks, so a file's entry dies with its last backend.
+static FILE_LOCKS: OnceLock<Mutex<HashMap<PathBuf, Weak<RwLock<()>>>>> = OnceLock::new();
+
+fn lock_for(path: PathBuf) -> Arc<RwLock<()>> {
+    let locks = FILE_LOCKS.get_or_init(|| Mutex::new(HashMap::new()));
+    let mut locks = locks.lock().unwrap();
+    if let Some(lock) = locks.get(&path).and_then(Weak::upgrade) {
+        return lock;
+    }
+    // Forget the files no backend points at anymore before adding this one
+    locks.retain(|_, lock| lock.strong_count() > 0);
+    let lock = Arc::new(RwLock::new(()));
+    locks.insert(path, Arc::downgrade(&lock));
+    lock
+}
 
 #[derive(Debug, Clone)]
 pub struct FileBackend {
     file_path: String,
+    lock: Arc<RwLock<()>>,
+    // In append mode writes add to the end of the file instead of replacing it
+    append: bool,
+    // In durable mode every write is synced to disk before it returns
+    durable: bool,
+    // Reads of more bytes than this fail instead of loading them
+    max_read_size: Option<usize>,
 }
 
 impl FileBackend {
@@ -16,34 +46,207 @@ impl FileBackend {
     pub async fn new(file_path: &str) -> io::Result<Self> {
         // Ensure that the file exists or is created
         File::create(file_path).await?;
+        // Key the lock by the canonical path so different spellings of the same
+        // file share it
+        let path = fs::canonicalize(file_path).await?;
         Ok(FileBackend {
             file_path: file_path.to_string(),
+            lock: lock_for(path),
+            append: false,
+            durable: false,
+            max_read_size: None,
         })
     }
+
+    // Create a FileBackend that appends every write to the end of the file, keeping
+    // whatever the file already contains. Use `append` to learn where each record
+    // starts and `read_from_offset` to read back from there.
+    pub async fn new_append(file_path: &str) -> io::Result<Self> {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(file_path)
+            .await?;
+        let path = fs::canonicalize(file_path).await?;
+        Ok(FileBackend {
+            file_path: file_path.to_string(),
+            lock: lock_for(path),
+            append: true,
+            durable: false,
+            max_read_size: None,
+        })
+    }
+
+    // Sync every write to disk with `sync_all` before returning, so a write that
+    // returned `Ok` survives a power failure. Each write then waits for the disk,
+    // which costs from well under a millisecond on an SSD to tens of
+    // milliseconds on a spinning disk or network storage, so this is off by
+    // default. Worth it for state that can't be lost, like a `SnapshotActor`'s.
+    pub fn with_durable(mut self, durable: bool) -> Self {
+        self.durable = durable;
+        self
+    }
+
+    // Fail reads of files over `max` bytes, without loading more than `max + 1`
+    // of them, so a corrupted or hostile file can't exhaust memory. Reads are
+    // unlimited by default.
+    pub fn with_max_read_size(mut self, max: usize) -> Self {
+        self.max_read_size = Some(max);
+        self
+    }
+
+    // Read `file` from where it is to the end, up to the read limit
+    async fn read_bounded(&self, mut file: File) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut content = Vec::new();
+        let Some(max) = self.max_read_size else {
+            file.read_to_end(&mut content).await?;
+            return Ok(content);
+        };
+        file.take(max as u64 + 1).read_to_end(&mut content).await?;
+        if content.len() > max {
+            return Err(format!(
+                "File {} holds more than the {} byte read limit",
+                self.file_path, max
+            )
+            .into());
+        }
+        Ok(content)
+    }
+
+    // Write all of `data` to `file`, syncing it to disk in durable mode. tokio
+    // hands the bytes to a blocking task, so flush to make sure they reached the
+    // file before the handle is dropped.
+    async fn write_to(&self, file: &mut File, data: &[u8]) -> io::Result<()> {
+        file.write_all(data).await?;
+        file.flush().await?;
+        if self.durable {
+            file.sync_all().await?;
+        }
+        Ok(())
+    }
+
+    // Append data to the end of the file and return the byte offset it starts at,
+    // whatever mode the backend was created in
+    pub async fn append(&mut self, data: &str) -> Result<u64, Box<dyn Error>> {
+        self.append_bytes(data.as_bytes()).await
+    }
+
+    // Append raw bytes to the end of the file and return the offset they start at
+    pub async fn append_bytes(&mut self, data: &[u8]) -> Result<u64, Box<dyn Error>> {
+        // Holding the write lock means the length can't change before we append
+        let _guard = self.lock.write().await;
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(&self.file_path)
+            .await?;
+        let offset = file.metadata().await?.len();
+        self.write_to(&mut file, data).await?;
+        Ok(offset)
+    }
+
+    // Read the contents of the file starting at the given byte offset, e.g. one
+    // returned by `append`. Reading from the end of the file returns nothing.
+    pub async fn read_from_offset(&mut self, offset: u64) -> Result<String, Box<dyn Error>> {
+        let _guard = self.lock.read().await;
+        let mut file = File::open(&self.file_path).await?;
+        file.seek(SeekFrom::Start(offset)).await?;
+        Ok(String::from_utf8(self.read_bounded(file).await?)?)
+    }
 }
 
 #[async_trait]
 impl StorageBackend for FileBackend {
     // Write data to the file
     async fn write(&mut self, data: &str) -> Result<(), Box<dyn Error>> {
+        if self.append {
+            return self.append(data).await.map(|_| ());
+        }
         // Open the file for writing and write data
+        let _guard = self.lock.write().await;
         let mut file = File::create(&self.file_path).await?;
-        file.write_all(data.as_bytes()).await?;
+        self.write_to(&mut file, data.as_bytes()).await?;
         Ok(())
     }
 
     // Read the contents of the file
     async fn read(&mut self) -> Result<String, Box<dyn Error>> {
         // Open the file for reading and read its content
+        let _guard = self.lock.read().await;
+        let file = File::open(&self.file_path).await?;
+        Ok(String::from_utf8(self.read_bounded(file).await?)?)
+    }
+
+    // Write raw bytes to the file
+    async fn write_bytes(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        if self.append {
+            return self.append_bytes(data).await.map(|_| ());
+        }
+        let _guard = self.lock.write().await;
+        let mut file = File::create(&self.file_path).await?;
+        self.write_to(&mut file, data).await?;
+        Ok(())
+    }
+
+    // Append raw bytes to the end of the file, whatever mode the backend is in
+    async fn extend_bytes(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        self.append_bytes(data).await.map(|_| ())
+    }
+
+    // Read the raw contents of the file
+    async fn read_bytes(&mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+        let _guard = self.lock.read().await;
+        let file = File::open(&self.file_path).await?;
+        self.read_bounded(file).await
+    }
+
+    // Read only the requested range of the file
+    async fn read_range(&mut self, start: usize, len: usize) -> Result<String, Box<dyn Error>> {
+        let _guard = self.lock.read().await;
         let mut file = File::open(&self.file_path).await?;
-        let mut content = String::new();
-        file.read_to_string(&mut content).await?;
-        Ok(content)
+        file.seek(SeekFrom::Start(start as u64)).await?;
+        let mut content = Vec::new();
+        file.take(len as u64).read_to_end(&mut content).await?;
+        Ok(String::from_utf8(content)?)
+    }
+
+    // Compare and write under the file's write lock, so no other FileBackend in
+    // this process can write in between. Other processes don't take the lock.
+    async fn compare_and_swap(
+        &mut self,
+        expected: &str,
+        new: &str,
+    ) -> Result<bool, Box<dyn Error>> {
+        let _guard = self.lock.write().await;
+        let file = File::open(&self.file_path).await?;
+        if self.read_bounded(file).await? != expected.as_bytes() {
+            return Ok(false);
+        }
+        let mut file = if self.append {
+            OpenOptions::new()
+                .append(true)
+                .open(&self.file_path)
+                .await?
+        } else {
+            File::create(&self.file_path).await?
+        };
+        self.write_to(&mut file, new.as_bytes()).await?;
+        Ok(true)
     }
 
     // Clean up by deleting the file
     async fn cleanup(&mut self) -> Result<(), Box<dyn Error>> {
+        let _guard = self.lock.write().await;
         fs::remove_file(&self.file_path).await?;
         Ok(())
     }
+
+    // The modification time of the file, or `None` once it has been cleaned up
+    async fn last_modified(&mut self) -> Result<Option<SystemTime>, Box<dyn Error>> {
+        let _guard = self.lock.read().await;
+        match fs::metadata(&self.file_path).await {
+            Ok(metadata) => Ok(Some(metadata.modified()?)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
 }
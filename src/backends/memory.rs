@@ -0,0 +1,93 @@
+// src/backends/memory.rs
+
+use super::storage::StorageBackend;
+use async_trait::async_trait;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+#[derive(Debug, Default)]
+struct Stored {
+    data: Vec<u8>,
+    // When `data` last changed, `None` until the first write
+    modified: Option<SystemTime>,
+}
+
+impl Stored {
+    fn set(&mut self, data: Vec<u8>) {
+        self.data = data;
+        self.modified = Some(SystemTime::now());
+    }
+}
+
+// An in-memory backend. Clones share the same storage, so several actors
+// holding a clone observe each other's writes, like handles on one file.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryBackend {
+    stored: Arc<Mutex<Stored>>,
+}
+
+impl MemoryBackend {
+    // Create a new, empty MemoryBackend
+    pub fn new() -> Self {
+        MemoryBackend::default()
+    }
+}
+
+#[async_trait]
+impl StorageBackend for MemoryBackend {
+    // Replace the stored data
+    async fn write(&mut self, data: &str) -> Result<(), Box<dyn Error>> {
+        self.stored.lock().unwrap().set(data.as_bytes().to_vec());
+        Ok(())
+    }
+
+    // Return a copy of the stored data
+    async fn read(&mut self) -> Result<String, Box<dyn Error>> {
+        Ok(String::from_utf8(self.stored.lock().unwrap().data.clone())?)
+    }
+
+    // Replace the stored data with raw bytes
+    async fn write_bytes(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        self.stored.lock().unwrap().set(data.to_vec());
+        Ok(())
+    }
+
+    // Append raw bytes to the stored data
+    async fn extend_bytes(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        let mut stored = self.stored.lock().unwrap();
+        stored.data.extend_from_slice(data);
+        stored.modified = Some(SystemTime::now());
+        Ok(())
+    }
+
+    // Return a copy of the stored bytes
+    async fn read_bytes(&mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+        Ok(self.stored.lock().unwrap().data.clone())
+    }
+
+    // Clear the stored data
+    async fn cleanup(&mut self) -> Result<(), Box<dyn Error>> {
+        self.stored.lock().unwrap().set(Vec::new());
+        Ok(())
+    }
+
+    // Compare and swap under a single lock, so it is atomic across clones
+    async fn compare_and_swap(
+        &mut self,
+        expected: &str,
+        new: &str,
+    ) -> Result<bool, Box<dyn Error>> {
+        let mut stored = self.stored.lock().unwrap();
+        if stored.data != expected.as_bytes() {
+            return Ok(false);
+        }
+        stored.set(new.as_bytes().to_vec());
+        Ok(true)
+    }
+
+    // When the data was last written, appended to or cleared
+    async fn last_modified(&mut self) -> Result<Option<SystemTime>, Box<dyn Error>> {
+        Ok(self.stored.lock().unwrap().modified)
+    }
+}
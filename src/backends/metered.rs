@@ -0,0 +1,303 @@
+// src/backends/metered.rs
+
+//! # Metered Backend
+//!
+//! `MeteredBackend` wraps another backend and records how long each operation
+//! takes, so slow storage can be told apart from slow actors. Latencies go into
+//! one histogram per kind of operation:
+//!
+//! - writes: `write`, `write_bytes`, `flush` and `compare_and_swap`
+//! - reads: `read` and `read_bytes`
+//! - cleanups: `cleanup`
+//!
+//! Failed operations are recorded too. Clones share the same histograms.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use astra::backends::memory::MemoryBackend;
+//! use astra::backends::metered::{BackendOperation, MeteredBackend};
+//! use astra::backends::storage::StorageBackend;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let mut backend = MeteredBackend::new(MemoryBackend::new());
+//!     backend.write("hello").await?;
+//!     backend.read().await?;
+//!
+//!     assert_eq!(backend.stats().count, 2);
+//!     let writes = backend.operation_stats(BackendOperation::Write);
+//!     println!("{} writes, p99 {:?}", writes.count, writes.p99);
+//!     Ok(())
+//! }
+//! ```
+
+use super::storage::StorageBackend;
+use async_trait::async_trait;
+use hdrhistogram::Histogram;
+use std::error::Error;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+// Latencies are recorded in microseconds, with 3 significant digits, from 1µs
+// up to an hour; longer operations are recorded as taking an hour
+const SIGNIFICANT_DIGITS: u8 = 3;
+const MAX_LATENCY_MICROS: u64 = 3_600_000_000;
+
+/// The kinds of operation `MeteredBackend` keeps apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackendOperation {
+    Write,
+    Read,
+    Cleanup,
+}
+
+/// Latency percentiles over the recorded operations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BackendStats {
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+    pub count: u64,
+}
+
+impl BackendStats {
+    fn from_histogram(histogram: &Histogram<u64>) -> Self {
+        let at = |quantile| Duration::from_micros(histogram.value_at_quantile(quantile));
+        BackendStats {
+            p50: at(0.50),
+            p95: at(0.95),
+            p99: at(0.99),
+            count: histogram.len(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Histograms {
+    write: Histogram<u64>,
+    read: Histogram<u64>,
+    cleanup: Histogram<u64>,
+}
+
+impl Histograms {
+    fn get_mut(&mut self, operation: BackendOperation) -> &mut Histogram<u64> {
+        match operation {
+            BackendOperation::Write => &mut self.write,
+            BackendOperation::Read => &mut self.read,
+            BackendOperation::Cleanup => &mut self.cleanup,
+        }
+    }
+}
+
+fn new_histogram() -> Histogram<u64> {
+    Histogram::new_with_bounds(1, MAX_LATENCY_MICROS, SIGNIFICANT_DIGITS)
+        .expect("valid histogram bounds")
+}
+
+/// The histograms of a `MeteredBackend`, readable without access to the backend
+/// itself (e.g. once it is boxed). Clones share the same histograms.
+#[derive(Debug, Clone)]
+pub struct BackendMetrics {
+    histograms: Arc<Mutex<Histograms>>,
+}
+
+impl BackendMetrics {
+    pub fn new() -> Self {
+        BackendMetrics {
+            histograms: Arc::new(Mutex::new(Histograms {
+                write: new_histogram(),
+                read: new_histogram(),
+                cleanup: new_histogram(),
+            })),
+        }
+    }
+
+    /// Latency percentiles over every operation recorded so far.
+    pub fn stats(&self) -> BackendStats {
+        let histograms = self.histograms.lock().unwrap();
+        let mut all = histograms.write.clone();
+        // Histograms with the same bounds always merge
+        all.add(&histograms.read).unwrap();
+        all.add(&histograms.cleanup).unwrap();
+        BackendStats::from_histogram(&all)
+    }
+
+    /// Latency percentiles for one kind of operation.
+    pub fn operation_stats(&self, operation: BackendOperation) -> BackendStats {
+        let mut histograms = self.histograms.lock().unwrap();
+        BackendStats::from_histogram(histograms.get_mut(operation))
+    }
+
+    /// Forgets every latency recorded so far.
+    pub fn reset(&self) {
+        let mut histograms = self.histograms.lock().unwrap();
+        histograms.write.reset();
+        histograms.read.reset();
+        histograms.cleanup.reset();
+    }
+}
+
+impl Default for BackendMetrics {
+    fn default() -> Self {
+        BackendMetrics::new()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MeteredBackend<B: StorageBackend> {
+    inner: B,
+    metrics: BackendMetrics,
+}
+
+impl<B: StorageBackend> MeteredBackend<B> {
+    // Create a new MeteredBackend recording the latencies of `inner`
+    pub fn new(inner: B) -> Self {
+        MeteredBackend::with_metrics(inner, BackendMetrics::new())
+    }
+
+    // Create a MeteredBackend recording into existing metrics, e.g. to aggregate
+    // several backends or to keep reading them after the backend is boxed
+    pub fn with_metrics(inner: B, metrics: BackendMetrics) -> Self {
+        MeteredBackend { inner, metrics }
+    }
+
+    /// Returns a handle on the histograms this backend records into.
+    pub fn metrics(&self) -> BackendMetrics {
+        self.metrics.clone()
+    }
+
+    /// Latency percentiles over every operation recorded so far.
+    pub fn stats(&self) -> BackendStats {
+        self.metrics.stats()
+    }
+
+    /// Latency percentiles for one kind of operation.
+    pub fn operation_stats(&self, operation: BackendOperation) -> BackendStats {
+        self.metrics.operation_stats(operation)
+    }
+
+    /// Forgets every latency recorded so far.
+    pub fn reset(&self) {
+        self.metrics.reset()
+    }
+
+    /// Returns a reference to the wrapped backend.
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+}
+
+// Run an operation on the inner backend and record how long it took
+async fn timed<T, F>(histograms: &Mutex<Histograms>, operation: BackendOperation, f: F) -> T
+where
+    F: Future<Output = T>,
+{
+    let started = Instant::now();
+    let result = f.await;
+    let micros = started.elapsed().as_micros().try_into().unwrap_or(u64::MAX);
+    histograms
+        .lock()
+        .unwrap()
+        .get_mut(operation)
+        .saturating_record(micros);
+    result
+}
+
+#[async_trait]
+impl<B: StorageBackend> StorageBackend for MeteredBackend<B> {
+    async fn write(&mut self, data: &str) -> Result<(), Box<dyn Error>> {
+        timed(
+            &self.metrics.histograms,
+            BackendOperation::Write,
+            self.inner.write(data),
+        )
+        .await
+    }
+
+    async fn read(&mut self) -> Result<String, Box<dyn Error>> {
+        timed(
+            &self.metrics.histograms,
+            BackendOperation::Read,
+            self.inner.read(),
+        )
+        .await
+    }
+
+    async fn cleanup(&mut self) -> Result<(), Box<dyn Error>> {
+        timed(
+            &self.metrics.histograms,
+            BackendOperation::Cleanup,
+            self.inner.cleanup(),
+        )
+        .await
+    }
+
+    async fn write_bytes(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        timed(
+            &self.metrics.histograms,
+            BackendOperation::Write,
+            self.inner.write_bytes(data),
+        )
+        .await
+    }
+
+    async fn extend_bytes(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        timed(
+            &self.metrics.histograms,
+            BackendOperation::Write,
+            self.inner.extend_bytes(data),
+        )
+        .await
+    }
+
+    async fn read_bytes(&mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+        timed(
+            &self.metrics.histograms,
+            BackendOperation::Read,
+            self.inner.read_bytes(),
+        )
+        .await
+    }
+
+    async fn read_range(&mut self, start: usize, len: usize) -> Result<String, Box<dyn Error>> {
+        timed(
+            &self.metrics.histograms,
+            BackendOperation::Read,
+            self.inner.read_range(start, len),
+        )
+        .await
+    }
+
+    async fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        timed(
+            &self.metrics.histograms,
+            BackendOperation::Write,
+            self.inner.flush(),
+        )
+        .await
+    }
+
+    async fn compare_and_swap(
+        &mut self,
+        expected: &str,
+        new: &str,
+    ) -> Result<bool, Box<dyn Error>> {
+        let swap = self.inner.compare_and_swap(expected, new);
+        timed(&self.metrics.histograms, BackendOperation::Write, swap).await
+    }
+
+    async fn last_modified(&mut self) -> Result<Option<SystemTime>, Box<dyn Error>> {
+        timed(
+            &self.metrics.histograms,
+            BackendOperation::Read,
+            self.inner.last_modified(),
+        )
+        .await
+    }
+
+    async fn health_check(&mut self) -> Result<(), Box<dyn Error>> {
+        self.inner.health_check().await
+    }
+}
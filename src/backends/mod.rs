@@ -1,4 +1,19 @@
 // src/backends/mod.rs
+pub mod blocking;
+pub mod boxed;
+pub mod buffered;
+pub mod builder;
+pub mod caching;
+pub mod config;
 pub mod database;
 pub mod file;
+pub mod memory;
+pub mod metered;
+pub mod quorum;
+pub mod read_only;
 pub mod storage;
+pub mod tee;
+pub mod transform;
+pub mod wal;
+
+pub use config::from_uri;
@@ -0,0 +1,226 @@
+// src/backends/quorum.rs
+
+//! # Quorum Backend
+//!
+//! `QuorumBackend` replicates state over several backends. Every write, as well
+//! as `flush` and `cleanup`, goes to all of them at once and succeeds as soon as
+//! `write_quorum` have acknowledged it, so up to `len - write_quorum` replicas
+//! may fail or hang. Replicas still busy by then are dropped mid-operation, as
+//! if they had failed. A read asks all replicas and settles on the value most of the first
+//! `read_quorum` answers agree on, the earliest answer winning a tie. When fewer
+//! replicas than the quorum succeed, the operation fails with a `QuorumError`.
+//!
+//! With `write_quorum + read_quorum > len` every read quorum overlaps every write
+//! quorum, so a read sees at least one copy of the latest write. Replicas that
+//! missed a write aren't repaired; they catch up with the next one.
+//!
+//! `compare_and_swap` is the trait's default read-compare-write, which is not
+//! atomic across replicas.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use astra::backends::boxed::BoxedBackend;
+//! use astra::backends::memory::MemoryBackend;
+//! use astra::backends::quorum::QuorumBackend;
+//! use astra::backends::storage::StorageBackend;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let replicas = (0..3).map(|_| BoxedBackend::new(MemoryBackend::new())).collect();
+//!     let mut backend = QuorumBackend::new(replicas, 2, 2)?;
+//!     backend.write("replicated").await?;
+//!     assert_eq!(backend.read().await?, "replicated");
+//!     Ok(())
+//! }
+//! ```
+
+use super::boxed::BoxedBackend;
+use super::storage::StorageBackend;
+use async_trait::async_trait;
+use futures::stream::{FuturesUnordered, StreamExt};
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+
+/// The error of an operation fewer replicas than its quorum succeeded at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuorumError {
+    /// The operation, e.g. `"write"`.
+    pub operation: &'static str,
+    pub succeeded: usize,
+    pub quorum: usize,
+    /// What the failed replicas reported.
+    pub errors: Vec<String>,
+}
+
+impl fmt::Display for QuorumError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Quorum not met for {}: {} of {} replicas succeeded",
+            self.operation, self.succeeded, self.quorum
+        )?;
+        if !self.errors.is_empty() {
+            write!(f, " ({})", self.errors.join("; "))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for QuorumError {}
+
+#[derive(Debug, Clone)]
+pub struct QuorumBackend {
+    replicas: Vec<BoxedBackend>,
+    write_quorum: usize,
+    read_quorum: usize,
+}
+
+impl QuorumBackend {
+    // Replicate over `replicas`; fails unless both quorums are between 1 and the
+    // number of replicas
+    pub fn new(
+        replicas: Vec<BoxedBackend>,
+        write_quorum: usize,
+        read_quorum: usize,
+    ) -> Result<Self, String> {
+        for (name, quorum) in [("write", write_quorum), ("read", read_quorum)] {
+            if quorum == 0 || quorum > replicas.len() {
+                return Err(format!(
+                    "A {} quorum of {} is impossible with {} replicas",
+                    name,
+                    quorum,
+                    replicas.len()
+                ));
+            }
+        }
+        Ok(QuorumBackend {
+            replicas,
+            write_quorum,
+            read_quorum,
+        })
+    }
+}
+
+// Succeed once `quorum` of `results` did what was asked of all replicas, or
+// fail once too many failed for that
+async fn settle<F>(
+    operation: &'static str,
+    quorum: usize,
+    mut results: FuturesUnordered<F>,
+) -> Result<(), Box<dyn Error>>
+where
+    F: Future<Output = Result<(), String>>,
+{
+    let replicas = results.len();
+    let mut succeeded = 0;
+    let mut errors = Vec::new();
+    while succeeded < quorum && replicas - errors.len() >= quorum {
+        match results.next().await {
+            Some(Ok(())) => succeeded += 1,
+            Some(Err(e)) => errors.push(e),
+            None => break,
+        }
+    }
+    if succeeded < quorum {
+        return Err(Box::new(QuorumError {
+            operation,
+            succeeded,
+            quorum,
+            errors,
+        }));
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl StorageBackend for QuorumBackend {
+    async fn write(&mut self, data: &str) -> Result<(), Box<dyn Error>> {
+        self.write_bytes(data.as_bytes()).await
+    }
+
+    async fn read(&mut self) -> Result<String, Box<dyn Error>> {
+        Ok(String::from_utf8(self.read_bytes().await?)?)
+    }
+
+    async fn cleanup(&mut self) -> Result<(), Box<dyn Error>> {
+        let results = self
+            .replicas
+            .iter_mut()
+            .map(|replica| async move { replica.cleanup().await.map_err(|e| e.to_string()) })
+            .collect();
+        settle("cleanup", self.write_quorum, results).await
+    }
+
+    async fn write_bytes(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        let results = self
+            .replicas
+            .iter_mut()
+            .map(
+                |replica| async move { replica.write_bytes(data).await.map_err(|e| e.to_string()) },
+            )
+            .collect();
+        settle("write", self.write_quorum, results).await
+    }
+
+    async fn extend_bytes(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        let results =
+            self.replicas
+                .iter_mut()
+                .map(|replica| async move {
+                    replica.extend_bytes(data).await.map_err(|e| e.to_string())
+                })
+                .collect();
+        settle("write", self.write_quorum, results).await
+    }
+
+    // The value most of the first `read_quorum` answers agree on
+    async fn read_bytes(&mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut reads: FuturesUnordered<_> = self
+            .replicas
+            .iter_mut()
+            .map(|replica| async move { replica.read_bytes().await.map_err(|e| e.to_string()) })
+            .collect();
+        // Each distinct value with how many replicas returned it, in order of arrival
+        let mut answers: Vec<(Vec<u8>, usize)> = Vec::new();
+        let mut succeeded = 0;
+        let mut errors = Vec::new();
+        while succeeded < self.read_quorum {
+            match reads.next().await {
+                Some(Ok(value)) => {
+                    succeeded += 1;
+                    match answers.iter_mut().find(|(seen, _)| *seen == value) {
+                        Some((_, count)) => *count += 1,
+                        None => answers.push((value, 1)),
+                    }
+                }
+                Some(Err(e)) => errors.push(e),
+                None => {
+                    return Err(Box::new(QuorumError {
+                        operation: "read",
+                        succeeded,
+                        quorum: self.read_quorum,
+                        errors,
+                    }))
+                }
+            }
+        }
+        // `max_by_key` keeps the last of equal counts, so search from the back
+        let (value, _) = answers
+            .into_iter()
+            .rev()
+            .max_by_key(|(_, count)| *count)
+            .expect("a read quorum is at least 1");
+        Ok(value)
+    }
+
+    async fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        let results = self
+            .replicas
+            .iter_mut()
+            .map(|replica| async move { replica.flush().await.map_err(|e| e.to_string()) })
+            .collect();
+        settle("flush", self.write_quorum, results).await
+    }
+}
@@ -0,0 +1,123 @@
+// src/backends/read_only.rs
+
+//! # Read-only Backend
+//!
+//! `ReadOnlyBackend` wraps another backend for components that must only ever
+//! read its state, such as a metrics reader or a replica. Reads go through to
+//! the wrapped backend; writes, `compare_and_swap` and `cleanup` fail with a
+//! `ReadOnlyError` without touching it. `flush` succeeds, as there is nothing to
+//! flush.
+//!
+//! `DataActor::read_only` turns a `DataActor` into one over a read-only view of
+//! its backend.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use astra::backends::memory::MemoryBackend;
+//! use astra::backends::read_only::{ReadOnlyBackend, ReadOnlyError};
+//! use astra::backends::storage::StorageBackend;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let mut shared = MemoryBackend::new();
+//!     shared.write("42").await?;
+//!
+//!     let mut reader = ReadOnlyBackend::new(shared.clone());
+//!     assert_eq!(reader.read().await?, "42");
+//!
+//!     let error = reader.write("43").await.unwrap_err();
+//!     assert!(error.downcast_ref::<ReadOnlyError>().is_some());
+//!     assert_eq!(shared.read().await?, "42");
+//!     Ok(())
+//! }
+//! ```
+
+use super::storage::StorageBackend;
+use async_trait::async_trait;
+use std::error::Error;
+use std::fmt;
+use std::time::SystemTime;
+
+/// The error every mutating operation of a `ReadOnlyBackend` fails with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadOnlyError {
+    /// The operation that was refused, e.g. `"write"`.
+    pub operation: &'static str,
+}
+
+impl fmt::Display for ReadOnlyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Backend is read-only, {} refused", self.operation)
+    }
+}
+
+impl std::error::Error for ReadOnlyError {}
+
+fn refuse<T>(operation: &'static str) -> Result<T, Box<dyn Error>> {
+    Err(Box::new(ReadOnlyError { operation }))
+}
+
+#[derive(Debug, Clone)]
+pub struct ReadOnlyBackend<B: StorageBackend> {
+    inner: B,
+}
+
+impl<B: StorageBackend> ReadOnlyBackend<B> {
+    // Create a read-only view of `inner`
+    pub fn new(inner: B) -> Self {
+        ReadOnlyBackend { inner }
+    }
+
+    /// Returns a reference to the wrapped backend.
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+}
+
+#[async_trait]
+impl<B: StorageBackend> StorageBackend for ReadOnlyBackend<B> {
+    async fn write(&mut self, _data: &str) -> Result<(), Box<dyn Error>> {
+        refuse("write")
+    }
+
+    async fn read(&mut self) -> Result<String, Box<dyn Error>> {
+        self.inner.read().await
+    }
+
+    async fn cleanup(&mut self) -> Result<(), Box<dyn Error>> {
+        refuse("cleanup")
+    }
+
+    async fn write_bytes(&mut self, _data: &[u8]) -> Result<(), Box<dyn Error>> {
+        refuse("write")
+    }
+
+    async fn extend_bytes(&mut self, _data: &[u8]) -> Result<(), Box<dyn Error>> {
+        refuse("write")
+    }
+
+    async fn read_bytes(&mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.inner.read_bytes().await
+    }
+
+    async fn read_range(&mut self, start: usize, len: usize) -> Result<String, Box<dyn Error>> {
+        self.inner.read_range(start, len).await
+    }
+
+    async fn compare_and_swap(
+        &mut self,
+        _expected: &str,
+        _new: &str,
+    ) -> Result<bool, Box<dyn Error>> {
+        refuse("compare_and_swap")
+    }
+
+    async fn last_modified(&mut self) -> Result<Option<SystemTime>, Box<dyn Error>> {
+        self.inner.last_modified().await
+    }
+
+    async fn health_check(&mut self) -> Result<(), Box<dyn Error>> {
+        self.inner.health_check().await
+    }
+}
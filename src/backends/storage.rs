@@ -1,10 +1,76 @@
 // src/backends/storage.rs
 use async_trait::async_trait;
 use std::error::Error;
+use std::time::SystemTime;
 
 #[async_trait]
 pub trait StorageBackend: Send + Sync + Clone {
     async fn write(&mut self, data: &str) -> Result<(), Box<dyn Error>>;
     async fn read(&mut self) -> Result<String, Box<dyn Error>>;
     async fn cleanup(&mut self) -> Result<(), Box<dyn Error>>;
+
+    // Write raw bytes. The default requires them to be valid UTF-8 and goes
+    // through `write`; backends that can store arbitrary bytes override it.
+    async fn write_bytes(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        let data = std::str::from_utf8(data)?;
+        self.write(data).await
+    }
+
+    // Add raw bytes to the end of the stored data. The default reads everything
+    // and writes it back; backends that can append in place override it.
+    async fn extend_bytes(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        let mut stored = self.read_bytes().await?;
+        stored.extend_from_slice(data);
+        self.write_bytes(&stored).await
+    }
+
+    // Read the stored data as raw bytes
+    async fn read_bytes(&mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+        Ok(self.read().await?.into_bytes())
+    }
+
+    // Read `len` bytes starting at byte `start`, stopping early at the end of the
+    // data. The default reads everything and slices it; backends that can fetch
+    // just the requested bytes override it. Fails if the range splits a UTF-8
+    // character.
+    async fn read_range(&mut self, start: usize, len: usize) -> Result<String, Box<dyn Error>> {
+        let data = self.read_bytes().await?;
+        let start = start.min(data.len());
+        let end = start.saturating_add(len).min(data.len());
+        Ok(String::from_utf8(data[start..end].to_vec())?)
+    }
+
+    // Push any buffered writes down to the underlying storage.
+    // Unbuffered backends have nothing to do here.
+    async fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    // Write `new` only if the stored data still equals `expected`, returning whether
+    // the write happened. The default reads, compares and writes in separate steps so
+    // it is NOT atomic; backends that can do better should override it.
+    async fn compare_and_swap(
+        &mut self,
+        expected: &str,
+        new: &str,
+    ) -> Result<bool, Box<dyn Error>> {
+        if self.read().await? != expected {
+            return Ok(false);
+        }
+        self.write(new).await?;
+        Ok(true)
+    }
+
+    // When the stored data last changed, e.g. to tell whether a cached copy is
+    // stale. `None` means the backend doesn't know, which is the default.
+    async fn last_modified(&mut self) -> Result<Option<SystemTime>, Box<dyn Error>> {
+        Ok(None)
+    }
+
+    // Check that the backend can currently serve requests, e.g. for a readiness
+    // probe. The default reads the stored data; backends with a cheaper way to
+    // tell, like a ping, override it.
+    async fn health_check(&mut self) -> Result<(), Box<dyn Error>> {
+        self.read_bytes().await.map(|_| ())
+    }
 }
@@ -0,0 +1,170 @@
+// src/backends/tee.rs
+
+//! # Tee Backend
+//!
+//! `TeeBackend` mirrors every write to a second backend, e.g. an audit log kept
+//! with `FileBackend::new_append`, without the actor knowing. Writes go to the
+//! primary first and, once it succeeded, to the secondary. Only the primary
+//! decides the outcome: a secondary failure is logged as a warning through the
+//! backend's logger, the `ConsoleLogger` unless set with `with_logger`, and the
+//! write still succeeds. A write the primary refused isn't mirrored.
+//!
+//! Reads, `cleanup` and `last_modified` only involve the primary, so cleaning
+//! up leaves the audit trail in place. A successful `compare_and_swap` is
+//! mirrored as a write of the new value, and `flush` flushes both.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use astra::backends::memory::MemoryBackend;
+//! use astra::backends::storage::StorageBackend;
+//! use astra::backends::tee::TeeBackend;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let audit = MemoryBackend::new();
+//!     let mut backend = TeeBackend::new(MemoryBackend::new(), audit.clone());
+//!     backend.write("balance=10").await?;
+//!     assert_eq!(audit.clone().read().await?, "balance=10");
+//!     Ok(())
+//! }
+//! ```
+
+use super::storage::StorageBackend;
+use crate::logging::{ConsoleLogger, LogLevel, Logger};
+use async_trait::async_trait;
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+#[derive(Clone)]
+pub struct TeeBackend<P: StorageBackend, S: StorageBackend> {
+    primary: P,
+    secondary: S,
+    logger: Arc<dyn Logger + Send + Sync>,
+}
+
+impl<P: StorageBackend, S: StorageBackend> TeeBackend<P, S> {
+    // Mirror the writes to `primary` into `secondary`
+    pub fn new(primary: P, secondary: S) -> Self {
+        TeeBackend {
+            primary,
+            secondary,
+            logger: Arc::new(ConsoleLogger::new()),
+        }
+    }
+
+    /// Sends the warnings about failed secondary writes to `logger` instead of
+    /// the console.
+    pub fn with_logger(mut self, logger: Arc<dyn Logger + Send + Sync>) -> Self {
+        self.logger = logger;
+        self
+    }
+
+    // Log a failure of the secondary, which doesn't fail the operation
+    async fn secondary_failed(&self, operation: &str, error: String) {
+        let message = format!("Secondary backend failed to {}: {}", operation, error);
+        self.logger.log(LogLevel::Warn, &message).await;
+    }
+}
+
+impl<P, S> fmt::Debug for TeeBackend<P, S>
+where
+    P: StorageBackend + fmt::Debug,
+    S: StorageBackend + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TeeBackend")
+            .field("primary", &self.primary)
+            .field("secondary", &self.secondary)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl<P: StorageBackend, S: StorageBackend> StorageBackend for TeeBackend<P, S> {
+    async fn write(&mut self, data: &str) -> Result<(), Box<dyn Error>> {
+        self.primary.write(data).await?;
+        // Only the message is kept, the error itself isn't `Send`
+        let mirrored = self.secondary.write(data).await.map_err(|e| e.to_string());
+        if let Err(e) = mirrored {
+            self.secondary_failed("write", e).await;
+        }
+        Ok(())
+    }
+
+    async fn read(&mut self) -> Result<String, Box<dyn Error>> {
+        self.primary.read().await
+    }
+
+    async fn cleanup(&mut self) -> Result<(), Box<dyn Error>> {
+        self.primary.cleanup().await
+    }
+
+    async fn write_bytes(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        self.primary.write_bytes(data).await?;
+        let mirrored = self
+            .secondary
+            .write_bytes(data)
+            .await
+            .map_err(|e| e.to_string());
+        if let Err(e) = mirrored {
+            self.secondary_failed("write", e).await;
+        }
+        Ok(())
+    }
+
+    async fn extend_bytes(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        self.primary.extend_bytes(data).await?;
+        let mirrored = self
+            .secondary
+            .extend_bytes(data)
+            .await
+            .map_err(|e| e.to_string());
+        if let Err(e) = mirrored {
+            self.secondary_failed("write", e).await;
+        }
+        Ok(())
+    }
+
+    async fn read_bytes(&mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.primary.read_bytes().await
+    }
+
+    async fn read_range(&mut self, start: usize, len: usize) -> Result<String, Box<dyn Error>> {
+        self.primary.read_range(start, len).await
+    }
+
+    async fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        self.primary.flush().await?;
+        let flushed = self.secondary.flush().await.map_err(|e| e.to_string());
+        if let Err(e) = flushed {
+            self.secondary_failed("flush", e).await;
+        }
+        Ok(())
+    }
+
+    async fn compare_and_swap(
+        &mut self,
+        expected: &str,
+        new: &str,
+    ) -> Result<bool, Box<dyn Error>> {
+        if !self.primary.compare_and_swap(expected, new).await? {
+            return Ok(false);
+        }
+        let mirrored = self.secondary.write(new).await.map_err(|e| e.to_string());
+        if let Err(e) = mirrored {
+            self.secondary_failed("write", e).await;
+        }
+        Ok(true)
+    }
+
+    async fn last_modified(&mut self) -> Result<Option<SystemTime>, Box<dyn Error>> {
+        self.primary.last_modified().await
+    }
+
+    async fn health_check(&mut self) -> Result<(), Box<dyn Error>> {
+        self.primary.health_check().await
+    }
+}
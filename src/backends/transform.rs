@@ -0,0 +1,112 @@
+// src/backends/transform.rs
+
+//! # Transform Backend
+//!
+//! `TransformBackend` rewrites data on its way into and out of another backend,
+//! so policies like redacting PII or normalizing input are applied in one place
+//! instead of in every actor. The write transform runs on everything written
+//! before it reaches the wrapped backend; the read transform runs on everything
+//! read from it, e.g. to unmask what the write transform masked.
+//!
+//! Byte writes and reads go through the transforms as well, so they must be
+//! valid UTF-8. `read_range` slices the transformed data, and
+//! `compare_and_swap` compares `expected` with it.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use astra::backends::memory::MemoryBackend;
+//! use astra::backends::storage::StorageBackend;
+//! use astra::backends::transform::TransformBackend;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let stored = MemoryBackend::new();
+//!     let mut backend = TransformBackend::new(
+//!         stored.clone(),
+//!         |data: &str| data.replace("secret", "******"),
+//!         |data: String| data,
+//!     );
+//!
+//!     backend.write("password: secret").await?;
+//!     assert_eq!(stored.clone().read().await?, "password: ******");
+//!     Ok(())
+//! }
+//! ```
+
+use super::storage::StorageBackend;
+use async_trait::async_trait;
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+type WriteTransform = Arc<dyn Fn(&str) -> String + Send + Sync>;
+type ReadTransform = Arc<dyn Fn(String) -> String + Send + Sync>;
+
+/// Applies a transform to the data written to and read from a backend, see the
+/// module docs. Clones share the transforms.
+#[derive(Clone)]
+pub struct TransformBackend<B: StorageBackend> {
+    inner: B,
+    write_transform: WriteTransform,
+    read_transform: ReadTransform,
+}
+
+impl<B: StorageBackend> TransformBackend<B> {
+    // Create a TransformBackend applying `write_transform` to what is written to
+    // `inner` and `read_transform` to what is read from it
+    pub fn new<W, R>(inner: B, write_transform: W, read_transform: R) -> Self
+    where
+        W: Fn(&str) -> String + Send + Sync + 'static,
+        R: Fn(String) -> String + Send + Sync + 'static,
+    {
+        TransformBackend {
+            inner,
+            write_transform: Arc::new(write_transform),
+            read_transform: Arc::new(read_transform),
+        }
+    }
+
+    /// Returns a reference to the wrapped backend.
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+}
+
+impl<B: StorageBackend + fmt::Debug> fmt::Debug for TransformBackend<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TransformBackend")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl<B: StorageBackend> StorageBackend for TransformBackend<B> {
+    async fn write(&mut self, data: &str) -> Result<(), Box<dyn Error>> {
+        let data = (self.write_transform)(data);
+        self.inner.write(&data).await
+    }
+
+    async fn read(&mut self) -> Result<String, Box<dyn Error>> {
+        let data = self.inner.read().await?;
+        Ok((self.read_transform)(data))
+    }
+
+    async fn cleanup(&mut self) -> Result<(), Box<dyn Error>> {
+        self.inner.cleanup().await
+    }
+
+    async fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        self.inner.flush().await
+    }
+
+    async fn last_modified(&mut self) -> Result<Option<SystemTime>, Box<dyn Error>> {
+        self.inner.last_modified().await
+    }
+
+    async fn health_check(&mut self) -> Result<(), Box<dyn Error>> {
+        self.inner.health_check().await
+    }
+}
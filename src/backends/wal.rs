@@ -0,0 +1,385 @@
+// src/backends/wal.rs
+
+//! # Write-Ahead Log Backend
+//!
+//! A backend that isn't atomic can be left half written when the process dies in
+//! the middle of a write. `WalBackend` wraps such a backend and records every
+//! write in a log file, synced to disk, before applying it; once the inner
+//! backend is done the entry is marked committed. When the backend is opened
+//! again, `open` replays the entries that never got their commit mark, so an
+//! interrupted write is completed instead of left torn.
+//!
+//! Replaying is safe because writes and swaps are idempotent, and an append is
+//! replayed on top of the data it was appended to, dropping any torn tail.
+//! Reads go straight to the inner backend.
+//!
+//! A write the inner backend reported as failed is marked aborted rather than
+//! committed: the caller got the error and decides whether to retry. The log is
+//! truncated every `DEFAULT_COMPACT_AFTER` finished operations (see
+//! `with_compact_after`) and on `open`, so it never grows much beyond that.
+//!
+//! Clones share the log, and write through it one at a time. An operation its
+//! caller abandoned, e.g. by dropping the future, is marked aborted when the next
+//! one starts, so it can't be replayed over later writes.
+//!
+//! Wrap the backend that does the actual writes: under a `BufferedBackend` a
+//! write is committed once it is buffered, so the log can't protect it.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use astra::backends::file::FileBackend;
+//! use astra::backends::storage::StorageBackend;
+//! use astra::backends::wal::WalBackend;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let file = FileBackend::new("wal_data.txt").await?;
+//!     // Completes whatever the previous run was writing when it died
+//!     let mut backend = WalBackend::open(file, "wal_data.wal").await?;
+//!
+//!     backend.write("balance=42").await?;
+//!     assert_eq!(backend.read().await?, "balance=42");
+//!
+//!     backend.cleanup().await?;
+//!     std::fs::remove_file("wal_data.wal")?;
+//!     Ok(())
+//! }
+//! ```
+
+use super::storage::StorageBackend;
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Write as _;
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+/// How many finished operations the log holds before it is truncated, unless
+/// configured otherwise.
+pub const DEFAULT_COMPACT_AFTER: usize = 64;
+
+// A change recorded in the log before it is applied to the inner backend
+#[derive(Debug, Clone, PartialEq)]
+enum Operation {
+    Write(Vec<u8>),
+    // Appends `data` to stored data that was `prior_len` bytes long
+    Extend { prior_len: usize, data: Vec<u8> },
+    Swap { expected: String, new: String },
+}
+
+impl Operation {
+    // One line of the log, without the newline
+    fn encode(&self, seq: u64) -> String {
+        match self {
+            Operation::Write(data) => format!("{} write {}", seq, to_hex(data)),
+            Operation::Extend { prior_len, data } => {
+                format!("{} extend {} {}", seq, prior_len, to_hex(data))
+            }
+            Operation::Swap { expected, new } => format!(
+                "{} swap {} {}",
+                seq,
+                to_hex(expected.as_bytes()),
+                to_hex(new.as_bytes())
+            ),
+        }
+    }
+
+    // Parse the fields of a line after its sequence number and kind
+    fn decode<'a>(kind: &str, mut fields: impl Iterator<Item = &'a str>) -> Option<Self> {
+        let operation = match kind {
+            "write" => Operation::Write(from_hex(fields.next()?)?),
+            "extend" => Operation::Extend {
+                prior_len: fields.next()?.parse().ok()?,
+                data: from_hex(fields.next()?)?,
+            },
+            "swap" => Operation::Swap {
+                expected: String::from_utf8(from_hex(fields.next()?)?).ok()?,
+                new: String::from_utf8(from_hex(fields.next()?)?).ok()?,
+            },
+            _ => return None,
+        };
+        Some(operation)
+    }
+
+    // Apply the operation, returning whether a swap happened
+    async fn apply<B: StorageBackend>(&self, inner: &mut B) -> Result<bool, Box<dyn Error>> {
+        match self {
+            Operation::Write(data) => inner.write_bytes(data).await.map(|()| true),
+            Operation::Extend { data, .. } => inner.extend_bytes(data).await.map(|()| true),
+            Operation::Swap { expected, new } => inner.compare_and_swap(expected, new).await,
+        }
+    }
+
+    // Apply the operation again after it may have been interrupted half way
+    async fn replay<B: StorageBackend>(&self, inner: &mut B) -> Result<(), Box<dyn Error>> {
+        match self {
+            Operation::Extend { prior_len, data } => {
+                let mut stored = inner.read_bytes().await?;
+                if stored.len() < *prior_len {
+                    return Err(format!(
+                        "Cannot replay an append to {} bytes over {} stored bytes",
+                        prior_len,
+                        stored.len()
+                    )
+                    .into());
+                }
+                // Whatever made it out of the interrupted append is dropped
+                stored.truncate(*prior_len);
+                stored.extend_from_slice(data);
+                inner.write_bytes(&stored).await
+            }
+            _ => self.apply(inner).await.map(|_| ()),
+        }
+    }
+}
+
+fn to_hex(data: &[u8]) -> String {
+    data.iter()
+        .fold(String::with_capacity(data.len() * 2), |mut hex, byte| {
+            let _ = write!(hex, "{:02x}", byte);
+            hex
+        })
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+// The operations in the log that were neither committed nor aborted, in order.
+// Parsing stops at the first unreadable line: only the last one can be torn.
+fn unfinished(log: &str) -> Vec<Operation> {
+    let mut pending = BTreeMap::new();
+    for line in log.lines() {
+        let mut fields = line.split(' ');
+        let (Some(seq), Some(kind)) = (fields.next(), fields.next()) else {
+            break;
+        };
+        let Ok(seq) = seq.parse::<u64>() else {
+            break;
+        };
+        match kind {
+            "commit" | "abort" => {
+                pending.remove(&seq);
+            }
+            _ => match Operation::decode(kind, fields) {
+                Some(operation) => {
+                    pending.insert(seq, operation);
+                }
+                None => break,
+            },
+        }
+    }
+    pending.into_values().collect()
+}
+
+// The log file, shared by every clone of a `WalBackend`
+#[derive(Debug)]
+struct Log {
+    file: File,
+    next_seq: u64,
+    // The operation recorded last, until it is finished
+    open: Option<u64>,
+    // Operations finished since the log was last truncated
+    finished: usize,
+}
+
+impl Log {
+    // Record an operation, durably, before it is applied
+    async fn record(&mut self, operation: &Operation) -> Result<u64, Box<dyn Error>> {
+        if let Some(abandoned) = self.open.take() {
+            // Its caller gave up on it, e.g. a dropped future. Replaying it later
+            // would undo the operations that came after it.
+            self.file
+                .write_all(format!("{} abort\n", abandoned).as_bytes())
+                .await?;
+        }
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        let line = operation.encode(seq) + "\n";
+        self.file.write_all(line.as_bytes()).await?;
+        self.file.sync_data().await?;
+        self.open = Some(seq);
+        Ok(seq)
+    }
+
+    // Mark an operation as done, truncating the log every `compact_after` of them.
+    // The mark needs no sync: if it is lost, the operation is replayed, which is
+    // harmless. It is flushed though, since the file buffers writes in the
+    // background, so a reader of the log sees it.
+    async fn finish(
+        &mut self,
+        seq: u64,
+        committed: bool,
+        compact_after: usize,
+    ) -> Result<(), Box<dyn Error>> {
+        self.open = None;
+        let mark = if committed { "commit" } else { "abort" };
+        self.file
+            .write_all(format!("{} {}\n", seq, mark).as_bytes())
+            .await?;
+        self.file.flush().await?;
+        self.finished += 1;
+        if self.finished >= compact_after {
+            self.truncate().await?;
+        }
+        Ok(())
+    }
+
+    async fn truncate(&mut self) -> Result<(), Box<dyn Error>> {
+        self.file.set_len(0).await?;
+        self.file.sync_data().await?;
+        self.finished = 0;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WalBackend<B: StorageBackend> {
+    inner: B,
+    log: Arc<Mutex<Log>>,
+    compact_after: usize,
+    replayed: usize,
+}
+
+impl<B: StorageBackend> WalBackend<B> {
+    /// Opens the log at `wal_path`, creating it if needed, and replays the
+    /// operations a previous run left unfinished against `inner` before
+    /// truncating it.
+    pub async fn open(mut inner: B, wal_path: &str) -> Result<Self, Box<dyn Error>> {
+        let log = match fs::read(wal_path).await {
+            Ok(log) => String::from_utf8_lossy(&log).into_owned(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let unfinished = unfinished(&log);
+        for operation in &unfinished {
+            operation.replay(&mut inner).await?;
+        }
+        inner.flush().await?;
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(wal_path)
+            .await?;
+        let mut log = Log {
+            file,
+            next_seq: 1,
+            open: None,
+            finished: 0,
+        };
+        log.truncate().await?;
+        Ok(WalBackend {
+            inner,
+            log: Arc::new(Mutex::new(log)),
+            compact_after: DEFAULT_COMPACT_AFTER,
+            replayed: unfinished.len(),
+        })
+    }
+
+    /// Sets how many finished operations the log holds before it is truncated.
+    pub fn with_compact_after(mut self, compact_after: usize) -> Self {
+        self.compact_after = compact_after.max(1);
+        self
+    }
+
+    /// How many unfinished operations `open` replayed.
+    pub fn replayed(&self) -> usize {
+        self.replayed
+    }
+
+    /// Returns a reference to the wrapped backend.
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    // Record the operation, apply it, then mark it committed or aborted
+    async fn logged(&mut self, operation: Operation) -> Result<bool, Box<dyn Error>> {
+        let mut log = self.log.lock().await;
+        let seq = log.record(&operation).await?;
+        // The error isn't `Send`, so it can't be held across the next await
+        let result = operation
+            .apply(&mut self.inner)
+            .await
+            .map_err(|e| e.to_string());
+        log.finish(seq, result.is_ok(), self.compact_after).await?;
+        Ok(result?)
+    }
+}
+
+#[async_trait]
+impl<B: StorageBackend> StorageBackend for WalBackend<B> {
+    async fn write(&mut self, data: &str) -> Result<(), Box<dyn Error>> {
+        self.write_bytes(data.as_bytes()).await
+    }
+
+    async fn read(&mut self) -> Result<String, Box<dyn Error>> {
+        self.inner.read().await
+    }
+
+    // Pending operations are moot once everything is gone, so the log goes first
+    async fn cleanup(&mut self) -> Result<(), Box<dyn Error>> {
+        let mut log = self.log.lock().await;
+        log.truncate().await?;
+        self.inner.cleanup().await
+    }
+
+    async fn write_bytes(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        self.logged(Operation::Write(data.to_vec()))
+            .await
+            .map(|_| ())
+    }
+
+    // Costs a read of the inner backend, to record where the append starts
+    async fn extend_bytes(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        let prior_len = self.inner.read_bytes().await?.len();
+        self.logged(Operation::Extend {
+            prior_len,
+            data: data.to_vec(),
+        })
+        .await
+        .map(|_| ())
+    }
+
+    async fn read_bytes(&mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.inner.read_bytes().await
+    }
+
+    async fn read_range(&mut self, start: usize, len: usize) -> Result<String, Box<dyn Error>> {
+        self.inner.read_range(start, len).await
+    }
+
+    async fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        self.inner.flush().await
+    }
+
+    async fn compare_and_swap(
+        &mut self,
+        expected: &str,
+        new: &str,
+    ) -> Result<bool, Box<dyn Error>> {
+        self.logged(Operation::Swap {
+            expected: expected.to_string(),
+            new: new.to_string(),
+        })
+        .await
+    }
+
+    async fn last_modified(&mut self) -> Result<Option<SystemTime>, Box<dyn Error>> {
+        self.inner.last_modified().await
+    }
+
+    async fn health_check(&mut self) -> Result<(), Box<dyn Error>> {
+        self.inner.health_check().await
+    }
+}
@@ -0,0 +1,289 @@
+// src/circuit_breaker.rs
+
+//! # Circuit Breaker
+//!
+//! When a backend or a remote node is down, every operation against it still
+//! tries and fails, often only after a timeout. A `CircuitBreaker` wraps a
+//! `StorageBackend` or a `CommunicationProtocol` and stops trying for a while
+//! instead:
+//!
+//! - **Closed**: operations go through. After `failure_threshold` consecutive
+//!   failures the breaker trips open.
+//! - **Open**: operations fail right away with `CircuitOpenError`, without
+//!   reaching the wrapped backend or protocol, until `cooldown` has passed.
+//! - **HalfOpen**: a single trial operation goes through. If it succeeds the
+//!   breaker closes again; if it fails it opens for another `cooldown`. Other
+//!   operations keep failing fast while the trial runs.
+//!
+//! Any error counts as a failure. Clones share the breaker, so every handle on
+//! a backend trips together. `state` reports where the breaker is, e.g. for a
+//! health endpoint.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use astra::backends::memory::MemoryBackend;
+//! use astra::backends::storage::StorageBackend;
+//! use astra::circuit_breaker::{BreakerState, CircuitBreaker};
+//! use std::time::Duration;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let mut backend = CircuitBreaker::new(MemoryBackend::new(), 5, Duration::from_secs(30));
+//!     backend.write("hello").await?;
+//!     assert_eq!(backend.state(), BreakerState::Closed);
+//!     Ok(())
+//! }
+//! ```
+
+use crate::backends::storage::StorageBackend;
+use crate::clock::{Clock, TokioClock};
+use crate::network::http::CommunicationProtocol;
+use async_trait::async_trait;
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Where a `CircuitBreaker` is, see the module docs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+/// The error operations fail with while the breaker is open.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitOpenError;
+
+impl fmt::Display for CircuitOpenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Circuit breaker is open")
+    }
+}
+
+impl std::error::Error for CircuitOpenError {}
+
+#[derive(Debug)]
+struct Core {
+    state: BreakerState,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    // Whether the half-open trial is running
+    trial: bool,
+}
+
+// The state shared by clones, and how it moves
+#[derive(Debug, Clone)]
+struct Breaker {
+    core: Arc<Mutex<Core>>,
+    failure_threshold: u32,
+    cooldown: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+// Lets an operation through; a trial that is dropped before it finished lets
+// the next operation try instead
+struct Permit<'a> {
+    breaker: &'a Breaker,
+    trial: bool,
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        if self.trial {
+            self.breaker.core.lock().unwrap().trial = false;
+        }
+    }
+}
+
+impl Breaker {
+    fn cooled_down(&self, core: &Core) -> bool {
+        core.opened_at
+            .is_some_and(|opened_at| self.clock.now() >= opened_at + self.cooldown)
+    }
+
+    fn state(&self) -> BreakerState {
+        let core = self.core.lock().unwrap();
+        match core.state {
+            BreakerState::Open if self.cooled_down(&core) => BreakerState::HalfOpen,
+            state => state,
+        }
+    }
+
+    fn admit(&self) -> Result<Permit<'_>, CircuitOpenError> {
+        let mut core = self.core.lock().unwrap();
+        let trial = match core.state {
+            BreakerState::Closed => false,
+            BreakerState::Open if self.cooled_down(&core) => true,
+            BreakerState::HalfOpen if !core.trial => true,
+            _ => return Err(CircuitOpenError),
+        };
+        if trial {
+            core.state = BreakerState::HalfOpen;
+            core.trial = true;
+        }
+        Ok(Permit {
+            breaker: self,
+            trial,
+        })
+    }
+
+    fn record(&self, permit: &Permit<'_>, succeeded: bool) {
+        let mut core = self.core.lock().unwrap();
+        if succeeded {
+            core.state = BreakerState::Closed;
+            core.consecutive_failures = 0;
+            core.opened_at = None;
+            return;
+        }
+        core.consecutive_failures = core.consecutive_failures.saturating_add(1);
+        if permit.trial || core.consecutive_failures >= self.failure_threshold {
+            core.state = BreakerState::Open;
+            core.opened_at = Some(self.clock.now());
+        }
+    }
+
+    // Run `operation` if the breaker lets it through, recording how it went
+    async fn call<T, E, F>(
+        &self,
+        operation: F,
+        open: impl FnOnce(CircuitOpenError) -> E,
+    ) -> Result<T, E>
+    where
+        F: Future<Output = Result<T, E>>,
+    {
+        let permit = self.admit().map_err(open)?;
+        let result = operation.await;
+        self.record(&permit, result.is_ok());
+        result
+    }
+}
+
+/// Wraps a backend or a protocol, failing fast while it keeps failing (see the
+/// module docs).
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker<T> {
+    inner: T,
+    breaker: Breaker,
+}
+
+impl<T> CircuitBreaker<T> {
+    /// Wraps `inner`, tripping open after `failure_threshold` consecutive
+    /// failures and trying again after `cooldown`.
+    pub fn new(inner: T, failure_threshold: u32, cooldown: Duration) -> Self {
+        CircuitBreaker {
+            inner,
+            breaker: Breaker {
+                core: Arc::new(Mutex::new(Core {
+                    state: BreakerState::Closed,
+                    consecutive_failures: 0,
+                    opened_at: None,
+                    trial: false,
+                })),
+                failure_threshold: failure_threshold.max(1),
+                cooldown,
+                clock: Arc::new(TokioClock),
+            },
+        }
+    }
+
+    /// Sets the clock the cooldown is measured on, e.g. a `MockClock` in tests.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.breaker.clock = clock;
+        self
+    }
+
+    /// Where the breaker is. An open breaker whose cooldown has passed reports
+    /// `HalfOpen`, as the next operation will be let through as the trial.
+    pub fn state(&self) -> BreakerState {
+        self.breaker.state()
+    }
+
+    /// How many operations failed in a row since the last success.
+    pub fn consecutive_failures(&self) -> u32 {
+        self.breaker.core.lock().unwrap().consecutive_failures
+    }
+
+    /// Returns a reference to the wrapped backend or protocol.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+}
+
+fn open_error(e: CircuitOpenError) -> Box<dyn Error> {
+    Box::new(e)
+}
+
+#[async_trait]
+impl<B: StorageBackend> StorageBackend for CircuitBreaker<B> {
+    async fn write(&mut self, data: &str) -> Result<(), Box<dyn Error>> {
+        self.breaker.call(self.inner.write(data), open_error).await
+    }
+
+    async fn read(&mut self) -> Result<String, Box<dyn Error>> {
+        self.breaker.call(self.inner.read(), open_error).await
+    }
+
+    async fn cleanup(&mut self) -> Result<(), Box<dyn Error>> {
+        self.breaker.call(self.inner.cleanup(), open_error).await
+    }
+
+    async fn write_bytes(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        self.breaker
+            .call(self.inner.write_bytes(data), open_error)
+            .await
+    }
+
+    async fn extend_bytes(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        self.breaker
+            .call(self.inner.extend_bytes(data), open_error)
+            .await
+    }
+
+    async fn read_bytes(&mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.breaker.call(self.inner.read_bytes(), open_error).await
+    }
+
+    async fn read_range(&mut self, start: usize, len: usize) -> Result<String, Box<dyn Error>> {
+        self.breaker
+            .call(self.inner.read_range(start, len), open_error)
+            .await
+    }
+
+    async fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        self.breaker.call(self.inner.flush(), open_error).await
+    }
+
+    async fn compare_and_swap(
+        &mut self,
+        expected: &str,
+        new: &str,
+    ) -> Result<bool, Box<dyn Error>> {
+        let swap = self.inner.compare_and_swap(expected, new);
+        self.breaker.call(swap, open_error).await
+    }
+
+    async fn last_modified(&mut self) -> Result<Option<SystemTime>, Box<dyn Error>> {
+        self.breaker
+            .call(self.inner.last_modified(), open_error)
+            .await
+    }
+}
+
+#[async_trait]
+impl<P: CommunicationProtocol + Send + Sync> CommunicationProtocol for CircuitBreaker<P> {
+    async fn send_message(&self, address: &str, message: &str) -> Result<(), String> {
+        self.breaker
+            .call(self.inner.send_message(address, message), |e| e.to_string())
+            .await
+    }
+
+    async fn ask(&self, address: &str, message: &str, timeout: Duration) -> Result<String, String> {
+        self.breaker
+            .call(self.inner.ask(address, message, timeout), |e| e.to_string())
+            .await
+    }
+}
@@ -0,0 +1,177 @@
+// src/clock.rs
+
+//! # Clock
+//!
+//! Timer-driven components (the `SnapshotActor` snapshot task, time-windowed
+//! `AggregatorActor`s) read time through the `Clock` trait instead of calling
+//! `tokio::time` directly. In production they use `TokioClock`; tests can inject a
+//! `MockClock` and advance it by hand, so time-based behavior runs instantly and
+//! deterministically instead of waiting on real sleeps.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use astra::clock::{Clock, MockClock};
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let clock = MockClock::new();
+//!     let sleeper = {
+//!         let clock = clock.clone();
+//!         tokio::spawn(async move { clock.sleep(Duration::from_secs(3600)).await })
+//!     };
+//!
+//!     // An hour passes instantly
+//!     clock.wait_for_sleepers(1).await;
+//!     clock.advance(Duration::from_secs(3600));
+//!     sleeper.await.unwrap();
+//! }
+//! ```
+
+use async_trait::async_trait;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+
+/// A source of time for timer-driven components.
+#[async_trait]
+pub trait Clock: Send + Sync + Debug {
+    /// The current time.
+    fn now(&self) -> Instant;
+
+    /// Waits until `deadline` has been reached.
+    async fn sleep_until(&self, deadline: Instant);
+
+    /// Waits for `duration` to elapse.
+    async fn sleep(&self, duration: Duration) {
+        self.sleep_until(self.now() + duration).await;
+    }
+}
+
+/// Creates an `Interval` ticking every `period` on the given clock.
+pub fn interval(clock: Arc<dyn Clock>, period: Duration) -> Interval {
+    let next = clock.now();
+    Interval {
+        clock,
+        next,
+        period,
+    }
+}
+
+/// Periodic ticks driven by a `Clock`. Like `tokio::time::interval`, the first
+/// tick completes immediately.
+#[derive(Debug)]
+pub struct Interval {
+    clock: Arc<dyn Clock>,
+    next: Instant,
+    period: Duration,
+}
+
+impl Interval {
+    /// Waits for the next tick.
+    pub async fn tick(&mut self) -> Instant {
+        self.clock.sleep_until(self.next).await;
+        let tick = self.next;
+        self.next += self.period;
+        tick
+    }
+}
+
+/// The real clock, backed by `tokio::time`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioClock;
+
+#[async_trait]
+impl Clock for TokioClock {
+    fn now(&self) -> Instant {
+        tokio::time::Instant::now().into_std()
+    }
+
+    async fn sleep_until(&self, deadline: Instant) {
+        tokio::time::sleep_until(deadline.into()).await;
+    }
+}
+
+/// A manually driven clock for tests. Time only moves when `advance` is called,
+/// which wakes every sleeper whose deadline has been reached. Clones share the
+/// same time.
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Debug)]
+struct MockState {
+    now: Instant,
+    sleepers: Vec<(Instant, oneshot::Sender<()>)>,
+}
+
+impl MockClock {
+    pub fn new() -> Self {
+        MockClock {
+            state: Arc::new(Mutex::new(MockState {
+                now: Instant::now(),
+                sleepers: Vec::new(),
+            })),
+        }
+    }
+
+    /// Moves time forward, waking the sleepers that are now due.
+    pub fn advance(&self, duration: Duration) {
+        let mut state = self.state.lock().unwrap();
+        state.now += duration;
+        let now = state.now;
+        let (due, pending) = std::mem::take(&mut state.sleepers)
+            .into_iter()
+            .partition(|(deadline, _)| *deadline <= now);
+        state.sleepers = pending;
+        for (_, waker) in due {
+            let _ = waker.send(());
+        }
+    }
+
+    /// Number of tasks currently sleeping on this clock.
+    pub fn sleepers(&self) -> usize {
+        let mut state = self.state.lock().unwrap();
+        // Forget sleepers whose future was dropped
+        state.sleepers.retain(|(_, waker)| !waker.is_closed());
+        state.sleepers.len()
+    }
+
+    /// Yields until at least `count` tasks are sleeping on this clock, i.e. until
+    /// the tasks under test have caught up and are waiting for time to pass.
+    pub async fn wait_for_sleepers(&self, count: usize) {
+        while self.sleepers() < count {
+            tokio::task::yield_now().await;
+        }
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        MockClock::new()
+    }
+}
+
+#[async_trait]
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.state.lock().unwrap().now
+    }
+
+    async fn sleep_until(&self, deadline: Instant) {
+        let woken = {
+            let mut state = self.state.lock().unwrap();
+            if deadline <= state.now {
+                return;
+            }
+            let (waker, woken) = oneshot::channel();
+            state.sleepers.push((deadline, waker));
+            woken
+        };
+        let _ = woken.await;
+    }
+}
@@ -20,19 +20,215 @@
 //!    Ok(())
 //! }
 //! ```
+//!
+//! ## Missing data
+//!
+//! What a fresh backend reads as depends on the backend: an empty string for a
+//! new file, an error once the file is gone. `with_missing_policy` makes it
+//! explicit: when the backend holds no data, or reports it doesn't exist,
+//! `read_from_backend` returns an empty string (`MissingPolicy::Empty`, the
+//! default), fails (`MissingPolicy::Error`), or returns a default value
+//! (`MissingPolicy::Default`).
+//!
+//! ## Read-only access
+//!
+//! `read_only` turns an actor into one over a `ReadOnlyBackend`, for components
+//! that must never change the data they read: its writes and cleanups fail
+//! without reaching the backend.
+//!
+//! ## Read limit
+//!
+//! `with_max_read_size(max)` makes reads fail when the backend holds more than
+//! `max` bytes, rather than handing a corrupted or unexpectedly large state to
+//! the caller. The data is checked once the backend has returned it, so to keep
+//! it from being loaded at all, limit the backend too where it can, e.g. with
+//! `FileBackend::with_max_read_size`. Reads are unlimited by default.
+//!
+//! ## Backpressure
+//!
+//! A mailbox in front of a slow backend fills up, and producers only find out
+//! once their sends block. `with_backpressure(max_latency)` lets them slow down
+//! before that: whenever a write sent as a message takes longer than
+//! `max_latency`, the actor raises its `Backpressure` signal until the write
+//! completes. Producers holding the signal (see `backpressure`) check it with
+//! `is_high`, or `.await` `eased` before sending more. The signal only advises;
+//! sends go through whether it is raised or not.
+//!
+//! ## Write coalescing
+//!
+//! Each write normally reaches the backend on its own, and for a
+//! `FileBackend` rewrites the whole file. For state updated in bursts,
+//! `with_coalescing(window)` merges them: the first write opens a window of
+//! `window`, later writes replace the value it holds, and once it closes only
+//! the last value is written (last write wins). No write waits longer than
+//! that: the window is written out when it closes even if the actor sits idle,
+//! and earlier by anything that needs the stored data to be current, i.e.
+//! reads, `update`, `compare_and_swap_on_backend`, `flush_backend`,
+//! checkpoints, cleanups and the `Shutdown` message. A window that fails to
+//! write out is kept and tried again by the next of those.
+//!
+//! Windows are written through a clone of the backend, so coalescing suits
+//! backends whose clones share their storage, which all but `BufferedBackend`
+//! do.
 
 // src/data_actor.rs
+use crate::backends::read_only::ReadOnlyBackend;
 use crate::backends::storage::StorageBackend;
 use async_trait::async_trait;
+use futures::future::BoxFuture;
 use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
 //use std::fmt::Debug;
 
-use crate::actor_system::{Actor, Message}; // Assuming Actor and Message are defined in a module named actor_system
+use crate::actor_system::{Actor, Checkpoint, CheckpointToken, Message}; // Assuming Actor and Message are defined in a module named actor_system
+
+/// What `DataActor::read_from_backend` returns when the backend holds no data.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum MissingPolicy {
+    /// Fail, for actors that can't start without their data.
+    Error,
+    /// Read it as empty.
+    #[default]
+    Empty,
+    /// Read it as the given value.
+    Default(String),
+}
+
+// Whether a read error means there is nothing stored yet
+fn is_absent(error: &(dyn Error + 'static)) -> bool {
+    error
+        .downcast_ref::<std::io::Error>()
+        .is_some_and(|e| e.kind() == std::io::ErrorKind::NotFound)
+}
+
+// How many times `update` retries before giving up under contention
+const MAX_UPDATE_ATTEMPTS: usize = 1000;
+
+/// Tells producers whether a `DataActor` wants them to hold off, see the module
+/// docs. Clones watch the same actor.
+#[derive(Debug, Clone)]
+pub struct Backpressure {
+    high: watch::Receiver<bool>,
+}
+
+impl Backpressure {
+    /// Whether the actor is stuck on a slow write.
+    pub fn is_high(&self) -> bool {
+        *self.high.borrow()
+    }
+
+    /// Waits until the pressure is low, returning at once if it already is or
+    /// if the actor is gone.
+    pub async fn eased(&mut self) {
+        let _ = self.high.wait_for(|high| !high).await;
+    }
+}
+
+// Raises the pressure while a write runs longer than `max_latency`
+#[derive(Debug, Clone)]
+struct PressureGauge {
+    max_latency: Duration,
+    high: Arc<watch::Sender<bool>>,
+}
+
+impl PressureGauge {
+    async fn watch<T>(&self, write: impl Future<Output = T>) -> T {
+        tokio::pin!(write);
+        if let Ok(result) = tokio::time::timeout(self.max_latency, &mut write).await {
+            return result;
+        }
+        self.high.send_replace(true);
+        let result = write.await;
+        self.high.send_replace(false);
+        result
+    }
+}
+
+// Writes the value of a coalescing window through a clone of the backend
+type Persist = Arc<dyn Fn(Vec<u8>) -> BoxFuture<'static, Result<(), String>> + Send + Sync>;
+
+// The state of the coalescing window
+#[derive(Default)]
+struct Window {
+    // The last value written since the window opened, not stored yet
+    data: Option<Vec<u8>>,
+    // Whether a timer will write the window out
+    timed: bool,
+}
+
+// Merges the writes made within `window` of each other, see the module docs
+#[derive(Clone)]
+struct Coalescer {
+    window: Duration,
+    // Held while the window is written out, so writes reach the backend in order
+    state: Arc<tokio::sync::Mutex<Window>>,
+    persist: Persist,
+}
+
+impl Coalescer {
+    // Hold `data` as the window's value, opening the window if needed
+    async fn write(&self, data: &[u8]) {
+        let mut state = self.state.lock().await;
+        if !state.timed {
+            state.timed = true;
+            let coalescer = self.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(coalescer.window).await;
+                if let Err(e) = coalescer.close().await {
+                    println!("Failed to write coalesced data: {}", e);
+                }
+            });
+        }
+        state.data = Some(data.to_vec());
+    }
+
+    // Write out the window when its timer fires
+    async fn close(&self) -> Result<(), String> {
+        let mut state = self.state.lock().await;
+        state.timed = false;
+        Self::write_out(&mut state, &self.persist).await
+    }
+
+    // Write out the window now, if it holds anything
+    async fn flush(&self) -> Result<(), String> {
+        let mut state = self.state.lock().await;
+        Self::write_out(&mut state, &self.persist).await
+    }
+
+    async fn write_out(state: &mut Window, persist: &Persist) -> Result<(), String> {
+        if let Some(data) = state.data.take() {
+            if let Err(e) = persist(data.clone()).await {
+                // Kept for the next flush
+                state.data = Some(data);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Coalescer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Coalescer")
+            .field("window", &self.window)
+            .finish_non_exhaustive()
+    }
+}
 
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct DataActor<B: StorageBackend> {
     backend: B,
+    missing_policy: MissingPolicy,
+    prepared_checkpoint: Option<u64>,
+    last_checkpoint: Option<u64>,
+    pressure: Option<PressureGauge>,
+    max_read_size: Option<usize>,
+    coalescer: Option<Coalescer>,
 }
 
 #[async_trait]
@@ -43,42 +239,277 @@ impl<B: StorageBackend + 'static> Actor for DataActor<B> {
     async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error> {
         match message {
             Message::Regular(data) => {
-                self.backend.write(&data).await?;
+                match (&self.coalescer, &self.pressure) {
+                    (Some(coalescer), _) => coalescer.write(data.as_bytes()).await,
+                    (None, Some(pressure)) => pressure.watch(self.backend.write(&data)).await?,
+                    (None, None) => self.backend.write(&data).await?,
+                }
                 Ok(())
             }
             Message::Shutdown => {
                 println!("Shutting down DataActor.");
+                self.settle().await?;
                 self.backend.cleanup().await?;
                 Ok(())
             }
+            Message::System(_) => Ok(()),
         }
     }
 
     async fn cleanup(&mut self) {
+        if let Err(e) = self.settle().await {
+            println!("Failed to write coalesced data: {:?}", e);
+        }
         if let Err(e) = self.backend.cleanup().await {
             println!("Failed to clean up backend: {:?}", e);
         }
     }
 }
 
+// Two-phase checkpoints driven by `ActorSystem::checkpoint_all`
+#[async_trait]
+impl<B: StorageBackend + 'static> Checkpoint for DataActor<B> {
+    async fn prepare_checkpoint(&mut self, checkpoint_id: u64) -> Result<CheckpointToken, String> {
+        // Flush buffered writes so everything up to here is durable
+        self.flush_backend()
+            .await
+            .map_err(|e| format!("Failed to flush backend: {}", e))?;
+        self.prepared_checkpoint = Some(checkpoint_id);
+        Ok(CheckpointToken::new(checkpoint_id))
+    }
+
+    async fn commit_checkpoint(&mut self, token: CheckpointToken) -> Result<(), String> {
+        if self.prepared_checkpoint != Some(token.checkpoint_id()) {
+            return Err(format!(
+                "Checkpoint {} was not prepared",
+                token.checkpoint_id()
+            ));
+        }
+        self.prepared_checkpoint = None;
+        self.last_checkpoint = Some(token.checkpoint_id());
+        Ok(())
+    }
+
+    async fn abort_checkpoint(&mut self, token: CheckpointToken) {
+        if self.prepared_checkpoint == Some(token.checkpoint_id()) {
+            self.prepared_checkpoint = None;
+        }
+    }
+}
+
 impl<B: StorageBackend> DataActor<B> {
     /// Creates a new `DataActor` with the given backend.
     pub fn new(backend: B) -> Self {
-        DataActor { backend }
+        DataActor {
+            backend,
+            missing_policy: MissingPolicy::default(),
+            prepared_checkpoint: None,
+            last_checkpoint: None,
+            pressure: None,
+            max_read_size: None,
+            coalescer: None,
+        }
+    }
+
+    /// Sets what reads return when the backend holds no data (see the module docs).
+    pub fn with_missing_policy(mut self, missing_policy: MissingPolicy) -> Self {
+        self.missing_policy = missing_policy;
+        self
+    }
+
+    /// Fails reads of more than `max` bytes (see the module docs).
+    pub fn with_max_read_size(mut self, max: usize) -> Self {
+        self.max_read_size = Some(max);
+        self
+    }
+
+    /// Raises the backpressure signal while a write sent as a message runs
+    /// longer than `max_latency` (see the module docs).
+    pub fn with_backpressure(mut self, max_latency: Duration) -> Self {
+        self.pressure = Some(PressureGauge {
+            max_latency,
+            high: Arc::new(watch::channel(false).0),
+        });
+        self
+    }
+
+    /// Merges the writes made within `window` of each other, storing only the
+    /// last (see the module docs).
+    pub fn with_coalescing(mut self, window: Duration) -> Self
+    where
+        B: 'static,
+    {
+        let backend = self.backend.clone();
+        self.coalescer = Some(Coalescer {
+            window,
+            state: Arc::default(),
+            persist: Arc::new(move |data| {
+                let mut backend = backend.clone();
+                Box::pin(async move { backend.write_bytes(&data).await.map_err(|e| e.to_string()) })
+            }),
+        });
+        self
+    }
+
+    /// The backpressure signal for producers, `None` unless set with
+    /// `with_backpressure`.
+    pub fn backpressure(&self) -> Option<Backpressure> {
+        self.pressure.as_ref().map(|pressure| Backpressure {
+            high: pressure.high.subscribe(),
+        })
+    }
+
+    /// Turns this actor into one that reads the same backend but can't change
+    /// it, keeping its missing policy. An open coalescing window is still
+    /// written out when it closes.
+    pub fn read_only(self) -> DataActor<ReadOnlyBackend<B>> {
+        DataActor {
+            backend: ReadOnlyBackend::new(self.backend),
+            missing_policy: self.missing_policy,
+            prepared_checkpoint: None,
+            last_checkpoint: self.last_checkpoint,
+            pressure: self.pressure,
+            max_read_size: self.max_read_size,
+            coalescer: None,
+        }
+    }
+
+    /// The id of the last checkpoint this actor committed, if any.
+    pub fn last_checkpoint(&self) -> Option<u64> {
+        self.last_checkpoint
     }
 
     /// Writes data to the backend.
     pub async fn write_to_backend(&mut self, data: &str) -> Result<(), Box<dyn Error>> {
-        self.backend.write(data).await
+        match &self.coalescer {
+            Some(coalescer) => {
+                coalescer.write(data.as_bytes()).await;
+                Ok(())
+            }
+            None => self.backend.write(data).await,
+        }
     }
 
-    /// Reads data from the backend.
+    /// Reads data from the backend, applying the missing policy if it holds none.
     pub async fn read_from_backend(&mut self) -> Result<String, Box<dyn Error>> {
-        self.backend.read().await
+        let data = self.read_bytes_from_backend().await?;
+        Ok(String::from_utf8(data)?)
+    }
+
+    /// Writes raw bytes to the backend.
+    pub async fn write_bytes_to_backend(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        match &self.coalescer {
+            Some(coalescer) => {
+                coalescer.write(data).await;
+                Ok(())
+            }
+            None => self.backend.write_bytes(data).await,
+        }
+    }
+
+    // Write out the open coalescing window, if any, so the backend is current
+    async fn settle(&self) -> Result<(), Box<dyn Error>> {
+        match &self.coalescer {
+            Some(coalescer) => Ok(coalescer.flush().await?),
+            None => Ok(()),
+        }
+    }
+
+    /// Reads raw bytes from the backend, applying the missing policy if it holds
+    /// none.
+    pub async fn read_bytes_from_backend(&mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.settle().await?;
+        match self
+            .backend
+            .read_bytes()
+            .await
+            .and_then(|data| self.bounded(data))
+        {
+            Ok(data) if !data.is_empty() => Ok(data),
+            Ok(_) => self.missing(),
+            Err(e) if is_absent(e.as_ref()) => self.missing(),
+            Err(e) => Err(e),
+        }
+    }
+
+    // Read raw bytes, empty if the backend holds none, whatever the missing policy
+    pub(crate) async fn read_stored_bytes(&mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.settle().await?;
+        match self.backend.read_bytes().await {
+            Err(e) if is_absent(e.as_ref()) => Ok(Vec::new()),
+            read => self.bounded(read?),
+        }
+    }
+
+    // Refuse data over the read limit
+    fn bounded(&self, data: Vec<u8>) -> Result<Vec<u8>, Box<dyn Error>> {
+        match self.max_read_size {
+            Some(max) if data.len() > max => Err(format!(
+                "The backend holds {} bytes, over the {} byte read limit",
+                data.len(),
+                max
+            )
+            .into()),
+            _ => Ok(data),
+        }
+    }
+
+    // What a read of a backend holding no data returns
+    fn missing(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        match &self.missing_policy {
+            MissingPolicy::Error => Err("The backend holds no data".into()),
+            MissingPolicy::Empty => Ok(Vec::new()),
+            MissingPolicy::Default(default) => Ok(default.as_bytes().to_vec()),
+        }
+    }
+
+    /// Atomically replaces the stored data with `f(current)`.
+    ///
+    /// The new value is written with the backend's `compare_and_swap`, so if another
+    /// writer changed the data in the meantime the update is retried against the
+    /// fresh value. `f` may therefore be called more than once and should be free of
+    /// side effects. Atomicity is only as strong as the backend's `compare_and_swap`.
+    pub async fn update<F>(&mut self, mut f: F) -> Result<(), Box<dyn Error>>
+    where
+        F: FnMut(String) -> String,
+    {
+        self.settle().await?;
+        for _ in 0..MAX_UPDATE_ATTEMPTS {
+            let current = self.backend.read().await?;
+            let new = f(current.clone());
+            if self.backend.compare_and_swap(&current, &new).await? {
+                return Ok(());
+            }
+            // Lost the race, let the other writer finish before retrying
+            tokio::task::yield_now().await;
+        }
+        Err(format!(
+            "update gave up after {} conflicting attempts",
+            MAX_UPDATE_ATTEMPTS
+        )
+        .into())
+    }
+
+    /// Writes `new` only if the backend still holds `expected`, returning
+    /// whether it did, with the backend's `compare_and_swap`.
+    pub async fn compare_and_swap_on_backend(
+        &mut self,
+        expected: &str,
+        new: &str,
+    ) -> Result<bool, Box<dyn Error>> {
+        self.settle().await?;
+        self.backend.compare_and_swap(expected, new).await
+    }
+
+    /// Pushes any buffered writes down to the backend's storage.
+    pub async fn flush_backend(&mut self) -> Result<(), Box<dyn Error>> {
+        self.settle().await?;
+        self.backend.flush().await
     }
 
     /// Cleans up the backend.
     pub async fn cleanup_backend(&mut self) -> Result<(), Box<dyn Error>> {
+        self.settle().await?;
         self.backend.cleanup().await
     }
 }
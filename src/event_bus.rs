@@ -0,0 +1,99 @@
+// event_bus.rs
+
+//! # Event bus
+//!
+//! An `EventBus` carries the events the system raises, one topic per event type:
+//! a subscriber asks for the type it cares about and gets every event of that
+//! type published from then on. Every `ActorSystem` has one, see
+//! `ActorSystem::event_bus`, on which it publishes:
+//!
+//! - `SupervisionEvent`s of the system's supervisor (see
+//!   `ActorSystem::with_supervisor`);
+//! - a `DeadLetterEvent` for every message that couldn't be delivered because
+//!   its actor is missing or stopped, whether or not the dead-letter queue is
+//!   enabled;
+//! - a `HighWaterEvent` whenever a mailbox crosses its high-water mark.
+//!
+//! Anything `Clone + Send + Sync + 'static` can be published as well, so
+//! application events can share the bus. Publishing to a topic nobody
+//! subscribed to is a no-op. A subscriber that falls more than 256 events behind
+//! misses the oldest ones, as with any `broadcast` channel.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use astra::event_bus::EventBus;
+//!
+//! #[derive(Debug, Clone, PartialEq)]
+//! struct Deployed(u32);
+//!
+//! let bus = EventBus::new();
+//! let mut deployments = bus.subscribe::<Deployed>();
+//! bus.publish(Deployed(7));
+//! // Nobody listens to this topic
+//! bus.publish("ignored".to_string());
+//!
+//! assert_eq!(deployments.try_recv().unwrap(), Deployed(7));
+//! assert!(deployments.try_recv().is_err());
+//! ```
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+// How many events a slow subscriber may fall behind before it starts missing them
+const TOPIC_CAPACITY: usize = 256;
+
+/// A handle to a set of typed topics; clones share the same topics.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    // The `broadcast::Sender<T>` of each topic, keyed by the `TypeId` of `T`
+    topics: Arc<Mutex<HashMap<TypeId, Box<dyn Any + Send>>>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Publishes `event` to the subscribers of its type, returning how many got it.
+    pub fn publish<T>(&self, event: T) -> usize
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        let topics = self.topics.lock().unwrap();
+        topics
+            .get(&TypeId::of::<T>())
+            .and_then(|sender| sender.downcast_ref::<broadcast::Sender<T>>())
+            .map_or(0, |sender| sender.send(event).unwrap_or(0))
+    }
+
+    /// Subscribes to the events of type `T` published from now on.
+    pub fn subscribe<T>(&self) -> broadcast::Receiver<T>
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        let mut topics = self.topics.lock().unwrap();
+        topics
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(broadcast::channel::<T>(TOPIC_CAPACITY).0))
+            .downcast_ref::<broadcast::Sender<T>>()
+            .expect("a topic holds the sender of its type")
+            .subscribe()
+    }
+
+    /// Whether both handles share the same topics.
+    pub fn same_bus(&self, other: &EventBus) -> bool {
+        Arc::ptr_eq(&self.topics, &other.topics)
+    }
+}
+
+impl fmt::Debug for EventBus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventBus")
+            .field("topics", &self.topics.lock().unwrap().len())
+            .finish()
+    }
+}
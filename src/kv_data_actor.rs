@@ -0,0 +1,165 @@
+//! # `KvDataActor` keeps typed values under string keys in a single backend.
+//!
+//! The entries are stored as one JSON object, so any `StorageBackend` works. Every
+//! change reads the object, edits it and writes it back with the backend's
+//! `compare_and_swap`, retrying if another writer got there first, so several
+//! actors can share a backend without losing each other's keys.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use astra::backends::memory::MemoryBackend;
+//! use astra::kv_data_actor::KvDataActor;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!    let mut actor: KvDataActor<_, u32> = KvDataActor::new(MemoryBackend::new());
+//!
+//!    actor.set("apples", 3).await?;
+//!    actor.set("pears", 5).await?;
+//!    assert_eq!(actor.get("apples").await?, Some(3));
+//!
+//!    assert_eq!(actor.remove("apples").await?, Some(3));
+//!    assert_eq!(actor.keys().await?, vec!["pears"]);
+//!
+//!    Ok(())
+//! }
+//! ```
+
+// src/kv_data_actor.rs
+use crate::actor_system::{Actor, Message};
+use crate::backends::storage::StorageBackend;
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::marker::PhantomData;
+
+// How many times a change retries before giving up under contention
+const MAX_CHANGE_ATTEMPTS: usize = 1000;
+
+/// A change sent to a `KvDataActor` through the actor system.
+#[derive(Debug, Clone, PartialEq)]
+pub enum KvMessage<V> {
+    Set(String, V),
+    Remove(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct KvDataActor<B: StorageBackend, V = String> {
+    backend: B,
+    _values: PhantomData<fn() -> V>,
+}
+
+#[async_trait]
+impl<B, V> Actor for KvDataActor<B, V>
+where
+    B: StorageBackend + 'static,
+    V: Serialize + DeserializeOwned + Send + std::fmt::Debug + 'static,
+{
+    type Message = KvMessage<V>;
+    type Error = Box<dyn Error>;
+
+    async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error> {
+        match message {
+            Message::Regular(KvMessage::Set(key, value)) => {
+                self.set(key, value).await?;
+                Ok(())
+            }
+            Message::Regular(KvMessage::Remove(key)) => {
+                self.remove(&key).await?;
+                Ok(())
+            }
+            Message::Shutdown => {
+                println!("Shutting down KvDataActor.");
+                self.backend.flush().await
+            }
+            Message::System(_) => Ok(()),
+        }
+    }
+}
+
+impl<B, V> KvDataActor<B, V>
+where
+    B: StorageBackend,
+    V: Serialize + DeserializeOwned + Send,
+{
+    /// Creates a new `KvDataActor` over the entries already in `backend`, if any.
+    pub fn new(backend: B) -> Self {
+        KvDataActor {
+            backend,
+            _values: PhantomData,
+        }
+    }
+
+    /// Returns the value stored under `key`.
+    pub async fn get(&mut self, key: &str) -> Result<Option<V>, Box<dyn Error>> {
+        let mut entries = self.load().await?;
+        Ok(entries.remove(key))
+    }
+
+    /// Stores `value` under `key`, returning the value it replaced.
+    pub async fn set(
+        &mut self,
+        key: impl Into<String>,
+        value: V,
+    ) -> Result<Option<V>, Box<dyn Error>> {
+        let key = key.into();
+        let value = serde_json::to_value(value)?;
+        self.change(|entries| entries.insert(key.clone(), value.clone()))
+            .await
+    }
+
+    /// Removes `key`, returning the value it held.
+    pub async fn remove(&mut self, key: &str) -> Result<Option<V>, Box<dyn Error>> {
+        self.change(|entries| entries.remove(key)).await
+    }
+
+    /// Returns every key, in sorted order.
+    pub async fn keys(&mut self) -> Result<Vec<String>, Box<dyn Error>> {
+        let entries = self.load().await?;
+        Ok(entries.into_keys().collect())
+    }
+
+    /// Cleans up the backend, dropping every entry.
+    pub async fn cleanup_backend(&mut self) -> Result<(), Box<dyn Error>> {
+        self.backend.cleanup().await
+    }
+
+    async fn load(&mut self) -> Result<BTreeMap<String, V>, Box<dyn Error>> {
+        parse_entries(&self.backend.read().await?)
+    }
+
+    // Apply `f` to the stored object and write it back, returning the value `f`
+    // took out. Retried against the fresh object if another writer interfered.
+    async fn change<F>(&mut self, mut f: F) -> Result<Option<V>, Box<dyn Error>>
+    where
+        F: FnMut(&mut BTreeMap<String, serde_json::Value>) -> Option<serde_json::Value>,
+    {
+        for _ in 0..MAX_CHANGE_ATTEMPTS {
+            let raw = self.backend.read().await?;
+            let mut entries = parse_entries(&raw)?;
+            let previous = f(&mut entries);
+            let new = serde_json::to_string(&entries)?;
+            if self.backend.compare_and_swap(&raw, &new).await? {
+                return Ok(previous.map(serde_json::from_value).transpose()?);
+            }
+            // Lost the race, let the other writer finish before retrying
+            tokio::task::yield_now().await;
+        }
+        Err(format!(
+            "change gave up after {} conflicting attempts",
+            MAX_CHANGE_ATTEMPTS
+        )
+        .into())
+    }
+}
+
+// An empty backend holds no entries yet
+fn parse_entries<T: DeserializeOwned>(raw: &str) -> Result<BTreeMap<String, T>, Box<dyn Error>> {
+    if raw.trim().is_empty() {
+        return Ok(BTreeMap::new());
+    }
+    Ok(serde_json::from_str(raw)?)
+}
@@ -2,8 +2,14 @@
 
 pub mod actor_system; // This module is the base system for the actor model
 pub mod backends; // This module is to create backends for the data actors
+pub mod circuit_breaker; // This module stops calling backends and protocols that keep failing
+pub mod clock; // This module provides time sources for timer-driven components
 pub mod data_actor; // This module is to create Data Actors
+pub mod event_bus; // This module lets callers subscribe to the events of the system
+pub mod kv_data_actor; // This module is to create key-value Data Actors
 pub mod logging; // This module provides logging utilities
 pub mod network; // This module provides different network protocols for the actor system
 pub mod snapshot_actor; // This module is to create Snapshot Actors
 pub mod supervision; // This module provides supervision strategies for actors
+pub mod test_util; // This module provides a harness for testing actors
+pub mod util; // This module provides helpers shared by the other modules
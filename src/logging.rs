@@ -1,39 +1,235 @@
 // logging.rs
 
 use async_trait::async_trait;
+use std::io::{IsTerminal, Write};
+use std::sync::Mutex;
+use tokio::fs::OpenOptions;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{mpsc, oneshot};
 
 // Define a trait for logging
 #[async_trait]
 pub trait Logger {
     async fn log(&self, level: LogLevel, message: &str);
+
+    // Log a line, reporting whether it reached the logger's sink. Used by
+    // `FallbackLogger` to tell when to fall back. The default logs and reports
+    // success, for loggers that can't tell.
+    async fn try_log(&self, level: LogLevel, message: &str) -> Result<(), String> {
+        self.log(level, message).await;
+        Ok(())
+    }
 }
 
-// Define log levels
-#[derive(Debug)]
+// Define log levels, ordered from least to most severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum LogLevel {
+    Debug,
     Info,
+    Warn,
     Error,
-    Debug,
 }
 
-// Console logger implementation
-pub struct ConsoleLogger;
+/// Console logger implementation. Errors and warnings go to stderr and the rest
+/// to stdout, unless `with_stderr_split(false)` sends everything to stdout. The
+/// level tag is colored when the output is a terminal and `NO_COLOR` is unset;
+/// `with_color` overrides the detection.
+///
+/// Build it with `ConsoleLogger::default()` or `new`: it is no longer a unit
+/// struct, so `ConsoleLogger` alone isn't a value anymore.
+///
+/// ```rust
+/// use astra::logging::ConsoleLogger;
+/// use astra::supervision::{SupervisionStrategy, Supervisor};
+/// use std::sync::Arc;
+///
+/// let supervisor = Supervisor::new(SupervisionStrategy::Restart)
+///     .with_logger(Arc::new(ConsoleLogger::default()));
+/// ```
+pub struct ConsoleLogger {
+    color: bool,
+    split: bool,
+    output: ConsoleOutput,
+}
+
+enum ConsoleOutput {
+    Std,
+    // Set by `with_writers`, e.g. to capture the output in tests
+    Writers {
+        stdout: Mutex<Box<dyn Write + Send>>,
+        stderr: Mutex<Box<dyn Write + Send>>,
+    },
+}
+
+impl ConsoleLogger {
+    pub fn new() -> Self {
+        let terminal = std::io::stdout().is_terminal() && std::io::stderr().is_terminal();
+        ConsoleLogger {
+            color: terminal && std::env::var_os("NO_COLOR").is_none(),
+            split: true,
+            output: ConsoleOutput::Std,
+        }
+    }
+
+    // Turn colored level tags on or off, whatever the output is
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    // Send errors and warnings to stderr (the default), or everything to stdout
+    pub fn with_stderr_split(mut self, split: bool) -> Self {
+        self.split = split;
+        self
+    }
+
+    // Write to the given writers instead of the process' stdout and stderr.
+    // Color is left as detected; call `with_color` after this to set it.
+    pub fn with_writers(
+        mut self,
+        stdout: impl Write + Send + 'static,
+        stderr: impl Write + Send + 'static,
+    ) -> Self {
+        self.output = ConsoleOutput::Writers {
+            stdout: Mutex::new(Box::new(stdout)),
+            stderr: Mutex::new(Box::new(stderr)),
+        };
+        self
+    }
+
+    fn line(&self, level: LogLevel, message: &str) -> String {
+        if !self.color {
+            return format!("[{:?}] {}\n", level, message);
+        }
+        let color = match level {
+            LogLevel::Error => "31",
+            LogLevel::Warn => "33",
+            LogLevel::Info => "32",
+            LogLevel::Debug => "34",
+        };
+        format!("[\x1b[{}m{:?}\x1b[0m] {}\n", color, level, message)
+    }
+}
+
+impl Default for ConsoleLogger {
+    fn default() -> Self {
+        ConsoleLogger::new()
+    }
+}
 
 #[async_trait]
 impl Logger for ConsoleLogger {
+    // A failed write is ignored: there is nowhere left to report it
     async fn log(&self, level: LogLevel, message: &str) {
-        println!("[{:?}] {}", level, message);
+        let _ = self.try_log(level, message).await;
+    }
+
+    async fn try_log(&self, level: LogLevel, message: &str) -> Result<(), String> {
+        let line = self.line(level, message);
+        let to_stderr = self.split && level >= LogLevel::Warn;
+        let written = match (&self.output, to_stderr) {
+            (ConsoleOutput::Std, false) => std::io::stdout().lock().write_all(line.as_bytes()),
+            (ConsoleOutput::Std, true) => std::io::stderr().lock().write_all(line.as_bytes()),
+            (ConsoleOutput::Writers { stdout, .. }, false) => {
+                stdout.lock().unwrap().write_all(line.as_bytes())
+            }
+            (ConsoleOutput::Writers { stderr, .. }, true) => {
+                stderr.lock().unwrap().write_all(line.as_bytes())
+            }
+        };
+        written.map_err(|e| format!("Failed to write log line: {}", e))
     }
 }
 
-// File logger implementation
+// File logger implementation. A single background task owns the file and
+// appends the lines in the order they were logged, so lines logged concurrently
+// from many actors are never interleaved. `log` only queues the line.
+#[derive(Debug, Clone)]
 pub struct FileLogger {
     file_path: String,
+    writer: mpsc::UnboundedSender<WriterCommand>,
+}
+
+#[derive(Debug)]
+enum WriterCommand {
+    Line(String),
+    // Like `Line`, but reports whether the line was written instead of sending
+    // it to stderr
+    Checked(String, oneshot::Sender<Result<(), String>>),
+    // Acknowledged once every line queued before it has been written
+    Flush(oneshot::Sender<()>),
 }
 
 impl FileLogger {
+    // Create a logger appending to `file_path`. Spawns the writer task, so it
+    // must be called from within a tokio runtime. The task stops once every
+    // clone of the logger has been dropped.
     pub fn new(file_path: String) -> Self {
-        FileLogger { file_path }
+        let (writer, lines) = mpsc::unbounded_channel();
+        tokio::spawn(write_lines(file_path.clone(), lines));
+        FileLogger { file_path, writer }
+    }
+
+    // The file the logger appends to
+    pub fn file_path(&self) -> &str {
+        &self.file_path
+    }
+
+    // Wait until every line logged so far has been written to the file
+    pub async fn flush(&self) {
+        let (done, written) = oneshot::channel();
+        if self.writer.send(WriterCommand::Flush(done)).is_ok() {
+            let _ = written.await;
+        }
+    }
+}
+
+// Append each queued line to the file, opened once for the lifetime of the task.
+// Logging must never take the caller down, so a line that can't be written goes
+// to stderr along with the error instead.
+async fn write_lines(file_path: String, mut commands: mpsc::UnboundedReceiver<WriterCommand>) {
+    let mut file = match OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&file_path)
+        .await
+    {
+        Ok(file) => Some(file),
+        Err(e) => {
+            eprintln!("Failed to open log file {}: {}", file_path, e);
+            None
+        }
+    };
+    while let Some(command) = commands.recv().await {
+        match (command, file.as_mut()) {
+            (WriterCommand::Line(line), Some(file)) => {
+                if let Err(e) = file.write_all(line.as_bytes()).await {
+                    eprintln!("Failed to write to log file {}: {}", file_path, e);
+                    eprint!("{}", line);
+                }
+            }
+            (WriterCommand::Line(line), None) => eprint!("{}", line),
+            (WriterCommand::Checked(line, done), Some(file)) => {
+                let written = file
+                    .write_all(line.as_bytes())
+                    .await
+                    .map_err(|e| format!("Failed to write to log file {}: {}", file_path, e));
+                let _ = done.send(written);
+            }
+            (WriterCommand::Checked(_, done), None) => {
+                let _ = done.send(Err(format!("Log file {} is not open", file_path)));
+            }
+            (WriterCommand::Flush(done), Some(file)) => {
+                if let Err(e) = file.flush().await {
+                    eprintln!("Failed to flush log file {}: {}", file_path, e);
+                }
+                let _ = done.send(());
+            }
+            // Lines went to stderr as they came
+            (WriterCommand::Flush(done), None) => {
+                let _ = done.send(());
+            }
+        }
     }
 }
 
@@ -41,8 +237,67 @@ impl FileLogger {
 impl Logger for FileLogger {
     async fn log(&self, level: LogLevel, message: &str) {
         let log_message = format!("[{:?}] {}\n", level, message);
-        tokio::fs::write(&self.file_path, log_message)
-            .await
-            .unwrap();
+        if let Err(unsent) = self.writer.send(WriterCommand::Line(log_message)) {
+            eprintln!("Log writer for {} has stopped", self.file_path);
+            if let WriterCommand::Line(line) = unsent.0 {
+                eprint!("{}", line);
+            }
+        }
+    }
+
+    // Waits for the writer task, so the line is known to be in the file
+    async fn try_log(&self, level: LogLevel, message: &str) -> Result<(), String> {
+        let log_message = format!("[{:?}] {}\n", level, message);
+        let stopped = || format!("Log writer for {} has stopped", self.file_path);
+        let (done, written) = oneshot::channel();
+        self.writer
+            .send(WriterCommand::Checked(log_message, done))
+            .map_err(|_| stopped())?;
+        written.await.map_err(|_| stopped())?
+    }
+}
+
+// Logs to `primary`, and to `secondary` whenever `primary` fails to write a
+// line, e.g. a `FileLogger` whose file can't be opened backed by the console.
+// Unlike logging to both, a line only goes to `secondary` when it would be lost.
+pub struct FallbackLogger<P, S> {
+    primary: P,
+    secondary: S,
+}
+
+impl<P: Logger, S: Logger> FallbackLogger<P, S> {
+    pub fn new(primary: P, secondary: S) -> Self {
+        FallbackLogger { primary, secondary }
+    }
+
+    // The logger tried first
+    pub fn primary(&self) -> &P {
+        &self.primary
+    }
+
+    // The logger tried when the primary fails
+    pub fn secondary(&self) -> &S {
+        &self.secondary
+    }
+}
+
+#[async_trait]
+impl<P, S> Logger for FallbackLogger<P, S>
+where
+    P: Logger + Send + Sync,
+    S: Logger + Send + Sync,
+{
+    async fn log(&self, level: LogLevel, message: &str) {
+        if self.primary.try_log(level, message).await.is_err() {
+            self.secondary.log(level, message).await;
+        }
+    }
+
+    // Fails only if both loggers did, with the secondary's error
+    async fn try_log(&self, level: LogLevel, message: &str) -> Result<(), String> {
+        match self.primary.try_log(level, message).await {
+            Ok(()) => Ok(()),
+            Err(_) => self.secondary.try_log(level, message).await,
+        }
     }
 }
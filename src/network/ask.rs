@@ -0,0 +1,166 @@
+// network/ask.rs
+
+//! # Asking remote actors
+//!
+//! `CommunicationProtocol::send_message` is fire and forget. To get an answer
+//! back, `ask` attaches a unique correlation id to the request and waits for the
+//! reply carrying the same id. `HttpProtocol` supports it, as does
+//! `RemoteActorRef::ask`; the other protocols refuse to ask.
+//!
+//! On the receiving node the `HttpServer` delivers an ask to the actor as a JSON
+//! `Ask` envelope holding the message and its correlation id. The actor answers
+//! through the server's `Replies`, and the server sends the answer back in the
+//! response, with the correlation id in the `X-Correlation-Id` header; the asking
+//! side refuses a reply whose id is missing or doesn't match its request.
+//!
+//! Both sides give up eventually: the asker after the timeout it passed to `ask`,
+//! the server after its `with_ask_timeout`. A reply that comes after the server
+//! gave up is dropped, and `Replies::reply` returns `false`.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use astra::actor_system::{Actor, ActorSystem, Message};
+//! use astra::network::ask::{Ask, Replies};
+//! use astra::network::http::{CommunicationProtocol, HttpProtocol, HttpServer};
+//! use async_trait::async_trait;
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//!
+//! // Answers every ask with the message in upper case
+//! struct Shouter {
+//!     replies: Arc<Replies>,
+//! }
+//!
+//! #[async_trait]
+//! impl Actor for Shouter {
+//!     type Message = String;
+//!     type Error = String;
+//!
+//!     async fn receive(&mut self, message: Message<String>) -> Result<(), String> {
+//!         if let Message::Regular(message) = message {
+//!             if let Some(ask) = Ask::parse(&message) {
+//!                 self.replies.reply(&ask.correlation_id, ask.message.to_uppercase());
+//!             }
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let replies = Arc::new(Replies::new());
+//!     let mut system = ActorSystem::new();
+//!     system.add_actor("shouter".to_string(), Shouter { replies: Arc::clone(&replies) });
+//!
+//!     let server = HttpServer::new(Arc::new(system))
+//!         .with_replies(replies)
+//!         .start("127.0.0.1:0".parse()?)
+//!         .await?;
+//!
+//!     let address = format!("http://{}/actors/shouter", server.local_addr());
+//!     let reply = HttpProtocol::new()
+//!         .ask(&address, "hello", Duration::from_secs(5))
+//!         .await?;
+//!     assert_eq!(reply, "HELLO");
+//!     server.stop();
+//!     Ok(())
+//! }
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::oneshot;
+
+/// The header carrying the correlation id of an ask and its reply.
+pub const CORRELATION_ID_HEADER: &str = "x-correlation-id";
+
+// Longest correlation id a server accepts
+const MAX_CORRELATION_ID_LEN: usize = 128;
+
+/// An ask as delivered to the actor: the message and the id to reply to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ask {
+    pub correlation_id: String,
+    pub message: String,
+}
+
+impl Ask {
+    /// Reads an ask envelope, or `None` if `message` is a plain message.
+    pub fn parse(message: &str) -> Option<Ask> {
+        serde_json::from_str(message).ok()
+    }
+
+    pub(crate) fn to_message(&self) -> String {
+        serde_json::to_string(self).expect("an ask serializes to JSON")
+    }
+}
+
+/// The asks a server is waiting on an actor to answer.
+#[derive(Debug, Default)]
+pub struct Replies {
+    pending: Mutex<HashMap<String, oneshot::Sender<String>>>,
+}
+
+impl Replies {
+    pub fn new() -> Self {
+        Replies::default()
+    }
+
+    /// Answers the ask with `correlation_id`. Returns `false`, dropping the
+    /// reply, if nobody is waiting for it, e.g. because the ask timed out.
+    pub fn reply(&self, correlation_id: &str, reply: impl Into<String>) -> bool {
+        match self.pending.lock().unwrap().remove(correlation_id) {
+            Some(waiting) => waiting.send(reply.into()).is_ok(),
+            None => false,
+        }
+    }
+
+    /// How many asks are waiting for a reply.
+    pub fn pending(&self) -> usize {
+        self.pending.lock().unwrap().len()
+    }
+
+    // Wait for the reply to `correlation_id`, unless it is already awaited
+    pub(crate) fn expect(&self, correlation_id: &str) -> Option<oneshot::Receiver<String>> {
+        let mut pending = self.pending.lock().unwrap();
+        if pending.contains_key(correlation_id) {
+            return None;
+        }
+        let (reply, waiting) = oneshot::channel();
+        pending.insert(correlation_id.to_string(), reply);
+        Some(waiting)
+    }
+
+    // Stop waiting, so a late reply is dropped
+    pub(crate) fn forget(&self, correlation_id: &str) {
+        self.pending.lock().unwrap().remove(correlation_id);
+    }
+}
+
+// A correlation id unique across processes in practice: the process id, the
+// time and a per-process counter
+pub(crate) fn new_correlation_id() -> String {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos());
+    format!(
+        "{:x}-{:x}-{:x}",
+        std::process::id(),
+        nanos,
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+// Ids are kept to a short run of letters, digits, `-` and `_`
+pub(crate) fn is_valid_correlation_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_CORRELATION_ID_LEN
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
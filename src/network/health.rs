@@ -0,0 +1,192 @@
+// network/health.rs
+
+//! # Health endpoints
+//!
+//! `HealthServer` answers the probes of an orchestrator such as Kubernetes,
+//! behind the `health` feature:
+//!
+//! - `GET /healthz` (liveness) returns 200 while the actor system runs, and
+//!   503 once its cancellation token is cancelled, as `shutdown_timeout` and
+//!   `run_until_signal` do.
+//! - `GET /readyz` (readiness) returns 200 only while the system runs and every
+//!   check passes: the `health_check` of each backend added with
+//!   `with_backend`, and that of the registry set with `with_registry`. A
+//!   failing check makes it return 503, with one line per failure in the body.
+//!
+//! The checks run on every probe, concurrently. The server keeps answering
+//! after the system shuts down, so probes see the 503 rather than a refused
+//! connection; `HealthServerHandle::stop` stops it.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use astra::actor_system::ActorSystem;
+//! use astra::backends::memory::MemoryBackend;
+//! use astra::network::health::HealthServer;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), String> {
+//!     let system: ActorSystem<String> = ActorSystem::new();
+//!     let health = HealthServer::new(&system)
+//!         .with_backend("state", MemoryBackend::new())
+//!         .start("127.0.0.1:0".parse().unwrap())
+//!         .await?;
+//!     println!("Probes go to http://{}/readyz", health.local_addr());
+//!     health.stop();
+//!     Ok(())
+//! }
+//! ```
+
+use super::registry::ActorRegistry;
+use crate::actor_system::ActorSystem;
+use crate::backends::boxed::BoxedBackend;
+use crate::backends::storage::StorageBackend;
+use futures::future::join_all;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+/// Serves `/healthz` and `/readyz` for an actor system, see the module docs.
+pub struct HealthServer {
+    // Cancelled once the system shuts down
+    running: CancellationToken,
+    backends: Vec<(String, BoxedBackend)>,
+    registry: Option<Arc<dyn ActorRegistry>>,
+}
+
+impl HealthServer {
+    pub fn new<M, E>(system: &ActorSystem<M, E>) -> Self
+    where
+        M: Send + 'static + std::fmt::Debug,
+        E: Send + 'static + std::fmt::Debug,
+    {
+        HealthServer {
+            running: system.cancellation_token(),
+            backends: Vec::new(),
+            registry: None,
+        }
+    }
+
+    /// Makes readiness depend on the `health_check` of `backend`, reported
+    /// under `name` when it fails.
+    pub fn with_backend<B: StorageBackend + 'static>(mut self, name: &str, backend: B) -> Self {
+        self.backends
+            .push((name.to_string(), BoxedBackend::new(backend)));
+        self
+    }
+
+    /// Makes readiness depend on the `health_check` of `registry`.
+    pub fn with_registry(mut self, registry: Arc<dyn ActorRegistry>) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Runs the readiness checks, returning the reason of each that failed.
+    pub async fn readiness(&self) -> Result<(), Vec<String>> {
+        let mut failures = Vec::new();
+        if self.running.is_cancelled() {
+            failures.push("system: shutting down".to_string());
+        }
+        let backends = self.backends.iter().map(|(name, backend)| {
+            // A clone works on the same storage, and needs no lock
+            let mut backend = backend.clone();
+            async move {
+                backend
+                    .health_check()
+                    .await
+                    .map_err(|e| format!("backend {}: {}", name, e))
+            }
+        });
+        let registry = async {
+            match &self.registry {
+                Some(registry) => registry
+                    .health_check()
+                    .await
+                    .map_err(|e| format!("registry: {}", e)),
+                None => Ok(()),
+            }
+        };
+        let (backends, registry) = tokio::join!(join_all(backends), registry);
+        failures.extend(
+            backends
+                .into_iter()
+                .chain([registry])
+                .filter_map(Result::err),
+        );
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(failures)
+        }
+    }
+
+    /// Binds to `addr` and serves the probes until the returned handle is
+    /// stopped.
+    pub async fn start(self, addr: SocketAddr) -> Result<HealthServerHandle, String> {
+        let health = Arc::new(self);
+        let make_svc = make_service_fn(move |_conn| {
+            let health = Arc::clone(&health);
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req| {
+                    let health = Arc::clone(&health);
+                    async move { Ok::<_, Infallible>(health.answer(req).await) }
+                }))
+            }
+        });
+        let server = Server::try_bind(&addr)
+            .map_err(|e| e.to_string())?
+            .serve(make_svc);
+        let local_addr = server.local_addr();
+        let task = tokio::spawn(async move {
+            if let Err(e) = server.await {
+                eprintln!("Health server error: {}", e);
+            }
+        });
+        Ok(HealthServerHandle { local_addr, task })
+    }
+
+    async fn answer(&self, req: Request<Body>) -> Response<Body> {
+        if req.method() != Method::GET {
+            return respond(StatusCode::NOT_FOUND, "Not found");
+        }
+        match req.uri().path() {
+            "/healthz" if self.running.is_cancelled() => {
+                respond(StatusCode::SERVICE_UNAVAILABLE, "shutting down")
+            }
+            "/healthz" => respond(StatusCode::OK, "ok"),
+            "/readyz" => match self.readiness().await {
+                Ok(()) => respond(StatusCode::OK, "ready"),
+                Err(failures) => respond(StatusCode::SERVICE_UNAVAILABLE, failures.join("\n")),
+            },
+            _ => respond(StatusCode::NOT_FOUND, "Not found"),
+        }
+    }
+}
+
+fn respond(status: StatusCode, body: impl Into<Body>) -> Response<Body> {
+    let mut response = Response::new(body.into());
+    *response.status_mut() = status;
+    response
+}
+
+/// A running `HealthServer`.
+pub struct HealthServerHandle {
+    local_addr: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+impl HealthServerHandle {
+    /// The address the server is listening on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Stops the server.
+    pub fn stop(self) {
+        self.task.abort();
+    }
+}
@@ -1,20 +1,217 @@
 // network/http.rs
 
+use super::ask::{self, Ask, Replies, CORRELATION_ID_HEADER};
+use crate::actor_system::{ActorSystem, SendError};
 use async_trait::async_trait;
+use hyper::body::HttpBody;
 use hyper::client::HttpConnector;
-use hyper::{Body, Client, Request};
+use hyper::header::{CONTENT_TYPE, RETRY_AFTER};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Client, Method, Request, Response, Server, StatusCode, Uri};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 
 #[async_trait]
 pub trait CommunicationProtocol {
     async fn send_message(&self, address: &str, message: &str) -> Result<(), String>;
+
+    /// Sends a message and waits up to `timeout` for the actor's reply, see
+    /// the `ask` module. Protocols that can't carry replies refuse to ask.
+    async fn ask(
+        &self,
+        _address: &str,
+        _message: &str,
+        _timeout: Duration,
+    ) -> Result<String, String> {
+        Err("This protocol does not support asking".to_string())
+    }
+}
+
+/// Why a protocol refused to send something. Protocols report their errors
+/// as strings, so this is what those strings are made from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ProtocolError {
+    /// The address isn't one the protocol can send to. Holds the address.
+    InvalidAddress(String),
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtocolError::InvalidAddress(address) => write!(
+                f,
+                "Invalid address {:?}, expected a URL like http://host:port/actors/name",
+                address
+            ),
+        }
+    }
 }
 
-// HTTP implementation
-pub struct HttpProtocol;
+impl std::error::Error for ProtocolError {}
+
+// Parse an address up front, so a typo in the configuration says so instead
+// of surfacing as a request that can't be built
+fn parse_address(address: &str) -> Result<Uri, ProtocolError> {
+    let invalid = || ProtocolError::InvalidAddress(address.to_string());
+    let uri: Uri = address.parse().map_err(|_| invalid())?;
+    if uri.scheme_str() != Some("http") || uri.authority().is_none() {
+        return Err(invalid());
+    }
+    Ok(uri)
+}
+
+/// The `Content-Type` of messages sent with `HttpCodec::Text`.
+pub const TEXT_CONTENT_TYPE: &str = "text/plain; charset=utf-8";
+/// The `Content-Type` of messages sent with `HttpCodec::Json`.
+pub const ENVELOPE_CONTENT_TYPE: &str = "application/vnd.astra.envelope+json";
+/// The `Content-Type` of messages sent with `HttpCodec::LengthPrefixed`.
+pub const LENGTH_PREFIXED_CONTENT_TYPE: &str = "application/vnd.astra.length-prefixed";
+
+/// How `HttpProtocol` encodes the body of a message. Each codec has its own
+/// `Content-Type`, which `HttpServer` reads to decode the body back into the
+/// message it delivers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HttpCodec {
+    /// The message as is, the default.
+    #[default]
+    Text,
+    /// The message wrapped in an `HttpEnvelope` naming its actor.
+    Json,
+    /// The length of the message as a big-endian `u32`, then the message, as
+    /// in the frames of the TCP transport.
+    LengthPrefixed,
+}
+
+/// The JSON body of a message sent with `HttpCodec::Json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HttpEnvelope {
+    /// The actor the message is for, the last segment of the address.
+    pub actor: String,
+    /// A fresh id for every message, or that of the ask it belongs to.
+    pub correlation_id: String,
+    pub message: String,
+}
+
+impl HttpCodec {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            HttpCodec::Text => TEXT_CONTENT_TYPE,
+            HttpCodec::Json => ENVELOPE_CONTENT_TYPE,
+            HttpCodec::LengthPrefixed => LENGTH_PREFIXED_CONTENT_TYPE,
+        }
+    }
+
+    fn encode(&self, uri: &Uri, correlation_id: &str, message: &str) -> Vec<u8> {
+        match self {
+            HttpCodec::Text => message.as_bytes().to_vec(),
+            HttpCodec::Json => {
+                let envelope = HttpEnvelope {
+                    actor: uri
+                        .path()
+                        .rsplit('/')
+                        .next()
+                        .unwrap_or_default()
+                        .to_string(),
+                    correlation_id: correlation_id.to_string(),
+                    message: message.to_string(),
+                };
+                serde_json::to_vec(&envelope).expect("an envelope serializes to JSON")
+            }
+            HttpCodec::LengthPrefixed => {
+                let mut body = (message.len() as u32).to_be_bytes().to_vec();
+                body.extend_from_slice(message.as_bytes());
+                body
+            }
+        }
+    }
+
+    // The codec a received body was encoded with; anything but the codecs' own
+    // content types is read as text
+    fn of_content_type(content_type: &str) -> HttpCodec {
+        match content_type {
+            ENVELOPE_CONTENT_TYPE => HttpCodec::Json,
+            LENGTH_PREFIXED_CONTENT_TYPE => HttpCodec::LengthPrefixed,
+            _ => HttpCodec::Text,
+        }
+    }
+
+    // Decode a body received for `actor_name`
+    fn decode(&self, actor_name: &str, body: Vec<u8>) -> Result<String, String> {
+        let text = |bytes: Vec<u8>| {
+            String::from_utf8(bytes).map_err(|_| "Body is not valid UTF-8".to_string())
+        };
+        match self {
+            HttpCodec::Text => text(body),
+            HttpCodec::Json => {
+                let envelope: HttpEnvelope = serde_json::from_slice(&body)
+                    .map_err(|e| format!("Malformed envelope: {}", e))?;
+                if envelope.actor != actor_name {
+                    return Err(format!(
+                        "Envelope is addressed to actor {}, not {}",
+                        envelope.actor, actor_name
+                    ));
+                }
+                Ok(envelope.message)
+            }
+            HttpCodec::LengthPrefixed => {
+                let Some((prefix, message)) = body.split_first_chunk::<4>() else {
+                    return Err("Body is too short for its length prefix".to_string());
+                };
+                if u32::from_be_bytes(*prefix) as usize != message.len() {
+                    return Err(format!(
+                        "Length prefix says {} bytes, but {} follow",
+                        u32::from_be_bytes(*prefix),
+                        message.len()
+                    ));
+                }
+                text(message.to_vec())
+            }
+        }
+    }
+}
+
+/// Sends messages to an `HttpServer` over HTTP. Build it with `new` or
+/// `Default`: it is no longer a unit struct, so `HttpProtocol` alone isn't a
+/// value anymore.
+#[derive(Debug, Clone, Default)]
+pub struct HttpProtocol {
+    max_message_size: Option<usize>,
+    codec: HttpCodec,
+}
+
+impl HttpProtocol {
+    pub fn new() -> Self {
+        HttpProtocol::default()
+    }
+
+    // Refuse to send messages larger than `max` bytes
+    pub fn with_max_message_size(mut self, max: usize) -> Self {
+        self.max_message_size = Some(max);
+        self
+    }
+
+    /// Sets how message bodies are encoded, `HttpCodec::Text` by default.
+    pub fn with_codec(mut self, codec: HttpCodec) -> Self {
+        self.codec = codec;
+        self
+    }
+}
 
 #[async_trait]
 impl CommunicationProtocol for HttpProtocol {
     async fn send_message(&self, address: &str, message: &str) -> Result<(), String> {
+        // Reject oversized messages before touching the network
+        check_message_size(message.len(), self.max_message_size)?;
+        let uri = parse_address(address).map_err(|e| e.to_string())?;
+
         // Create an HTTP connector with default settings
         let connector = HttpConnector::new();
 
@@ -22,8 +219,10 @@ impl CommunicationProtocol for HttpProtocol {
         let client = Client::builder().build::<_, Body>(connector);
 
         // Create a request using owned message data
-        let req = Request::post(address)
-            .body(Body::from(message.to_string())) // Convert to owned data
+        let body = self.codec.encode(&uri, &ask::new_correlation_id(), message);
+        let req = Request::post(uri)
+            .header(CONTENT_TYPE, self.codec.content_type())
+            .body(Body::from(body))
             .map_err(|e| format!("Failed to build request: {}", e))?;
 
         // Send the request asynchronously
@@ -34,4 +233,425 @@ impl CommunicationProtocol for HttpProtocol {
 
         Ok(())
     }
+
+    async fn ask(&self, address: &str, message: &str, timeout: Duration) -> Result<String, String> {
+        check_message_size(message.len(), self.max_message_size)?;
+        let uri = parse_address(address).map_err(|e| e.to_string())?;
+
+        let correlation_id = ask::new_correlation_id();
+        let body = self.codec.encode(&uri, &correlation_id, message);
+        let req = Request::post(uri)
+            .header(CORRELATION_ID_HEADER, &correlation_id)
+            .header(CONTENT_TYPE, self.codec.content_type())
+            .body(Body::from(body))
+            .map_err(|e| format!("Failed to build request: {}", e))?;
+
+        // Giving up drops the request, and with it any reply still on its way
+        let exchange = async {
+            let response = Client::new()
+                .request(req)
+                .await
+                .map_err(|e| format!("Failed to send request: {}", e))?;
+            let (parts, body) = response.into_parts();
+            let body = hyper::body::to_bytes(body)
+                .await
+                .map_err(|e| format!("Failed to read reply: {}", e))?;
+            Ok::<_, String>((parts, body))
+        };
+        let (parts, body) = tokio::time::timeout(timeout, exchange)
+            .await
+            .map_err(|_| format!("No reply from {} within {:?}", address, timeout))??;
+        let body =
+            String::from_utf8(body.to_vec()).map_err(|_| "Reply is not valid UTF-8".to_string())?;
+        if parts.status != StatusCode::OK {
+            return Err(format!("Ask failed with {}: {}", parts.status, body));
+        }
+
+        match parts.headers.get(CORRELATION_ID_HEADER) {
+            Some(id) if id.as_bytes() == correlation_id.as_bytes() => Ok(body),
+            Some(id) => Err(format!(
+                "Reply carries correlation id {:?}, expected {}",
+                id, correlation_id
+            )),
+            None => Err("Reply carries no correlation id".to_string()),
+        }
+    }
+}
+
+/// Receives messages over HTTP and forwards them into an `ActorSystem`.
+///
+/// A `POST /actors/{name}` request delivers its body to the named actor. Delivery
+/// uses `try_send_message`, so a saturated actor never holds the connection open;
+/// the sender gets a backpressure signal instead:
+///
+/// - `202 Accepted`: the message was enqueued
+/// - `503 Service Unavailable` with `Retry-After`: the actor's mailbox is full,
+///   or the system is at its global mailbox limit
+/// - `503 Service Unavailable` without `Retry-After`: the system is quiescing
+/// - `429 Too Many Requests`: the actor's rate limit is exceeded
+/// - `404 Not Found`: unknown path or actor
+/// - `410 Gone`: the actor has stopped or is shutting down
+/// - `413 Payload Too Large`: the body exceeds the configured `max_message_size`
+/// - `400 Bad Request`: the body isn't UTF-8, can't be decoded as its
+///   `Content-Type` says, doesn't deserialize into the type set with
+///   `with_message_type`, or the actor rejected it; the response body says why
+///
+/// Bodies sent with `HttpCodec::Json` or `HttpCodec::LengthPrefixed`, as told by
+/// their `Content-Type`, are decoded into the message they carry; an envelope
+/// addressed to another actor than the path's is refused. Any other body is the
+/// message itself.
+///
+/// A request with an `X-Correlation-Id` header is an ask (see the `ask` module):
+/// the actor gets an `Ask` envelope, and the server waits for its answer through
+/// `replies` before responding:
+///
+/// - `200 OK`: the answer, with the correlation id echoed in the header
+/// - `504 Gateway Timeout`: no answer within the ask timeout
+/// - `409 Conflict`: an ask with the same correlation id is already waiting
+/// - `400 Bad Request`: the correlation id is malformed
+/// - any status above when the message can't be delivered
+///
+/// The server shuts down by itself once the system's cancellation token is
+/// cancelled, or with `HttpServerHandle::shutdown`: it stops accepting
+/// connections and gives the requests in flight up to the drain timeout (see
+/// `with_drain_timeout`) to finish forwarding into the system, so a message that
+/// arrived just before isn't lost. Idle connections are closed right away.
+pub struct HttpServer {
+    system: Arc<ActorSystem<String>>,
+    config: ServerConfig,
+    drain_timeout: Duration,
+}
+
+/// How long a shutting down server waits for the requests in flight, unless
+/// configured otherwise.
+pub const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long the server waits for an actor to answer an ask, unless configured
+/// otherwise.
+pub const DEFAULT_ASK_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Settings shared by every request a server handles
+#[derive(Clone)]
+struct ServerConfig {
+    retry_after: Duration,
+    max_message_size: Option<usize>,
+    parse: Option<ParseCheck>,
+    replies: Arc<Replies>,
+    ask_timeout: Duration,
+}
+
+// Checks that a received message parses into the type the actors expect
+pub(crate) type ParseCheck = Arc<dyn Fn(&str) -> Result<(), String> + Send + Sync>;
+
+// Shared by the servers: accept the messages that are JSON for a `T`
+pub(crate) fn parses_as<T: DeserializeOwned>() -> ParseCheck {
+    Arc::new(|message| {
+        serde_json::from_str::<T>(message)
+            .map(|_| ())
+            .map_err(|e| format!("Malformed message: {}", e))
+    })
+}
+
+/// A running `HttpServer`.
+pub struct HttpServerHandle {
+    local_addr: SocketAddr,
+    // Cancelled along with the system, or by `shutdown`
+    shutdown: CancellationToken,
+    task: JoinHandle<()>,
+}
+
+impl HttpServer {
+    pub fn new(system: Arc<ActorSystem<String>>) -> Self {
+        HttpServer {
+            system,
+            config: ServerConfig {
+                retry_after: Duration::from_secs(1),
+                max_message_size: None,
+                parse: None,
+                replies: Arc::new(Replies::new()),
+                ask_timeout: DEFAULT_ASK_TIMEOUT,
+            },
+            drain_timeout: DEFAULT_DRAIN_TIMEOUT,
+        }
+    }
+
+    /// Rejects request bodies larger than `max` bytes with `413 Payload Too Large`.
+    /// The body is never buffered past the limit, protecting the server from peers
+    /// sending huge payloads.
+    pub fn with_max_message_size(mut self, max: usize) -> Self {
+        self.config.max_message_size = Some(max);
+        self
+    }
+
+    /// Rejects bodies that aren't JSON for a `T` with `400 Bad Request` and the
+    /// parse error, before they reach any actor. The body is still delivered as
+    /// the string it was received as.
+    pub fn with_message_type<T: DeserializeOwned>(mut self) -> Self {
+        self.config.parse = Some(parses_as::<T>());
+        self
+    }
+
+    /// Sets the delay suggested to senders in the `Retry-After` header (whole seconds).
+    pub fn with_retry_after(mut self, retry_after: Duration) -> Self {
+        self.config.retry_after = retry_after;
+        self
+    }
+
+    /// Sets the `Replies` actors answer asks through, shared with them when they
+    /// are created.
+    pub fn with_replies(mut self, replies: Arc<Replies>) -> Self {
+        self.config.replies = replies;
+        self
+    }
+
+    /// Sets how long the server waits for an actor to answer an ask.
+    pub fn with_ask_timeout(mut self, timeout: Duration) -> Self {
+        self.config.ask_timeout = timeout;
+        self
+    }
+
+    /// Sets how long a shutting down server waits for the requests in flight
+    /// before it stops waiting for them.
+    pub fn with_drain_timeout(mut self, timeout: Duration) -> Self {
+        self.drain_timeout = timeout;
+        self
+    }
+
+    /// The `Replies` actors answer asks through.
+    pub fn replies(&self) -> Arc<Replies> {
+        Arc::clone(&self.config.replies)
+    }
+
+    /// Binds to `addr` and serves requests on a background task.
+    pub async fn start(self, addr: SocketAddr) -> Result<HttpServerHandle, String> {
+        let system = self.system;
+        let shutdown = system.cancellation_token().child_token();
+        let config = Arc::new(self.config);
+        let drain_timeout = self.drain_timeout;
+
+        let make_svc = make_service_fn(move |_conn| {
+            let system = Arc::clone(&system);
+            let config = Arc::clone(&config);
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req| {
+                    handle_request(Arc::clone(&system), Arc::clone(&config), req)
+                }))
+            }
+        });
+
+        let server = Server::try_bind(&addr)
+            .map_err(|e| format!("Failed to bind {}: {}", addr, e))?
+            .serve(make_svc);
+        let local_addr = server.local_addr();
+
+        let draining = shutdown.clone();
+        let server = server.with_graceful_shutdown(async move { draining.cancelled().await });
+        let cancel = shutdown.clone();
+        let task = tokio::spawn(async move {
+            tokio::select! {
+                result = server => {
+                    if let Err(e) = result {
+                        eprintln!("HTTP server error: {}", e);
+                    }
+                }
+                // Requests still in flight by then are left to themselves
+                _ = async {
+                    cancel.cancelled().await;
+                    tokio::time::sleep(drain_timeout).await;
+                } => {}
+            }
+        });
+
+        Ok(HttpServerHandle {
+            local_addr,
+            shutdown,
+            task,
+        })
+    }
+}
+
+impl HttpServerHandle {
+    /// The address the server is listening on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Stops the server right away, cutting off the requests in flight.
+    pub fn stop(self) {
+        self.task.abort();
+    }
+
+    /// Shuts the server down gracefully, see `HttpServer`, and returns once it
+    /// has exited.
+    pub async fn shutdown(self) {
+        self.shutdown.cancel();
+        let _ = self.task.await;
+    }
+}
+
+// Route a request to its actor and translate the outcome into a status code
+async fn handle_request(
+    system: Arc<ActorSystem<String>>,
+    config: Arc<ServerConfig>,
+    req: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+    let actor_name = match (req.method(), req.uri().path().strip_prefix("/actors/")) {
+        (&Method::POST, Some(name)) if !name.is_empty() => name.to_string(),
+        _ => return Ok(respond(StatusCode::NOT_FOUND, "Not found".to_string())),
+    };
+    let correlation_id = match req.headers().get(CORRELATION_ID_HEADER) {
+        None => None,
+        Some(id) => match id.to_str() {
+            Ok(id) if ask::is_valid_correlation_id(id) => Some(id.to_string()),
+            _ => {
+                return Ok(respond(
+                    StatusCode::BAD_REQUEST,
+                    "Malformed correlation id".to_string(),
+                ))
+            }
+        },
+    };
+
+    let codec = req
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .map_or(HttpCodec::Text, HttpCodec::of_content_type);
+
+    let body = match read_body(req.into_body(), config.max_message_size).await {
+        Ok(bytes) => bytes,
+        Err(response) => return Ok(response),
+    };
+    let message = match codec.decode(&actor_name, body) {
+        Ok(message) => message,
+        Err(e) => return Ok(respond(StatusCode::BAD_REQUEST, e)),
+    };
+    if let Some(parse) = &config.parse {
+        if let Err(e) = parse(&message) {
+            return Ok(respond(StatusCode::BAD_REQUEST, e));
+        }
+    }
+
+    let response = match correlation_id {
+        Some(correlation_id) => {
+            let ask = Ask {
+                correlation_id,
+                message,
+            };
+            handle_ask(&system, &config, &actor_name, ask).await
+        }
+        None => match system.try_send_message(&actor_name, message) {
+            Ok(()) => respond(StatusCode::ACCEPTED, String::new()),
+            Err(e) => refused(e, &config),
+        },
+    };
+    Ok(response)
+}
+
+// Deliver an ask and respond with the actor's answer
+async fn handle_ask(
+    system: &ActorSystem<String>,
+    config: &ServerConfig,
+    actor_name: &str,
+    ask: Ask,
+) -> Response<Body> {
+    let Some(answer) = config.replies.expect(&ask.correlation_id) else {
+        return respond(
+            StatusCode::CONFLICT,
+            format!("Ask {} is already waiting for a reply", ask.correlation_id),
+        );
+    };
+    if let Err(e) = system.try_send_message(actor_name, ask.to_message()) {
+        config.replies.forget(&ask.correlation_id);
+        return refused(e, config);
+    }
+
+    match tokio::time::timeout(config.ask_timeout, answer).await {
+        Ok(Ok(reply)) => {
+            let mut response = respond(StatusCode::OK, reply);
+            response
+                .headers_mut()
+                .insert(CORRELATION_ID_HEADER, ask.correlation_id.parse().unwrap());
+            response
+        }
+        _ => {
+            // A reply that still comes is dropped
+            config.replies.forget(&ask.correlation_id);
+            respond(
+                StatusCode::GATEWAY_TIMEOUT,
+                format!("Actor {} did not reply in time", actor_name),
+            )
+        }
+    }
+}
+
+// Translate a refused delivery into a status code
+fn refused(error: SendError, config: &ServerConfig) -> Response<Body> {
+    match error {
+        e @ SendError::MailboxFull(_) | e @ SendError::SystemFull { .. } => {
+            let retry_after = config.retry_after.as_secs().max(1);
+            let mut response = respond(StatusCode::SERVICE_UNAVAILABLE, e.to_string());
+            response
+                .headers_mut()
+                .insert(RETRY_AFTER, retry_after.to_string().parse().unwrap());
+            response
+        }
+        e @ SendError::Quiescing(_) => respond(StatusCode::SERVICE_UNAVAILABLE, e.to_string()),
+        e @ SendError::RateLimited(_) => respond(StatusCode::TOO_MANY_REQUESTS, e.to_string()),
+        e @ SendError::NotFound(_) => respond(StatusCode::NOT_FOUND, e.to_string()),
+        e @ SendError::Closed(_) | e @ SendError::Closing(_) => {
+            respond(StatusCode::GONE, e.to_string())
+        }
+        e @ SendError::Rejected { .. } => respond(StatusCode::BAD_REQUEST, e.to_string()),
+        e @ SendError::TooLarge { .. } => respond(StatusCode::PAYLOAD_TOO_LARGE, e.to_string()),
+    }
+}
+
+// Read the request body, giving up as soon as it grows past the size limit
+async fn read_body(mut body: Body, max: Option<usize>) -> Result<Vec<u8>, Response<Body>> {
+    let too_large = |size: usize| {
+        respond(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            check_message_size(size, max).unwrap_err(),
+        )
+    };
+    if let Some(max) = max {
+        if let Some(length) = body.size_hint().exact() {
+            if length as usize > max {
+                return Err(too_large(length as usize));
+            }
+        }
+    }
+
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|e| {
+            respond(
+                StatusCode::BAD_REQUEST,
+                format!("Failed to read body: {}", e),
+            )
+        })?;
+        bytes.extend_from_slice(&chunk);
+        if max.is_some_and(|max| bytes.len() > max) {
+            return Err(too_large(bytes.len()));
+        }
+    }
+    Ok(bytes)
+}
+
+// Shared by the protocols: error out if a message is over the configured limit
+pub(crate) fn check_message_size(size: usize, max: Option<usize>) -> Result<(), String> {
+    match max {
+        Some(max) if size > max => Err(format!(
+            "Message is {} bytes, exceeding the {} byte limit",
+            size, max
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn respond(status: StatusCode, body: String) -> Response<Body> {
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = status;
+    response
 }
@@ -0,0 +1,332 @@
+// network/migration.rs
+
+//! # Actor migration
+//!
+//! `migrate_actor` moves a live actor to another node: its state is shipped to
+//! the target, the registry is pointed at the target, and only then is the
+//! local actor stopped. Actors opt in by implementing `Migratable`, which turns
+//! their state into a snapshot string and back, and by running inside a
+//! `MigratableActor`.
+//!
+//! The target node must already run the actor in standby, added as
+//! `MigratableActor::standby` under the address passed to `migrate_actor`.
+//! A standby actor holds on to the regular messages it receives until the
+//! snapshot arrives, restores it, then hands it the held messages in order; a
+//! standby shut down before that drops them.
+//!
+//! Migrating happens in this order:
+//!
+//! 1. the local actor refuses new messages with `SendError::Closing`;
+//! 2. once it has handled everything queued, its snapshot is taken;
+//! 3. the snapshot is sent to `target_node` as a `Handoff` message;
+//! 4. the actor is registered at `target_node`;
+//! 5. the local actor is stopped.
+//!
+//! Messages sent to the actor while it migrates are refused rather than lost. A
+//! `RemoteActorRef` whose send was refused looks the actor up again, and finds
+//! it on the target once the registry is updated. If shipping the snapshot or
+//! updating the registry fails, the local actor accepts messages again and
+//! keeps running, though a target that did get the snapshot keeps it too.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use astra::actor_system::{Actor, ActorSystem, Message};
+//! use astra::network::migration::{Handoff, Migratable, MigratableActor};
+//! use async_trait::async_trait;
+//!
+//! #[derive(Default)]
+//! struct Counter {
+//!     count: u64,
+//! }
+//!
+//! #[async_trait]
+//! impl Actor for Counter {
+//!     type Message = String;
+//!     type Error = String;
+//!
+//!     async fn receive(&mut self, message: Message<String>) -> Result<(), String> {
+//!         if let Message::Regular(_) = message {
+//!             self.count += 1;
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//!
+//! impl Migratable for Counter {
+//!     fn snapshot(&self) -> String {
+//!         self.count.to_string()
+//!     }
+//!
+//!     fn restore(&mut self, snapshot: &str) -> Result<(), String> {
+//!         self.count = snapshot.parse().map_err(|_| "Bad count".to_string())?;
+//!         Ok(())
+//!     }
+//! }
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), String> {
+//!     // The node the counter moves to runs it in standby
+//!     let mut target = ActorSystem::new();
+//!     target.add_actor(
+//!         "counter".to_string(),
+//!         MigratableActor::standby(Counter::default()),
+//!     );
+//!     // Held until the snapshot arrives, then counted on top of it
+//!     target.send_message("counter", "tick".to_string()).await?;
+//!     // What `migrate_actor` sends once the old node has counted to 41
+//!     let handoff = Handoff {
+//!         snapshot: "41".to_string(),
+//!     };
+//!     target.send_message("counter", handoff.to_message()).await?;
+//!     target.wait_quiesced().await;
+//!     assert_eq!(target.inspect("counter").await.as_deref(), Some("42"));
+//!     target.shutdown().await;
+//!     Ok(())
+//! }
+//! ```
+
+use super::http::CommunicationProtocol;
+use super::registry::ActorRegistry;
+use crate::actor_system::{Actor, ActorSystem, Context, Message};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// An actor whose state can be moved to another node.
+pub trait Migratable {
+    /// The actor's state, as `restore` reads it back.
+    fn snapshot(&self) -> String;
+
+    /// Replaces the actor's state with a snapshot taken on another node.
+    fn restore(&mut self, snapshot: &str) -> Result<(), String>;
+}
+
+/// The message carrying an actor's snapshot to its new node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Handoff {
+    pub snapshot: String,
+}
+
+impl Handoff {
+    /// Reads a handoff, or `None` if `message` is a plain message.
+    pub fn parse(message: &str) -> Option<Handoff> {
+        serde_json::from_str(message).ok()
+    }
+
+    pub fn to_message(&self) -> String {
+        serde_json::to_string(self).expect("a handoff serializes to JSON")
+    }
+}
+
+/// Why `migrate_actor` failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MigrationError {
+    /// No such actor runs on this node.
+    NotFound(String),
+    /// The actor doesn't run inside a `MigratableActor`, or is in standby.
+    NotMigratable(String),
+    /// The snapshot could not be sent to the target node.
+    Transfer { actor_id: String, reason: String },
+    /// The target got the snapshot, but the registry could not be updated.
+    Registry { actor_id: String, reason: String },
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::NotFound(actor_id) => write!(f, "Actor {} not found", actor_id),
+            MigrationError::NotMigratable(actor_id) => {
+                write!(f, "Actor {} has no state to migrate", actor_id)
+            }
+            MigrationError::Transfer { actor_id, reason } => {
+                write!(f, "Failed to ship actor {}: {}", actor_id, reason)
+            }
+            MigrationError::Registry { actor_id, reason } => {
+                write!(
+                    f,
+                    "Failed to register migrated actor {}: {}",
+                    actor_id, reason
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+/// Wraps a `Migratable` actor so it can be migrated to or from another node,
+/// see the module docs. Its `inspect_state` is the actor's snapshot, which is
+/// how `migrate_actor` takes it.
+pub struct MigratableActor<A> {
+    actor: A,
+    // Messages held until the snapshot arrives, `None` once the actor is live
+    held: Option<Vec<String>>,
+}
+
+impl<A> MigratableActor<A> {
+    /// Wraps an actor running with its own state.
+    pub fn new(actor: A) -> Self {
+        MigratableActor { actor, held: None }
+    }
+
+    /// Wraps an actor waiting for the state of an actor migrating to this node.
+    pub fn standby(actor: A) -> Self {
+        MigratableActor {
+            actor,
+            held: Some(Vec::new()),
+        }
+    }
+
+    /// Whether the actor is still waiting for its snapshot.
+    pub fn is_standby(&self) -> bool {
+        self.held.is_some()
+    }
+}
+
+impl<A> MigratableActor<A>
+where
+    A: Actor<Message = String> + Migratable,
+    A::Error: From<String>,
+{
+    // The messages to hand the actor now: none while holding them for the
+    // snapshot, and those held so far once it is restored
+    fn admit(&mut self, message: Message<String>) -> Result<Vec<Message<String>>, A::Error> {
+        match message {
+            Message::Regular(text) => {
+                if let Some(handoff) = Handoff::parse(&text) {
+                    self.actor.restore(&handoff.snapshot)?;
+                    let held = self.held.take().unwrap_or_default();
+                    return Ok(held.into_iter().map(Message::Regular).collect());
+                }
+                match &mut self.held {
+                    Some(held) => {
+                        held.push(text);
+                        Ok(Vec::new())
+                    }
+                    None => Ok(vec![Message::Regular(text)]),
+                }
+            }
+            message => Ok(vec![message]),
+        }
+    }
+}
+
+#[async_trait]
+impl<A> Actor for MigratableActor<A>
+where
+    A: Actor<Message = String> + Migratable + Send,
+    A::Error: From<String> + Send,
+{
+    type Message = String;
+    type Error = A::Error;
+
+    async fn receive(&mut self, message: Message<String>) -> Result<(), Self::Error> {
+        let mut first_error = None;
+        for message in self.admit(message)? {
+            if let Err(e) = self.actor.receive(message).await {
+                first_error.get_or_insert(e);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    async fn receive_with_context(
+        &mut self,
+        context: &Context<String, Self::Error>,
+        message: Message<String>,
+    ) -> Result<(), Self::Error>
+    where
+        Self::Error: Send,
+    {
+        let mut first_error = None;
+        for message in self.admit(message)? {
+            if let Err(e) = self.actor.receive_with_context(context, message).await {
+                first_error.get_or_insert(e);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    async fn cleanup(&mut self) {
+        self.actor.cleanup().await;
+    }
+
+    async fn try_cleanup(&mut self) -> Result<(), Self::Error>
+    where
+        Self::Error: Send,
+    {
+        self.actor.try_cleanup().await
+    }
+
+    fn receives_system_messages(&self) -> bool {
+        self.actor.receives_system_messages()
+    }
+
+    fn inspect_state(&self) -> Option<String> {
+        match self.held {
+            Some(_) => None,
+            None => Some(self.actor.snapshot()),
+        }
+    }
+}
+
+/// Moves the actor `actor_id` of `system` to `target_node`, the address of its
+/// standby on the new node in the form `protocol` sends to, see the module docs.
+/// Returns once the local actor has stopped.
+pub async fn migrate_actor<E, P>(
+    system: &ActorSystem<String, E>,
+    actor_id: &str,
+    target_node: &str,
+    protocol: &P,
+    registry: &dyn ActorRegistry,
+) -> Result<(), MigrationError>
+where
+    E: Send + 'static + fmt::Debug,
+    P: CommunicationProtocol + Sync,
+{
+    let actor = system
+        .actor_ref(actor_id)
+        .ok_or_else(|| MigrationError::NotFound(actor_id.to_string()))?;
+    if actor.is_closing() {
+        return Err(MigrationError::NotFound(actor_id.to_string()));
+    }
+    actor.set_closing(true);
+    let shipped = async {
+        // Once everything queued is handled, the snapshot is the final state
+        actor
+            .ping()
+            .await
+            .map_err(|_| MigrationError::NotFound(actor_id.to_string()))?;
+        let snapshot = system
+            .inspect(actor_id)
+            .await
+            .ok_or_else(|| MigrationError::NotMigratable(actor_id.to_string()))?;
+        protocol
+            .send_message(target_node, &Handoff { snapshot }.to_message())
+            .await
+            .map_err(|reason| MigrationError::Transfer {
+                actor_id: actor_id.to_string(),
+                reason,
+            })?;
+        registry
+            .register_actor(actor_id, target_node)
+            .await
+            .map_err(|reason| MigrationError::Registry {
+                actor_id: actor_id.to_string(),
+                reason,
+            })
+    }
+    .await;
+    if let Err(e) = shipped {
+        // The actor stays here, so it takes messages again
+        actor.set_closing(false);
+        return Err(e);
+    }
+    system
+        .stop_actor(actor_id)
+        .await
+        .map_err(|_| MigrationError::NotFound(actor_id.to_string()))?;
+    Ok(())
+}
@@ -1,6 +1,14 @@
 // network/mod.rs
 
+pub mod ask;
 pub mod grpc;
+#[cfg(feature = "health")]
+pub mod health;
 pub mod http;
+pub mod migration;
+pub mod multicast;
 pub mod registry;
+pub mod remote;
 pub mod tcp;
+#[cfg(feature = "websocket")]
+pub mod ws;
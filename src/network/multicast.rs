@@ -0,0 +1,100 @@
+// network/multicast.rs
+
+//! # Multicast
+//!
+//! A `MulticastSender` pushes the same message to many nodes, e.g. to invalidate
+//! a cache cluster-wide, through any `CommunicationProtocol`. The sends run
+//! concurrently, at most `DEFAULT_CONCURRENCY` at a time unless set with
+//! `with_concurrency`, and each gives up after `DEFAULT_TIMEOUT` unless set
+//! with `with_timeout`. One unreachable node doesn't stop the others: `send`
+//! reports how the send to each address went, in the order the addresses were
+//! given.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use astra::network::http::HttpProtocol;
+//! use astra::network::multicast::MulticastSender;
+//! use std::time::Duration;
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let nodes = vec![
+//!         "http://127.0.0.1:1/actors/cache".to_string(),
+//!         "http://127.0.0.1:2/actors/cache".to_string(),
+//!     ];
+//!     let multicast = MulticastSender::new(nodes, HttpProtocol::new())
+//!         .with_concurrency(8)
+//!         .with_timeout(Duration::from_secs(1));
+//!     for (address, result) in multicast.send("invalidate users").await {
+//!         if let Err(e) = result {
+//!             println!("{} missed the invalidation: {}", address, e);
+//!         }
+//!     }
+//! }
+//! ```
+
+use super::http::CommunicationProtocol;
+use futures::stream::{self, StreamExt};
+use std::time::Duration;
+
+/// How many sends run at once, unless configured otherwise.
+pub const DEFAULT_CONCURRENCY: usize = 16;
+
+/// How long a single send may take, unless configured otherwise.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Sends messages to a fixed set of addresses, see the module docs.
+#[derive(Debug, Clone)]
+pub struct MulticastSender<P> {
+    addresses: Vec<String>,
+    protocol: P,
+    concurrency: usize,
+    timeout: Duration,
+}
+
+impl<P: CommunicationProtocol + Send + Sync> MulticastSender<P> {
+    pub fn new(addresses: Vec<String>, protocol: P) -> Self {
+        MulticastSender {
+            addresses,
+            protocol,
+            concurrency: DEFAULT_CONCURRENCY,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Sets how many sends run at once.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Sets how long a single send may take before it counts as failed.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// The addresses messages are sent to.
+    pub fn addresses(&self) -> &[String] {
+        &self.addresses
+    }
+
+    /// Sends `message` to every address, returning each address with the outcome
+    /// of the send to it.
+    pub async fn send(&self, message: &str) -> Vec<(String, Result<(), String>)> {
+        stream::iter(&self.addresses)
+            .map(|address| async move {
+                let sent = tokio::time::timeout(
+                    self.timeout,
+                    self.protocol.send_message(address, message),
+                )
+                .await
+                .unwrap_or_else(|_| Err(format!("Send timed out after {:?}", self.timeout)));
+                (address.clone(), sent)
+            })
+            .buffered(self.concurrency)
+            .collect()
+            .await
+    }
+}
@@ -29,47 +29,463 @@
 //!    Ok(())
 //! }
 //! ```
+//!
+//! When the registry is optional (e.g. single-node deployments), use
+//! `DistributedRegistry::new_optional`: if etcd can't be reached it falls back to a
+//! `LocalRegistry`, so the application still boots and lookups only see actors
+//! registered in this process.
+//!
+//! ```rust
+//! use astra::network::registry::DistributedRegistry;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), String> {
+//!    let registry = DistributedRegistry::new_optional(&["http://127.0.0.1:1"]).await;
+//!    registry.register_actor("actor1", "http://127.0.0.1:8080").await?;
+//!    assert_eq!(registry.lookup_actor("actor1").await?, "http://127.0.0.1:8080");
+//!    Ok(())
+//! }
+//! ```
+//!
+//! `register_actor` overwrites any existing entry. To make sure two nodes don't
+//! claim the same actor, use `register_actor_unique`, which fails with
+//! `RegistryError::Conflict` if the actor is already registered elsewhere.
+//!
+//! A registry may report the registrations made through it, see `watch`; a
+//! `LocalRegistry` does, a `DistributedRegistry` doesn't yet.
+//!
+//! A `DistributedRegistry` can be shared by many actors: its calls don't lock
+//! each other out, they run concurrently over the same etcd connection.
+//!
+//! Each endpoint gets a connection of its own, and calls go to the active one.
+//! When a call fails because that endpoint is unavailable, it is retried against
+//! the others, healthiest first, and the first that answers becomes the active
+//! endpoint. `endpoint_health` reports how each endpoint has fared. Errors etcd
+//! returns for the request itself, like a failed precondition, are not retried.
 
-use etcd_client::{Client, GetOptions, PutOptions};
+use async_trait::async_trait;
+use etcd_client::{Client, Compare, CompareOp, GetOptions, PutOptions, Txn, TxnOp, TxnOpResponse};
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{broadcast, Mutex};
 use tokio::time::{timeout, Duration};
 
+/// The interface shared by all actor registries.
+#[async_trait]
+pub trait ActorRegistry: Send + Sync {
+    /// Records the node address an actor can be reached at.
+    async fn register_actor(&self, actor_id: &str, node_address: &str) -> Result<(), String>;
+
+    /// Records the node address an actor can be reached at, unless the actor is
+    /// already registered to a different address. Registering the same address
+    /// again succeeds.
+    ///
+    /// The default looks the actor up and then registers it, which isn't atomic:
+    /// two nodes racing to register the same actor may both succeed. Registries
+    /// that can check and write in one step should override it.
+    async fn register_actor_unique(
+        &self,
+        actor_id: &str,
+        node_address: &str,
+    ) -> Result<(), RegistryError> {
+        match self.lookup_actor(actor_id).await {
+            Ok(existing_address) => check_existing(actor_id, node_address, existing_address),
+            Err(e) if e == ACTOR_NOT_FOUND => self
+                .register_actor(actor_id, node_address)
+                .await
+                .map_err(RegistryError::Backend),
+            Err(e) => Err(RegistryError::Backend(e)),
+        }
+    }
+
+    /// Returns the node address an actor was registered with, failing with
+    /// `ACTOR_NOT_FOUND` if it isn't registered.
+    async fn lookup_actor(&self, actor_id: &str) -> Result<String, String>;
+
+    /// Subscribes to the registrations made from now on, or `None` if the
+    /// registry can't report them.
+    fn watch(&self) -> Option<broadcast::Receiver<Registration>> {
+        None
+    }
+
+    /// Checks that the registry can currently be reached, e.g. for a readiness
+    /// probe. In-process registries always can.
+    async fn health_check(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// The error `lookup_actor` returns for an actor that isn't registered.
+pub const ACTOR_NOT_FOUND: &str = "Actor not found";
+
+// How many registrations a slow watcher may fall behind before it misses some
+const WATCH_CAPACITY: usize = 256;
+
+/// An actor registered to an address, as reported by `ActorRegistry::watch`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Registration {
+    pub actor_id: String,
+    pub node_address: String,
+}
+
+/// Why `register_actor_unique` failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistryError {
+    /// The actor is already registered to another address.
+    Conflict {
+        actor_id: String,
+        existing_address: String,
+    },
+    /// The registry itself could not be reached or returned an error.
+    Backend(String),
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistryError::Conflict {
+                actor_id,
+                existing_address,
+            } => write!(
+                f,
+                "Actor {} is already registered at {}",
+                actor_id, existing_address
+            ),
+            RegistryError::Backend(e) => write!(f, "Registry error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {}
+
+// Decide the outcome of a unique registration given the address already stored
+fn check_existing(
+    actor_id: &str,
+    node_address: &str,
+    existing_address: String,
+) -> Result<(), RegistryError> {
+    if existing_address == node_address {
+        Ok(())
+    } else {
+        Err(RegistryError::Conflict {
+            actor_id: actor_id.to_string(),
+            existing_address,
+        })
+    }
+}
+
+/// How an etcd endpoint of a `DistributedRegistry` has fared.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndpointHealth {
+    pub endpoint: String,
+    /// Whether calls currently go to this endpoint.
+    pub active: bool,
+    /// Calls that failed on this endpoint since it last answered.
+    pub consecutive_failures: u32,
+}
+
+impl EndpointHealth {
+    /// Returns true unless the last call sent to the endpoint failed.
+    pub fn is_healthy(&self) -> bool {
+        self.consecutive_failures == 0
+    }
+}
+
+// One etcd endpoint and its connection
+struct Endpoint {
+    address: String,
+    // The client multiplexes requests over a shared channel, so each call works
+    // on its own cheap clone and concurrent calls don't wait for each other
+    client: Client,
+    consecutive_failures: AtomicU32,
+}
+
+// Whether an error means the endpoint, not the request, is at fault
+fn is_unavailable(error: &etcd_client::Error) -> bool {
+    use tonic::Code;
+    match error {
+        etcd_client::Error::TransportError(_) | etcd_client::Error::IoError(_) => true,
+        etcd_client::Error::GRpcStatus(status) => matches!(
+            status.code(),
+            Code::Unavailable | Code::DeadlineExceeded | Code::Unknown | Code::Cancelled
+        ),
+        _ => false,
+    }
+}
+
 pub struct DistributedRegistry {
-    client: Arc<Mutex<Client>>,
+    endpoints: Vec<Endpoint>,
+    // Index of the endpoint calls go to first
+    active: AtomicUsize,
 }
 
 impl DistributedRegistry {
     pub async fn new(endpoints: &[&str]) -> Result<Self, String> {
-        let client = timeout(Duration::from_secs(5), Client::connect(endpoints, None))
-            .await
-            .map_err(|_| "Connection timed out".to_string())?
-            .map_err(|e| e.to_string())?;
+        if endpoints.is_empty() {
+            return Err("No etcd endpoints given".to_string());
+        }
+        let mut connected = Vec::with_capacity(endpoints.len());
+        for endpoint in endpoints {
+            let client = timeout(Duration::from_secs(5), Client::connect([endpoint], None))
+                .await
+                .map_err(|_| "Connection timed out".to_string())?
+                .map_err(|e| e.to_string())?;
+            connected.push(Endpoint {
+                address: endpoint.to_string(),
+                client,
+                consecutive_failures: AtomicU32::new(0),
+            });
+        }
 
         Ok(DistributedRegistry {
-            client: Arc::new(Mutex::new(client)),
+            endpoints: connected,
+            active: AtomicUsize::new(0),
         })
     }
 
-    pub async fn register_actor(&self, actor_id: &str, node_address: &str) -> Result<(), String> {
-        let mut client = self.client.lock().await;
-        client
-            .put(actor_id, node_address, Some(PutOptions::new()))
+    /// The endpoint calls currently go to.
+    pub fn active_endpoint(&self) -> &str {
+        &self.endpoints[self.active.load(Ordering::SeqCst)].address
+    }
+
+    /// The health of each endpoint, in the order they were given.
+    pub fn endpoint_health(&self) -> Vec<EndpointHealth> {
+        let active = self.active.load(Ordering::SeqCst);
+        self.endpoints
+            .iter()
+            .enumerate()
+            .map(|(i, endpoint)| EndpointHealth {
+                endpoint: endpoint.address.clone(),
+                active: i == active,
+                consecutive_failures: endpoint.consecutive_failures.load(Ordering::SeqCst),
+            })
+            .collect()
+    }
+
+    // The active endpoint first, then the others, fewest failures first
+    fn attempt_order(&self) -> Vec<usize> {
+        let active = self.active.load(Ordering::SeqCst);
+        let mut others: Vec<_> = (0..self.endpoints.len()).filter(|i| *i != active).collect();
+        others.sort_by_key(|i| {
+            self.endpoints[*i]
+                .consecutive_failures
+                .load(Ordering::SeqCst)
+        });
+        std::iter::once(active).chain(others).collect()
+    }
+
+    // Run `call` against the active endpoint, failing over to the next one for as
+    // long as endpoints turn out to be unavailable
+    async fn with_failover<T, F, Fut>(&self, call: F) -> Result<T, etcd_client::Error>
+    where
+        F: Fn(Client) -> Fut,
+        Fut: Future<Output = Result<T, etcd_client::Error>>,
+    {
+        let mut last_error = None;
+        for i in self.attempt_order() {
+            let endpoint = &self.endpoints[i];
+            match call(endpoint.client.clone()).await {
+                Err(e) if is_unavailable(&e) => {
+                    endpoint.consecutive_failures.fetch_add(1, Ordering::SeqCst);
+                    last_error = Some(e);
+                }
+                answered => {
+                    endpoint.consecutive_failures.store(0, Ordering::SeqCst);
+                    self.active.store(i, Ordering::SeqCst);
+                    return answered;
+                }
+            }
+        }
+        Err(last_error.expect("a registry has at least one endpoint"))
+    }
+
+    /// Connects to etcd like `new`, but degrades to an in-memory `LocalRegistry`
+    /// (logging a warning) when none of the endpoints can be reached.
+    pub async fn new_optional(endpoints: &[&str]) -> Box<dyn ActorRegistry> {
+        let connected = match DistributedRegistry::new(endpoints).await {
+            // The client connects lazily, so make sure etcd actually answers
+            Ok(registry) => registry.ping().await.map(|_| registry),
+            Err(e) => Err(e),
+        };
+        match connected {
+            Ok(registry) => Box::new(registry),
+            Err(e) => {
+                eprintln!(
+                    "Warning: etcd unavailable ({}), falling back to a local registry",
+                    e
+                );
+                Box::new(LocalRegistry::new())
+            }
+        }
+    }
+
+    // Check that etcd is reachable by asking for its status
+    async fn ping(&self) -> Result<(), String> {
+        let status = self.with_failover(|mut client| async move { client.status().await });
+        timeout(Duration::from_secs(5), status)
             .await
+            .map_err(|_| "Connection timed out".to_string())?
             .map_err(|e| e.to_string())?;
         Ok(())
     }
 
+    pub async fn register_actor(&self, actor_id: &str, node_address: &str) -> Result<(), String> {
+        self.with_failover(|mut client| async move {
+            client
+                .put(actor_id, node_address, Some(PutOptions::new()))
+                .await
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// Registers the actor in a single etcd transaction that only writes if the key
+    /// does not exist yet, so concurrent registrations can't overwrite each other.
+    pub async fn register_actor_unique(
+        &self,
+        actor_id: &str,
+        node_address: &str,
+    ) -> Result<(), RegistryError> {
+        let resp = self
+            .with_failover(|mut client| async move {
+                let txn = Txn::new()
+                    .when(vec![Compare::version(actor_id, CompareOp::Equal, 0)])
+                    .and_then(vec![TxnOp::put(actor_id, node_address, None)])
+                    .or_else(vec![TxnOp::get(actor_id, None)]);
+                client.txn(txn).await
+            })
+            .await
+            .map_err(|e| RegistryError::Backend(e.to_string()))?;
+        if resp.succeeded() {
+            return Ok(());
+        }
+
+        // The key already exists: the else branch fetched its current value
+        let existing = resp.op_responses().into_iter().find_map(|op| match op {
+            TxnOpResponse::Get(get) => get
+                .kvs()
+                .first()
+                .map(|kv| String::from_utf8_lossy(kv.value()).into_owned()),
+            _ => None,
+        });
+        match existing {
+            Some(existing_address) => check_existing(actor_id, node_address, existing_address),
+            None => Err(RegistryError::Backend(format!(
+                "Registration of actor {} failed without an existing entry",
+                actor_id
+            ))),
+        }
+    }
+
     pub async fn lookup_actor(&self, actor_id: &str) -> Result<String, String> {
-        let mut client = self.client.lock().await;
-        let resp = client
-            .get(actor_id, Some(GetOptions::new()))
+        let resp = self
+            .with_failover(|mut client| async move {
+                client.get(actor_id, Some(GetOptions::new())).await
+            })
             .await
             .map_err(|e| e.to_string())?;
         if let Some(kv) = resp.kvs().first() {
             Ok(String::from_utf8(kv.value().to_vec()).unwrap())
         } else {
-            Err("Actor not found".to_string())
+            Err(ACTOR_NOT_FOUND.to_string())
+        }
+    }
+}
+
+#[async_trait]
+impl ActorRegistry for DistributedRegistry {
+    async fn register_actor(&self, actor_id: &str, node_address: &str) -> Result<(), String> {
+        DistributedRegistry::register_actor(self, actor_id, node_address).await
+    }
+
+    async fn register_actor_unique(
+        &self,
+        actor_id: &str,
+        node_address: &str,
+    ) -> Result<(), RegistryError> {
+        DistributedRegistry::register_actor_unique(self, actor_id, node_address).await
+    }
+
+    async fn lookup_actor(&self, actor_id: &str) -> Result<String, String> {
+        DistributedRegistry::lookup_actor(self, actor_id).await
+    }
+
+    async fn health_check(&self) -> Result<(), String> {
+        self.ping().await
+    }
+}
+
+/// An in-process registry, only aware of actors registered through it.
+#[derive(Debug, Clone)]
+pub struct LocalRegistry {
+    actors: Arc<Mutex<HashMap<String, String>>>,
+    registrations: broadcast::Sender<Registration>,
+}
+
+impl LocalRegistry {
+    pub fn new() -> Self {
+        LocalRegistry {
+            actors: Arc::default(),
+            registrations: broadcast::channel(WATCH_CAPACITY).0,
+        }
+    }
+
+    // Report a registration, it is fine for nobody to be watching
+    fn registered(&self, actor_id: &str, node_address: &str) {
+        let _ = self.registrations.send(Registration {
+            actor_id: actor_id.to_string(),
+            node_address: node_address.to_string(),
+        });
+    }
+}
+
+impl Default for LocalRegistry {
+    fn default() -> Self {
+        LocalRegistry::new()
+    }
+}
+
+#[async_trait]
+impl ActorRegistry for LocalRegistry {
+    async fn register_actor(&self, actor_id: &str, node_address: &str) -> Result<(), String> {
+        self.actors
+            .lock()
+            .await
+            .insert(actor_id.to_string(), node_address.to_string());
+        self.registered(actor_id, node_address);
+        Ok(())
+    }
+
+    async fn register_actor_unique(
+        &self,
+        actor_id: &str,
+        node_address: &str,
+    ) -> Result<(), RegistryError> {
+        let mut actors = self.actors.lock().await;
+        match actors.get(actor_id) {
+            Some(existing) => check_existing(actor_id, node_address, existing.clone()),
+            None => {
+                actors.insert(actor_id.to_string(), node_address.to_string());
+                self.registered(actor_id, node_address);
+                Ok(())
+            }
         }
     }
+
+    async fn lookup_actor(&self, actor_id: &str) -> Result<String, String> {
+        self.actors
+            .lock()
+            .await
+            .get(actor_id)
+            .cloned()
+            .ok_or_else(|| ACTOR_NOT_FOUND.to_string())
+    }
+
+    fn watch(&self) -> Option<broadcast::Receiver<Registration>> {
+        Some(self.registrations.subscribe())
+    }
 }
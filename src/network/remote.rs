@@ -0,0 +1,333 @@
+// network/remote.rs
+
+//! # Remote actor references
+//!
+//! A `RemoteActorRef` sends messages to an actor on another node, finding the
+//! node through an `ActorRegistry`. The address the actor was registered with is
+//! handed to the `CommunicationProtocol` as is, so it must be in the form that
+//! protocol expects (e.g. `http://host:port/actors/name` for `HttpProtocol`).
+//!
+//! Looking the actor up on every send would add a registry round trip to each
+//! message, so the address is cached: it is only looked up again once it is
+//! older than the refresh interval (`DEFAULT_REFRESH_INTERVAL` unless set with
+//! `with_refresh_interval`), or after a send to it failed, in case the actor
+//! moved.
+//!
+//! `ask` sends a message and waits for the actor's reply, see the `ask` module.
+//!
+//! ## Refreshing every reference
+//!
+//! After a network partition heals, many cached addresses may be stale at once.
+//! A `RemoteRefRegistry` keeps track of the references handed to `track`, for
+//! as long as they live, and `refresh_all` looks each of their actors up again
+//! in one go. A reference whose actor is no longer registered is marked dead
+//! (see `RemoteActorRef::is_dead`) until a lookup finds it again. With `follow`,
+//! registrations the `ActorRegistry` reports through `watch` update the tracked
+//! references as they happen.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use astra::network::http::HttpProtocol;
+//! use astra::network::registry::{ActorRegistry, LocalRegistry};
+//! use astra::network::remote::RemoteActorRef;
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), String> {
+//!    let registry = Arc::new(LocalRegistry::new());
+//!    registry
+//!        .register_actor("printer", "http://127.0.0.1:8080/actors/printer")
+//!        .await?;
+//!
+//!    let printer = RemoteActorRef::new("printer", registry, HttpProtocol::new())
+//!        .with_refresh_interval(Duration::from_secs(5));
+//!    assert_eq!(
+//!        printer.resolve().await?,
+//!        "http://127.0.0.1:8080/actors/printer"
+//!    );
+//!    Ok(())
+//! }
+//! ```
+
+use super::http::CommunicationProtocol;
+use super::registry::{ActorRegistry, ACTOR_NOT_FOUND};
+use crate::clock::{Clock, TokioClock};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+/// How long a looked up address is used before it is looked up again, unless
+/// configured otherwise.
+pub const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+// What a reference knows of its actor's whereabouts, shared with the
+// `RemoteRefRegistry` tracking it
+struct AddressCache {
+    actor_id: String,
+    clock: Arc<dyn Clock>,
+    // The last address looked up, and when
+    cached: Mutex<Option<(String, Instant)>>,
+    // Set when the registry reported the actor as not registered
+    dead: AtomicBool,
+}
+
+impl AddressCache {
+    fn new(actor_id: &str, clock: Arc<dyn Clock>) -> Self {
+        AddressCache {
+            actor_id: actor_id.to_string(),
+            clock,
+            cached: Mutex::new(None),
+            dead: AtomicBool::new(false),
+        }
+    }
+
+    // Record the outcome of looking the actor up
+    fn update(&self, lookup: &Result<String, String>) {
+        match lookup {
+            Ok(address) => {
+                *self.cached.lock().unwrap() = Some((address.clone(), self.clock.now()));
+                self.dead.store(false, Ordering::SeqCst);
+            }
+            Err(e) if e == ACTOR_NOT_FOUND => {
+                *self.cached.lock().unwrap() = None;
+                self.dead.store(true, Ordering::SeqCst);
+            }
+            Err(_) => {}
+        }
+    }
+}
+
+/// A handle to an actor on another node, see the module docs.
+pub struct RemoteActorRef<P> {
+    registry: Arc<dyn ActorRegistry>,
+    protocol: P,
+    refresh_interval: Duration,
+    cache: Arc<AddressCache>,
+}
+
+impl<P: CommunicationProtocol + Send + Sync> RemoteActorRef<P> {
+    pub fn new(actor_id: &str, registry: Arc<dyn ActorRegistry>, protocol: P) -> Self {
+        RemoteActorRef {
+            registry,
+            protocol,
+            refresh_interval: DEFAULT_REFRESH_INTERVAL,
+            cache: Arc::new(AddressCache::new(actor_id, Arc::new(TokioClock))),
+        }
+    }
+
+    /// Sets how long a looked up address is used before it is looked up again.
+    pub fn with_refresh_interval(mut self, interval: Duration) -> Self {
+        self.refresh_interval = interval;
+        self
+    }
+
+    /// Sets the clock the age of the cached address is measured with, e.g. a
+    /// `MockClock` in tests. Set it before handing the reference to
+    /// `RemoteRefRegistry::track`.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.cache = Arc::new(AddressCache::new(&self.cache.actor_id, clock));
+        self
+    }
+
+    /// The id of the remote actor.
+    pub fn actor_id(&self) -> &str {
+        &self.cache.actor_id
+    }
+
+    /// Whether the last lookup found the actor no longer registered.
+    pub fn is_dead(&self) -> bool {
+        self.cache.dead.load(Ordering::SeqCst)
+    }
+
+    /// Sends a message to the actor. If the send fails, the cached address is
+    /// dropped so the next send looks the actor up again.
+    pub async fn send(&self, message: &str) -> Result<(), String> {
+        let address = self.resolve().await?;
+        let sent = self.protocol.send_message(&address, message).await;
+        if sent.is_err() {
+            self.invalidate();
+        }
+        sent
+    }
+
+    /// Sends a message and waits up to `timeout` for the actor's reply, see the
+    /// `ask` module. Like `send`, a failure drops the cached address.
+    pub async fn ask(&self, message: &str, timeout: Duration) -> Result<String, String> {
+        let address = self.resolve().await?;
+        let reply = self.protocol.ask(&address, message, timeout).await;
+        if reply.is_err() {
+            self.invalidate();
+        }
+        reply
+    }
+
+    /// Returns the actor's address, from the cache while it is fresh and from
+    /// the registry otherwise.
+    pub async fn resolve(&self) -> Result<String, String> {
+        if let Some(address) = self.cached_address() {
+            return Ok(address);
+        }
+        let lookup = self.registry.lookup_actor(&self.cache.actor_id).await;
+        self.cache.update(&lookup);
+        lookup
+    }
+
+    /// The cached address, unless it is older than the refresh interval.
+    pub fn cached_address(&self) -> Option<String> {
+        match &*self.cache.cached.lock().unwrap() {
+            Some((address, looked_up))
+                if self.cache.clock.now().duration_since(*looked_up) < self.refresh_interval =>
+            {
+                Some(address.clone())
+            }
+            _ => None,
+        }
+    }
+
+    /// Drops the cached address, so the next send looks the actor up again.
+    pub fn invalidate(&self) {
+        *self.cache.cached.lock().unwrap() = None;
+    }
+}
+
+impl<P> fmt::Debug for RemoteActorRef<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RemoteActorRef")
+            .field("actor_id", &self.cache.actor_id)
+            .field("refresh_interval", &self.refresh_interval)
+            .field("cached", &*self.cache.cached.lock().unwrap())
+            .field("dead", &self.cache.dead.load(Ordering::SeqCst))
+            .finish()
+    }
+}
+
+/// What `RemoteRefRegistry::refresh_all` found, by actor id.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RefreshReport {
+    /// Actors looked up again, their references now using the current address.
+    pub refreshed: Vec<String>,
+    /// Actors no longer registered, their references marked dead.
+    pub dead: Vec<String>,
+    /// Actors the registry failed to look up, with the error; their references
+    /// keep what they had.
+    pub failed: Vec<(String, String)>,
+}
+
+/// Tracks live `RemoteActorRef`s to refresh their addresses in bulk, see the
+/// module docs.
+pub struct RemoteRefRegistry {
+    registry: Arc<dyn ActorRegistry>,
+    refs: Arc<Mutex<Vec<Weak<AddressCache>>>>,
+}
+
+impl RemoteRefRegistry {
+    /// Refreshes the tracked references against `registry`, which should be
+    /// the one they were created with.
+    pub fn new(registry: Arc<dyn ActorRegistry>) -> Self {
+        RemoteRefRegistry {
+            registry,
+            refs: Arc::default(),
+        }
+    }
+
+    /// Tracks `remote` until it is dropped.
+    pub fn track<P>(&self, remote: &RemoteActorRef<P>) {
+        self.refs
+            .lock()
+            .unwrap()
+            .push(Arc::downgrade(&remote.cache));
+    }
+
+    /// Number of tracked references still alive.
+    pub fn len(&self) -> usize {
+        live(&self.refs).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Looks every tracked reference's actor up again, once per actor, and
+    /// updates the references with the outcome.
+    pub async fn refresh_all(&self) -> RefreshReport {
+        refresh(self.registry.as_ref(), &live(&self.refs)).await
+    }
+
+    /// Keeps the tracked references up to date with the registrations the
+    /// registry reports, until the returned task is aborted or this registry is
+    /// dropped. After missing some, everything is refreshed. Returns `None` if
+    /// the registry can't report registrations (see `ActorRegistry::watch`).
+    pub fn follow(&self) -> Option<JoinHandle<()>> {
+        let mut registrations = self.registry.watch()?;
+        let registry = Arc::clone(&self.registry);
+        let refs = Arc::downgrade(&self.refs);
+        Some(tokio::spawn(async move {
+            loop {
+                let received = registrations.recv().await;
+                let Some(refs) = refs.upgrade() else {
+                    return;
+                };
+                match received {
+                    Ok(registration) => {
+                        let lookup = Ok(registration.node_address);
+                        for cache in live(&refs) {
+                            if cache.actor_id == registration.actor_id {
+                                cache.update(&lookup);
+                            }
+                        }
+                    }
+                    Err(RecvError::Lagged(_)) => {
+                        refresh(registry.as_ref(), &live(&refs)).await;
+                    }
+                    Err(RecvError::Closed) => return,
+                }
+            }
+        }))
+    }
+}
+
+impl fmt::Debug for RemoteRefRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RemoteRefRegistry")
+            .field("refs", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+// The tracked references still alive, forgetting the dropped ones
+fn live(refs: &Mutex<Vec<Weak<AddressCache>>>) -> Vec<Arc<AddressCache>> {
+    let mut refs = refs.lock().unwrap();
+    refs.retain(|cache| cache.strong_count() > 0);
+    refs.iter().filter_map(Weak::upgrade).collect()
+}
+
+async fn refresh(registry: &dyn ActorRegistry, caches: &[Arc<AddressCache>]) -> RefreshReport {
+    let mut by_actor: HashMap<&str, Vec<&AddressCache>> = HashMap::new();
+    let mut actor_ids = Vec::new();
+    for cache in caches {
+        let refs = by_actor.entry(&cache.actor_id).or_default();
+        if refs.is_empty() {
+            actor_ids.push(cache.actor_id.as_str());
+        }
+        refs.push(cache);
+    }
+    let mut report = RefreshReport::default();
+    for actor_id in actor_ids {
+        let lookup = registry.lookup_actor(actor_id).await;
+        for cache in &by_actor[actor_id] {
+            cache.update(&lookup);
+        }
+        match lookup {
+            Ok(_) => report.refreshed.push(actor_id.to_string()),
+            Err(e) if e == ACTOR_NOT_FOUND => report.dead.push(actor_id.to_string()),
+            Err(e) => report.failed.push((actor_id.to_string(), e)),
+        }
+    }
+    report
+}
@@ -0,0 +1,645 @@
+// network/tcp.rs
+
+//! # TCP transport
+//!
+//! `TcpProtocol` sends messages to a `TcpServer` over plain TCP. Addresses have the
+//! form `host:port/actor_name`.
+//!
+//! Each message travels as a frame made of the target actor name and the payload,
+//! both prefixed with their length as a big-endian `u32`. The server answers every
+//! frame with a single status byte. Because lengths come first, a server configured
+//! with `max_message_size` rejects an oversized frame from its header alone: the
+//! payload is skipped without ever being buffered, so peers can't exhaust memory.
+//!
+//! ## Connection pooling
+//!
+//! `TcpProtocol` keeps idle connections per address and reuses them for later sends,
+//! up to `max_connections_per_address` connections at a time (further sends wait for
+//! one to free up). An idle connection is checked before reuse, and if writing to a
+//! reused connection still fails it is evicted and the send is retried once on a
+//! fresh connection. Once the frame is written the send is never retried, since the
+//! server may have delivered it: if no answer comes back the send fails. Clones of a
+//! `TcpProtocol` share its pool.
+//!
+//! ## Reconnecting
+//!
+//! When a connection to an address can't be opened, the send fails and a
+//! background task starts reconnecting with exponential backoff, following the
+//! `ReconnectPolicy` (see `with_reconnect_policy`). Until it succeeds, sends to
+//! that address fail fast instead of each trying to connect in turn; once it does,
+//! the new connection is pooled and sends resume. If the policy runs out of
+//! attempts the address is marked `Failed` and the next send tries to connect
+//! again. `connection_state` reports where an address stands.
+//!
+//! Reconnect tasks stop once the token given to `with_cancellation_token` (e.g.
+//! the `ActorSystem`'s) is cancelled, leaving the address `Failed`;
+//! `wait_until_stopped` waits for them to exit.
+
+use super::http::{
+    check_message_size, parses_as, CommunicationProtocol, ParseCheck, DEFAULT_DRAIN_TIMEOUT,
+};
+use crate::actor_system::{ActorSystem, SendError};
+use crate::clock::{Clock, TokioClock};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Semaphore;
+use tokio::task::{JoinHandle, JoinSet};
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+
+// Actor names longer than this are treated as a malformed frame
+const MAX_ACTOR_NAME_LEN: usize = 1024;
+
+// Connections kept per address unless configured otherwise
+const DEFAULT_MAX_CONNECTIONS_PER_ADDRESS: usize = 4;
+
+// Status byte sent back by the server for each frame
+const STATUS_ACCEPTED: u8 = 0;
+const STATUS_NOT_FOUND: u8 = 1;
+const STATUS_MAILBOX_FULL: u8 = 2;
+const STATUS_CLOSED: u8 = 3;
+const STATUS_REJECTED: u8 = 4;
+const STATUS_TOO_LARGE: u8 = 5;
+const STATUS_MALFORMED: u8 = 6;
+const STATUS_RATE_LIMITED: u8 = 7;
+const STATUS_CLOSING: u8 = 8;
+const STATUS_QUIESCING: u8 = 9;
+const STATUS_SYSTEM_FULL: u8 = 10;
+
+/// How `TcpProtocol` (and `WebSocketProtocol`) retry connecting to an address
+/// they lost: the first retry waits `base_delay`, each later one `multiplier`
+/// times longer, up to `max_delay`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReconnectPolicy {
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub multiplier: f64,
+    /// Attempts before the address is marked `Failed`, `None` to keep trying.
+    pub max_attempts: Option<u32>,
+}
+
+impl ReconnectPolicy {
+    pub fn new(base_delay: Duration, max_delay: Duration, multiplier: f64) -> Self {
+        ReconnectPolicy {
+            base_delay,
+            max_delay,
+            multiplier: multiplier.max(1.0),
+            max_attempts: None,
+        }
+    }
+
+    pub fn with_max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = Some(attempts.max(1));
+        self
+    }
+
+    /// How long to wait before reconnect attempt `attempt`, counting from 1.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = self.multiplier.powi(attempt.saturating_sub(1) as i32);
+        self.base_delay.mul_f64(factor).min(self.max_delay)
+    }
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        ReconnectPolicy::new(Duration::from_millis(100), Duration::from_secs(10), 2.0)
+            .with_max_attempts(10)
+    }
+}
+
+/// Where `TcpProtocol` or `WebSocketProtocol` stands with an address, see
+/// `connection_state`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// The last connection attempt succeeded.
+    Connected,
+    /// A background task is waiting `next_delay` before reconnect attempt
+    /// `attempt`; sends fail fast meanwhile.
+    Reconnecting { attempt: u32, next_delay: Duration },
+    /// Reconnecting ran out of attempts or was stopped; the next send tries to
+    /// connect again.
+    Failed,
+}
+
+// TCP implementation
+#[derive(Debug, Clone)]
+pub struct TcpProtocol {
+    max_message_size: Option<usize>,
+    max_connections_per_address: usize,
+    reconnect: ReconnectPolicy,
+    // The time source of reconnect delays
+    clock: Arc<dyn Clock>,
+    pool: Arc<ConnectionPool>,
+    // Stops the reconnect tasks, which `tasks` keeps track of
+    shutdown: CancellationToken,
+    tasks: TaskTracker,
+}
+
+impl TcpProtocol {
+    pub fn new() -> Self {
+        TcpProtocol {
+            max_message_size: None,
+            max_connections_per_address: DEFAULT_MAX_CONNECTIONS_PER_ADDRESS,
+            reconnect: ReconnectPolicy::default(),
+            clock: Arc::new(TokioClock),
+            pool: Arc::new(ConnectionPool::default()),
+            shutdown: CancellationToken::new(),
+            tasks: TaskTracker::new(),
+        }
+    }
+
+    // Refuse to send messages larger than `max` bytes
+    pub fn with_max_message_size(mut self, max: usize) -> Self {
+        self.max_message_size = Some(max);
+        self
+    }
+
+    // Limit how many connections are opened to a single address
+    pub fn with_max_connections_per_address(mut self, max: usize) -> Self {
+        self.max_connections_per_address = max.max(1);
+        self
+    }
+
+    // Retry lost addresses following `policy` rather than the default one
+    pub fn with_reconnect_policy(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect = policy;
+        self
+    }
+
+    // Time reconnect delays with `clock`, e.g. a `MockClock` in tests
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Stops reconnecting once `parent` is cancelled, e.g. the cancellation
+    /// token of the `ActorSystem` the protocol sends for.
+    pub fn with_cancellation_token(mut self, parent: &CancellationToken) -> Self {
+        self.shutdown = parent.child_token();
+        self
+    }
+
+    /// Waits until every reconnect task has exited, typically after cancelling
+    /// the token given to `with_cancellation_token`.
+    pub async fn wait_until_stopped(&self) {
+        self.tasks.close();
+        self.tasks.wait().await;
+        // Allow reconnect tasks started later to be waited on again
+        self.tasks.reopen();
+    }
+
+    // The state of the connections to `host:port`, `None` if never used
+    pub fn connection_state(&self, host: &str) -> Option<ConnectionState> {
+        self.pool
+            .addresses
+            .lock()
+            .unwrap()
+            .get(host)
+            .map(|pool| *pool.state.lock().unwrap())
+    }
+
+    // Open a connection, starting to reconnect in the background if it fails
+    async fn connect(&self, pool: &Arc<AddressPool>, host: &str) -> Result<TcpStream, String> {
+        match connect(host).await {
+            Ok(stream) => {
+                *pool.state.lock().unwrap() = ConnectionState::Connected;
+                Ok(stream)
+            }
+            Err(e) => {
+                if pool.start_reconnecting(self.reconnect.delay(1)) {
+                    self.tasks.spawn(reconnect(
+                        Arc::clone(pool),
+                        host.to_string(),
+                        self.reconnect,
+                        Arc::clone(&self.clock),
+                        self.shutdown.clone(),
+                    ));
+                }
+                Err(e)
+            }
+        }
+    }
+
+    // Number of idle connections currently pooled for `host:port`
+    pub fn pooled_connections(&self, host: &str) -> usize {
+        self.pool
+            .addresses
+            .lock()
+            .unwrap()
+            .get(host)
+            .map_or(0, |pool| pool.idle.lock().unwrap().len())
+    }
+}
+
+impl Default for TcpProtocol {
+    fn default() -> Self {
+        TcpProtocol::new()
+    }
+}
+
+#[async_trait]
+impl CommunicationProtocol for TcpProtocol {
+    async fn send_message(&self, address: &str, message: &str) -> Result<(), String> {
+        // Reject oversized messages before touching the network
+        check_message_size(message.len(), self.max_message_size)?;
+        let (host, actor) = parse_address(address)?;
+
+        let pool = self
+            .pool
+            .for_address(host, self.max_connections_per_address);
+        let _permit = pool
+            .permits
+            .acquire()
+            .await
+            .map_err(|e| format!("Connection pool closed: {}", e))?;
+
+        let (mut stream, reused) = match pool.take_idle() {
+            Some(stream) => (stream, true),
+            None => {
+                if let ConnectionState::Reconnecting { .. } = *pool.state.lock().unwrap() {
+                    return Err(format!("Reconnecting to {}", host));
+                }
+                (self.connect(&pool, host).await?, false)
+            }
+        };
+        let written = match write_frame(&mut stream, actor, message).await {
+            // The pooled connection went stale: drop it and retry once on a new one
+            Err(_) if reused => {
+                stream = self.connect(&pool, host).await?;
+                write_frame(&mut stream, actor, message).await
+            }
+            written => written,
+        };
+        written.map_err(|e| format!("Failed to send message: {}", e))?;
+        // Once the frame is out the server may have delivered it, so a lost
+        // answer is never retried
+        let status = stream.read_u8().await.map_err(|e| {
+            format!(
+                "No answer from {}, the message may or may not have been delivered: {}",
+                host, e
+            )
+        })?;
+
+        pool.put_back(stream);
+        status_to_result(status, actor, message)
+    }
+}
+
+// Idle connections, grouped by `host:port`
+#[derive(Debug, Default)]
+struct ConnectionPool {
+    addresses: Mutex<HashMap<String, Arc<AddressPool>>>,
+}
+
+#[derive(Debug)]
+struct AddressPool {
+    idle: Mutex<Vec<TcpStream>>,
+    permits: Semaphore,
+    state: Mutex<ConnectionState>,
+}
+
+impl ConnectionPool {
+    fn for_address(&self, host: &str, max_connections: usize) -> Arc<AddressPool> {
+        let mut addresses = self.addresses.lock().unwrap();
+        let pool = addresses.entry(host.to_string()).or_insert_with(|| {
+            Arc::new(AddressPool {
+                idle: Mutex::new(Vec::new()),
+                permits: Semaphore::new(max_connections),
+                state: Mutex::new(ConnectionState::Connected),
+            })
+        });
+        Arc::clone(pool)
+    }
+}
+
+impl AddressPool {
+    // Pop idle connections until one is still alive
+    fn take_idle(&self) -> Option<TcpStream> {
+        let mut idle = self.idle.lock().unwrap();
+        while let Some(stream) = idle.pop() {
+            if is_alive(&stream) {
+                return Some(stream);
+            }
+        }
+        None
+    }
+
+    fn put_back(&self, stream: TcpStream) {
+        self.idle.lock().unwrap().push(stream);
+    }
+
+    // Switch to reconnecting, unless a reconnect task is already running
+    fn start_reconnecting(&self, first_delay: Duration) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if let ConnectionState::Reconnecting { .. } = *state {
+            return false;
+        }
+        *state = ConnectionState::Reconnecting {
+            attempt: 1,
+            next_delay: first_delay,
+        };
+        true
+    }
+}
+
+// Retry connecting to `host` with backoff, pooling the connection once it's
+// back, until the policy runs out of attempts or `shutdown` is cancelled
+async fn reconnect(
+    pool: Arc<AddressPool>,
+    host: String,
+    policy: ReconnectPolicy,
+    clock: Arc<dyn Clock>,
+    shutdown: CancellationToken,
+) {
+    let mut attempt = 1;
+    loop {
+        let delay = policy.delay(attempt);
+        *pool.state.lock().unwrap() = ConnectionState::Reconnecting {
+            attempt,
+            next_delay: delay,
+        };
+        tokio::select! {
+            _ = clock.sleep(delay) => {}
+            _ = shutdown.cancelled() => {
+                *pool.state.lock().unwrap() = ConnectionState::Failed;
+                return;
+            }
+        }
+        if let Ok(stream) = connect(&host).await {
+            pool.put_back(stream);
+            *pool.state.lock().unwrap() = ConnectionState::Connected;
+            return;
+        }
+        if policy.max_attempts.is_some_and(|max| attempt >= max) {
+            eprintln!(
+                "Giving up reconnecting to {} after {} attempts",
+                host, attempt
+            );
+            *pool.state.lock().unwrap() = ConnectionState::Failed;
+            return;
+        }
+        attempt += 1;
+    }
+}
+
+// An idle connection has nothing to read: EOF or stray data mean it's unusable
+fn is_alive(stream: &TcpStream) -> bool {
+    let mut buf = [0; 1];
+    matches!(stream.try_read(&mut buf), Err(e) if e.kind() == io::ErrorKind::WouldBlock)
+}
+
+async fn connect(host: &str) -> Result<TcpStream, String> {
+    TcpStream::connect(host)
+        .await
+        .map_err(|e| format!("Failed to connect to {}: {}", host, e))
+}
+
+// Split `host:port/actor` into its socket address and actor name
+pub(crate) fn parse_address(address: &str) -> Result<(&str, &str), String> {
+    match address.split_once('/') {
+        Some((host, actor)) if !host.is_empty() && !actor.is_empty() => Ok((host, actor)),
+        _ => Err(format!(
+            "Invalid TCP address {}, expected host:port/actor",
+            address
+        )),
+    }
+}
+
+// Write one frame, leaving the server's status byte to be read
+async fn write_frame(stream: &mut TcpStream, actor: &str, message: &str) -> io::Result<()> {
+    let mut frame = Vec::with_capacity(8 + actor.len() + message.len());
+    frame.extend_from_slice(&(actor.len() as u32).to_be_bytes());
+    frame.extend_from_slice(actor.as_bytes());
+    frame.extend_from_slice(&(message.len() as u32).to_be_bytes());
+    frame.extend_from_slice(message.as_bytes());
+    stream.write_all(&frame).await
+}
+
+fn status_to_result(status: u8, actor: &str, message: &str) -> Result<(), String> {
+    match status {
+        STATUS_ACCEPTED => Ok(()),
+        STATUS_NOT_FOUND => Err(format!("Actor {} not found", actor)),
+        STATUS_MAILBOX_FULL => Err(format!("Mailbox of actor {} is full", actor)),
+        STATUS_CLOSED => Err(format!("Actor {} is closed", actor)),
+        STATUS_REJECTED => Err(format!("Actor {} rejected message", actor)),
+        STATUS_TOO_LARGE => Err(format!(
+            "Message of {} bytes is too large for the server",
+            message.len()
+        )),
+        STATUS_MALFORMED => Err("Server could not parse the message".to_string()),
+        STATUS_RATE_LIMITED => Err(format!("Actor {} is rate limited", actor)),
+        STATUS_CLOSING => Err(format!("Actor {} is shutting down", actor)),
+        STATUS_QUIESCING => Err("Server is quiescing and takes no new messages".to_string()),
+        STATUS_SYSTEM_FULL => Err(format!(
+            "Server holds too many queued messages, actor {} takes no more",
+            actor
+        )),
+        other => Err(format!("Unknown response status {}", other)),
+    }
+}
+
+/// Receives frames sent by `TcpProtocol` and forwards them into an `ActorSystem`.
+///
+/// The server shuts down by itself once the system's cancellation token is
+/// cancelled, or with `TcpServerHandle::shutdown`: it stops accepting
+/// connections, lets each connection finish the frame it is receiving and then
+/// closes it, waiting up to the drain timeout (see `with_drain_timeout`) before
+/// cutting off the connections still busy.
+pub struct TcpServer {
+    system: Arc<ActorSystem<String>>,
+    max_message_size: Option<usize>,
+    parse: Option<ParseCheck>,
+    drain_timeout: Duration,
+}
+
+/// A running `TcpServer`.
+pub struct TcpServerHandle {
+    local_addr: SocketAddr,
+    // Cancelled along with the system, or by `shutdown`
+    shutdown: CancellationToken,
+    task: JoinHandle<()>,
+}
+
+impl TcpServer {
+    pub fn new(system: Arc<ActorSystem<String>>) -> Self {
+        TcpServer {
+            system,
+            max_message_size: None,
+            parse: None,
+            drain_timeout: DEFAULT_DRAIN_TIMEOUT,
+        }
+    }
+
+    /// Rejects frames whose payload is larger than `max` bytes, based on the frame
+    /// header. The payload of a rejected frame is discarded unread.
+    pub fn with_max_message_size(mut self, max: usize) -> Self {
+        self.max_message_size = Some(max);
+        self
+    }
+
+    /// Answers frames whose payload isn't JSON for a `T` as malformed, before
+    /// they reach any actor.
+    pub fn with_message_type<T: DeserializeOwned>(mut self) -> Self {
+        self.parse = Some(parses_as::<T>());
+        self
+    }
+
+    /// Sets how long a shutting down server waits for the frames in flight
+    /// before it closes the connections still busy.
+    pub fn with_drain_timeout(mut self, timeout: Duration) -> Self {
+        self.drain_timeout = timeout;
+        self
+    }
+
+    /// Binds to `addr` and accepts connections on a background task.
+    pub async fn start(self, addr: SocketAddr) -> Result<TcpServerHandle, String> {
+        let listener = TcpListener::bind(addr)
+            .await
+            .map_err(|e| format!("Failed to bind {}: {}", addr, e))?;
+        let local_addr = listener
+            .local_addr()
+            .map_err(|e| format!("Failed to read local address: {}", e))?;
+
+        let system = self.system;
+        let shutdown = system.cancellation_token().child_token();
+        let cancel = shutdown.clone();
+        let max_message_size = self.max_message_size;
+        let parse = self.parse;
+        let drain_timeout = self.drain_timeout;
+        let task = tokio::spawn(async move {
+            let mut connections = JoinSet::new();
+            loop {
+                let accepted = tokio::select! {
+                    accepted = listener.accept() => accepted,
+                    // Forget the connections that are done
+                    Some(_) = connections.join_next() => continue,
+                    // Stop along with the actor system
+                    _ = cancel.cancelled() => break,
+                };
+                match accepted {
+                    Ok((stream, _)) => {
+                        connections.spawn(handle_connection(
+                            Arc::clone(&system),
+                            max_message_size,
+                            parse.clone(),
+                            cancel.clone(),
+                            stream,
+                        ));
+                    }
+                    Err(e) => eprintln!("TCP server failed to accept connection: {}", e),
+                }
+            }
+            drop(listener);
+            let drained = async { while connections.join_next().await.is_some() {} };
+            if tokio::time::timeout(drain_timeout, drained).await.is_err() {
+                connections.abort_all();
+            }
+        });
+
+        Ok(TcpServerHandle {
+            local_addr,
+            shutdown,
+            task,
+        })
+    }
+}
+
+impl TcpServerHandle {
+    /// The address the server is listening on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Stops the server right away, cutting off the open connections.
+    pub fn stop(self) {
+        self.task.abort();
+    }
+
+    /// Shuts the server down gracefully, see `TcpServer`, and returns once it
+    /// has exited.
+    pub async fn shutdown(self) {
+        self.shutdown.cancel();
+        let _ = self.task.await;
+    }
+}
+
+// Serve frames from one connection until the peer disconnects
+async fn handle_connection(
+    system: Arc<ActorSystem<String>>,
+    max_message_size: Option<usize>,
+    parse: Option<ParseCheck>,
+    shutdown: CancellationToken,
+    mut stream: TcpStream,
+) {
+    loop {
+        // A frame that started arriving is finished even while shutting down
+        let name_len = tokio::select! {
+            len = stream.read_u32() => match len {
+                Ok(len) => len as usize,
+                // The peer closed the connection
+                Err(_) => return,
+            },
+            _ = shutdown.cancelled() => return,
+        };
+        if name_len > MAX_ACTOR_NAME_LEN {
+            let _ = stream.write_u8(STATUS_MALFORMED).await;
+            return;
+        }
+        let mut name = vec![0; name_len];
+        if stream.read_exact(&mut name).await.is_err() {
+            return;
+        }
+
+        let payload_len = match stream.read_u32().await {
+            Ok(len) => len as usize,
+            Err(_) => return,
+        };
+        if check_message_size(payload_len, max_message_size).is_err() {
+            // Skip over the payload without buffering it, keeping the stream in sync
+            let mut payload = (&mut stream).take(payload_len as u64);
+            match tokio::io::copy(&mut payload, &mut tokio::io::sink()).await {
+                Ok(skipped) if skipped == payload_len as u64 => {}
+                _ => return,
+            }
+            if stream.write_u8(STATUS_TOO_LARGE).await.is_err() {
+                return;
+            }
+            continue;
+        }
+        let mut payload = vec![0; payload_len];
+        if stream.read_exact(&mut payload).await.is_err() {
+            return;
+        }
+
+        let status = match (String::from_utf8(name), String::from_utf8(payload)) {
+            (Ok(_), Ok(message))
+                if parse.as_ref().is_some_and(|parse| parse(&message).is_err()) =>
+            {
+                STATUS_MALFORMED
+            }
+            (Ok(name), Ok(message)) => match system.try_send_message(&name, message) {
+                Ok(()) => STATUS_ACCEPTED,
+                Err(SendError::NotFound(_)) => STATUS_NOT_FOUND,
+                Err(SendError::MailboxFull(_)) => STATUS_MAILBOX_FULL,
+                Err(SendError::Closed(_)) => STATUS_CLOSED,
+                Err(SendError::Closing(_)) => STATUS_CLOSING,
+                Err(SendError::Quiescing(_)) => STATUS_QUIESCING,
+                Err(SendError::Rejected { .. }) => STATUS_REJECTED,
+                Err(SendError::TooLarge { .. }) => STATUS_TOO_LARGE,
+                Err(SendError::RateLimited(_)) => STATUS_RATE_LIMITED,
+                Err(SendError::SystemFull { .. }) => STATUS_SYSTEM_FULL,
+            },
+            _ => STATUS_MALFORMED,
+        };
+        if stream.write_u8(status).await.is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,353 @@
+// network/ws.rs
+
+//! # WebSocket transport
+//!
+//! `WebSocketProtocol` sends messages as text frames over a WebSocket connection
+//! (addresses are `ws://` or `wss://` URLs). One connection is opened per address
+//! and kept open for later sends; if it breaks, the next send reconnects.
+//!
+//! When a connection can't be opened, the send fails and a background task
+//! starts reconnecting with exponential backoff, following the
+//! `ReconnectPolicy` (see `with_reconnect_policy`), the same way `TcpProtocol`
+//! does: sends to that address fail fast until it is back, and
+//! `connection_state` reports where it stands. The reconnect tasks, and those
+//! forwarding pushed messages, stop once the token given to
+//! `with_cancellation_token` is cancelled.
+//!
+//! Because the link is bidirectional, the remote end can push messages back over
+//! the same connection. Those arrive on the stream returned by `take_incoming`,
+//! tagged with the address they came from.
+//!
+//! Requires the `websocket` feature.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use astra::network::http::CommunicationProtocol;
+//! use astra::network::ws::WebSocketProtocol;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), String> {
+//!     let protocol = WebSocketProtocol::new();
+//!     let mut incoming = protocol.take_incoming().unwrap();
+//!
+//!     protocol.send_message("ws://127.0.0.1:9000/control", "ping").await?;
+//!     if let Some(reply) = incoming.recv().await {
+//!         println!("{} pushed {}", reply.address, reply.message);
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use super::http::{check_message_size, CommunicationProtocol};
+use super::tcp::{ConnectionState, ReconnectPolicy};
+use crate::clock::{Clock, TokioClock};
+use async_trait::async_trait;
+use futures_util::stream::{SplitSink, StreamExt};
+use futures_util::SinkExt;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+
+type WsSink = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, WsMessage>;
+
+/// A text message pushed by a remote end.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncomingMessage {
+    /// The address of the connection the message arrived on.
+    pub address: String,
+    pub message: String,
+}
+
+// One open connection; `id` tells a reconnected link apart from the one it replaced
+struct Link {
+    id: u64,
+    sink: Arc<tokio::sync::Mutex<WsSink>>,
+}
+
+#[derive(Default)]
+struct Links {
+    next_id: u64,
+    by_address: HashMap<String, Link>,
+    states: HashMap<String, ConnectionState>,
+}
+
+// WebSocket implementation
+#[derive(Clone)]
+pub struct WebSocketProtocol {
+    max_message_size: Option<usize>,
+    reconnect: ReconnectPolicy,
+    // The time source of reconnect delays
+    clock: Arc<dyn Clock>,
+    // Stops the background tasks, which `tasks` keeps track of
+    shutdown: CancellationToken,
+    tasks: TaskTracker,
+    links: Arc<Mutex<Links>>,
+    incoming_tx: mpsc::UnboundedSender<IncomingMessage>,
+    incoming_rx: Arc<Mutex<Option<mpsc::UnboundedReceiver<IncomingMessage>>>>,
+}
+
+impl WebSocketProtocol {
+    pub fn new() -> Self {
+        let (incoming_tx, incoming_rx) = mpsc::unbounded_channel();
+        WebSocketProtocol {
+            max_message_size: None,
+            reconnect: ReconnectPolicy::default(),
+            clock: Arc::new(TokioClock),
+            shutdown: CancellationToken::new(),
+            tasks: TaskTracker::new(),
+            links: Arc::new(Mutex::new(Links::default())),
+            incoming_tx,
+            incoming_rx: Arc::new(Mutex::new(Some(incoming_rx))),
+        }
+    }
+
+    // Refuse to send messages larger than `max` bytes
+    pub fn with_max_message_size(mut self, max: usize) -> Self {
+        self.max_message_size = Some(max);
+        self
+    }
+
+    // Retry lost addresses following `policy` rather than the default one
+    pub fn with_reconnect_policy(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect = policy;
+        self
+    }
+
+    // Time reconnect delays with `clock`, e.g. a `MockClock` in tests
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Stops reconnecting, and forwarding pushed messages, once `parent` is
+    /// cancelled, e.g. the cancellation token of the `ActorSystem` the protocol
+    /// sends for.
+    pub fn with_cancellation_token(mut self, parent: &CancellationToken) -> Self {
+        self.shutdown = parent.child_token();
+        self
+    }
+
+    /// Waits until every background task has exited, typically after
+    /// cancelling the token given to `with_cancellation_token`.
+    pub async fn wait_until_stopped(&self) {
+        self.tasks.close();
+        self.tasks.wait().await;
+        // Allow tasks started later to be waited on again
+        self.tasks.reopen();
+    }
+
+    // The state of the connection to `address`, `None` if never used
+    pub fn connection_state(&self, address: &str) -> Option<ConnectionState> {
+        self.links.lock().unwrap().states.get(address).copied()
+    }
+
+    /// Takes the stream of messages pushed by remote ends. It can only be taken once
+    /// (clones share it); later calls return `None`.
+    pub fn take_incoming(&self) -> Option<mpsc::UnboundedReceiver<IncomingMessage>> {
+        self.incoming_rx.lock().unwrap().take()
+    }
+
+    // Number of connections currently open
+    pub fn open_connections(&self) -> usize {
+        self.links.lock().unwrap().by_address.len()
+    }
+
+    // Reuse the open connection to `address`, or open a new one, starting to
+    // reconnect in the background if that fails
+    async fn link(&self, address: &str) -> Result<(u64, Arc<tokio::sync::Mutex<WsSink>>), String> {
+        {
+            let links = self.links.lock().unwrap();
+            if let Some(link) = links.by_address.get(address) {
+                return Ok((link.id, Arc::clone(&link.sink)));
+            }
+            if let Some(ConnectionState::Reconnecting { .. }) = links.states.get(address) {
+                return Err(format!("Reconnecting to {}", address));
+            }
+        }
+        match self.open(address).await {
+            Ok(link) => Ok(link),
+            Err(e) => {
+                if self.start_reconnecting(address) {
+                    let protocol = self.clone();
+                    let address = address.to_string();
+                    self.tasks
+                        .spawn(async move { protocol.reconnect(&address).await });
+                }
+                Err(e)
+            }
+        }
+    }
+
+    // Open a connection to `address` and forward what it pushes
+    async fn open(&self, address: &str) -> Result<(u64, Arc<tokio::sync::Mutex<WsSink>>), String> {
+        let (stream, _) = connect_async(address)
+            .await
+            .map_err(|e| format!("Failed to connect to {}: {}", address, e))?;
+        let (sink, mut stream) = stream.split();
+        let sink = Arc::new(tokio::sync::Mutex::new(sink));
+
+        let id = {
+            let mut links = self.links.lock().unwrap();
+            links.next_id += 1;
+            let id = links.next_id;
+            links.by_address.insert(
+                address.to_string(),
+                Link {
+                    id,
+                    sink: Arc::clone(&sink),
+                },
+            );
+            links
+                .states
+                .insert(address.to_string(), ConnectionState::Connected);
+            id
+        };
+
+        // Forward pushed messages until the connection closes, then forget it
+        let links = Arc::clone(&self.links);
+        let incoming = self.incoming_tx.clone();
+        let address = address.to_string();
+        let shutdown = self.shutdown.clone();
+        self.tasks.spawn(async move {
+            loop {
+                let frame = tokio::select! {
+                    frame = stream.next() => frame,
+                    _ = shutdown.cancelled() => break,
+                };
+                match frame {
+                    Some(Ok(WsMessage::Text(message))) => {
+                        let _ = incoming.send(IncomingMessage {
+                            address: address.clone(),
+                            message,
+                        });
+                    }
+                    Some(Ok(WsMessage::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => {}
+                }
+            }
+            forget(&links, &address, id);
+        });
+
+        Ok((id, sink))
+    }
+
+    // Switch `address` to reconnecting, unless a reconnect task is already running
+    fn start_reconnecting(&self, address: &str) -> bool {
+        let mut links = self.links.lock().unwrap();
+        if let Some(ConnectionState::Reconnecting { .. }) = links.states.get(address) {
+            return false;
+        }
+        links.states.insert(
+            address.to_string(),
+            ConnectionState::Reconnecting {
+                attempt: 1,
+                next_delay: self.reconnect.delay(1),
+            },
+        );
+        true
+    }
+
+    fn set_state(&self, address: &str, state: ConnectionState) {
+        self.links
+            .lock()
+            .unwrap()
+            .states
+            .insert(address.to_string(), state);
+    }
+
+    // Retry connecting to `address` with backoff until it is back, the policy
+    // runs out of attempts or the protocol is shut down
+    async fn reconnect(&self, address: &str) {
+        let mut attempt = 1;
+        loop {
+            let delay = self.reconnect.delay(attempt);
+            self.set_state(
+                address,
+                ConnectionState::Reconnecting {
+                    attempt,
+                    next_delay: delay,
+                },
+            );
+            tokio::select! {
+                _ = self.clock.sleep(delay) => {}
+                _ = self.shutdown.cancelled() => {
+                    self.set_state(address, ConnectionState::Failed);
+                    return;
+                }
+            }
+            if self.open(address).await.is_ok() {
+                return;
+            }
+            if self
+                .reconnect
+                .max_attempts
+                .is_some_and(|max| attempt >= max)
+            {
+                eprintln!(
+                    "Giving up reconnecting to {} after {} attempts",
+                    address, attempt
+                );
+                self.set_state(address, ConnectionState::Failed);
+                return;
+            }
+            attempt += 1;
+        }
+    }
+}
+
+// Drop the connection to `address`, unless it was already replaced by a newer one
+fn forget(links: &Mutex<Links>, address: &str, id: u64) {
+    let mut links = links.lock().unwrap();
+    if links.by_address.get(address).map(|link| link.id) == Some(id) {
+        links.by_address.remove(address);
+    }
+}
+
+impl Default for WebSocketProtocol {
+    fn default() -> Self {
+        WebSocketProtocol::new()
+    }
+}
+
+impl std::fmt::Debug for WebSocketProtocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebSocketProtocol")
+            .field("max_message_size", &self.max_message_size)
+            .field("open_connections", &self.open_connections())
+            .finish()
+    }
+}
+
+#[async_trait]
+impl CommunicationProtocol for WebSocketProtocol {
+    async fn send_message(&self, address: &str, message: &str) -> Result<(), String> {
+        // Reject oversized messages before touching the network
+        check_message_size(message.len(), self.max_message_size)?;
+
+        let (id, sink) = self.link(address).await?;
+        let sent = sink
+            .lock()
+            .await
+            .send(WsMessage::Text(message.to_string()))
+            .await;
+        if sent.is_ok() {
+            return Ok(());
+        }
+
+        // The connection went stale: drop it and retry once on a new one
+        forget(&self.links, address, id);
+        let (_, sink) = self.link(address).await?;
+        let result = sink
+            .lock()
+            .await
+            .send(WsMessage::Text(message.to_string()))
+            .await;
+        result.map_err(|e| format!("Failed to send message: {}", e))
+    }
+}
@@ -6,126 +6,1094 @@
 //!
 //! The actor periodically saves its state using the underlying backend and can be gracefully shut down.
 //!
+//! The state can be any serializable type (a `String` by default). It is persisted as
+//! `{actor_id}:{format}:{sequence}:{payload}`, where the payload is encoded with the actor's
+//! `SnapshotFormat`: JSON by default, which is human-debuggable, or the more compact
+//! and faster Bincode/MessagePack behind the `bincode`/`msgpack` features.
+//!
+//! Snapshots written before formats were tagged, as `{actor_id}:{state}`, still
+//! load into a `String` state, whatever the actor's format, and the next save
+//! rewrites them in the current layout. Loading one into any other state type
+//! fails with an error saying so.
+//!
+//! `load_state` tells whether it restored anything: `LoadOutcome::Loaded`, or
+//! `LoadOutcome::NoPriorState` when the backend holds no snapshot of this
+//! actor, leaving the state as it was for the caller to initialize.
+//!
+//! ## Sequence numbers
+//!
+//! Every full snapshot carries a sequence number, one more than that of the
+//! snapshot it replaces, so snapshots of an actor can be ordered.
+//! `current_sequence` reads the number of the stored snapshot and
+//! `last_sequence` is the one this actor last loaded or saved: if the stored one
+//! is higher, another process saved since. Once an actor has loaded or saved,
+//! `save_state` refuses to go backwards: it fails with a `StaleSnapshot` instead
+//! of overwriting a higher sequence number, and the caller can `load_state` and
+//! retry; with optimistic saves it fails with a `SnapshotConflict` as usual. An
+//! actor that has done neither carries on from the stored number.
+//! Snapshots written before sequence numbers count as 0, and delta saves leave
+//! the number as it is.
+//!
+//! ## Shutdown
+//!
+//! The snapshot task stops on `shutdown`, when the token given to
+//! `with_cancellation_token` is cancelled, or, with `with_system`, once the
+//! actors of the `ActorSystem` have stopped on its way down. When it stops it
+//! runs `close`, which always happens in this order:
+//!
+//! 1. the state is saved one last time;
+//! 2. the backend is flushed, so buffered writes reach the storage;
+//! 3. only with `ShutdownMode::Cleanup`, the backend is cleaned up.
+//!
+//! The default, `ShutdownMode::Persist`, leaves the snapshot in place so
+//! `load_state` picks it up after a restart. Cleaning up is for throwaway state:
+//! for a `FileBackend` it deletes the snapshot file.
+//!
+//! ## Delta snapshots
+//!
+//! Rewriting a large state that barely changes on every save is wasteful. With
+//! `with_delta_snapshots(log, full_every)` only the first of every `full_every`
+//! saves writes the whole state to the actor's backend; the saves in between
+//! append what changed since the previous save to the `log` backend, as one JSON
+//! line each, and a save that changed nothing writes nothing. Changes are
+//! tracked field by field through nested structs and maps, while arrays and other
+//! values are replaced whole.
+//!
+//! `load_state` reads the last full snapshot and applies the logged deltas in
+//! order. If the log is corrupted, e.g. by a write torn in a crash, it falls back
+//! to the last full snapshot and the next save writes a fresh one.
+//!
+//! The log must be a backend whose `write_bytes` replaces its contents (like
+//! `FileBackend::new`, not `FileBackend::new_append`), because it is emptied
+//! whenever a new full snapshot is taken.
+//!
+//! ## Manifest
+//!
+//! With `with_manifest(backend)` every full snapshot is followed by a small JSON
+//! manifest in that backend: when the snapshot was written, its format and
+//! schema version (see `with_schema_version`), the astra version that wrote it,
+//! and the length and CRC-32 of the stored blob. `snapshot_info` reads only the
+//! manifest, so compatibility can be checked, or the time of the last save shown,
+//! without loading the state. The manifest is written after the blob: if the two
+//! disagree after a crash, the checksum tells.
+//!
+//! ## Export and import
+//!
+//! `export` packs the actor's current state into a portable archive, e.g. for
+//! an offline backup or to move the state to an actor over another backend.
+//! The archive is JSON whatever the backend and `SnapshotFormat`: the archive
+//! version, the actor id, the state, and a manifest like the one above whose
+//! length and checksum cover the state. `import` checks all of these before
+//! taking the state, then saves it to the actor's own backend. It refuses an
+//! archive of a newer archive version, of another actor, of another schema
+//! version, or whose state doesn't match its checksum.
+//!
+//! ## Optimistic saves
+//!
+//! Two processes saving the same actor's state into one backend overwrite each
+//! other, so a process that fell behind can undo a newer save. With
+//! `with_optimistic_saves` a full snapshot is only written if the backend still
+//! holds what this actor last loaded or saved, using the backend's
+//! `compare_and_swap`; otherwise `save_state` fails with a `SnapshotConflict`,
+//! and the caller can `load_state` and retry. An actor that never loaded expects
+//! an empty backend. The stored data is compared as text, so this needs the JSON
+//! format, and it is only as atomic as the backend's `compare_and_swap`. Delta
+//! saves are appended to their log unchecked.
+//!
+//! ## Failing saves
+//!
+//! When a periodic save fails the snapshot task keeps going, but counts the
+//! consecutive failures (see `consecutive_failures`) and logs each one through
+//! the actor's logger, the `ConsoleLogger` unless set with `with_logger`: as
+//! `LogLevel::Warn` at first and as `Error` from the third failure in a row.
+//! From then on it also backs off, skipping one tick after the third failure,
+//! two after the fourth and so on, up to eight, so a broken backend isn't
+//! retried on every tick. The first save that succeeds again is logged as
+//! `Info` and resets the count. With `with_supervisor` the failure is also
+//! reported to a `Supervisor` once that many saves in a row have failed.
+//!
+//! ## Clones
+//!
+//! Clones of an actor share its state: the snapshot task runs on a clone, and
+//! saves whatever `set_state` last stored on any of them. They also share the
+//! shutdown signal, and a lock that makes their saves and loads take turns, so
+//! a load never reads a snapshot another clone is halfway through writing. The
+//! bookkeeping of delta mode is per clone, so in that mode let a single clone,
+//! usually the one running the task, do the saving.
+//!
 //! # Example
 //!
 //! ```rust,no_run
 //! use astra::backends::file::FileBackend;
-//! use astra::snapshot_actor::SnapshotActor;
-//! use std::sync::Arc;
-//! use tokio::sync::Mutex;
+//! use astra::snapshot_actor::{LoadOutcome, SnapshotActor};
 //! use tokio::time::{sleep, Duration};
 //!
 //! #[tokio::main]
 //! async fn main() {
 //!   // Initialize the backend and actor
 //!   let file_backend = FileBackend::new("snapshot.txt").await.unwrap();
-//!   let actor = Arc::new(Mutex::new(SnapshotActor::new("actor1".to_string(), file_backend)));
+//!   let mut actor = SnapshotActor::new("actor1".to_string(), file_backend);
 //!
 //!   // Optionally load the actor's previous state from the backend
-//!   actor.lock().await.load_state().await.unwrap();
-//!
-//!   // Set the actor's state
-//!   actor.lock().await.set_state("new_state".to_string());
+//!   if actor.load_state().await.unwrap() == LoadOutcome::NoPriorState {
+//!       actor.set_state("initial_state".to_string());
+//!   }
 //!
 //!   // Start the snapshot task in the background (saving state every 60 seconds)
-//!   let actor_clone = Arc::clone(&actor);
+//!   let mut snapshotter = actor.clone();
 //!   let snapshot_task = tokio::spawn(async move {
-//!       actor_clone.lock().await.start_snapshot_task().await;
+//!       snapshotter.start_snapshot_task().await;
 //!   });
 //!
+//!   // The task saves the state set here, as the clones share it
+//!   actor.set_state("new_state".to_string());
+//!
 //!   // Simulate some work for 5 seconds instead of 2 minutes for the doctest
 //!   sleep(Duration::from_secs(5)).await;
 //!
 //!   // Send a shutdown signal to stop the snapshot task
-//!   actor.lock().await.shutdown();
+//!   actor.shutdown();
 //!
 //!   // Wait for the snapshot task to finish
 //!   snapshot_task.await.unwrap();
 //!
-//!   // Optionally save the final state and verify it
-//!   actor.lock().await.save_state().await.unwrap();
-//!   println!("Final actor state: {}", actor.lock().await.get_state());
+//!   // The task saved the final state on its way out
+//!   println!("Final actor state: {}", actor.get_state());
 //! }
 //! ```
 
+use crate::actor_system::ActorSystem;
 use crate::backends::storage::StorageBackend;
-use crate::data_actor::DataActor;
+use crate::clock::{self, Clock, TokioClock};
+use crate::data_actor::{DataActor, MissingPolicy};
+use crate::logging::{ConsoleLogger, LogLevel, Logger};
+use crate::supervision::Supervisor;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
 use std::error::Error;
-use tokio::sync::watch;
-use tokio::time::{interval, Duration};
+use std::fmt;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use tokio::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+// How often the snapshot task saves the state unless configured otherwise
+const DEFAULT_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(60);
+
+// Consecutive failed saves after which the snapshot task logs errors and backs off
+const FAILURES_BEFORE_BACKOFF: u32 = 3;
+
+// The most ticks the snapshot task skips after a failed save
+const MAX_SKIPPED_TICKS: u32 = 8;
+
+/// The wire format used for the persisted state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SnapshotFormat {
+    #[default]
+    Json,
+    #[cfg(feature = "bincode")]
+    Bincode,
+    #[cfg(feature = "msgpack")]
+    MessagePack,
+}
+
+impl SnapshotFormat {
+    // Tag stored in front of the payload so the format can be checked on load
+    fn tag(&self) -> &'static str {
+        match self {
+            SnapshotFormat::Json => "json",
+            #[cfg(feature = "bincode")]
+            SnapshotFormat::Bincode => "bincode",
+            #[cfg(feature = "msgpack")]
+            SnapshotFormat::MessagePack => "msgpack",
+        }
+    }
+
+    fn encode<S: Serialize>(&self, state: &S) -> Result<Vec<u8>, Box<dyn Error>> {
+        match self {
+            SnapshotFormat::Json => Ok(serde_json::to_vec(state)?),
+            #[cfg(feature = "bincode")]
+            SnapshotFormat::Bincode => Ok(bincode::serialize(state)?),
+            #[cfg(feature = "msgpack")]
+            SnapshotFormat::MessagePack => Ok(rmp_serde::to_vec(state)?),
+        }
+    }
+
+    fn decode<S: DeserializeOwned>(&self, payload: &[u8]) -> Result<S, Box<dyn Error>> {
+        match self {
+            SnapshotFormat::Json => Ok(serde_json::from_slice(payload)?),
+            #[cfg(feature = "bincode")]
+            SnapshotFormat::Bincode => Ok(bincode::deserialize(payload)?),
+            #[cfg(feature = "msgpack")]
+            SnapshotFormat::MessagePack => Ok(rmp_serde::from_slice(payload)?),
+        }
+    }
+}
+
+/// The metadata recorded in the manifest of a snapshot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotInfo {
+    pub actor_id: String,
+    pub saved_at: SystemTime,
+    /// The `SnapshotFormat` of the payload, as stored in front of it.
+    pub format: String,
+    /// The version of the state's schema, set with `with_schema_version`.
+    pub schema_version: u32,
+    /// The sequence number of the snapshot, 0 in manifests written before
+    /// snapshots had one.
+    #[serde(default)]
+    pub sequence: u64,
+    /// The version of astra that wrote the snapshot.
+    pub writer_version: String,
+    /// The length of the stored blob, in bytes.
+    pub len: usize,
+    /// The CRC-32 of the stored blob.
+    pub checksum: u32,
+}
+
+impl SnapshotInfo {
+    /// Returns true if `data` is the blob this manifest describes.
+    pub fn matches(&self, data: &[u8]) -> bool {
+        data.len() == self.len && crc32(data) == self.checksum
+    }
+}
+
+// CRC-32 (IEEE), computed bit by bit: snapshots are written rarely enough
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// The version of the archives `SnapshotActor::export` writes; `import` reads
+/// this one and older.
+pub const SNAPSHOT_ARCHIVE_VERSION: u32 = 1;
 
+// What `export` writes and `import` reads, as JSON
+#[derive(Debug, Serialize, Deserialize)]
+struct SnapshotArchive {
+    archive_version: u32,
+    actor_id: String,
+    state: Value,
+    // Its length and checksum cover the state as serialized here
+    manifest: SnapshotInfo,
+}
+
+/// What `SnapshotActor::load_state` found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadOutcome {
+    /// A snapshot was stored and is now the actor's state.
+    Loaded,
+    /// No snapshot of this actor was stored; the state is unchanged.
+    NoPriorState,
+}
+
+/// What happens to the backend once the actor shuts down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ShutdownMode {
+    /// Keep the saved state so it survives a restart.
+    #[default]
+    Persist,
+    /// Clean up the backend after the final save, e.g. deleting the file of a
+    /// `FileBackend`.
+    Cleanup,
+}
+
+// Where delta mode keeps the changes made since the last full snapshot
 #[derive(Debug, Clone)]
-pub struct SnapshotActor<B: StorageBackend> {
-    state: String,
+struct DeltaLog<B> {
+    backend: B,
+    full_every: usize,
+    // Deltas appended since the last full snapshot
+    written: usize,
+    // The state as last persisted, which the next delta is computed against
+    saved: Option<Value>,
+}
+
+impl<B: StorageBackend> DeltaLog<B> {
+    fn full_due(&self) -> bool {
+        self.saved.is_none() || self.written + 1 >= self.full_every
+    }
+
+    // Empty the log ahead of a new full snapshot
+    async fn reset(&mut self) -> Result<(), Box<dyn Error>> {
+        self.backend.write_bytes(b"").await?;
+        self.written = 0;
+        self.saved = None;
+        Ok(())
+    }
+
+    // Append the change from the last persisted state to `current`, if any
+    async fn append(&mut self, current: Value) -> Result<(), Box<dyn Error>> {
+        if let Some(patch) = self.saved.as_ref().and_then(|saved| diff(saved, &current)) {
+            let line = format!("{}:{}\n", self.written + 1, serde_json::to_string(&patch)?);
+            self.backend.extend_bytes(line.as_bytes()).await?;
+            self.written += 1;
+        }
+        self.saved = Some(current);
+        Ok(())
+    }
+}
+
+// A change between two JSON values: a replacement or, between two objects, the
+// fields that changed
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Patch {
+    Replace(Value),
+    Merge {
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        set: BTreeMap<String, Patch>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        remove: Vec<String>,
+    },
+}
+
+impl Patch {
+    // Fails if the patch was not computed against a value shaped like `target`
+    fn apply(self, target: &mut Value) -> Result<(), Box<dyn Error>> {
+        match self {
+            Patch::Replace(value) => *target = value,
+            Patch::Merge { set, remove } => {
+                let Value::Object(fields) = target else {
+                    return Err("delta merges into a value that is not an object".into());
+                };
+                for key in remove {
+                    if fields.remove(&key).is_none() {
+                        return Err(format!("delta removes missing field {}", key).into());
+                    }
+                }
+                for (key, patch) in set {
+                    match (fields.get_mut(&key), patch) {
+                        (Some(field), patch) => patch.apply(field)?,
+                        (None, Patch::Replace(value)) => {
+                            fields.insert(key, value);
+                        }
+                        (None, Patch::Merge { .. }) => {
+                            return Err(format!("delta merges into missing field {}", key).into());
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// The patch turning `old` into `new`, or `None` if they are equal
+fn diff(old: &Value, new: &Value) -> Option<Patch> {
+    if old == new {
+        return None;
+    }
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            let set = new
+                .iter()
+                .filter_map(|(key, value)| {
+                    let patch = match old.get(key) {
+                        Some(previous) => diff(previous, value)?,
+                        None => Patch::Replace(value.clone()),
+                    };
+                    Some((key.clone(), patch))
+                })
+                .collect();
+            let remove = old
+                .keys()
+                .filter(|key| !new.contains_key(*key))
+                .cloned()
+                .collect();
+            Some(Patch::Merge { set, remove })
+        }
+        _ => Some(Patch::Replace(new.clone())),
+    }
+}
+
+// Apply every delta in `log` to `state`, in order, returning how many there were.
+// A line that is torn, out of sequence or doesn't fit the state is corruption.
+fn replay(state: &mut Value, log: &[u8]) -> Result<usize, Box<dyn Error>> {
+    let mut applied = 0;
+    for line in std::str::from_utf8(log)?.lines() {
+        let (seq, patch) = line
+            .split_once(':')
+            .ok_or("delta without a sequence number")?;
+        if seq.parse::<usize>().ok() != Some(applied + 1) {
+            return Err(format!("expected delta {}, found {:?}", applied + 1, seq).into());
+        }
+        let patch: Patch = serde_json::from_str(patch)?;
+        patch.apply(state)?;
+        applied += 1;
+    }
+    Ok(applied)
+}
+
+/// The error `save_state` fails with when optimistic saves are on and the
+/// stored snapshot changed since this actor loaded or saved it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotConflict {
+    pub actor_id: String,
+}
+
+impl fmt::Display for SnapshotConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Snapshot of actor {} changed since it was loaded, reload and retry",
+            self.actor_id
+        )
+    }
+}
+
+impl std::error::Error for SnapshotConflict {}
+
+/// The error `save_state` fails with when the stored snapshot has a higher
+/// sequence number than the one this actor last loaded or saved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaleSnapshot {
+    pub actor_id: String,
+    /// The sequence number this actor last loaded or saved.
+    pub sequence: u64,
+    /// The sequence number of the stored snapshot.
+    pub stored: u64,
+}
+
+impl fmt::Display for StaleSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Snapshot of actor {} is at sequence {}, ahead of {} seen here, reload and retry",
+            self.actor_id, self.stored, self.sequence
+        )
+    }
+}
+
+impl std::error::Error for StaleSnapshot {}
+
+// The tags of every `SnapshotFormat`, whether or not its feature is enabled
+const FORMAT_TAGS: [&[u8]; 3] = [b"json", b"bincode", b"msgpack"];
+
+// A stored snapshot, split into its parts
+struct Stored<'a> {
+    actor_id: &'a [u8],
+    // `None` for snapshots from before formats were tagged
+    tag: Option<&'a [u8]>,
+    sequence: u64,
+    payload: &'a [u8],
+}
+
+// Split a stored snapshot. Snapshots from before sequence numbers have none,
+// which reads as 0. Those from before formats were tagged are a bare
+// `{actor_id}:{state}`; a state that itself starts with a format tag and a
+// colon can't be told apart from a tagged snapshot
+fn split_snapshot(data: &[u8]) -> Option<Stored<'_>> {
+    let colon = data.iter().position(|b| *b == b':')?;
+    let (actor_id, rest) = (&data[..colon], &data[colon + 1..]);
+    let tagged = rest
+        .iter()
+        .position(|b| *b == b':')
+        .filter(|end| FORMAT_TAGS.contains(&&rest[..*end]));
+    let Some(end) = tagged else {
+        return Some(Stored {
+            actor_id,
+            tag: None,
+            sequence: 0,
+            payload: rest,
+        });
+    };
+    let (tag, rest) = (&rest[..end], &rest[end + 1..]);
+    let numbered = rest.iter().position(|b| *b == b':').and_then(|end| {
+        let digits = &rest[..end];
+        if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+        let sequence = std::str::from_utf8(digits).ok()?.parse().ok()?;
+        Some((sequence, &rest[end + 1..]))
+    });
+    let (sequence, payload) = numbered.unwrap_or((0, rest));
+    Some(Stored {
+        actor_id,
+        tag: Some(tag),
+        sequence,
+        payload,
+    })
+}
+
+#[derive(Clone)]
+pub struct SnapshotActor<B: StorageBackend, S = String> {
+    // Shared by clones, so the snapshot task saves the live state
+    state: Arc<Mutex<S>>,
+    format: SnapshotFormat,
     data_actor: DataActor<B>,
     actor_id: String,
-    shutdown_tx: watch::Sender<()>,
-    shutdown_rx: watch::Receiver<()>,
+    snapshot_interval: Duration,
+    shutdown_mode: ShutdownMode,
+    clock: Arc<dyn Clock>,
+    // Set in delta mode
+    delta: Option<DeltaLog<B>>,
+    // Where the manifest of the last full snapshot is written, if anywhere
+    manifest: Option<B>,
+    schema_version: u32,
+    // Shared by clones, so `shutdown` on any of them stops the snapshot task
+    shutdown: CancellationToken,
+    optimistic: bool,
+    // What the backend held when this actor last loaded or saved, shared by
+    // clones like the state
+    seen: Arc<Mutex<Option<Vec<u8>>>>,
+    // The sequence number this actor last loaded or saved, shared by clones
+    sequence: Arc<Mutex<Option<u64>>>,
+    logger: Arc<dyn Logger + Send + Sync>,
+    // Reported to once `supervise_after` saves in a row have failed
+    supervisor: Option<(Arc<Supervisor>, u32)>,
+    // Failed saves since the last one that succeeded, shared by clones
+    failures: Arc<AtomicU32>,
+    // Held by clones while they save or read the stored snapshot
+    io: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl<B: StorageBackend + fmt::Debug, S: fmt::Debug> fmt::Debug for SnapshotActor<B, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SnapshotActor")
+            .field("actor_id", &self.actor_id)
+            .field("state", &self.state)
+            .field("format", &self.format)
+            .field("data_actor", &self.data_actor)
+            .field("snapshot_interval", &self.snapshot_interval)
+            .field("shutdown_mode", &self.shutdown_mode)
+            .field("failures", &self.failures)
+            .finish_non_exhaustive()
+    }
 }
 
-impl<B: StorageBackend> SnapshotActor<B> {
+impl<B, S> SnapshotActor<B, S>
+where
+    B: StorageBackend,
+    S: Serialize + DeserializeOwned + Clone + Default + Send + Sync,
+{
     pub fn new(actor_id: String, backend: B) -> Self {
         let data_actor = DataActor::new(backend);
-        let (shutdown_tx, shutdown_rx) = watch::channel(());
 
         SnapshotActor {
-            state: String::new(),
+            state: Arc::new(Mutex::new(S::default())),
+            format: SnapshotFormat::default(),
             data_actor,
             actor_id,
-            shutdown_tx,
-            shutdown_rx,
+            snapshot_interval: DEFAULT_SNAPSHOT_INTERVAL,
+            shutdown_mode: ShutdownMode::default(),
+            clock: Arc::new(TokioClock),
+            delta: None,
+            manifest: None,
+            schema_version: 1,
+            shutdown: CancellationToken::new(),
+            optimistic: false,
+            seen: Arc::new(Mutex::new(None)),
+            sequence: Arc::new(Mutex::new(None)),
+            logger: Arc::new(ConsoleLogger::new()),
+            supervisor: None,
+            failures: Arc::new(AtomicU32::new(0)),
+            io: Arc::new(tokio::sync::Mutex::new(())),
+        }
+    }
+
+    /// Sets how often `start_snapshot_task` saves the state (every 60 seconds by default).
+    pub fn with_snapshot_interval(mut self, snapshot_interval: Duration) -> Self {
+        self.snapshot_interval = snapshot_interval;
+        self
+    }
+
+    /// Chooses whether the backend is cleaned up when the actor shuts down; by
+    /// default the state persists.
+    pub fn with_shutdown_mode(mut self, shutdown_mode: ShutdownMode) -> Self {
+        self.shutdown_mode = shutdown_mode;
+        self
+    }
+
+    /// Sets the clock driving the snapshot task, e.g. a `MockClock` in tests.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Also stops the snapshot task when `parent` is cancelled, e.g. with the
+    /// token of the `ActorSystem` the actor belongs to.
+    pub fn with_cancellation_token(mut self, parent: &CancellationToken) -> Self {
+        self.shutdown = parent.child_token();
+        self
+    }
+
+    /// Also stops the snapshot task when `system` shuts down, once its actors
+    /// have stopped, so the final save has what they left behind (see
+    /// `ActorSystem::shutdown_token`).
+    pub fn with_system<M, E>(self, system: &ActorSystem<M, E>) -> Self
+    where
+        M: Send + 'static + fmt::Debug,
+        E: Send + 'static + fmt::Debug,
+    {
+        self.with_cancellation_token(&system.shutdown_token())
+    }
+
+    /// Sets what `load_state` does when nothing was saved yet: with
+    /// `MissingPolicy::Error` it fails rather than keeping the default state.
+    pub fn with_missing_policy(mut self, missing_policy: MissingPolicy) -> Self {
+        self.data_actor = self.data_actor.with_missing_policy(missing_policy);
+        self
+    }
+
+    /// Selects the wire format used to persist the state.
+    pub fn with_format(mut self, format: SnapshotFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Persists only what changed since the previous save, appended to `log`,
+    /// with a full snapshot every `full_every` saves (see the module docs).
+    pub fn with_delta_snapshots(mut self, log: B, full_every: usize) -> Self {
+        self.delta = Some(DeltaLog {
+            backend: log,
+            full_every: full_every.max(1),
+            written: 0,
+            saved: None,
+        });
+        self
+    }
+
+    /// Writes a manifest describing each full snapshot to `manifest` (see the
+    /// module docs).
+    pub fn with_manifest(mut self, manifest: B) -> Self {
+        self.manifest = Some(manifest);
+        self
+    }
+
+    /// Sets the schema version recorded in the manifest, 1 by default. Bump it
+    /// when the state's type changes incompatibly.
+    pub fn with_schema_version(mut self, schema_version: u32) -> Self {
+        self.schema_version = schema_version;
+        self
+    }
+
+    /// Refuses to overwrite a snapshot saved by someone else since this actor
+    /// loaded or saved it (see the module docs).
+    pub fn with_optimistic_saves(mut self) -> Self {
+        self.optimistic = true;
+        self
+    }
+
+    /// Sends what the snapshot task reports about failed saves to `logger`
+    /// instead of the console.
+    pub fn with_logger(mut self, logger: Arc<dyn Logger + Send + Sync>) -> Self {
+        self.logger = logger;
+        self
+    }
+
+    /// Reports the failure to `supervisor`, under the actor's id, once
+    /// `after_failures` periodic saves in a row have failed.
+    pub fn with_supervisor(mut self, supervisor: Arc<Supervisor>, after_failures: u32) -> Self {
+        self.supervisor = Some((supervisor, after_failures.max(1)));
+        self
+    }
+
+    /// How many periodic saves in a row have failed, 0 after one succeeds.
+    pub fn consecutive_failures(&self) -> u32 {
+        self.failures.load(Ordering::SeqCst)
+    }
+
+    /// Reads the manifest of the last full snapshot, without the state. Fails if
+    /// the actor has no manifest backend or nothing was saved yet.
+    pub async fn snapshot_info(&mut self) -> Result<SnapshotInfo, Box<dyn Error>> {
+        let io = Arc::clone(&self.io);
+        let _io = io.lock().await;
+        let Some(manifest) = &mut self.manifest else {
+            return Err(format!("Snapshot actor {} keeps no manifest", self.actor_id).into());
+        };
+        let raw = manifest.read_bytes().await?;
+        if raw.is_empty() {
+            return Err(format!("No snapshot saved for actor {}", self.actor_id).into());
         }
+        Ok(serde_json::from_slice(&raw)?)
     }
 
     // Save state using the DataActor's methods
     pub async fn save_state(&mut self) -> Result<(), Box<dyn Error>> {
-        let data = format!("{}:{}", self.actor_id, self.state);
-        self.data_actor.write_to_backend(&data).await?;
+        let io = Arc::clone(&self.io);
+        let _io = io.lock().await;
+        self.save_unlocked().await
+    }
+
+    // Save, with the lock held
+    async fn save_unlocked(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.delta.as_ref().is_some_and(|log| !log.full_due()) {
+            let current = serde_json::to_value(self.get_state())?;
+            if let Some(log) = &mut self.delta {
+                log.append(current).await?;
+            }
+            return Ok(());
+        }
+        if let Some(log) = &mut self.delta {
+            // Empty the log before the new baseline lands: a crash in between
+            // leaves the previous baseline, never stale deltas on top of a new one
+            log.reset().await?;
+        }
+        let sequence = self.next_sequence().await?;
+        // One copy for the blob and the delta baseline, so they can't disagree
+        let state = self.get_state();
+        let mut data =
+            format!("{}:{}:{}:", self.actor_id, self.format.tag(), sequence).into_bytes();
+        data.extend(self.format.encode(&state)?);
+        if self.optimistic {
+            self.swap_snapshot(&data).await?;
+        } else {
+            self.data_actor.write_bytes_to_backend(&data).await?;
+        }
+        if let Some(manifest) = &mut self.manifest {
+            let info = SnapshotInfo {
+                actor_id: self.actor_id.clone(),
+                saved_at: SystemTime::now(),
+                format: self.format.tag().to_string(),
+                schema_version: self.schema_version,
+                sequence,
+                writer_version: env!("CARGO_PKG_VERSION").to_string(),
+                len: data.len(),
+                checksum: crc32(&data),
+            };
+            manifest.write_bytes(&serde_json::to_vec(&info)?).await?;
+        }
+        if let Some(log) = &mut self.delta {
+            log.saved = Some(serde_json::to_value(&state)?);
+        }
+        *self.sequence.lock().unwrap() = Some(sequence);
         Ok(())
     }
 
-    // Load state using the DataActor's methods
-    pub async fn load_state(&mut self) -> Result<(), Box<dyn Error>> {
-        let data = self.data_actor.read_from_backend().await?;
-        let parts: Vec<&str> = data.splitn(2, ':').collect();
-        if parts.len() == 2 && parts[0] == self.actor_id {
-            self.state = parts[1].to_string();
+    // The sequence number of the next full snapshot, unless the stored one is
+    // ahead of what this actor last loaded or saved. Optimistic saves leave that
+    // to the compare-and-swap, which reports it as a `SnapshotConflict`
+    async fn next_sequence(&mut self) -> Result<u64, Box<dyn Error>> {
+        let stored = self.stored_sequence().await?;
+        match self.last_sequence() {
+            Some(sequence) if stored > sequence && !self.optimistic => {
+                Err(Box::new(StaleSnapshot {
+                    actor_id: self.actor_id.clone(),
+                    sequence,
+                    stored,
+                }))
+            }
+            Some(sequence) => Ok(sequence.max(stored) + 1),
+            None => Ok(stored + 1),
+        }
+    }
+
+    /// The sequence number of the snapshot stored for this actor, 0 if there is
+    /// none or it predates sequence numbers.
+    pub async fn current_sequence(&mut self) -> Result<u64, Box<dyn Error>> {
+        let io = Arc::clone(&self.io);
+        let _io = io.lock().await;
+        self.stored_sequence().await
+    }
+
+    // Read the stored sequence number, with the lock held
+    async fn stored_sequence(&mut self) -> Result<u64, Box<dyn Error>> {
+        let data = self.data_actor.read_stored_bytes().await?;
+        Ok(match split_snapshot(&data) {
+            Some(stored) if stored.actor_id == self.actor_id.as_bytes() => stored.sequence,
+            _ => 0,
+        })
+    }
+
+    /// The sequence number this actor last loaded or saved, `None` if it has
+    /// done neither yet.
+    pub fn last_sequence(&self) -> Option<u64> {
+        *self.sequence.lock().unwrap()
+    }
+
+    /// Packs the current state into a portable archive (see the module docs).
+    pub async fn export(&mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+        let sequence = match self.last_sequence() {
+            Some(sequence) => sequence,
+            None => self.current_sequence().await?,
+        };
+        let state = serde_json::to_value(self.get_state())?;
+        let payload = serde_json::to_vec(&state)?;
+        let archive = SnapshotArchive {
+            archive_version: SNAPSHOT_ARCHIVE_VERSION,
+            actor_id: self.actor_id.clone(),
+            state,
+            manifest: SnapshotInfo {
+                actor_id: self.actor_id.clone(),
+                saved_at: SystemTime::now(),
+                format: SnapshotFormat::Json.tag().to_string(),
+                schema_version: self.schema_version,
+                sequence,
+                writer_version: env!("CARGO_PKG_VERSION").to_string(),
+                len: payload.len(),
+                checksum: crc32(&payload),
+            },
+        };
+        Ok(serde_json::to_vec(&archive)?)
+    }
+
+    /// Replaces the state with the one in an archive written by `export`, and
+    /// saves it. Fails without touching the state if the archive doesn't belong
+    /// to this actor (see the module docs).
+    pub async fn import(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        let archive: SnapshotArchive =
+            serde_json::from_slice(data).map_err(|e| format!("Not a snapshot archive: {}", e))?;
+        if archive.archive_version > SNAPSHOT_ARCHIVE_VERSION {
+            return Err(format!(
+                "Snapshot archive version {} is not supported, expected {} or older",
+                archive.archive_version, SNAPSHOT_ARCHIVE_VERSION
+            )
+            .into());
         }
+        if archive.actor_id != self.actor_id || archive.manifest.actor_id != self.actor_id {
+            return Err(format!(
+                "Snapshot archive is for actor {}, not {}",
+                archive.actor_id, self.actor_id
+            )
+            .into());
+        }
+        if archive.manifest.schema_version != self.schema_version {
+            return Err(format!(
+                "Snapshot archive of actor {} has schema version {}, expected {}",
+                self.actor_id, archive.manifest.schema_version, self.schema_version
+            )
+            .into());
+        }
+        if !archive
+            .manifest
+            .matches(&serde_json::to_vec(&archive.state)?)
+        {
+            return Err(format!(
+                "Snapshot archive of actor {} is corrupted: its state doesn't match the manifest",
+                self.actor_id
+            )
+            .into());
+        }
+        let state: S = serde_json::from_value(archive.state)?;
+        self.set_state(state);
+        self.save_state().await
+    }
+
+    // Write a full snapshot only over the one this actor last saw
+    async fn swap_snapshot(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        let seen = self.seen.lock().unwrap().clone().unwrap_or_default();
+        let text = |bytes| {
+            std::str::from_utf8(bytes).map_err(|_| {
+                format!(
+                    "Optimistic saves of actor {} need a text format like JSON",
+                    self.actor_id
+                )
+            })
+        };
+        let swapped = self
+            .data_actor
+            .compare_and_swap_on_backend(text(&seen)?, text(data)?)
+            .await?;
+        if !swapped {
+            return Err(Box::new(SnapshotConflict {
+                actor_id: self.actor_id.clone(),
+            }));
+        }
+        *self.seen.lock().unwrap() = Some(data.to_vec());
         Ok(())
     }
 
+    /// Loads the stored state, telling whether there was any (see the module
+    /// docs).
+    pub async fn load_state(&mut self) -> Result<LoadOutcome, Box<dyn Error>> {
+        let io = Arc::clone(&self.io);
+        let _io = io.lock().await;
+        self.load_unlocked().await
+    }
+
+    // Load, with the lock held
+    async fn load_unlocked(&mut self) -> Result<LoadOutcome, Box<dyn Error>> {
+        let data = self.data_actor.read_bytes_from_backend().await?;
+        if self.optimistic {
+            *self.seen.lock().unwrap() = Some(data.clone());
+        }
+        let Some(Stored {
+            actor_id,
+            tag,
+            sequence,
+            payload,
+        }) = split_snapshot(&data)
+        else {
+            // Nothing stored yet, which a later save must not find changed
+            *self.sequence.lock().unwrap() = Some(0);
+            return Ok(LoadOutcome::NoPriorState);
+        };
+        if actor_id != self.actor_id.as_bytes() {
+            *self.sequence.lock().unwrap() = Some(0);
+            return Ok(LoadOutcome::NoPriorState);
+        }
+        let mut loaded: S = match tag {
+            Some(tag) if tag != self.format.tag().as_bytes() => {
+                return Err(format!(
+                    "Snapshot for actor {} is stored as {}, expected {}",
+                    self.actor_id,
+                    String::from_utf8_lossy(tag),
+                    self.format.tag()
+                )
+                .into());
+            }
+            Some(_) => self.format.decode(payload)?,
+            None => self.decode_legacy(payload)?,
+        };
+        if let Some(log) = &mut self.delta {
+            let mut state = serde_json::to_value(&loaded)?;
+            let raw = log.backend.read_bytes().await?;
+            let replayed = replay(&mut state, &raw)
+                .and_then(|applied| Ok((serde_json::from_value(state.clone())?, applied)));
+            match replayed {
+                Ok((replayed, applied)) => {
+                    loaded = replayed;
+                    log.written = applied;
+                    log.saved = Some(state);
+                }
+                Err(e) => {
+                    eprintln!(
+                        "Delta log of actor {} is corrupted, falling back to the last full snapshot: {}",
+                        self.actor_id, e
+                    );
+                    // The next save starts over from a full snapshot
+                    log.written = 0;
+                    log.saved = None;
+                }
+            }
+        }
+        self.set_state(loaded);
+        *self.sequence.lock().unwrap() = Some(sequence);
+        Ok(LoadOutcome::Loaded)
+    }
+
+    // Read the state of an untagged snapshot, which is the text of a `String`
+    fn decode_legacy(&self, payload: &[u8]) -> Result<S, Box<dyn Error>> {
+        let text = String::from_utf8_lossy(payload).into_owned();
+        serde_json::from_value(serde_json::Value::String(text)).map_err(|e| {
+            format!(
+                "Snapshot for actor {} is in the untagged format, which only holds a String state: {}",
+                self.actor_id, e
+            )
+            .into()
+        })
+    }
+
     // Method to set the state
-    pub fn set_state(&mut self, state: String) {
-        self.state = state;
+    pub fn set_state(&mut self, state: S) {
+        *self.state.lock().unwrap() = state;
     }
 
     // Get the current state
-    pub fn get_state(&self) -> String {
-        self.state.clone()
+    pub fn get_state(&self) -> S {
+        self.state.lock().unwrap().clone()
     }
 
     // Start a task to save the state periodically
     pub async fn start_snapshot_task(&mut self) {
-        let mut interval = interval(Duration::from_secs(60)); // Save state every 60 seconds
-        let mut shutdown_rx = self.shutdown_rx.clone(); // Clone receiver for the task
+        let mut interval = clock::interval(Arc::clone(&self.clock), self.snapshot_interval);
+        let shutdown = self.shutdown.clone(); // Clone the token for the task
+
+        // Ticks left to skip, backing off after repeated failures
+        let mut skip = 0;
 
         loop {
             tokio::select! {
                 _ = interval.tick() => {
-                    if let Err(e) = self.save_state().await {
-                        eprintln!("Failed to save state: {}", e);
+                    if skip > 0 {
+                        skip -= 1;
+                        continue;
                     }
+                    skip = self.periodic_save().await;
                 },
-                _ = shutdown_rx.changed() => {
+                _ = shutdown.cancelled() => {
                     println!("Received shutdown signal, stopping snapshot task");
                     break;
                 }
             }
         }
+        if let Err(e) = self.close().await {
+            eprintln!("Failed to close snapshot actor: {}", e);
+        }
+    }
+
+    // Save on a tick of the snapshot task, returning how many ticks to skip
+    async fn periodic_save(&mut self) -> u32 {
+        // Only the message is kept, the error itself isn't `Send`
+        let saved = self.save_state().await.map_err(|e| e.to_string());
+        let error = match saved {
+            Ok(()) => {
+                let failures = self.failures.swap(0, Ordering::SeqCst);
+                if failures > 0 {
+                    let message = format!(
+                        "Saved state of actor {} again after {} failed saves",
+                        self.actor_id, failures
+                    );
+                    self.logger.log(LogLevel::Info, &message).await;
+                }
+                return 0;
+            }
+            Err(error) => error,
+        };
+        let failures = self.failures.fetch_add(1, Ordering::SeqCst) + 1;
+        let level = if failures >= FAILURES_BEFORE_BACKOFF {
+            LogLevel::Error
+        } else {
+            LogLevel::Warn
+        };
+        let message = format!(
+            "Failed to save state of actor {} ({} in a row): {}",
+            self.actor_id, failures, error
+        );
+        self.logger.log(level, &message).await;
+        if let Some((supervisor, after_failures)) = &self.supervisor {
+            if failures == *after_failures {
+                supervisor.handle_failure(&self.actor_id, &error);
+            }
+        }
+        if failures < FAILURES_BEFORE_BACKOFF {
+            return 0;
+        }
+        // 1, 2, 4, ... ticks, up to the maximum
+        let doublings = (failures - FAILURES_BEFORE_BACKOFF).min(MAX_SKIPPED_TICKS.ilog2());
+        (1 << doublings).min(MAX_SKIPPED_TICKS)
+    }
+
+    /// Runs the shutdown sequence: saves the state, flushes the backend, then
+    /// cleans it up if the shutdown mode is `ShutdownMode::Cleanup`. Called by the
+    /// snapshot task when it stops; call it directly when the task isn't used.
+    pub async fn close(&mut self) -> Result<(), Box<dyn Error>> {
+        self.save_state().await?;
+        self.data_actor.flush_backend().await?;
+        if let Some(log) = &mut self.delta {
+            log.backend.flush().await?;
+        }
+        if let Some(manifest) = &mut self.manifest {
+            manifest.flush().await?;
+        }
+        if self.shutdown_mode == ShutdownMode::Cleanup {
+            self.data_actor.cleanup_backend().await?;
+            if let Some(log) = &mut self.delta {
+                log.backend.cleanup().await?;
+            }
+            if let Some(manifest) = &mut self.manifest {
+                manifest.cleanup().await?;
+            }
+        }
+        Ok(())
     }
 
     // Send a shutdown signal to stop the snapshot task
     pub fn shutdown(&self) {
-        let _ = self.shutdown_tx.send(());
+        self.shutdown.cancel();
     }
 }
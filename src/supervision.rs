@@ -1,33 +1,544 @@
 // supervision.rs
 
+//! # Supervision
+//!
+//! A `Supervisor` applies a single `SupervisionStrategy` to failing actors.
+//!
+//! A `SupervisorTree` arranges supervisors into a hierarchy, OTP style. Each node
+//! supervises a set of actors and may have child supervisors; when a node's strategy
+//! is `Escalate`, the failure is forwarded to its parent, which applies its own
+//! strategy. A parent that restarts an escalated failure restarts the whole branch
+//! the failure came from.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use astra::supervision::{SupervisionOutcome, SupervisionStrategy, SupervisorTree};
+//!
+//! let root = SupervisorTree::root("root", SupervisionStrategy::Restart);
+//! let workers = root.child("workers", SupervisionStrategy::Escalate);
+//! workers.supervise("worker-1", || println!("restarting worker-1"));
+//! workers.supervise("worker-2", || println!("restarting worker-2"));
+//!
+//! // worker-1 fails, `workers` escalates and `root` restarts the whole branch
+//! let outcome = workers.handle_failure("worker-1", "boom");
+//! assert_eq!(
+//!     outcome,
+//!     SupervisionOutcome::Restarted {
+//!         supervisor: "root".to_string(),
+//!         actors: vec!["worker-1".to_string(), "worker-2".to_string()],
+//!     }
+//! );
+//! ```
+//!
+//! Both kinds of supervisor publish a `SupervisionEvent` for every failure and for
+//! what was done about it. Subscribe with `events()`. A `Supervisor` doesn't
+//! restart actors itself, so its `Restarted` events come from `restarted`, called
+//! once the actor is back; `ActorSystem` does so for the actors it can rebuild,
+//! those added with a factory:
+//!
+//! ```rust
+//! use astra::supervision::{SupervisionEventKind, SupervisionStrategy, Supervisor};
+//!
+//! let supervisor = Supervisor::new(SupervisionStrategy::Restart);
+//! let mut events = supervisor.events();
+//! supervisor.handle_failure("worker-1", "boom");
+//! assert_eq!(events.try_recv().unwrap().kind, SupervisionEventKind::Failed);
+//! assert!(events.try_recv().is_err());
+//!
+//! // The worker was rebuilt
+//! supervisor.restarted("worker-1", "boom");
+//! assert_eq!(events.try_recv().unwrap().kind, SupervisionEventKind::Restarted);
+//! ```
+//!
+//! A `Supervisor` given to `ActorSystem::with_supervisor` also publishes its
+//! events on the system's event bus, see `ActorSystem::event_bus`.
+//!
+//! ## Escalating to shutdown
+//!
+//! A flat `Supervisor` has no parent to escalate to, so by default `Escalate`
+//! only logs the failure and publishes a `SupervisionEventKind::Escalated`
+//! event (`EscalationPolicy::Parent`). When a failure the supervisor can't
+//! handle means the system can't go on,
+//! `with_escalation(EscalationPolicy::Shutdown)` makes each escalation also
+//! request the shutdown of the systems the supervisor was given to (see
+//! `ActorSystem::request_shutdown`), which the code running them with
+//! `ActorSystem::run_until_shutdown` carries out.
+//!
+//! A `Supervisor` also logs what it does through a `Logger`, the `ConsoleLogger`
+//! unless set with `with_logger`: failures as `LogLevel::Error`, restarts and
+//! escalations as `Warn` and ignored failures as `Info`. A `SupervisorTree` logs
+//! its decisions the same way, see `SupervisorTree::with_logger`.
+
+use crate::event_bus::EventBus;
+use crate::logging::{ConsoleLogger, LogLevel, Logger};
+use std::fmt;
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::time::SystemTime;
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+
+// How many events a slow subscriber may fall behind before it starts missing them
+const EVENT_CAPACITY: usize = 256;
+
+/// What happened to a supervised actor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SupervisionEventKind {
+    /// The actor failed; followed by what was done about it, unless the failure
+    /// was ignored or the actor couldn't be restarted.
+    Failed,
+    /// The actor was restarted, published once the restart has happened.
+    Restarted,
+    /// The failure was passed on to a parent supervisor.
+    Escalated,
+    /// Nobody handled the failure.
+    GaveUp,
+}
+
+/// An observable supervision decision, published on a supervisor's event stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SupervisionEvent {
+    pub actor: String,
+    pub kind: SupervisionEventKind,
+    pub error: String,
+    pub timestamp: SystemTime,
+}
+
+fn event(actor: &str, kind: SupervisionEventKind, error: &str) -> SupervisionEvent {
+    SupervisionEvent {
+        actor: actor.to_string(),
+        kind,
+        error: error.to_string(),
+        timestamp: SystemTime::now(),
+    }
+}
+
+// Publish an event, it is fine for nobody to be listening
+fn publish(
+    events: &broadcast::Sender<SupervisionEvent>,
+    actor: &str,
+    kind: SupervisionEventKind,
+    error: &str,
+) {
+    let _ = events.send(event(actor, kind, error));
+}
+
 pub struct Supervisor {
     strategy: SupervisionStrategy,
+    events: broadcast::Sender<SupervisionEvent>,
+    // The event buses of the systems the supervisor was given to
+    buses: Mutex<Vec<EventBus>>,
+    escalation: EscalationPolicy,
+    // Requests the shutdown of each system the supervisor was given to
+    shutdowns: Mutex<Vec<CancellationToken>>,
+    logger: Arc<dyn Logger + Send + Sync>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SupervisionStrategy {
     Restart,
     Ignore,
     Escalate,
 }
 
+/// What a `Supervisor` does beyond reporting a failure it escalates, see the
+/// module docs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EscalationPolicy {
+    /// Leave it to a parent supervisor; a flat supervisor only reports it.
+    #[default]
+    Parent,
+    /// Request the shutdown of the supervised systems.
+    Shutdown,
+}
+
 impl Supervisor {
     pub fn new(strategy: SupervisionStrategy) -> Self {
-        Supervisor { strategy }
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+        Supervisor {
+            strategy,
+            events,
+            buses: Mutex::new(Vec::new()),
+            escalation: EscalationPolicy::default(),
+            shutdowns: Mutex::new(Vec::new()),
+            logger: Arc::new(ConsoleLogger::new()),
+        }
+    }
+
+    /// Sends what the supervisor does to `logger` instead of the console. Inside
+    /// a Tokio runtime the lines are logged from a task of their own, so
+    /// `handle_failure` never waits on the logger; outside one it logs them
+    /// before returning.
+    pub fn with_logger(mut self, logger: Arc<dyn Logger + Send + Sync>) -> Self {
+        self.logger = logger;
+        self
+    }
+
+    /// Sets what escalated failures lead to, `EscalationPolicy::Parent` unless
+    /// set.
+    pub fn with_escalation(mut self, escalation: EscalationPolicy) -> Self {
+        self.escalation = escalation;
+        self
+    }
+
+    /// The strategy the supervisor applies to every failure.
+    pub fn strategy(&self) -> SupervisionStrategy {
+        self.strategy
     }
 
+    /// Subscribes to the events of this supervisor.
+    pub fn events(&self) -> broadcast::Receiver<SupervisionEvent> {
+        self.events.subscribe()
+    }
+
+    // Also publish the events on `bus`, once however often it is attached
+    pub(crate) fn attach_event_bus(&self, bus: &EventBus) {
+        let mut buses = self.buses.lock().unwrap();
+        if !buses.iter().any(|attached| attached.same_bus(bus)) {
+            buses.push(bus.clone());
+        }
+    }
+
+    // Cancel `shutdown` when escalating to shutdown, once however often it is
+    // attached
+    pub(crate) fn attach_shutdown(&self, shutdown: &CancellationToken) {
+        let mut shutdowns = self.shutdowns.lock().unwrap();
+        if !shutdowns.iter().any(|attached| attached == shutdown) {
+            shutdowns.push(shutdown.clone());
+        }
+    }
+
+    fn publish(&self, actor: &str, kind: SupervisionEventKind, error: &str) {
+        let event = event(actor, kind, error);
+        for bus in self.buses.lock().unwrap().iter() {
+            bus.publish(event.clone());
+        }
+        let _ = self.events.send(event);
+    }
+
+    /// Applies the strategy to a failure of the named actor. With `Restart` it
+    /// only decides on the restart: whoever carries it out reports it with
+    /// `restarted`, as `ActorSystem` does for the actors it can rebuild.
     pub fn handle_failure(&self, actor_name: &str, error: &str) {
+        self.publish(actor_name, SupervisionEventKind::Failed, error);
+        let mut lines = vec![(
+            LogLevel::Error,
+            format!("Actor {} failed: {}", actor_name, error),
+        )];
         match self.strategy {
             SupervisionStrategy::Restart => {
-                println!("Restarting actor {} due to error: {}", actor_name, error);
-                // Logic to restart the actor
+                lines.push((
+                    LogLevel::Warn,
+                    format!("Restarting actor {} due to error: {}", actor_name, error),
+                ));
             }
             SupervisionStrategy::Ignore => {
-                println!("Ignoring error for actor {}: {}", actor_name, error);
+                lines.push((
+                    LogLevel::Info,
+                    format!("Ignoring error for actor {}: {}", actor_name, error),
+                ));
             }
             SupervisionStrategy::Escalate => {
-                println!("Escalating error for actor {}: {}", actor_name, error);
-                // Logic to escalate the error
+                lines.push((
+                    LogLevel::Warn,
+                    format!("Escalating error for actor {}: {}", actor_name, error),
+                ));
+                self.publish(actor_name, SupervisionEventKind::Escalated, error);
+                if self.escalation == EscalationPolicy::Shutdown {
+                    lines.push((
+                        LogLevel::Error,
+                        format!("Shutting down after actor {} escalated", actor_name),
+                    ));
+                    for shutdown in self.shutdowns.lock().unwrap().iter() {
+                        shutdown.cancel();
+                    }
+                }
+            }
+        }
+        log_lines(&self.logger, lines);
+    }
+
+    /// Reports that the named actor was restarted after failing with `error`,
+    /// publishing a `SupervisionEventKind::Restarted` event.
+    pub fn restarted(&self, actor_name: &str, error: &str) {
+        self.publish(actor_name, SupervisionEventKind::Restarted, error);
+    }
+}
+
+// Hand `lines` to `logger` in order without blocking the caller: from a task
+// on the current runtime if there is one, otherwise right here
+fn log_lines(logger: &Arc<dyn Logger + Send + Sync>, lines: Vec<(LogLevel, String)>) {
+    let logger = Arc::clone(logger);
+    let log = async move {
+        for (level, message) in lines {
+            logger.log(level, &message).await;
+        }
+    };
+    match tokio::runtime::Handle::try_current() {
+        Ok(runtime) => {
+            runtime.spawn(log);
+        }
+        Err(_) => futures::executor::block_on(log),
+    }
+}
+
+/// What a `SupervisorTree` ended up doing with a failure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SupervisionOutcome {
+    /// The named supervisor restarted the listed actors.
+    Restarted {
+        supervisor: String,
+        actors: Vec<String>,
+    },
+    /// The named supervisor decided to ignore the failure.
+    Ignored { supervisor: String },
+    /// The failure was escalated past the root, nobody handled it.
+    Unhandled,
+}
+
+type RestartFn = Box<dyn Fn() + Send + Sync>;
+
+struct TreeNode {
+    name: String,
+    strategy: SupervisionStrategy,
+    parent: Option<Weak<TreeNode>>,
+    children: Mutex<Vec<Arc<TreeNode>>>,
+    actors: Mutex<Vec<(String, RestartFn)>>,
+    // Shared by the whole tree
+    events: broadcast::Sender<SupervisionEvent>,
+    // Inherited from the parent, see `SupervisorTree::with_logger`
+    logger: RwLock<Arc<dyn Logger + Send + Sync>>,
+}
+
+/// A handle to a supervisor node in a supervision tree.
+///
+/// Handles are cheap to clone; the tree is kept alive by its root handle, children
+/// only hold a weak reference to their parent.
+#[derive(Clone)]
+pub struct SupervisorTree {
+    node: Arc<TreeNode>,
+}
+
+impl SupervisorTree {
+    /// Creates the root supervisor of a new tree.
+    pub fn root(name: &str, strategy: SupervisionStrategy) -> Self {
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+        SupervisorTree {
+            node: Arc::new(TreeNode {
+                name: name.to_string(),
+                strategy,
+                parent: None,
+                children: Mutex::new(Vec::new()),
+                actors: Mutex::new(Vec::new()),
+                events,
+                logger: RwLock::new(Arc::new(ConsoleLogger::new())),
+            }),
+        }
+    }
+
+    /// Creates a child supervisor under this one.
+    pub fn child(&self, name: &str, strategy: SupervisionStrategy) -> Self {
+        let node = Arc::new(TreeNode {
+            name: name.to_string(),
+            strategy,
+            parent: Some(Arc::downgrade(&self.node)),
+            children: Mutex::new(Vec::new()),
+            actors: Mutex::new(Vec::new()),
+            events: self.node.events.clone(),
+            logger: RwLock::new(self.node.logger()),
+        });
+        self.node.children.lock().unwrap().push(Arc::clone(&node));
+        SupervisorTree { node }
+    }
+
+    /// Sends what this supervisor and the ones under it do to `logger` instead
+    /// of the console, as `Supervisor::with_logger` does. Supervisors created
+    /// under it afterwards inherit the logger.
+    pub fn with_logger(self, logger: Arc<dyn Logger + Send + Sync>) -> Self {
+        self.node.set_logger(&logger);
+        self
+    }
+
+    /// Places an actor under this supervisor. `restart` is called whenever the
+    /// supervisor decides the actor has to be restarted.
+    pub fn supervise<F>(&self, actor_name: &str, restart: F)
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.node
+            .actors
+            .lock()
+            .unwrap()
+            .push((actor_name.to_string(), Box::new(restart)));
+    }
+
+    /// The name of this supervisor.
+    pub fn name(&self) -> &str {
+        &self.node.name
+    }
+
+    /// The strategy this supervisor applies.
+    pub fn strategy(&self) -> SupervisionStrategy {
+        self.node.strategy
+    }
+
+    /// Subscribes to the events of the whole tree this supervisor belongs to.
+    pub fn events(&self) -> broadcast::Receiver<SupervisionEvent> {
+        self.node.events.subscribe()
+    }
+
+    /// Returns the parent supervisor, or `None` for the root.
+    pub fn parent(&self) -> Option<SupervisorTree> {
+        self.node
+            .parent
+            .as_ref()
+            .and_then(Weak::upgrade)
+            .map(|node| SupervisorTree { node })
+    }
+
+    /// Handles the failure of an actor supervised by this node.
+    pub fn handle_failure(&self, actor_name: &str, error: &str) -> SupervisionOutcome {
+        self.publish(actor_name, SupervisionEventKind::Failed, error);
+        match self.node.strategy {
+            SupervisionStrategy::Restart => {
+                self.log(vec![(
+                    LogLevel::Warn,
+                    format!(
+                        "Supervisor {} restarting actor {} due to error: {}",
+                        self.node.name, actor_name, error
+                    ),
+                )]);
+                let actors = self.node.restart_actor(actor_name);
+                for actor in &actors {
+                    self.publish(actor, SupervisionEventKind::Restarted, error);
+                }
+                SupervisionOutcome::Restarted {
+                    supervisor: self.node.name.clone(),
+                    actors,
+                }
+            }
+            SupervisionStrategy::Ignore => {
+                self.log(vec![(
+                    LogLevel::Info,
+                    format!(
+                        "Supervisor {} ignoring error for actor {}: {}",
+                        self.node.name, actor_name, error
+                    ),
+                )]);
+                SupervisionOutcome::Ignored {
+                    supervisor: self.node.name.clone(),
+                }
+            }
+            SupervisionStrategy::Escalate => self.escalate(actor_name, error),
+        }
+    }
+
+    // Forward a failure to the parent supervisor, which applies its own strategy
+    fn escalate(&self, actor_name: &str, error: &str) -> SupervisionOutcome {
+        let mut lines = Vec::new();
+        let mut from = Arc::clone(&self.node);
+        while let Some(parent) = from.parent.as_ref().and_then(Weak::upgrade) {
+            lines.push((
+                LogLevel::Warn,
+                format!(
+                    "Supervisor {} escalating error for actor {} to {}: {}",
+                    from.name, actor_name, parent.name, error
+                ),
+            ));
+            self.publish(actor_name, SupervisionEventKind::Escalated, error);
+            match parent.strategy {
+                SupervisionStrategy::Restart => {
+                    // Restarting an escalated failure restarts the whole branch
+                    self.log(lines);
+                    let actors = from.restart_subtree();
+                    for actor in &actors {
+                        self.publish(actor, SupervisionEventKind::Restarted, error);
+                    }
+                    return SupervisionOutcome::Restarted {
+                        supervisor: parent.name.clone(),
+                        actors,
+                    };
+                }
+                SupervisionStrategy::Ignore => {
+                    self.log(lines);
+                    return SupervisionOutcome::Ignored {
+                        supervisor: parent.name.clone(),
+                    };
+                }
+                SupervisionStrategy::Escalate => from = parent,
             }
         }
+        lines.push((
+            LogLevel::Error,
+            format!(
+                "Error for actor {} escalated past root supervisor {}: {}",
+                actor_name, from.name, error
+            ),
+        ));
+        self.log(lines);
+        self.publish(actor_name, SupervisionEventKind::GaveUp, error);
+        SupervisionOutcome::Unhandled
+    }
+
+    fn publish(&self, actor: &str, kind: SupervisionEventKind, error: &str) {
+        publish(&self.node.events, actor, kind, error);
+    }
+
+    fn log(&self, lines: Vec<(LogLevel, String)>) {
+        log_lines(&self.node.logger(), lines);
+    }
+}
+
+impl TreeNode {
+    fn logger(&self) -> Arc<dyn Logger + Send + Sync> {
+        Arc::clone(&self.logger.read().unwrap())
+    }
+
+    // Use `logger` here and in the whole subtree
+    fn set_logger(&self, logger: &Arc<dyn Logger + Send + Sync>) {
+        *self.logger.write().unwrap() = Arc::clone(logger);
+        for child in self.children.lock().unwrap().iter() {
+            child.set_logger(logger);
+        }
+    }
+
+    // Restart a single actor directly supervised by this node
+    fn restart_actor(&self, actor_name: &str) -> Vec<String> {
+        let actors = self.actors.lock().unwrap();
+        actors
+            .iter()
+            .filter(|(name, _)| name == actor_name)
+            .map(|(name, restart)| {
+                restart();
+                name.clone()
+            })
+            .collect()
+    }
+
+    // Restart every actor in this node's subtree, depth first
+    fn restart_subtree(&self) -> Vec<String> {
+        let mut restarted: Vec<String> = self
+            .actors
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, restart)| {
+                restart();
+                name.clone()
+            })
+            .collect();
+        for child in self.children.lock().unwrap().iter() {
+            restarted.extend(child.restart_subtree());
+        }
+        restarted
+    }
+}
+
+impl fmt::Debug for SupervisorTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SupervisorTree")
+            .field("name", &self.node.name)
+            .field("strategy", &self.node.strategy)
+            .finish()
     }
 }
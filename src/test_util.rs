@@ -0,0 +1,272 @@
+// src/test_util.rs
+
+//! # Test utilities
+//!
+//! `TestHarness` wraps an `ActorSystem` so actor tests can wait for work to be
+//! done instead of sleeping and hoping it was. `send_and_settle` delivers a
+//! message and returns once the system is idle again: the message, and anything
+//! the actors sent each other because of it, has been handled. The harness also
+//! counts the messages each of its actors handled and captures the system's log
+//! lines in a `CapturingLogger`.
+//!
+//! Run harness tests on tokio's default current-thread test runtime, so the
+//! actors only run while the test awaits and nothing races the assertions.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use astra::actor_system::{Actor, Message};
+//! use astra::test_util::TestHarness;
+//! use async_trait::async_trait;
+//!
+//! struct Greeter;
+//!
+//! #[async_trait]
+//! impl Actor for Greeter {
+//!     type Message = String;
+//!     type Error = String;
+//!
+//!     async fn receive(&mut self, message: Message<String>) -> Result<(), String> {
+//!         if let Message::Regular(name) = message {
+//!             println!("Hello, {}!", name);
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//!
+//! #[tokio::main(flavor = "current_thread")]
+//! async fn main() -> Result<(), String> {
+//!     let mut harness = TestHarness::new();
+//!     harness.add_actor("greeter", Greeter);
+//!
+//!     harness.send_and_settle("greeter", "world".to_string()).await?;
+//!     harness.assert_processed("greeter", 1);
+//!     harness.shutdown().await;
+//!     Ok(())
+//! }
+//! ```
+
+use crate::actor_system::{Actor, ActorOptions, ActorSystem, Context, Message};
+use crate::logging::{LogLevel, Logger};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How long `settle` waits for the system to go idle unless configured otherwise.
+pub const DEFAULT_SETTLE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A `Logger` keeping every line logged through it, for assertions.
+#[derive(Debug, Default)]
+pub struct CapturingLogger {
+    lines: Mutex<Vec<(LogLevel, String)>>,
+}
+
+impl CapturingLogger {
+    pub fn new() -> Self {
+        CapturingLogger::default()
+    }
+
+    /// The lines logged so far, oldest first.
+    pub fn lines(&self) -> Vec<(LogLevel, String)> {
+        self.lines.lock().unwrap().clone()
+    }
+
+    /// Whether a line at `level` containing `text` was logged.
+    pub fn contains(&self, level: LogLevel, text: &str) -> bool {
+        self.lines
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|(logged, line)| *logged == level && line.contains(text))
+    }
+}
+
+#[async_trait]
+impl Logger for CapturingLogger {
+    async fn log(&self, level: LogLevel, message: &str) {
+        self.lines
+            .lock()
+            .unwrap()
+            .push((level, message.to_string()));
+    }
+}
+
+// Counts the regular messages the wrapped actor handled, failed or not
+struct Counted<A> {
+    actor: A,
+    processed: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl<A> Actor for Counted<A>
+where
+    A: Actor + Send,
+    A::Message: Send + 'static,
+{
+    type Message = A::Message;
+    type Error = A::Error;
+
+    async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error> {
+        let regular = matches!(message, Message::Regular(_));
+        let result = self.actor.receive(message).await;
+        if regular {
+            self.processed.fetch_add(1, Ordering::SeqCst);
+        }
+        result
+    }
+
+    async fn receive_with_context(
+        &mut self,
+        context: &Context<Self::Message, Self::Error>,
+        message: Message<Self::Message>,
+    ) -> Result<(), Self::Error>
+    where
+        Self::Message: Send,
+        Self::Error: Send,
+    {
+        let regular = matches!(message, Message::Regular(_));
+        let result = self.actor.receive_with_context(context, message).await;
+        if regular {
+            self.processed.fetch_add(1, Ordering::SeqCst);
+        }
+        result
+    }
+
+    async fn receive_batch(&mut self, messages: Vec<Self::Message>) -> Result<(), Self::Error>
+    where
+        Self::Message: Send,
+        Self::Error: Send,
+    {
+        let count = messages.len();
+        let result = self.actor.receive_batch(messages).await;
+        self.processed.fetch_add(count, Ordering::SeqCst);
+        result
+    }
+
+    async fn cleanup(&mut self) {
+        self.actor.cleanup().await;
+    }
+
+    fn receives_system_messages(&self) -> bool {
+        self.actor.receives_system_messages()
+    }
+
+    fn inspect_state(&self) -> Option<String> {
+        self.actor.inspect_state()
+    }
+}
+
+/// Drives an `ActorSystem` in tests, see the module docs.
+pub struct TestHarness<M, E = String> {
+    system: ActorSystem<M, E>,
+    logger: Arc<CapturingLogger>,
+    processed: HashMap<String, Arc<AtomicUsize>>,
+    settle_timeout: Duration,
+}
+
+impl<M, E> TestHarness<M, E>
+where
+    M: Send + 'static + std::fmt::Debug,
+    E: Send + 'static + std::fmt::Debug,
+{
+    /// Creates a harness around a new system logging into its `CapturingLogger`.
+    pub fn new() -> Self {
+        let logger = Arc::new(CapturingLogger::new());
+        TestHarness {
+            system: ActorSystem::new().with_logger(logger.clone()),
+            logger,
+            processed: HashMap::new(),
+            settle_timeout: DEFAULT_SETTLE_TIMEOUT,
+        }
+    }
+
+    /// Sets how long `settle` waits before giving up on a system that stays busy.
+    pub fn with_settle_timeout(mut self, timeout: Duration) -> Self {
+        self.settle_timeout = timeout;
+        self
+    }
+
+    /// The system under test, e.g. to send messages without settling.
+    pub fn system(&self) -> &ActorSystem<M, E> {
+        &self.system
+    }
+
+    /// The logger the system under test logs into.
+    pub fn logger(&self) -> Arc<CapturingLogger> {
+        Arc::clone(&self.logger)
+    }
+
+    /// Adds an actor whose handled messages are counted.
+    pub fn add_actor<A>(&mut self, name: &str, actor: A)
+    where
+        A: Actor<Message = M, Error = E> + Send + 'static,
+    {
+        self.add_actor_with_options(name, actor, ActorOptions::default());
+    }
+
+    /// Adds an actor configured with the given `ActorOptions`.
+    pub fn add_actor_with_options<A>(&mut self, name: &str, actor: A, options: ActorOptions)
+    where
+        A: Actor<Message = M, Error = E> + Send + 'static,
+    {
+        let processed = Arc::new(AtomicUsize::new(0));
+        self.processed
+            .insert(name.to_string(), Arc::clone(&processed));
+        self.system
+            .add_actor_with_options(name.to_string(), Counted { actor, processed }, options);
+    }
+
+    /// Sends a message and waits until it, and everything sent because of it,
+    /// has been handled.
+    pub async fn send_and_settle(&self, actor_name: &str, message: M) -> Result<(), String> {
+        self.system.send_message(actor_name, message).await?;
+        self.settle().await
+    }
+
+    /// Waits until no actor has a message left to handle.
+    pub async fn settle(&self) -> Result<(), String> {
+        tokio::time::timeout(self.settle_timeout, self.system.wait_quiesced())
+            .await
+            .map_err(|_| format!("System did not settle within {:?}", self.settle_timeout))
+    }
+
+    /// How many messages the named actor has handled.
+    pub fn processed(&self, actor_name: &str) -> usize {
+        self.processed
+            .get(actor_name)
+            .map_or(0, |count| count.load(Ordering::SeqCst))
+    }
+
+    /// Panics unless the named actor has handled exactly `expected` messages.
+    #[track_caller]
+    pub fn assert_processed(&self, actor_name: &str, expected: usize) {
+        assert!(
+            self.processed.contains_key(actor_name),
+            "Actor {} was not added through the harness",
+            actor_name
+        );
+        let processed = self.processed(actor_name);
+        assert_eq!(
+            processed, expected,
+            "Actor {} handled {} messages, expected {}",
+            actor_name, processed, expected
+        );
+    }
+
+    /// Shuts the system under test down.
+    pub async fn shutdown(&self) {
+        self.system.shutdown().await;
+    }
+}
+
+impl<M, E> Default for TestHarness<M, E>
+where
+    M: Send + 'static + std::fmt::Debug,
+    E: Send + 'static + std::fmt::Debug,
+{
+    fn default() -> Self {
+        TestHarness::new()
+    }
+}
@@ -0,0 +1,98 @@
+// src/util.rs
+
+//! # Utilities
+//!
+//! `retry_on_conflict` runs the "read, modify, write if unchanged" loop of
+//! optimistic operations: the closure makes one attempt, failing with
+//! `ConflictError::Conflict` when someone else changed the data first, and is
+//! called again after a short backoff, starting at 1ms and doubling up to
+//! 100ms. Any other error, `ConflictError::Failed`, is returned at once. After
+//! `max_attempts` conflicting attempts the helper gives up with
+//! `ConflictError::Conflict`.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use astra::backends::memory::MemoryBackend;
+//! use astra::backends::storage::StorageBackend;
+//! use astra::util::{retry_on_conflict, ConflictError};
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let backend = MemoryBackend::new();
+//!     let written = retry_on_conflict(10, || {
+//!         let mut backend = backend.clone();
+//!         async move {
+//!             let current = backend.read().await.map_err(|e| e.to_string())?;
+//!             let new = format!("{}!", current);
+//!             match backend.compare_and_swap(&current, &new).await {
+//!                 Ok(true) => Ok(new),
+//!                 Ok(false) => Err(ConflictError::Conflict),
+//!                 Err(e) => Err(ConflictError::Failed(e.to_string())),
+//!             }
+//!         }
+//!     })
+//!     .await?;
+//!     assert_eq!(written, "!");
+//!     Ok(())
+//! }
+//! ```
+
+use std::fmt;
+use std::future::Future;
+use std::time::Duration;
+
+// The backoff before the first retry, doubled before each of the next ones
+const FIRST_RETRY_DELAY: Duration = Duration::from_millis(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Why an optimistic attempt failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConflictError<E> {
+    /// The data changed under the attempt; trying again may succeed.
+    Conflict,
+    /// Any other failure, not worth retrying.
+    Failed(E),
+}
+
+impl<E> From<E> for ConflictError<E> {
+    fn from(error: E) -> Self {
+        ConflictError::Failed(error)
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for ConflictError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConflictError::Conflict => write!(f, "Conflicting write, gave up retrying"),
+            ConflictError::Failed(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for ConflictError<E> {}
+
+/// Calls `f` until it succeeds, fails with `ConflictError::Failed`, or has
+/// conflicted `max_attempts` times, backing off between attempts (see the
+/// module docs). `max_attempts` of 0 counts as 1.
+pub async fn retry_on_conflict<F, Fut, T, E>(
+    max_attempts: usize,
+    mut f: F,
+) -> Result<T, ConflictError<E>>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, ConflictError<E>>>,
+{
+    let mut delay = FIRST_RETRY_DELAY;
+    let mut attempt = 1;
+    loop {
+        match f().await {
+            Err(ConflictError::Conflict) if attempt < max_attempts => {
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(MAX_RETRY_DELAY);
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
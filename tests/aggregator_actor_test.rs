@@ -0,0 +1,113 @@
+use astra::actor_system::{Actor, ActorSystem, AggregatorActor, Message, Window};
+use astra::clock::MockClock;
+use async_trait::async_trait;
+use std::error::Error;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::timeout;
+
+struct Collector {
+    results: mpsc::UnboundedSender<u64>,
+}
+
+#[async_trait]
+impl Actor for Collector {
+    type Message = u64;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error> {
+        if let Message::Regular(result) = message {
+            self.results.send(result).unwrap();
+        }
+        Ok(())
+    }
+}
+
+fn system_with_summer(window: Window) -> (ActorSystem<u64>, mpsc::UnboundedReceiver<u64>) {
+    let (results_tx, results_rx) = mpsc::unbounded_channel();
+    let mut system = ActorSystem::new();
+    system.add_actor(
+        "collector".to_string(),
+        Collector {
+            results: results_tx,
+        },
+    );
+
+    let target = system.actor_ref("collector").unwrap();
+    let summer = AggregatorActor::new(target, window, |batch: Vec<u64>| batch.iter().sum());
+    system.add_actor("summer".to_string(), summer);
+    (system, results_rx)
+}
+
+#[tokio::test]
+async fn test_count_window_sums_ten_numbers() -> Result<(), Box<dyn Error>> {
+    let (system, mut results) = system_with_summer(Window::Count(10));
+
+    for n in 1..=10 {
+        system.send_message("summer", n).await?;
+    }
+
+    assert_eq!(results.recv().await, Some(55));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_duration_window_emits_once_closed() -> Result<(), Box<dyn Error>> {
+    let (system, mut results) = system_with_summer(Window::Duration(Duration::from_millis(50)));
+
+    for n in 1..=10 {
+        system.send_message("summer", n).await?;
+    }
+
+    assert_eq!(results.recv().await, Some(55));
+    // Nothing else arrives: empty windows are skipped
+    assert!(timeout(Duration::from_millis(150), results.recv())
+        .await
+        .is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_partial_window_flushed_on_shutdown() -> Result<(), Box<dyn Error>> {
+    let (system, mut results) = system_with_summer(Window::Count(10));
+
+    for n in 1..=3 {
+        system.send_message("summer", n).await?;
+    }
+    system.shutdown().await;
+
+    assert_eq!(results.recv().await, Some(6));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_window_timer_stops_with_the_system() -> Result<(), Box<dyn Error>> {
+    let (results_tx, _results) = mpsc::unbounded_channel();
+    let clock = MockClock::new();
+    let mut system = ActorSystem::new();
+    system.add_actor(
+        "collector".to_string(),
+        Collector {
+            results: results_tx,
+        },
+    );
+    let target = system.actor_ref("collector").unwrap();
+    let summer = AggregatorActor::new(
+        target,
+        Window::Duration(Duration::from_secs(60)),
+        |batch: Vec<u64>| batch.iter().sum(),
+    )
+    .with_clock(Arc::new(clock.clone()));
+    system.add_actor("summer".to_string(), summer);
+
+    system.send_message("summer", 1).await?;
+    clock.wait_for_sleepers(1).await;
+
+    // The system's tasks, the window timer among them, all stop
+    system.cancellation_token().cancel();
+    timeout(Duration::from_secs(1), system.wait_until_stopped()).await?;
+    assert_eq!(clock.sleepers(), 0);
+    assert_eq!(system.running_tasks(), 0);
+    Ok(())
+}
@@ -0,0 +1,90 @@
+use astra::actor_system::{Actor, ActorSystem, Message};
+use async_trait::async_trait;
+use futures::StreamExt;
+use std::error::Error;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+
+#[derive(Debug)]
+enum Query {
+    // Emit `count` lines
+    Tail(usize, mpsc::Sender<String>),
+    // Emit numbers until nobody listens, then report how many were sent
+    Count(mpsc::Sender<u64>, oneshot::Sender<u64>),
+}
+
+struct Log;
+
+#[async_trait]
+impl Actor for Log {
+    type Message = Query;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error> {
+        match message {
+            Message::Regular(Query::Tail(count, responses)) => {
+                for i in 0..count {
+                    responses
+                        .send(format!("line {}", i))
+                        .await
+                        .map_err(|e| e.to_string())?;
+                }
+            }
+            Message::Regular(Query::Count(responses, stopped)) => {
+                let mut sent = 0;
+                while responses.send(sent).await.is_ok() {
+                    sent += 1;
+                }
+                let _ = stopped.send(sent);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_stream_yields_every_response() -> Result<(), Box<dyn Error>> {
+    let mut system = ActorSystem::new();
+    system.add_actor("log".to_string(), Log);
+
+    let stream = system
+        .ask_stream("log", |responses| Query::Tail(3, responses))
+        .await?;
+    let lines: Vec<String> = stream.collect().await;
+    assert_eq!(lines, ["line 0", "line 1", "line 2"]);
+
+    system.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_dropping_the_stream_stops_the_actor_producing() -> Result<(), Box<dyn Error>> {
+    let mut system = ActorSystem::new();
+    system.add_actor("log".to_string(), Log);
+
+    let (stopped, sent) = oneshot::channel();
+    let mut stream = Box::pin(
+        system
+            .ask_stream("log", |responses| Query::Count(responses, stopped))
+            .await?,
+    );
+    assert_eq!(stream.next().await, Some(0));
+    assert_eq!(stream.next().await, Some(1));
+    drop(stream);
+
+    let sent = tokio::time::timeout(Duration::from_secs(5), sent).await??;
+    assert!(sent >= 2);
+
+    system.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_unknown_actor_fails_the_ask() {
+    let system: ActorSystem<Query> = ActorSystem::new();
+    let result = system
+        .ask_stream("missing", |responses| Query::Tail(1, responses))
+        .await;
+    assert!(result.is_err());
+}
@@ -0,0 +1,158 @@
+mod common;
+
+use astra::actor_system::{
+    ActorSystem, Autoscaler, ConsistentHashRouter, ScaleAction, ScalingPolicy,
+};
+use astra::clock::MockClock;
+use common::{handled_so_far, Gated};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Semaphore};
+
+#[tokio::test]
+async fn test_pool_scales_up_under_load_and_back_down_when_idle() -> Result<(), String> {
+    let gate = Arc::new(Semaphore::new(0));
+    let (seen, mut handled) = mpsc::unbounded_channel();
+    let clock = MockClock::new();
+    let system = ActorSystem::new();
+    let router = Arc::new(ConsistentHashRouter::new(|n: &u32| *n));
+    let policy = ScalingPolicy::new(1, 3)
+        .with_thresholds(4, 0)
+        .with_sustain(2)
+        .with_cooldown(Duration::from_secs(1));
+    let factory = {
+        let gate = Arc::clone(&gate);
+        move || Gated {
+            gate: Arc::clone(&gate),
+            seen: seen.clone(),
+            cleaned_up: Arc::default(),
+        }
+    };
+    let mut autoscaler = Autoscaler::new(system.clone(), router, "worker", factory, policy)
+        .with_clock(Arc::new(clock.clone()));
+    let pool = autoscaler.handle();
+    assert_eq!(autoscaler.workers(), vec!["worker-0"]);
+
+    // The only worker is stuck, so its mailbox piles up
+    for n in 0..40 {
+        pool.send(n).await?;
+    }
+    assert!(autoscaler.average_depth() > 4);
+
+    // The load has to be sustained over two samples
+    assert_eq!(autoscaler.sample().await, ScaleAction::Held);
+    assert_eq!(
+        autoscaler.sample().await,
+        ScaleAction::Grew("worker-1".to_string())
+    );
+    // Then the cooldown holds off the next action
+    assert_eq!(autoscaler.sample().await, ScaleAction::Held);
+    assert_eq!(autoscaler.sample().await, ScaleAction::Held);
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(
+        autoscaler.sample().await,
+        ScaleAction::Grew("worker-2".to_string())
+    );
+    // And the pool never grows past its maximum
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(autoscaler.sample().await, ScaleAction::Held);
+    assert_eq!(autoscaler.sample().await, ScaleAction::Held);
+    assert_eq!(autoscaler.workers().len(), 3);
+
+    // New messages are spread over the grown pool
+    for n in 40..100 {
+        pool.send(n).await?;
+    }
+
+    // Once the work is done the pool shrinks back, one worker per cooldown
+    gate.add_permits(1000);
+    system.wait_quiesced().await;
+    assert_eq!(autoscaler.average_depth(), 0);
+    assert_eq!(autoscaler.sample().await, ScaleAction::Held);
+    assert!(matches!(autoscaler.sample().await, ScaleAction::Shrank(_)));
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(autoscaler.sample().await, ScaleAction::Held);
+    assert!(matches!(autoscaler.sample().await, ScaleAction::Shrank(_)));
+    // But never below its minimum
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(autoscaler.sample().await, ScaleAction::Held);
+    assert_eq!(autoscaler.sample().await, ScaleAction::Held);
+    assert_eq!(autoscaler.workers().len(), 1);
+
+    // The removed workers drained their queues, and the rest of the pool takes
+    // every key
+    for n in 100..110 {
+        pool.send(n).await?;
+    }
+    system.wait_quiesced().await;
+    assert_eq!(handled_so_far(&mut handled).len(), 110);
+    system.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_removed_worker_drains_its_queue_first() -> Result<(), String> {
+    // The semaphore of each worker and what it handles, in the order they are
+    // built
+    let gates: Vec<_> = (0..2).map(|_| Arc::new(Semaphore::new(0))).collect();
+    let (built, mut handled): (Vec<Gated<u32>>, Vec<_>) = gates.iter().map(Gated::new).unzip();
+    let system = ActorSystem::new();
+    let router = Arc::new(ConsistentHashRouter::new(|n: &u32| *n));
+    let policy = ScalingPolicy::new(1, 2)
+        .with_thresholds(4, 4)
+        .with_sustain(1)
+        .with_cooldown(Duration::ZERO);
+    let factory = {
+        let mut built = built.into_iter();
+        move || built.next().unwrap()
+    };
+    let mut autoscaler = Autoscaler::new(
+        system.clone(),
+        Arc::clone(&router),
+        "worker",
+        factory,
+        policy,
+    );
+    let pool = autoscaler.handle();
+
+    for n in 0..10 {
+        pool.send(n).await?;
+    }
+    assert_eq!(
+        autoscaler.sample().await,
+        ScaleAction::Grew("worker-1".to_string())
+    );
+    let keys_of_new_worker: Vec<u32> = (10..)
+        .filter(|n| router.worker_for(n).as_deref() == Some("worker-1"))
+        .take(3)
+        .collect();
+    for n in keys_of_new_worker {
+        pool.send(n).await?;
+    }
+
+    // Let the first worker catch up until its queue is longer than the new
+    // one's, yet light enough to scale down
+    gates[0].add_permits(6);
+    for _ in 0..6 {
+        handled[0].recv().await;
+    }
+    // The new worker, with the shortest queue, goes; it only gets to work once
+    // it is being removed
+    let new_worker = Arc::clone(&gates[1]);
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        new_worker.add_permits(3);
+    });
+    assert_eq!(
+        autoscaler.sample().await,
+        ScaleAction::Shrank("worker-1".to_string())
+    );
+    assert_eq!(handled_so_far(&mut handled[1]).len(), 3);
+    assert_eq!(autoscaler.workers(), vec!["worker-0"]);
+
+    gates[0].add_permits(4);
+    system.wait_quiesced().await;
+    assert_eq!(handled_so_far(&mut handled[0]).len(), 4);
+    system.shutdown().await;
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use astra::backends::builder::BackendBuilder;
+use astra::backends::file::FileBackend;
+use astra::backends::metered::BackendOperation;
+use astra::backends::storage::StorageBackend;
+use std::error::Error;
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_three_layer_stack_round_trips() -> Result<(), Box<dyn Error>> {
+    let path = std::env::temp_dir().join(format!("astra_{}_builder.txt", std::process::id()));
+    let path = path.to_str().unwrap();
+    // Layers are given out of order, the builder still puts the cache outermost
+    let builder = BackendBuilder::new(FileBackend::new(path).await?)
+        .with_cache(Duration::from_secs(60))
+        .with_metrics()
+        .with_buffering(2);
+    let metrics = builder.metrics().unwrap();
+    let mut backend = builder.build();
+
+    backend.write("first").await?;
+    // Served by the cache, the write is still buffered
+    assert_eq!(backend.read().await?, "first");
+    assert_eq!(metrics.operation_stats(BackendOperation::Write).count, 0);
+
+    // The second write fills the buffer and flushes it down to the file
+    backend.write("second").await?;
+    assert!(metrics.operation_stats(BackendOperation::Write).count > 0);
+    assert_eq!(FileBackend::new_append(path).await?.read().await?, "second");
+    assert_eq!(metrics.operation_stats(BackendOperation::Read).count, 0);
+
+    // A clone of the boxed stack works on the same storage
+    let mut other = backend.clone();
+    assert_eq!(other.read().await?, "second");
+
+    backend.cleanup().await?;
+    Ok(())
+}
@@ -0,0 +1,40 @@
+use astra::backends::config::{BackendConfig, BackendConfigError};
+use astra::backends::from_uri;
+use astra::backends::storage::StorageBackend;
+use std::error::Error;
+
+#[tokio::test]
+async fn test_supported_schemes_build_working_backends() -> Result<(), Box<dyn Error>> {
+    assert_eq!("memory://".parse::<BackendConfig>()?, BackendConfig::Memory);
+    let mut memory = from_uri("memory://").await?;
+    memory.write("in memory").await?;
+    assert_eq!(memory.read().await?, "in memory");
+
+    let path = std::env::temp_dir().join(format!("astra_{}_config.txt", std::process::id()));
+    let uri = format!("file://{}", path.to_str().unwrap());
+    assert_eq!(
+        uri.parse::<BackendConfig>()?,
+        BackendConfig::File {
+            path: path.to_str().unwrap().to_string()
+        }
+    );
+    let mut file = from_uri(&uri).await?;
+    file.write("on disk").await?;
+    assert_eq!(std::fs::read_to_string(&path)?, "on disk");
+    file.cleanup().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_unknown_and_malformed_uris_are_refused() {
+    assert_eq!(
+        from_uri("redis://host/key").await.unwrap_err(),
+        BackendConfigError::UnknownScheme("redis".to_string())
+    );
+    for uri in ["/tmp/state", "file://", "memory://extra"] {
+        assert!(matches!(
+            uri.parse::<BackendConfig>(),
+            Err(BackendConfigError::Malformed { .. })
+        ));
+    }
+}
@@ -0,0 +1,132 @@
+use astra::actor_system::{Actor, ActorOptions, ActorSystem, Message};
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// Records each batch it is handed, and whether it saw a shutdown
+#[derive(Default)]
+struct BulkWriter {
+    batches: Arc<Mutex<Vec<Vec<u32>>>>,
+    singles: Arc<Mutex<Vec<u32>>>,
+    shut_down: Arc<Mutex<bool>>,
+}
+
+#[async_trait]
+impl Actor for BulkWriter {
+    type Message = u32;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<u32>) -> Result<(), String> {
+        match message {
+            Message::Regular(n) => self.singles.lock().unwrap().push(n),
+            Message::Shutdown => *self.shut_down.lock().unwrap() = true,
+            Message::System(_) => {}
+        }
+        Ok(())
+    }
+
+    async fn receive_batch(&mut self, messages: Vec<u32>) -> Result<(), String> {
+        self.batches.lock().unwrap().push(messages);
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_batching_actor_receives_groups() -> Result<(), String> {
+    let writer = BulkWriter::default();
+    let (batches, singles) = (Arc::clone(&writer.batches), Arc::clone(&writer.singles));
+    let mut system = ActorSystem::new();
+    system.add_actor_with_options(
+        "writer".to_string(),
+        writer,
+        ActorOptions::new().with_batching(4, Duration::from_millis(50)),
+    );
+
+    for n in 0..10 {
+        system.send_message("writer", n).await?;
+    }
+    system.wait_quiesced().await;
+
+    // Grouped by up to four, in order, and never delivered one at a time
+    let batches = batches.lock().unwrap().clone();
+    assert_eq!(
+        batches,
+        vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7], vec![8, 9]]
+    );
+    assert!(singles.lock().unwrap().is_empty());
+    system.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_linger_delivers_a_partial_batch() -> Result<(), String> {
+    let writer = BulkWriter::default();
+    let batches = Arc::clone(&writer.batches);
+    let mut system = ActorSystem::new();
+    system.add_actor_with_options(
+        "writer".to_string(),
+        writer,
+        ActorOptions::new().with_batching(100, Duration::from_millis(20)),
+    );
+
+    // Nothing else comes, so the batch goes out once the linger is over
+    system.send_message("writer", 1).await?;
+    tokio::time::timeout(Duration::from_secs(5), system.wait_quiesced())
+        .await
+        .map_err(|_| "batch was never delivered".to_string())?;
+    assert_eq!(*batches.lock().unwrap(), vec![vec![1]]);
+    system.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_shutdown_cuts_a_batch_short() {
+    let writer = BulkWriter::default();
+    let (batches, shut_down) = (Arc::clone(&writer.batches), Arc::clone(&writer.shut_down));
+    let mut system = ActorSystem::new();
+    system.add_actor_with_options(
+        "writer".to_string(),
+        writer,
+        ActorOptions::new().with_batching(100, Duration::from_secs(60)),
+    );
+
+    system.send_message("writer", 1).await.unwrap();
+    system.send_message("writer", 2).await.unwrap();
+    system.shutdown().await;
+
+    // The batch went out without waiting for the linger, then the shutdown
+    assert_eq!(*batches.lock().unwrap(), vec![vec![1, 2]]);
+    assert!(*shut_down.lock().unwrap());
+}
+
+#[tokio::test]
+async fn test_default_receive_batch_calls_receive() -> Result<(), String> {
+    // An actor that doesn't override receive_batch handles each message on its own
+    struct Summer(Arc<Mutex<u32>>);
+
+    #[async_trait]
+    impl Actor for Summer {
+        type Message = u32;
+        type Error = String;
+
+        async fn receive(&mut self, message: Message<u32>) -> Result<(), String> {
+            match message {
+                Message::Regular(0) => Err("zero".to_string()),
+                Message::Regular(n) => {
+                    *self.0.lock().unwrap() += n;
+                    Ok(())
+                }
+                Message::Shutdown | Message::System(_) => Ok(()),
+            }
+        }
+    }
+
+    let sum = Arc::new(Mutex::new(0));
+    let mut summer = Summer(Arc::clone(&sum));
+    assert_eq!(
+        summer.receive_batch(vec![1, 0, 2, 3]).await,
+        Err("zero".to_string())
+    );
+    assert_eq!(*sum.lock().unwrap(), 6);
+    Ok(())
+}
@@ -0,0 +1,90 @@
+use astra::actor_system::{Actor, ActorSystem, Message};
+use astra::backends::blocking::BlockingBackend;
+use astra::backends::storage::StorageBackend;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+// A synchronous key-value store, each write taking `delay` of blocking work
+fn map_backend(map: Arc<Mutex<HashMap<String, Vec<u8>>>>, delay: Duration) -> BlockingBackend {
+    let (read, write, cleanup) = (map.clone(), map.clone(), map);
+    BlockingBackend::new(
+        move || {
+            Ok(read
+                .lock()
+                .unwrap()
+                .get("state")
+                .cloned()
+                .unwrap_or_default())
+        },
+        move |data: &[u8]| {
+            std::thread::sleep(delay);
+            write
+                .lock()
+                .unwrap()
+                .insert("state".to_string(), data.to_vec());
+            Ok(())
+        },
+        move || {
+            cleanup.lock().unwrap().remove("state");
+            Ok(())
+        },
+    )
+}
+
+struct Echo {
+    seen: mpsc::UnboundedSender<String>,
+}
+
+#[async_trait]
+impl Actor for Echo {
+    type Message = String;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error> {
+        if let Message::Regular(msg) = message {
+            let _ = self.seen.send(msg);
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_blocking_backend_round_trips_through_a_sync_map() -> Result<(), Box<dyn Error>> {
+    let map = Arc::new(Mutex::new(HashMap::new()));
+    let mut backend = map_backend(map.clone(), Duration::ZERO);
+
+    backend.write("first").await?;
+    assert_eq!(map.lock().unwrap()["state"], b"first");
+    backend.extend_bytes(b" second").await?;
+    assert_eq!(backend.read().await?, "first second");
+
+    backend.cleanup().await?;
+    assert!(map.lock().unwrap().is_empty());
+    assert_eq!(backend.read().await?, "");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_slow_blocking_write_leaves_actors_responsive() -> Result<(), Box<dyn Error>> {
+    let (seen_tx, mut seen) = mpsc::unbounded_channel();
+    let mut system = ActorSystem::new();
+    system.add_actor("echo".to_string(), Echo { seen: seen_tx });
+
+    // The test runtime has a single worker thread, which the write must not hold
+    let mut backend = map_backend(Arc::default(), Duration::from_millis(500));
+    let write = tokio::spawn(async move { backend.write("slow").await.map_err(|e| e.to_string()) });
+    tokio::task::yield_now().await;
+
+    system.send_message("echo", "ping".to_string()).await?;
+    let echoed = tokio::time::timeout(Duration::from_millis(250), seen.recv()).await?;
+    assert_eq!(echoed.as_deref(), Some("ping"));
+    assert!(!write.is_finished());
+
+    write.await??;
+    system.shutdown().await;
+    Ok(())
+}
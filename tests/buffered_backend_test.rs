@@ -0,0 +1,59 @@
+use astra::backends::buffered::BufferedBackend;
+use astra::backends::file::FileBackend;
+use astra::backends::storage::StorageBackend;
+use astra::snapshot_actor::SnapshotActor;
+use std::error::Error;
+
+fn temp_path(name: &str) -> String {
+    std::env::temp_dir()
+        .join(format!("astra_{}_{}", std::process::id(), name))
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[tokio::test]
+async fn test_buffered_read_sees_latest_write() -> Result<(), Box<dyn Error>> {
+    let file_backend = FileBackend::new(&temp_path("buffered_consistent.txt")).await?;
+    let mut backend = BufferedBackend::new(file_backend, 10);
+
+    backend.write("first").await?;
+    backend.write("latest").await?;
+    assert!(backend.has_pending());
+
+    // The read flushes the buffered write before going to the file
+    assert_eq!(backend.read().await?, "latest");
+    assert!(!backend.has_pending());
+
+    backend.cleanup().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_buffered_inconsistent_read_skips_flush() -> Result<(), Box<dyn Error>> {
+    let file_backend = FileBackend::new(&temp_path("buffered_inconsistent.txt")).await?;
+    let mut backend = BufferedBackend::new(file_backend, 10).with_read_consistent(false);
+
+    backend.write("buffered").await?;
+    assert_eq!(backend.read().await?, "");
+
+    backend.flush().await?;
+    assert_eq!(backend.read().await?, "buffered");
+
+    backend.cleanup().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_snapshot_save_then_load_through_buffer() -> Result<(), Box<dyn Error>> {
+    let file_backend = FileBackend::new(&temp_path("buffered_snapshot.txt")).await?;
+    let mut actor =
+        SnapshotActor::new("actor1".to_string(), BufferedBackend::new(file_backend, 10));
+
+    actor.set_state("saved".to_string());
+    actor.save_state().await?;
+    actor.set_state("unsaved".to_string());
+    actor.load_state().await?;
+
+    assert_eq!(actor.get_state(), "saved");
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use astra::backends::caching::CachingBackend;
+use astra::backends::memory::MemoryBackend;
+use astra::backends::storage::StorageBackend;
+use async_trait::async_trait;
+use std::error::Error;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+// Counts the reads reaching the wrapped backend
+#[derive(Clone, Default)]
+struct CountingBackend {
+    inner: MemoryBackend,
+    reads: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl StorageBackend for CountingBackend {
+    async fn write(&mut self, data: &str) -> Result<(), Box<dyn Error>> {
+        self.inner.write(data).await
+    }
+
+    async fn read(&mut self) -> Result<String, Box<dyn Error>> {
+        self.reads.fetch_add(1, Ordering::SeqCst);
+        self.inner.read().await
+    }
+
+    async fn cleanup(&mut self) -> Result<(), Box<dyn Error>> {
+        self.inner.cleanup().await
+    }
+}
+
+#[tokio::test]
+async fn test_second_read_within_ttl_is_cached() -> Result<(), Box<dyn Error>> {
+    let inner = CountingBackend::default();
+    let reads = Arc::clone(&inner.reads);
+    inner.inner.clone().write("stored").await?;
+    let mut backend = CachingBackend::new(inner, Duration::from_secs(60));
+
+    assert_eq!(backend.read().await?, "stored");
+    assert_eq!(backend.read().await?, "stored");
+    assert_eq!(reads.load(Ordering::SeqCst), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_expired_value_is_read_again() -> Result<(), Box<dyn Error>> {
+    let inner = CountingBackend::default();
+    let reads = Arc::clone(&inner.reads);
+    let mut backend = CachingBackend::new(inner, Duration::from_millis(50));
+
+    backend.read().await?;
+    tokio::time::sleep(Duration::from_millis(80)).await;
+    assert!(!backend.is_cached());
+    backend.read().await?;
+    assert_eq!(reads.load(Ordering::SeqCst), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_write_updates_cache_and_inner() -> Result<(), Box<dyn Error>> {
+    let inner = CountingBackend::default();
+    let reads = Arc::clone(&inner.reads);
+    let mut storage = inner.inner.clone();
+    let mut backend = CachingBackend::new(inner, Duration::from_secs(60));
+
+    backend.write("old").await?;
+    backend.write("new").await?;
+    assert_eq!(backend.read().await?, "new");
+    assert_eq!(storage.read().await?, "new");
+    // The read was served from what was just written
+    assert_eq!(reads.load(Ordering::SeqCst), 0);
+    Ok(())
+}
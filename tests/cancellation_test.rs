@@ -0,0 +1,74 @@
+use astra::actor_system::{Actor, ActorSystem, Message};
+use astra::backends::memory::MemoryBackend;
+use astra::snapshot_actor::SnapshotActor;
+use async_trait::async_trait;
+use futures::stream;
+use std::error::Error;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::time::{timeout, Duration};
+use tokio_util::sync::CancellationToken;
+
+struct Idle {
+    cleanups: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl Actor for Idle {
+    type Message = String;
+    type Error = String;
+
+    async fn receive(&mut self, _message: Message<Self::Message>) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    async fn cleanup(&mut self) {
+        self.cleanups.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[tokio::test]
+async fn test_cancelling_parent_token_stops_everything() -> Result<(), Box<dyn Error>> {
+    let app = CancellationToken::new();
+    let cleanups = Arc::new(AtomicUsize::new(0));
+    let mut system = ActorSystem::with_cancellation_token(app.clone());
+    for name in ["first", "second"] {
+        system.add_actor(
+            name.to_string(),
+            Idle {
+                cleanups: Arc::clone(&cleanups),
+            },
+        );
+    }
+    let pipe = system.pipe_stream("first", stream::pending())?;
+
+    let mut snapshot: SnapshotActor<_, String> =
+        SnapshotActor::new("snap".to_string(), MemoryBackend::new())
+            .with_cancellation_token(&system.cancellation_token());
+    let snapshot_task = tokio::spawn(async move { snapshot.start_snapshot_task().await });
+
+    assert_eq!(system.running_tasks(), 3);
+    app.cancel();
+
+    timeout(Duration::from_secs(5), system.wait_until_stopped()).await?;
+    timeout(Duration::from_secs(5), snapshot_task).await??;
+    assert_eq!(system.running_tasks(), 0);
+    assert_eq!(cleanups.load(Ordering::SeqCst), 2);
+    assert!(pipe.is_finished());
+    // Stopped actors no longer accept messages
+    assert!(system
+        .send_message("first", "late".to_string())
+        .await
+        .is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_system_token_does_not_cancel_parent() {
+    let app = CancellationToken::new();
+    let system: ActorSystem<String> = ActorSystem::with_cancellation_token(app.clone());
+
+    system.cancellation_token().cancel();
+    system.wait_until_stopped().await;
+    assert!(!app.is_cancelled());
+}
@@ -0,0 +1,68 @@
+use astra::actor_system::{ActorSystem, Checkpoint, CheckpointToken};
+use astra::backends::buffered::BufferedBackend;
+use astra::backends::memory::MemoryBackend;
+use astra::backends::storage::StorageBackend;
+use astra::data_actor::DataActor;
+use async_trait::async_trait;
+use std::error::Error;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+type BufferedActor = Arc<Mutex<DataActor<BufferedBackend<MemoryBackend>>>>;
+
+// A DataActor whose writes stay buffered until the next checkpoint
+async fn buffered_actor(data: &str) -> Result<(BufferedActor, MemoryBackend), Box<dyn Error>> {
+    let storage = MemoryBackend::new();
+    let mut actor = DataActor::new(BufferedBackend::new(storage.clone(), 100));
+    actor.write_to_backend(data).await?;
+    Ok((Arc::new(Mutex::new(actor)), storage))
+}
+
+struct FailingParticipant;
+
+#[async_trait]
+impl Checkpoint for FailingParticipant {
+    async fn prepare_checkpoint(&mut self, _checkpoint_id: u64) -> Result<CheckpointToken, String> {
+        Err("disk full".to_string())
+    }
+
+    async fn commit_checkpoint(&mut self, _token: CheckpointToken) -> Result<(), String> {
+        panic!("commit must not be reached after a failed prepare");
+    }
+}
+
+#[tokio::test]
+async fn test_checkpoint_flushes_every_data_actor() -> Result<(), Box<dyn Error>> {
+    let (first, mut first_storage) = buffered_actor("first").await?;
+    let (second, mut second_storage) = buffered_actor("second").await?;
+    assert_eq!(first_storage.read().await?, "");
+
+    let mut system: ActorSystem<String> = ActorSystem::new();
+    system.add_checkpoint_participant("first", Arc::clone(&first));
+    system.add_checkpoint_participant("second", Arc::clone(&second));
+
+    let checkpoint = system.checkpoint_all().await?;
+
+    assert_eq!(first_storage.read().await?, "first");
+    assert_eq!(second_storage.read().await?, "second");
+    assert_eq!(first.lock().await.last_checkpoint(), Some(checkpoint));
+    assert_eq!(second.lock().await.last_checkpoint(), Some(checkpoint));
+
+    // Each checkpoint gets a new id
+    assert!(system.checkpoint_all().await? > checkpoint);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_failed_prepare_aborts_checkpoint() -> Result<(), Box<dyn Error>> {
+    let (actor, _storage) = buffered_actor("data").await?;
+
+    let mut system: ActorSystem<String> = ActorSystem::new();
+    system.add_checkpoint_participant("actor", Arc::clone(&actor));
+    system.add_checkpoint_participant("failing", Arc::new(Mutex::new(FailingParticipant)));
+
+    let err = system.checkpoint_all().await.unwrap_err();
+    assert!(err.contains("failing failed to prepare: disk full"));
+    assert_eq!(actor.lock().await.last_checkpoint(), None);
+    Ok(())
+}
@@ -0,0 +1,141 @@
+use astra::actor_system::{Actor, ActorOptions, ActorSystem, Context, Message};
+use astra::supervision::{SupervisionEventKind, SupervisionStrategy, Supervisor};
+use async_trait::async_trait;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use tokio::time::{timeout, Duration};
+
+// Hangs on "stuck", and records its own cleanup
+struct Child {
+    name: &'static str,
+    stopped: Arc<Mutex<Vec<String>>>,
+}
+
+#[async_trait]
+impl Actor for Child {
+    type Message = String;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error> {
+        if let Message::Regular(msg) = message {
+            if msg == "stuck" {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+            }
+        }
+        Ok(())
+    }
+
+    async fn cleanup(&mut self) {
+        self.stopped.lock().unwrap().push(self.name.to_string());
+    }
+}
+
+// Spawns its children on "spawn" and forwards "stuck" to the first one
+struct Parent {
+    stopped: Arc<Mutex<Vec<String>>>,
+    children: Arc<Mutex<Vec<String>>>,
+}
+
+#[async_trait]
+impl Actor for Parent {
+    type Message = String;
+    type Error = String;
+
+    async fn receive(&mut self, _message: Message<Self::Message>) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    async fn receive_with_context(
+        &mut self,
+        context: &Context<String>,
+        message: Message<String>,
+    ) -> Result<(), String> {
+        match message {
+            Message::Regular(msg) if msg == "spawn" => {
+                for name in ["first", "second"] {
+                    let child = Child {
+                        name,
+                        stopped: Arc::clone(&self.stopped),
+                    };
+                    let options =
+                        ActorOptions::new().with_receive_timeout(Duration::from_millis(50));
+                    context.spawn_child_with_options(name, child, options);
+                }
+                *self.children.lock().unwrap() = context.children();
+            }
+            Message::Regular(msg) => {
+                let first = context.child("first").ok_or("no child")?;
+                first.send(msg).await?;
+            }
+            Message::Shutdown | Message::System(_) => {}
+        }
+        Ok(())
+    }
+
+    async fn cleanup(&mut self) {
+        self.stopped.lock().unwrap().push("parent".to_string());
+    }
+}
+
+// Names recorded by the actors, in order
+type Log = Arc<Mutex<Vec<String>>>;
+
+fn parent_system(supervisor: Option<Arc<Supervisor>>) -> (ActorSystem<String>, Log, Log) {
+    let stopped = Arc::new(Mutex::new(Vec::new()));
+    let children = Arc::new(Mutex::new(Vec::new()));
+    let mut system = ActorSystem::new();
+    if let Some(supervisor) = supervisor {
+        system = system.with_supervisor(supervisor);
+    }
+    system.add_actor(
+        "parent".to_string(),
+        Parent {
+            stopped: Arc::clone(&stopped),
+            children: Arc::clone(&children),
+        },
+    );
+    (system, stopped, children)
+}
+
+#[tokio::test]
+async fn test_stopping_the_parent_stops_its_children() -> Result<(), Box<dyn Error>> {
+    let (system, stopped, children) = parent_system(None);
+    system.send_message("parent", "spawn".to_string()).await?;
+    system.shutdown().await;
+
+    assert_eq!(*children.lock().unwrap(), ["parent/first", "parent/second"]);
+    // The children stop first, the last spawned first, before the parent cleans up
+    assert_eq!(*stopped.lock().unwrap(), ["second", "first", "parent"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cancelling_the_system_stops_children() -> Result<(), Box<dyn Error>> {
+    let (system, stopped, _) = parent_system(None);
+    system.send_message("parent", "spawn".to_string()).await?;
+    system.quiesce();
+    system.wait_quiesced().await;
+
+    system.cancellation_token().cancel();
+    timeout(Duration::from_secs(5), system.wait_until_stopped()).await?;
+    let mut stopped = stopped.lock().unwrap().clone();
+    stopped.sort();
+    assert_eq!(stopped, ["first", "parent", "second"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_child_failures_go_to_the_parents_supervisor() -> Result<(), Box<dyn Error>> {
+    let supervisor = Arc::new(Supervisor::new(SupervisionStrategy::Restart));
+    let mut events = supervisor.events();
+    let (system, _, _) = parent_system(Some(supervisor));
+    system.send_message("parent", "spawn".to_string()).await?;
+    system.send_message("parent", "stuck".to_string()).await?;
+
+    let failed = timeout(Duration::from_secs(5), events.recv()).await??;
+    assert_eq!(failed.kind, SupervisionEventKind::Failed);
+    assert_eq!(failed.actor, "parent/first");
+    assert!(failed.error.contains("timed out"), "{}", failed.error);
+    system.shutdown().await;
+    Ok(())
+}
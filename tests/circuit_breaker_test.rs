@@ -0,0 +1,147 @@
+use astra::backends::storage::StorageBackend;
+use astra::circuit_breaker::{BreakerState, CircuitBreaker, CircuitOpenError};
+use astra::clock::MockClock;
+use astra::network::http::CommunicationProtocol;
+use async_trait::async_trait;
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+const COOLDOWN: Duration = Duration::from_secs(30);
+
+// Counts the calls that reach it and fails them while `down` is set
+#[derive(Clone, Default)]
+struct Flaky {
+    calls: Arc<AtomicUsize>,
+    down: Arc<AtomicBool>,
+}
+
+impl Flaky {
+    fn call(&self) -> Result<(), String> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        if self.down.load(Ordering::SeqCst) {
+            return Err("storage unavailable".to_string());
+        }
+        Ok(())
+    }
+
+    fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl StorageBackend for Flaky {
+    async fn write(&mut self, _data: &str) -> Result<(), Box<dyn Error>> {
+        Ok(self.call()?)
+    }
+
+    async fn read(&mut self) -> Result<String, Box<dyn Error>> {
+        self.call()?;
+        Ok(String::new())
+    }
+
+    async fn cleanup(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(self.call()?)
+    }
+}
+
+#[async_trait]
+impl CommunicationProtocol for Flaky {
+    async fn send_message(&self, _address: &str, _message: &str) -> Result<(), String> {
+        self.call()
+    }
+}
+
+fn breaker(inner: &Flaky, clock: &MockClock) -> CircuitBreaker<Flaky> {
+    CircuitBreaker::new(inner.clone(), 3, COOLDOWN).with_clock(Arc::new(clock.clone()))
+}
+
+#[tokio::test]
+async fn test_trips_open_and_fails_fast() {
+    let flaky = Flaky::default();
+    let clock = MockClock::new();
+    let mut backend = breaker(&flaky, &clock);
+    flaky.down.store(true, Ordering::SeqCst);
+
+    for _ in 0..3 {
+        assert!(backend.write("data").await.is_err());
+    }
+    assert_eq!(flaky.calls(), 3);
+    assert_eq!(backend.state(), BreakerState::Open);
+
+    // Open: nothing reaches the inner backend
+    for _ in 0..10 {
+        let error = backend.read().await.unwrap_err();
+        assert!(error.downcast_ref::<CircuitOpenError>().is_some());
+    }
+    assert_eq!(flaky.calls(), 3);
+    assert_eq!(backend.consecutive_failures(), 3);
+}
+
+#[tokio::test]
+async fn test_half_opens_after_cooldown() -> Result<(), Box<dyn Error>> {
+    let flaky = Flaky::default();
+    let clock = MockClock::new();
+    let mut backend = breaker(&flaky, &clock);
+    flaky.down.store(true, Ordering::SeqCst);
+    for _ in 0..3 {
+        assert!(backend.write("data").await.is_err());
+    }
+
+    // A failed trial opens the breaker for another cooldown
+    clock.advance(COOLDOWN);
+    assert_eq!(backend.state(), BreakerState::HalfOpen);
+    assert!(backend.write("data").await.is_err());
+    assert_eq!(flaky.calls(), 4);
+    assert_eq!(backend.state(), BreakerState::Open);
+    assert!(backend.write("data").await.is_err());
+    assert_eq!(flaky.calls(), 4);
+
+    // A successful one closes it
+    flaky.down.store(false, Ordering::SeqCst);
+    clock.advance(COOLDOWN);
+    backend.write("data").await?;
+    assert_eq!(backend.state(), BreakerState::Closed);
+    assert_eq!(backend.consecutive_failures(), 0);
+    backend.write("data").await?;
+    assert_eq!(flaky.calls(), 6);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_success_resets_the_failure_count() -> Result<(), Box<dyn Error>> {
+    let flaky = Flaky::default();
+    let clock = MockClock::new();
+    let mut backend = breaker(&flaky, &clock);
+
+    for _ in 0..5 {
+        flaky.down.store(true, Ordering::SeqCst);
+        assert!(backend.write("data").await.is_err());
+        assert!(backend.write("data").await.is_err());
+        flaky.down.store(false, Ordering::SeqCst);
+        backend.write("data").await?;
+    }
+    assert_eq!(backend.state(), BreakerState::Closed);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_protocol_breaker_fails_fast() {
+    let flaky = Flaky::default();
+    let clock = MockClock::new();
+    let protocol = breaker(&flaky, &clock);
+    flaky.down.store(true, Ordering::SeqCst);
+
+    for _ in 0..3 {
+        assert!(protocol.send_message("node-1", "hello").await.is_err());
+    }
+    let error = protocol.send_message("node-1", "hello").await.unwrap_err();
+    assert_eq!(error, "Circuit breaker is open");
+    assert_eq!(flaky.calls(), 3);
+
+    // Clones share the breaker
+    let clone = protocol.clone();
+    assert_eq!(clone.state(), BreakerState::Open);
+}
@@ -0,0 +1,92 @@
+use astra::backends::memory::MemoryBackend;
+use astra::backends::storage::StorageBackend;
+use astra::clock::{Clock, MockClock};
+use astra::snapshot_actor::SnapshotActor;
+use async_trait::async_trait;
+use std::error::Error;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+// Counts the writes reaching the wrapped backend
+#[derive(Clone, Default)]
+struct CountingBackend {
+    inner: MemoryBackend,
+    writes: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl StorageBackend for CountingBackend {
+    async fn write(&mut self, data: &str) -> Result<(), Box<dyn Error>> {
+        self.writes.fetch_add(1, Ordering::SeqCst);
+        self.inner.write(data).await
+    }
+
+    async fn read(&mut self) -> Result<String, Box<dyn Error>> {
+        self.inner.read().await
+    }
+
+    async fn cleanup(&mut self) -> Result<(), Box<dyn Error>> {
+        self.inner.cleanup().await
+    }
+
+    async fn write_bytes(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        self.writes.fetch_add(1, Ordering::SeqCst);
+        self.inner.write_bytes(data).await
+    }
+}
+
+#[tokio::test]
+async fn test_mock_clock_wakes_sleepers_when_advanced() {
+    let clock = MockClock::new();
+    let start = clock.now();
+    let sleeper = {
+        let clock = clock.clone();
+        tokio::spawn(async move { clock.sleep(Duration::from_secs(10)).await })
+    };
+
+    clock.wait_for_sleepers(1).await;
+    clock.advance(Duration::from_secs(9));
+    assert_eq!(clock.sleepers(), 1);
+    assert!(!sleeper.is_finished());
+
+    clock.advance(Duration::from_secs(1));
+    sleeper.await.unwrap();
+    assert_eq!(clock.now() - start, Duration::from_secs(10));
+}
+
+#[tokio::test]
+async fn test_snapshot_task_saves_once_per_tick() -> Result<(), Box<dyn Error>> {
+    let clock = MockClock::new();
+    let backend = CountingBackend::default();
+    let writes = Arc::clone(&backend.writes);
+    let mut actor = SnapshotActor::new("actor1".to_string(), backend)
+        .with_snapshot_interval(Duration::from_secs(60))
+        .with_clock(Arc::new(clock.clone()));
+    actor.set_state("state".to_string());
+
+    let mut actor_clone = actor.clone();
+    let snapshot_task = tokio::spawn(async move {
+        actor_clone.start_snapshot_task().await;
+    });
+
+    // The first tick fires immediately, as with tokio's interval
+    clock.wait_for_sleepers(1).await;
+    assert_eq!(writes.load(Ordering::SeqCst), 1);
+
+    // Three more intervals pass without waiting on real time
+    for _ in 0..3 {
+        clock.advance(Duration::from_secs(60));
+        clock.wait_for_sleepers(1).await;
+    }
+    assert_eq!(writes.load(Ordering::SeqCst), 4);
+
+    // Less than an interval does not trigger a save
+    clock.advance(Duration::from_secs(59));
+    tokio::task::yield_now().await;
+    assert_eq!(writes.load(Ordering::SeqCst), 4);
+
+    actor.shutdown();
+    snapshot_task.await?;
+    Ok(())
+}
@@ -0,0 +1,99 @@
+// tests/common/mod.rs
+
+// Helpers shared by the integration tests. Each test crate uses only some of
+// them, hence the `dead_code` allowances.
+
+use astra::actor_system::{Actor, ActorOptions, ActorSystem, Message};
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::{mpsc, Semaphore};
+
+// Forwards every regular message it receives to `seen`
+pub struct Recorder {
+    pub seen: mpsc::UnboundedSender<String>,
+}
+
+#[async_trait]
+impl Actor for Recorder {
+    type Message = String;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<String>) -> Result<(), String> {
+        if let Message::Regular(msg) = message {
+            let _ = self.seen.send(msg);
+        }
+        Ok(())
+    }
+}
+
+// A system with a single `Recorder` named `name`, and what it records
+#[allow(dead_code)]
+pub fn recording_system(name: &str) -> (ActorSystem<String>, mpsc::UnboundedReceiver<String>) {
+    let (seen_tx, seen_rx) = mpsc::unbounded_channel();
+    let mut system = ActorSystem::new();
+    system.add_actor(name.to_string(), Recorder { seen: seen_tx });
+    (system, seen_rx)
+}
+
+// Handles each regular message only once the test hands out a permit on
+// `gate`, then passes it on to `seen`; counts its cleanups
+pub struct Gated<T = String> {
+    pub gate: Arc<Semaphore>,
+    pub seen: mpsc::UnboundedSender<T>,
+    pub cleaned_up: Arc<AtomicUsize>,
+}
+
+#[allow(dead_code)]
+impl<T> Gated<T> {
+    // A gated actor waiting on `gate`, and what it handles
+    pub fn new(gate: &Arc<Semaphore>) -> (Self, mpsc::UnboundedReceiver<T>) {
+        let (seen, seen_rx) = mpsc::unbounded_channel();
+        let gated = Gated {
+            gate: Arc::clone(gate),
+            seen,
+            cleaned_up: Arc::new(AtomicUsize::new(0)),
+        };
+        (gated, seen_rx)
+    }
+}
+
+#[async_trait]
+impl<T: std::fmt::Debug + Send + 'static> Actor for Gated<T> {
+    type Message = T;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<T>) -> Result<(), String> {
+        if let Message::Regular(msg) = message {
+            self.gate.acquire().await.unwrap().forget();
+            let _ = self.seen.send(msg);
+        }
+        Ok(())
+    }
+
+    async fn cleanup(&mut self) {
+        self.cleaned_up.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+// A system with a single `Gated` actor named "worker", and what it handles
+#[allow(dead_code)]
+pub fn gated_system<T: std::fmt::Debug + Send + 'static>(
+    options: ActorOptions,
+    gate: &Arc<Semaphore>,
+) -> (ActorSystem<T>, mpsc::UnboundedReceiver<T>) {
+    let (gated, seen) = Gated::new(gate);
+    let mut system = ActorSystem::new();
+    system.add_actor_with_options("worker".to_string(), gated, options);
+    (system, seen)
+}
+
+// The messages handled so far, without waiting for more
+#[allow(dead_code)]
+pub fn handled_so_far<T>(seen: &mut mpsc::UnboundedReceiver<T>) -> Vec<T> {
+    let mut handled = Vec::new();
+    while let Ok(message) = seen.try_recv() {
+        handled.push(message);
+    }
+    handled
+}
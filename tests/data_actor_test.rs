@@ -1,6 +1,14 @@
+use astra::actor_system::{Actor, Message};
+use astra::backends::blocking::BlockingBackend;
 use astra::backends::file::FileBackend;
-use astra::data_actor::DataActor;
+use astra::backends::memory::MemoryBackend;
+use astra::backends::metered::{BackendOperation, MeteredBackend};
+use astra::backends::storage::StorageBackend;
+use astra::data_actor::{DataActor, MissingPolicy};
+use astra::snapshot_actor::SnapshotActor;
 use std::error::Error;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
 
 #[tokio::test]
 async fn test_data_actor() -> Result<(), Box<dyn Error>> {
@@ -24,3 +32,185 @@ async fn test_data_actor() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
+
+// Two updaters each add 200 to a counter in `backend`
+async fn updaters_lose_nothing<B>(backend: B) -> Result<(), Box<dyn Error>>
+where
+    B: StorageBackend + Clone + Send + 'static,
+{
+    let mut actor = DataActor::new(backend.clone());
+    actor.write_to_backend("0").await?;
+
+    let mut updaters = Vec::new();
+    for _ in 0..2 {
+        let mut updater = DataActor::new(backend.clone());
+        updaters.push(tokio::spawn(async move {
+            for _ in 0..200 {
+                updater
+                    .update(|current| (current.parse::<u32>().unwrap() + 1).to_string())
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+            Ok::<(), String>(())
+        }));
+    }
+    for updater in updaters {
+        updater.await??;
+    }
+
+    assert_eq!(actor.read_from_backend().await?, "400");
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_concurrent_updates_lose_nothing() -> Result<(), Box<dyn Error>> {
+    updaters_lose_nothing(MemoryBackend::new()).await
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_concurrent_updates_of_a_file_lose_nothing() -> Result<(), Box<dyn Error>> {
+    let path = std::env::temp_dir().join(format!("astra_{}_counter.txt", std::process::id()));
+    let mut backend = FileBackend::new(path.to_str().unwrap()).await?;
+    updaters_lose_nothing(backend.clone()).await?;
+    backend.cleanup().await
+}
+
+#[tokio::test]
+async fn test_missing_data_reads_as_empty_by_default() -> Result<(), Box<dyn Error>> {
+    let mut actor = DataActor::new(MemoryBackend::new());
+    assert_eq!(actor.read_from_backend().await?, "");
+
+    // A file that was removed reads the same as one never written
+    let mut actor = DataActor::new(FileBackend::new("missing_empty.txt").await?);
+    actor.cleanup_backend().await?;
+    assert_eq!(actor.read_from_backend().await?, "");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_missing_policy_error_fails_fast() -> Result<(), Box<dyn Error>> {
+    let mut actor = DataActor::new(MemoryBackend::new()).with_missing_policy(MissingPolicy::Error);
+    assert!(actor.read_from_backend().await.is_err());
+    assert!(actor.read_bytes_from_backend().await.is_err());
+
+    let mut actor = DataActor::new(FileBackend::new("missing_error.txt").await?)
+        .with_missing_policy(MissingPolicy::Error);
+    actor.cleanup_backend().await?;
+    assert!(actor.read_from_backend().await.is_err());
+
+    // Data that is there reads as usual
+    actor.write_to_backend("present").await?;
+    assert_eq!(actor.read_from_backend().await?, "present");
+    actor.cleanup_backend().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_missing_policy_default_supplies_a_value() -> Result<(), Box<dyn Error>> {
+    let policy = MissingPolicy::Default("count=0".to_string());
+    let mut actor = DataActor::new(MemoryBackend::new()).with_missing_policy(policy);
+    assert_eq!(actor.read_from_backend().await?, "count=0");
+    assert_eq!(actor.read_bytes_from_backend().await?, b"count=0");
+
+    actor.write_to_backend("count=5").await?;
+    assert_eq!(actor.read_from_backend().await?, "count=5");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_snapshot_actor_can_refuse_to_start_empty() -> Result<(), Box<dyn Error>> {
+    let mut actor: SnapshotActor<_, String> =
+        SnapshotActor::new("fresh".to_string(), MemoryBackend::new());
+    actor.load_state().await?;
+
+    let mut actor: SnapshotActor<_, String> =
+        SnapshotActor::new("fresh".to_string(), MemoryBackend::new())
+            .with_missing_policy(MissingPolicy::Error);
+    assert!(actor.load_state().await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_slow_write_raises_backpressure_until_it_completes() -> Result<(), Box<dyn Error>> {
+    // Each write blocks until released
+    let (release, released) = mpsc::channel::<()>();
+    let released = Arc::new(Mutex::new(released));
+    let backend = BlockingBackend::new(
+        || Ok(Vec::new()),
+        move |_: &[u8]| {
+            released.lock().unwrap().recv()?;
+            Ok(())
+        },
+        || Ok(()),
+    );
+    let mut actor = DataActor::new(backend).with_backpressure(Duration::from_millis(20));
+    let pressure = actor.backpressure().unwrap();
+    assert!(!pressure.is_high());
+
+    let writer = tokio::spawn(async move {
+        let written = actor.receive(Message::Regular("slow".to_string())).await;
+        written.is_ok()
+    });
+    tokio::time::timeout(Duration::from_secs(1), async {
+        while !pressure.is_high() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await?;
+
+    // The producer holds off while the write is stuck
+    let mut waiting = pressure.clone();
+    let producer = tokio::spawn(async move { waiting.eased().await });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!producer.is_finished());
+
+    release.send(())?;
+    tokio::time::timeout(Duration::from_secs(1), producer).await??;
+    assert!(writer.await?);
+    assert!(!pressure.is_high());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_coalescing_merges_a_burst_of_writes() -> Result<(), Box<dyn Error>> {
+    let backend = MeteredBackend::new(MemoryBackend::new());
+    let writes = |backend: &MeteredBackend<MemoryBackend>| {
+        backend.operation_stats(BackendOperation::Write).count
+    };
+    let mut actor = DataActor::new(backend.clone()).with_coalescing(Duration::from_millis(200));
+
+    for n in 1..=100 {
+        actor
+            .receive(Message::Regular(format!("value {}", n)))
+            .await?;
+    }
+    assert!(writes(&backend) < 10, "{} writes", writes(&backend));
+
+    // The idle actor still writes the window out once it closes
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    assert_eq!(backend.clone().read().await?, "value 100");
+    assert!(writes(&backend) < 10, "{} writes", writes(&backend));
+
+    // A read sees a write still waiting in its window
+    actor.write_to_backend("latest").await?;
+    assert_eq!(actor.read_from_backend().await?, "latest");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_coalesced_write_is_flushed_on_shutdown() -> Result<(), Box<dyn Error>> {
+    let backend = MeteredBackend::new(MemoryBackend::new());
+    let mut actor = DataActor::new(backend.clone()).with_coalescing(Duration::from_secs(60));
+    actor.write_to_backend("pending").await?;
+    assert_eq!(backend.operation_stats(BackendOperation::Write).count, 0);
+
+    actor.flush_backend().await?;
+    assert_eq!(backend.clone().read().await?, "pending");
+
+    // Shutdown writes the window out before cleaning up; the metering counts
+    // the earlier write and flush as two writes already
+    actor.write_to_backend("final").await?;
+    actor.receive(Message::Shutdown).await?;
+    assert_eq!(backend.operation_stats(BackendOperation::Write).count, 3);
+    Ok(())
+}
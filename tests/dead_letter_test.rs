@@ -0,0 +1,86 @@
+mod common;
+
+use astra::actor_system::{ActorSystem, ReprocessReport};
+use astra::backends::memory::MemoryBackend;
+use astra::backends::storage::StorageBackend;
+use common::Recorder;
+use std::error::Error;
+use tokio::sync::mpsc;
+
+#[tokio::test]
+async fn test_reprocess_delivers_to_late_actor() -> Result<(), Box<dyn Error>> {
+    let mut system = ActorSystem::new().with_dead_letters(10);
+
+    // Neither actor exists yet, so both messages end up as dead letters
+    assert!(system
+        .send_message("late", "hello".to_string())
+        .await
+        .is_err());
+    assert!(system
+        .send_message("never", "lost".to_string())
+        .await
+        .is_err());
+    let letters = system.dead_letters();
+    assert_eq!(letters.len(), 2);
+    assert_eq!(letters[0].target, "late");
+    assert_eq!(letters[0].message, "hello");
+
+    let (seen_tx, mut seen_rx) = mpsc::unbounded_channel();
+    system.add_actor("late".to_string(), Recorder { seen: seen_tx });
+
+    let report = system.reprocess_dead_letters().await?;
+    assert_eq!(
+        report,
+        ReprocessReport {
+            redelivered: 1,
+            still_failing: 1
+        }
+    );
+    assert_eq!(seen_rx.recv().await.unwrap(), "hello");
+
+    // The message for the missing actor stays queued
+    let letters = system.dead_letters();
+    assert_eq!(letters.len(), 1);
+    assert_eq!(letters[0].target, "never");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_reprocess_requires_dead_letter_queue() {
+    let system: ActorSystem<String> = ActorSystem::new();
+    assert!(system
+        .send_message("missing", "hi".to_string())
+        .await
+        .is_err());
+    assert_eq!(system.dead_letter_count(), 0);
+    assert!(system.reprocess_dead_letters().await.is_err());
+}
+
+#[tokio::test]
+async fn test_persisted_dead_letters_survive_a_restart() -> Result<(), Box<dyn Error>> {
+    let mut backend = MemoryBackend::new();
+    let system: ActorSystem<String> =
+        ActorSystem::new().with_persistent_dead_letters(backend.clone(), 10);
+    assert!(system
+        .send_message("late", "hello".to_string())
+        .await
+        .is_err());
+    system.shutdown().await;
+
+    // The restarted process finds the dead letter in the store
+    let mut restarted = ActorSystem::new().with_persistent_dead_letters(backend.clone(), 10);
+    assert_eq!(restarted.dead_letter_count(), 0);
+    assert_eq!(restarted.load_dead_letters().await?, 1);
+    let letters = restarted.dead_letters();
+    assert_eq!(letters[0].target, "late");
+    assert_eq!(letters[0].message, "hello");
+
+    let (seen_tx, mut seen_rx) = mpsc::unbounded_channel();
+    restarted.add_actor("late".to_string(), Recorder { seen: seen_tx });
+    let report = restarted.reprocess_dead_letters().await?;
+    assert_eq!(report.redelivered, 1);
+    assert_eq!(seen_rx.recv().await.unwrap(), "hello");
+    assert_eq!(backend.read().await?, "[]");
+    restarted.shutdown().await;
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use astra::actor_system::{Actor, ActorSystem, Message};
+use async_trait::async_trait;
+use std::error::Error;
+use tokio::sync::mpsc;
+
+// Records messages, failing the first time it sees "flaky"
+struct Recorder {
+    seen: mpsc::UnboundedSender<String>,
+    failed_once: bool,
+}
+
+#[async_trait]
+impl Actor for Recorder {
+    type Message = String;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error> {
+        if let Message::Regular(msg) = message {
+            if msg == "flaky" && !self.failed_once {
+                self.failed_once = true;
+                return Err("flaky failed".to_string());
+            }
+            let _ = self.seen.send(msg);
+        }
+        Ok(())
+    }
+}
+
+async fn handled(messages: &[&str]) -> Result<Vec<String>, Box<dyn Error>> {
+    let (seen_tx, mut seen) = mpsc::unbounded_channel();
+    let mut system = ActorSystem::new();
+    system.add_dedup_actor(
+        "recorder".to_string(),
+        Recorder {
+            seen: seen_tx,
+            failed_once: false,
+        },
+    );
+    for message in messages {
+        system.send_message("recorder", message.to_string()).await?;
+    }
+    system.shutdown().await;
+
+    let mut handled = Vec::new();
+    while let Some(message) = seen.recv().await {
+        handled.push(message);
+    }
+    Ok(handled)
+}
+
+#[tokio::test]
+async fn test_consecutive_duplicates_are_skipped() -> Result<(), Box<dyn Error>> {
+    assert_eq!(handled(&["a", "a", "b", "a"]).await?, vec!["a", "b", "a"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_failed_message_is_retried_when_repeated() -> Result<(), Box<dyn Error>> {
+    assert_eq!(handled(&["flaky", "flaky", "flaky"]).await?, vec!["flaky"]);
+    Ok(())
+}
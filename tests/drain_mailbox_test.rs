@@ -0,0 +1,154 @@
+mod common;
+
+use astra::actor_system::{ActorOptions, ActorSystem, OverflowPolicy, SendError};
+use common::{gated_system, handled_so_far};
+use std::error::Error;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+async fn drains_queued_messages(policy: OverflowPolicy) -> Result<(), Box<dyn Error>> {
+    let permits = Arc::new(Semaphore::new(0));
+    let (system, mut handled) = gated_system(
+        ActorOptions::new()
+            .with_mailbox_capacity(8)
+            .with_overflow_policy(policy),
+        &permits,
+    );
+
+    // The actor is stuck on the first message while the others queue up
+    for n in 0..4 {
+        system.send_message("worker", n).await?;
+    }
+    let actor = system.actor_ref("worker").unwrap();
+    while actor.mailbox_depth() > 3 {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+
+    assert_eq!(system.drain_mailbox("worker"), vec![1, 2, 3]);
+    assert_eq!(actor.mailbox_depth(), 0);
+    assert!(system.drain_mailbox("worker").is_empty());
+
+    permits.add_permits(10);
+    system.ping("worker").await?;
+    assert_eq!(handled_so_far(&mut handled), vec![0]);
+
+    // Drained messages no longer count as pending
+    system.quiesce();
+    tokio::time::timeout(Duration::from_secs(5), system.wait_quiesced()).await?;
+    system.resume();
+
+    system.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_drain_removes_queued_messages_from_blocking_mailbox() -> Result<(), Box<dyn Error>> {
+    drains_queued_messages(OverflowPolicy::Block).await
+}
+
+#[tokio::test]
+async fn test_drain_removes_queued_messages_from_ring_mailbox() -> Result<(), Box<dyn Error>> {
+    drains_queued_messages(OverflowPolicy::DropOldest).await
+}
+
+#[tokio::test]
+async fn test_drain_keeps_shutdown_queued() -> Result<(), Box<dyn Error>> {
+    let permits = Arc::new(Semaphore::new(0));
+    let (system, mut handled) = gated_system(ActorOptions::new(), &permits);
+    system.send_message("worker", 0).await?;
+    system.send_message("worker", 1).await?;
+
+    // Shutdown queues behind message 1 and survives the drain
+    let shutdown = tokio::spawn({
+        let system = system.clone();
+        async move { system.shutdown().await }
+    });
+    let actor = system.actor_ref("worker").unwrap();
+    while !actor.is_closing() || actor.mailbox_depth() < 2 {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+    assert_eq!(system.drain_mailbox("worker"), vec![1]);
+
+    permits.add_permits(10);
+    tokio::time::timeout(Duration::from_secs(5), shutdown).await??;
+    assert_eq!(handled_so_far(&mut handled), vec![0]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_drain_unknown_actor_is_empty() {
+    let system = ActorSystem::<u32>::new();
+    assert!(system.drain_mailbox("nobody").is_empty());
+}
+
+async fn peeks_queued_messages(policy: OverflowPolicy) -> Result<(), Box<dyn Error>> {
+    let permits = Arc::new(Semaphore::new(0));
+    let (system, mut handled) = gated_system(
+        ActorOptions::new()
+            .with_mailbox_capacity(8)
+            .with_overflow_policy(policy),
+        &permits,
+    );
+
+    // The actor is stuck on the first message while the others queue up
+    for n in 0..4 {
+        system.send_message("worker", n).await?;
+    }
+    let actor = system.actor_ref("worker").unwrap();
+    while actor.mailbox_depth() > 3 {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+
+    assert_eq!(system.peek_mailbox("worker", 2), vec![1, 2]);
+    assert_eq!(system.peek_mailbox("worker", 10), vec![1, 2, 3]);
+    assert_eq!(system.peek_mailbox("worker", 10), vec![1, 2, 3]);
+
+    // Peeked messages are still handled, in order
+    permits.add_permits(10);
+    system.ping("worker").await?;
+    assert_eq!(handled_so_far(&mut handled), vec![0, 1, 2, 3]);
+    assert!(system.peek_mailbox("worker", 10).is_empty());
+
+    system.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_peek_copies_queued_messages_from_blocking_mailbox() -> Result<(), Box<dyn Error>> {
+    peeks_queued_messages(OverflowPolicy::Block).await
+}
+
+#[tokio::test]
+async fn test_peek_copies_queued_messages_from_ring_mailbox() -> Result<(), Box<dyn Error>> {
+    peeks_queued_messages(OverflowPolicy::DropOldest).await
+}
+
+#[tokio::test]
+async fn test_peek_leaves_a_full_mailbox_full() -> Result<(), Box<dyn Error>> {
+    let permits = Arc::new(Semaphore::new(0));
+    let (system, mut handled) =
+        gated_system(ActorOptions::new().with_mailbox_capacity(3), &permits);
+    system.send_message("worker", 0).await?;
+    let actor = system.actor_ref("worker").unwrap();
+    while actor.mailbox_depth() > 0 {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+    for n in 1..=3 {
+        system.send_message("worker", n).await?;
+    }
+
+    // The peeked messages still take up the mailbox
+    assert_eq!(system.peek_mailbox("worker", 10), vec![1, 2, 3]);
+    assert_eq!(actor.mailbox_depth(), 3);
+    assert_eq!(
+        system.try_send_message("worker", 4),
+        Err(SendError::MailboxFull("worker".to_string()))
+    );
+
+    permits.add_permits(10);
+    system.ping("worker").await?;
+    assert_eq!(handled_so_far(&mut handled), vec![0, 1, 2, 3]);
+    system.shutdown().await;
+    Ok(())
+}
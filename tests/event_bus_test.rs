@@ -0,0 +1,55 @@
+use astra::actor_system::{Actor, ActorOptions, ActorSystem, DeadLetterEvent, Message};
+use astra::supervision::{SupervisionEvent, SupervisionEventKind, SupervisionStrategy, Supervisor};
+use async_trait::async_trait;
+use std::error::Error;
+use std::sync::Arc;
+use tokio::time::{timeout, Duration};
+
+// Hangs on "stuck"
+struct Sleepy;
+
+#[async_trait]
+impl Actor for Sleepy {
+    type Message = String;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error> {
+        if let Message::Regular(msg) = message {
+            if msg == "stuck" {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_supervision_and_dead_letters_arrive_on_the_bus() -> Result<(), Box<dyn Error>> {
+    let supervisor = Arc::new(Supervisor::new(SupervisionStrategy::Ignore));
+    let mut system = ActorSystem::new().with_supervisor(supervisor);
+    let bus = system.event_bus();
+    let mut failures = bus.subscribe::<SupervisionEvent>();
+    let mut dead_letters = bus.subscribe::<DeadLetterEvent>();
+    system.add_actor_with_options(
+        "sleepy".to_string(),
+        Sleepy,
+        ActorOptions::default().with_receive_timeout(Duration::from_millis(50)),
+    );
+
+    system.send_message("sleepy", "stuck".to_string()).await?;
+    let failure = timeout(Duration::from_secs(5), failures.recv()).await??;
+    assert_eq!(failure.actor, "sleepy");
+    assert_eq!(failure.kind, SupervisionEventKind::Failed);
+
+    assert!(system
+        .send_message("ghost", "lost".to_string())
+        .await
+        .is_err());
+    let dead_letter = dead_letters.try_recv()?;
+    assert_eq!(dead_letter.target, "ghost");
+    assert_eq!(dead_letter.message, "\"lost\"");
+    assert!(dead_letters.try_recv().is_err());
+
+    system.shutdown().await;
+    Ok(())
+}
@@ -0,0 +1,80 @@
+use astra::actor_system::{Actor, ActorSystem, Federation, Message};
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+
+// Reports every message it gets along with where it runs
+struct Recorder {
+    place: &'static str,
+    seen: mpsc::UnboundedSender<(&'static str, String)>,
+}
+
+#[async_trait]
+impl Actor for Recorder {
+    type Message = String;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<String>) -> Result<(), String> {
+        if let Message::Regular(msg) = message {
+            let _ = self.seen.send((self.place, msg));
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_federation_routes_to_actors_of_each_system() -> Result<(), String> {
+    let (seen_tx, mut seen) = mpsc::unbounded_channel();
+    let recorder = |place| Recorder {
+        place,
+        seen: seen_tx.clone(),
+    };
+    let mut billing = ActorSystem::new();
+    billing.add_actor("invoices".to_string(), recorder("billing/invoices"));
+    billing.add_actor("audit".to_string(), recorder("billing/audit"));
+    let mut shipping = ActorSystem::new();
+    shipping.add_actor("parcels".to_string(), recorder("shipping/parcels"));
+    shipping.add_actor("audit".to_string(), recorder("shipping/audit"));
+
+    let mut federation = Federation::new().with_default("shipping");
+    federation.add_system("billing", billing)?;
+    federation.add_system("shipping", shipping)?;
+    assert!(federation
+        .add_system("billing", ActorSystem::new())
+        .is_err());
+    assert_eq!(
+        federation.actor_names(),
+        vec![
+            "billing/invoices",
+            "billing/audit",
+            "shipping/parcels",
+            "shipping/audit"
+        ]
+    );
+
+    federation
+        .send("billing", "invoices", "1".to_string())
+        .await?;
+    assert_eq!(seen.recv().await.unwrap(), ("billing/invoices", "1".into()));
+    federation.send("billing", "audit", "2".to_string()).await?;
+    assert_eq!(seen.recv().await.unwrap(), ("billing/audit", "2".into()));
+
+    // Unqualified names go to the default system first, then to any other
+    federation.send_to("audit", "3".to_string()).await?;
+    assert_eq!(seen.recv().await.unwrap(), ("shipping/audit", "3".into()));
+    federation.send_to("invoices", "4".to_string()).await?;
+    assert_eq!(seen.recv().await.unwrap(), ("billing/invoices", "4".into()));
+
+    assert!(federation
+        .send("returns", "audit", "5".to_string())
+        .await
+        .is_err());
+    assert!(federation
+        .send_to("couriers", "6".to_string())
+        .await
+        .is_err());
+
+    let reports = federation.shutdown().await;
+    assert_eq!(reports.len(), 2);
+    assert!(reports.iter().all(|(_, report)| report.is_clean()));
+    Ok(())
+}
@@ -0,0 +1,177 @@
+use astra::backends::boxed::BoxedBackend;
+use astra::backends::file::FileBackend;
+use astra::backends::memory::MemoryBackend;
+use astra::backends::metered::MeteredBackend;
+use astra::backends::storage::StorageBackend;
+use astra::data_actor::DataActor;
+use std::error::Error;
+use std::time::{Duration, SystemTime};
+
+const VALUE_LEN: usize = 256 * 1024;
+
+// A large value made of a single repeated character, so a torn read is easy to spot
+fn value(c: char) -> String {
+    c.to_string().repeat(VALUE_LEN)
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_readers_never_see_torn_writes() -> Result<(), Box<dyn Error>> {
+    let path = std::env::temp_dir().join(format!("astra_{}_torn_reads.txt", std::process::id()));
+    let path = path.to_str().unwrap();
+    let mut backend = FileBackend::new(path).await?;
+    // A separately created backend on the same path must share the same lock
+    let other = FileBackend::new(path).await?;
+    backend.write(&value('a')).await?;
+
+    let mut tasks = Vec::new();
+    for (mut writer, c) in [(backend.clone(), 'b'), (other, 'c')] {
+        tasks.push(tokio::spawn(async move {
+            for _ in 0..20 {
+                writer.write(&value(c)).await.unwrap();
+            }
+        }));
+    }
+    for _ in 0..4 {
+        let mut reader = backend.clone();
+        tasks.push(tokio::spawn(async move {
+            for _ in 0..50 {
+                let content = reader.read().await.unwrap();
+                assert_eq!(content.len(), VALUE_LEN, "reader saw a partial file");
+                let first = content.chars().next().unwrap();
+                assert!(content.chars().all(|c| c == first), "reader saw mixed data");
+            }
+        }));
+    }
+    for task in tasks {
+        task.await?;
+    }
+
+    backend.cleanup().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_append_mode_reads_from_offset() -> Result<(), Box<dyn Error>> {
+    let path = std::env::temp_dir().join(format!("astra_{}_append_log.txt", std::process::id()));
+    let path = path.to_str().unwrap();
+    let mut backend = FileBackend::new_append(path).await?;
+
+    let first = backend.append("record-1\n").await?;
+    let second = backend.append("record-2\n").await?;
+    let third = backend.append("record-3\n").await?;
+    assert_eq!((first, second, third), (0, 9, 18));
+
+    assert_eq!(
+        backend.read_from_offset(second).await?,
+        "record-2\nrecord-3\n"
+    );
+    // Plain writes append too, and a reopened backend keeps the existing records
+    let mut reopened = FileBackend::new_append(path).await?;
+    reopened.write("record-4\n").await?;
+    assert_eq!(backend.read_from_offset(27).await?, "record-4\n");
+    assert_eq!(backend.read_from_offset(36).await?, "");
+
+    backend.cleanup().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_read_range_returns_middle_slice() -> Result<(), Box<dyn Error>> {
+    let path = std::env::temp_dir().join(format!("astra_{}_read_range.txt", std::process::id()));
+    let path = path.to_str().unwrap();
+    let mut backend = FileBackend::new(path).await?;
+    backend.write("header|payload|trailer").await?;
+
+    assert_eq!(backend.read_range(7, 7).await?, "payload");
+    // Ranges running past the end stop there
+    assert_eq!(backend.read_range(15, 100).await?, "trailer");
+    assert_eq!(backend.read_range(100, 5).await?, "");
+
+    // The default implementation agrees with the seeking one
+    let mut memory = MemoryBackend::new();
+    memory.write("header|payload|trailer").await?;
+    assert_eq!(memory.read_range(7, 7).await?, "payload");
+    assert_eq!(memory.read_range(15, 100).await?, "trailer");
+
+    backend.cleanup().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_last_modified_is_recent_after_a_write() -> Result<(), Box<dyn Error>> {
+    let path = std::env::temp_dir().join(format!("astra_{}_last_modified.txt", std::process::id()));
+    let path = path.to_str().unwrap();
+    let mut backend = FileBackend::new(path).await?;
+    backend.write("fresh").await?;
+
+    let modified = backend.last_modified().await?.expect("file has an mtime");
+    let age = SystemTime::now()
+        .duration_since(modified)
+        .unwrap_or(Duration::ZERO);
+    assert!(age < Duration::from_secs(5), "modified {age:?} ago");
+
+    // A cleaned up file has nothing stored, so no modification time either
+    backend.cleanup().await?;
+    assert_eq!(backend.last_modified().await?, None);
+
+    let mut memory = MemoryBackend::new();
+    assert_eq!(memory.last_modified().await?, None);
+    memory.write("fresh").await?;
+    let modified = memory.last_modified().await?.expect("written");
+    assert!(SystemTime::now().duration_since(modified)? < Duration::from_secs(5));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_last_modified_goes_through_wrappers() -> Result<(), Box<dyn Error>> {
+    let mut backend = BoxedBackend::new(MeteredBackend::new(MemoryBackend::new()));
+    assert_eq!(backend.last_modified().await?, None);
+    backend.write("fresh").await?;
+    assert!(backend.last_modified().await?.is_some());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_durable_writes_round_trip() -> Result<(), Box<dyn Error>> {
+    let path = std::env::temp_dir().join(format!("astra_{}_durable.txt", std::process::id()));
+    let path = path.to_str().unwrap();
+
+    let mut backend = FileBackend::new(path).await?.with_durable(true);
+    backend.write("first").await?;
+    assert_eq!(backend.read().await?, "first");
+    backend.write_bytes(b"second").await?;
+    backend.extend_bytes(b" and more").await?;
+    assert_eq!(std::fs::read_to_string(path)?, "second and more");
+
+    let mut appender = FileBackend::new_append(path).await?.with_durable(true);
+    let offset = appender.append("!").await?;
+    assert_eq!(appender.read_from_offset(offset).await?, "!");
+
+    backend.cleanup().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_reads_over_the_size_limit_fail() -> Result<(), Box<dyn Error>> {
+    let path = std::env::temp_dir().join(format!("astra_{}_limit.txt", std::process::id()));
+    let path = path.to_str().unwrap();
+
+    let mut backend = FileBackend::new(path).await?.with_max_read_size(1024);
+    backend.write(&"a".repeat(1024)).await?;
+    assert_eq!(backend.read().await?.len(), 1024);
+
+    // One byte over is refused by every kind of read
+    backend.extend_bytes(b"b").await?;
+    assert!(backend.read().await.is_err());
+    assert!(backend.read_bytes().await.is_err());
+    assert!(backend.read_from_offset(0).await.is_err());
+    assert_eq!(backend.read_from_offset(1).await?.len(), 1024);
+
+    // The actor checks its own limit, whatever the backend's
+    let mut actor = DataActor::new(FileBackend::new_append(path).await?).with_max_read_size(1024);
+    let refused = actor.read_from_backend().await.unwrap_err();
+    assert!(refused.to_string().contains("1025 bytes"), "{}", refused);
+
+    backend.cleanup().await?;
+    Ok(())
+}
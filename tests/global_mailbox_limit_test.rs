@@ -0,0 +1,75 @@
+mod common;
+
+use astra::actor_system::{ActorSystem, SendError};
+use common::Gated;
+use std::error::Error;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+// A system with room for `limit` messages and `actors` gated actors sharing
+// the permits
+fn limited_system(limit: usize, actors: usize) -> (ActorSystem<u32>, Arc<Semaphore>) {
+    let permits = Arc::new(Semaphore::new(0));
+    let mut system = ActorSystem::new().with_global_mailbox_limit(limit);
+    for i in 0..actors {
+        let (gated, _) = Gated::new(&permits);
+        system.add_actor(format!("actor-{}", i), gated);
+    }
+    (system, permits)
+}
+
+#[tokio::test]
+async fn test_sends_are_rejected_once_the_global_limit_is_hit() -> Result<(), Box<dyn Error>> {
+    let (system, permits) = limited_system(5, 3);
+
+    // Each mailbox has room for 100 messages, but the system only for 5
+    for i in 0..5 {
+        system.try_send_message(&format!("actor-{}", i % 3), i)?;
+    }
+    assert_eq!(system.queued_messages(), 5);
+    for name in ["actor-0", "actor-1", "actor-2"] {
+        assert_eq!(
+            system.try_send_message(name, 99),
+            Err(SendError::SystemFull {
+                actor: name.to_string(),
+                limit: 5
+            })
+        );
+    }
+
+    // Handling a message makes room for another
+    permits.add_permits(1);
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while system.try_send_message("actor-2", 5).is_err() {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+    })
+    .await?;
+    assert_eq!(system.queued_messages(), 5);
+
+    permits.add_permits(100);
+    system.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_send_waits_for_room_under_the_global_limit() -> Result<(), Box<dyn Error>> {
+    let (system, permits) = limited_system(2, 2);
+    system.send_message("actor-0", 0).await?;
+    system.send_message("actor-1", 1).await?;
+
+    let waiting = tokio::spawn({
+        let system = system.clone();
+        async move { system.send_message("actor-0", 2).await }
+    });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(!waiting.is_finished());
+
+    permits.add_permits(1);
+    tokio::time::timeout(Duration::from_secs(5), waiting).await???;
+
+    permits.add_permits(100);
+    system.shutdown().await;
+    Ok(())
+}
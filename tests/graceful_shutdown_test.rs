@@ -0,0 +1,69 @@
+mod common;
+
+use astra::network::http::HttpServer;
+use astra::network::tcp::TcpServer;
+use common::recording_system;
+use hyper::{Body, Client, Request, StatusCode};
+use std::error::Error;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+#[tokio::test]
+async fn test_http_server_finishes_in_flight_request_on_shutdown() -> Result<(), Box<dyn Error>> {
+    let (system, mut seen) = recording_system("recorder");
+    let server = HttpServer::new(Arc::new(system))
+        .start("127.0.0.1:0".parse()?)
+        .await?;
+    let url = format!("http://{}/actors/recorder", server.local_addr());
+
+    // The body arrives slowly, so the request is still in flight at shutdown
+    let (mut body, streamed) = Body::channel();
+    let request = Request::post(&url).body(streamed)?;
+    let response = tokio::spawn(Client::new().request(request));
+    body.send_data("slow ".into()).await?;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let shutdown = tokio::spawn(server.shutdown());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!shutdown.is_finished());
+
+    body.send_data("message".into()).await?;
+    drop(body);
+    assert_eq!(response.await??.status(), StatusCode::ACCEPTED);
+    assert_eq!(seen.recv().await.unwrap(), "slow message");
+    tokio::time::timeout(Duration::from_secs(5), shutdown).await??;
+
+    // No new request is taken
+    assert!(Client::new()
+        .request(Request::post(&url).body(Body::from("late"))?)
+        .await
+        .is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tcp_server_finishes_frame_in_flight_on_shutdown() -> Result<(), Box<dyn Error>> {
+    let (system, mut seen) = recording_system("recorder");
+    let server = TcpServer::new(Arc::new(system))
+        .start("127.0.0.1:0".parse()?)
+        .await?;
+    let mut stream = TcpStream::connect(server.local_addr()).await?;
+
+    // Half a frame: the actor name, but not the payload yet
+    stream.write_u32(8).await?;
+    stream.write_all(b"recorder").await?;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let shutdown = tokio::spawn(server.shutdown());
+    stream.write_u32(5).await?;
+    stream.write_all(b"hello").await?;
+    assert_eq!(stream.read_u8().await?, 0);
+    assert_eq!(seen.recv().await.unwrap(), "hello");
+
+    // The connection is closed after the frame
+    assert!(stream.read_u8().await.is_err());
+    tokio::time::timeout(Duration::from_secs(5), shutdown).await??;
+    Ok(())
+}
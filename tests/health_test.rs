@@ -0,0 +1,85 @@
+#![cfg(feature = "health")]
+
+use astra::actor_system::ActorSystem;
+use astra::backends::memory::MemoryBackend;
+use astra::backends::storage::StorageBackend;
+use astra::network::health::HealthServer;
+use astra::network::registry::LocalRegistry;
+use async_trait::async_trait;
+use hyper::{Client, StatusCode};
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+// A backend whose health is switched from the test
+#[derive(Clone, Default)]
+struct FlakyBackend {
+    down: Arc<AtomicBool>,
+}
+
+#[async_trait]
+impl StorageBackend for FlakyBackend {
+    async fn write(&mut self, _data: &str) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    async fn read(&mut self) -> Result<String, Box<dyn Error>> {
+        Ok(String::new())
+    }
+
+    async fn cleanup(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    async fn health_check(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.down.load(Ordering::SeqCst) {
+            return Err("connection refused".into());
+        }
+        Ok(())
+    }
+}
+
+async fn probe(url: &str) -> Result<(StatusCode, String), Box<dyn Error>> {
+    let response = Client::new().get(url.parse()?).await?;
+    let status = response.status();
+    let body = hyper::body::to_bytes(response.into_body()).await?;
+    Ok((status, String::from_utf8(body.to_vec())?))
+}
+
+#[tokio::test]
+async fn test_readyz_follows_the_health_of_the_backends() -> Result<(), Box<dyn Error>> {
+    let system: ActorSystem<String> = ActorSystem::new();
+    let flaky = FlakyBackend::default();
+    flaky.down.store(true, Ordering::SeqCst);
+    let health = HealthServer::new(&system)
+        .with_backend("state", MemoryBackend::new())
+        .with_backend("orders", flaky.clone())
+        .with_registry(Arc::new(LocalRegistry::new()))
+        .start("127.0.0.1:0".parse()?)
+        .await?;
+    let url = |path| format!("http://{}{}", health.local_addr(), path);
+
+    // Alive, but not ready while a backend is down
+    assert_eq!(probe(&url("/healthz")).await?.0, StatusCode::OK);
+    let (status, body) = probe(&url("/readyz")).await?;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body, "backend orders: connection refused");
+
+    flaky.down.store(false, Ordering::SeqCst);
+    assert_eq!(probe(&url("/readyz")).await?.0, StatusCode::OK);
+
+    // Neither once the system shuts down
+    system.shutdown_timeout(Duration::from_secs(1)).await?;
+    assert_eq!(
+        probe(&url("/healthz")).await?.0,
+        StatusCode::SERVICE_UNAVAILABLE
+    );
+    assert_eq!(
+        probe(&url("/readyz")).await?.0,
+        StatusCode::SERVICE_UNAVAILABLE
+    );
+    assert_eq!(probe(&url("/metrics")).await?.0, StatusCode::NOT_FOUND);
+    health.stop();
+    Ok(())
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use astra::actor_system::{ActorOptions, ActorSystem};
+use astra::logging::{LogLevel, Logger};
+use async_trait::async_trait;
+use common::Gated;
+use std::error::Error;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Semaphore};
+
+// Passes every line on to the test
+struct ChannelLogger {
+    lines: mpsc::UnboundedSender<(LogLevel, String)>,
+}
+
+#[async_trait]
+impl Logger for ChannelLogger {
+    async fn log(&self, level: LogLevel, message: &str) {
+        let _ = self.lines.send((level, message.to_string()));
+    }
+}
+
+// A system with a gated actor whose mailbox warns at 80%, and its log lines
+fn logged_system(
+    permits: &Arc<Semaphore>,
+) -> (
+    ActorSystem<String>,
+    mpsc::UnboundedReceiver<(LogLevel, String)>,
+) {
+    let (lines_tx, lines) = mpsc::unbounded_channel();
+    let mut system = ActorSystem::new().with_logger(Arc::new(ChannelLogger { lines: lines_tx }));
+    system.add_actor_with_options(
+        "slow".to_string(),
+        Gated::new(permits).0,
+        ActorOptions::new()
+            .with_mailbox_capacity(5)
+            .with_high_water_mark(80),
+    );
+    (system, lines)
+}
+
+#[tokio::test]
+async fn test_warns_when_mailbox_crosses_high_water_mark() -> Result<(), Box<dyn Error>> {
+    let permits = Arc::new(Semaphore::new(0));
+    let (system, mut lines) = logged_system(&permits);
+
+    for i in 0..5 {
+        system.send_message("slow", format!("job-{}", i)).await?;
+    }
+
+    let (level, line) = tokio::time::timeout(Duration::from_secs(1), lines.recv())
+        .await?
+        .expect("a warning is logged");
+    assert_eq!(level, LogLevel::Warn);
+    assert!(line.contains("slow"), "{}", line);
+    assert!(line.contains("of 5 messages"), "{}", line);
+
+    let actor = system.actor_ref("slow").unwrap();
+    assert!(actor.mailbox_depth() >= 4);
+    assert_eq!(actor.mailbox_capacity(), 5);
+    assert_eq!(actor.high_water_crossings(), 1);
+
+    permits.add_permits(5);
+    system.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_warns_once_per_crossing() -> Result<(), Box<dyn Error>> {
+    let permits = Arc::new(Semaphore::new(0));
+    let (system, mut lines) = logged_system(&permits);
+    let actor = system.actor_ref("slow").unwrap();
+
+    // The first message is taken off the mailbox and blocks the actor
+    system.send_message("slow", "first".to_string()).await?;
+    while actor.mailbox_depth() > 0 {
+        tokio::task::yield_now().await;
+    }
+    // Three queued stay below the mark, the fourth crosses it, the fifth doesn't
+    for i in 0..5 {
+        system.try_send_message("slow", format!("job-{}", i))?;
+        assert_eq!(actor.high_water_crossings(), u64::from(i >= 3));
+    }
+
+    // Drain the mailbox, then cross again
+    permits.add_permits(6);
+    while actor.mailbox_depth() > 0 {
+        tokio::task::yield_now().await;
+    }
+    system.send_message("slow", "refill".to_string()).await?;
+    assert_eq!(actor.high_water_crossings(), 1);
+    for i in 0..4 {
+        system.send_message("slow", format!("again-{}", i)).await?;
+    }
+    assert_eq!(actor.high_water_crossings(), 2);
+
+    permits.add_permits(5);
+    system.shutdown().await;
+    system.wait_until_stopped().await;
+    let mut warnings = 0;
+    while let Ok((level, _)) = lines.try_recv() {
+        assert_eq!(level, LogLevel::Warn);
+        warnings += 1;
+    }
+    assert_eq!(warnings, 2);
+    Ok(())
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use astra::actor_system::ActorSystem;
+use astra::network::http::{
+    CommunicationProtocol, HttpCodec, HttpEnvelope, HttpProtocol, HttpServer, ENVELOPE_CONTENT_TYPE,
+};
+use common::Recorder;
+use hyper::header::CONTENT_TYPE;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Client, Request, Response, Server, StatusCode};
+use std::convert::Infallible;
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+// Answers every request with 202, handing over its content type and body
+async fn capturing_server() -> (SocketAddr, mpsc::UnboundedReceiver<(String, Vec<u8>)>) {
+    let (captured_tx, captured) = mpsc::unbounded_channel();
+    let make_svc = make_service_fn(move |_conn| {
+        let captured_tx = captured_tx.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req: Request<Body>| {
+                let captured_tx = captured_tx.clone();
+                async move {
+                    let content_type = req.headers()[CONTENT_TYPE].to_str().unwrap().to_string();
+                    let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+                    let _ = captured_tx.send((content_type, body.to_vec()));
+                    let mut response = Response::new(Body::empty());
+                    *response.status_mut() = StatusCode::ACCEPTED;
+                    Ok::<_, Infallible>(response)
+                }
+            }))
+        }
+    });
+    let server = Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(make_svc);
+    let addr = server.local_addr();
+    tokio::spawn(server);
+    (addr, captured)
+}
+
+#[tokio::test]
+async fn test_each_codec_sets_its_content_type_and_encoding() -> Result<(), Box<dyn Error>> {
+    let (addr, mut captured) = capturing_server().await;
+    let address = format!("http://{}/actors/greeter", addr);
+
+    HttpProtocol::new().send_message(&address, "hello").await?;
+    let (content_type, body) = captured.recv().await.unwrap();
+    assert_eq!(content_type, "text/plain; charset=utf-8");
+    assert_eq!(body, b"hello");
+
+    let json = HttpProtocol::new().with_codec(HttpCodec::Json);
+    json.send_message(&address, "hello").await?;
+    let (content_type, body) = captured.recv().await.unwrap();
+    assert_eq!(content_type, ENVELOPE_CONTENT_TYPE);
+    let envelope: HttpEnvelope = serde_json::from_slice(&body)?;
+    assert_eq!(envelope.actor, "greeter");
+    assert_eq!(envelope.message, "hello");
+    assert!(!envelope.correlation_id.is_empty());
+    // Every message gets its own correlation id
+    json.send_message(&address, "hello").await?;
+    let (_, body) = captured.recv().await.unwrap();
+    let second: HttpEnvelope = serde_json::from_slice(&body)?;
+    assert_ne!(second.correlation_id, envelope.correlation_id);
+
+    HttpProtocol::new()
+        .with_codec(HttpCodec::LengthPrefixed)
+        .send_message(&address, "hello")
+        .await?;
+    let (content_type, body) = captured.recv().await.unwrap();
+    assert_eq!(content_type, "application/vnd.astra.length-prefixed");
+    assert_eq!(body, b"\0\0\0\x05hello");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_server_decodes_every_codec() -> Result<(), Box<dyn Error>> {
+    let (seen_tx, mut seen) = mpsc::unbounded_channel();
+    let mut system = ActorSystem::new();
+    system.add_actor("greeter".to_string(), Recorder { seen: seen_tx });
+    let server = HttpServer::new(Arc::new(system))
+        .start("127.0.0.1:0".parse()?)
+        .await?;
+    let address = format!("http://{}/actors/greeter", server.local_addr());
+
+    for codec in [HttpCodec::Text, HttpCodec::Json, HttpCodec::LengthPrefixed] {
+        let http = HttpProtocol::new().with_codec(codec);
+        http.send_message(&address, "hello").await?;
+        assert_eq!(seen.recv().await.unwrap(), "hello", "{:?}", codec);
+    }
+
+    // An envelope must be for the actor it is posted to
+    let misaddressed = HttpEnvelope {
+        actor: "other".to_string(),
+        correlation_id: "1".to_string(),
+        message: "hello".to_string(),
+    };
+    let req = Request::post(&address)
+        .header(CONTENT_TYPE, ENVELOPE_CONTENT_TYPE)
+        .body(Body::from(serde_json::to_vec(&misaddressed)?))?;
+    let response = Client::new().request(req).await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    server.stop();
+    Ok(())
+}
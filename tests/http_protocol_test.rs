@@ -0,0 +1,28 @@
+use astra::network::http::{CommunicationProtocol, HttpProtocol, ProtocolError};
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_malformed_address_is_reported_as_invalid() {
+    let http = HttpProtocol::new();
+    for address in [
+        "not a url",
+        "127.0.0.1:1/actors/a",
+        "ftp://127.0.0.1:1/actors/a",
+    ] {
+        let err = http.send_message(address, "hello").await.unwrap_err();
+        assert_eq!(
+            err,
+            ProtocolError::InvalidAddress(address.to_string()).to_string()
+        );
+    }
+
+    let err = http
+        .ask("not a url", "hello", Duration::from_secs(1))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err,
+        ProtocolError::InvalidAddress("not a url".to_string()).to_string()
+    );
+    assert!(err.contains("\"not a url\""), "{}", err);
+}
@@ -0,0 +1,89 @@
+use astra::actor_system::{Actor, ActorOptions, ActorSystem, Message};
+use astra::network::http::HttpServer;
+use async_trait::async_trait;
+use hyper::{Body, Client, Request, StatusCode};
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Semaphore};
+
+// Signals when it starts handling a message, then blocks until released
+struct BlockingActor {
+    started: mpsc::UnboundedSender<()>,
+    release: Arc<Semaphore>,
+}
+
+#[async_trait]
+impl Actor for BlockingActor {
+    type Message = String;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error> {
+        if let Message::Regular(_) = message {
+            let _ = self.started.send(());
+            self.release.acquire().await.unwrap().forget();
+        }
+        Ok(())
+    }
+}
+
+async fn post(addr: SocketAddr, path: &str, body: &str) -> hyper::Response<Body> {
+    let req = Request::post(format!("http://{}{}", addr, path))
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    Client::new().request(req).await.unwrap()
+}
+
+#[tokio::test]
+async fn test_full_mailbox_returns_503() -> Result<(), Box<dyn Error>> {
+    let (started_tx, mut started_rx) = mpsc::unbounded_channel();
+    let release = Arc::new(Semaphore::new(0));
+
+    let mut system = ActorSystem::new();
+    system.add_actor_with_options(
+        "slow".to_string(),
+        BlockingActor {
+            started: started_tx,
+            release: Arc::clone(&release),
+        },
+        ActorOptions::new().with_mailbox_capacity(1),
+    );
+
+    let server = HttpServer::new(Arc::new(system))
+        .start("127.0.0.1:0".parse()?)
+        .await?;
+    let addr = server.local_addr();
+
+    // The actor picks up the first message and blocks on it
+    assert_eq!(
+        post(addr, "/actors/slow", "one").await.status(),
+        StatusCode::ACCEPTED
+    );
+    started_rx.recv().await.unwrap();
+
+    // The second fills the single mailbox slot, the third is turned away
+    assert_eq!(
+        post(addr, "/actors/slow", "two").await.status(),
+        StatusCode::ACCEPTED
+    );
+    let response = post(addr, "/actors/slow", "three").await;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.headers()["retry-after"], "1");
+
+    // Once the actor drains its mailbox, requests are accepted again
+    release.add_permits(2);
+    started_rx.recv().await.unwrap();
+    assert_eq!(
+        post(addr, "/actors/slow", "four").await.status(),
+        StatusCode::ACCEPTED
+    );
+
+    assert_eq!(
+        post(addr, "/actors/missing", "hi").await.status(),
+        StatusCode::NOT_FOUND
+    );
+
+    release.add_permits(1);
+    server.stop();
+    Ok(())
+}
@@ -0,0 +1,56 @@
+use astra::actor_system::{Actor, ActorSystem, IdGenerator, Message, SequentialGen, UuidGen};
+use async_trait::async_trait;
+use std::collections::HashSet;
+use std::error::Error;
+use std::sync::Arc;
+
+struct Worker;
+
+#[async_trait]
+impl Actor for Worker {
+    type Message = String;
+    type Error = String;
+
+    async fn receive(&mut self, _message: Message<Self::Message>) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_auto_named_actors_get_unique_names() -> Result<(), Box<dyn Error>> {
+    for ids in [
+        Arc::new(SequentialGen::default()) as Arc<dyn IdGenerator>,
+        Arc::new(UuidGen),
+    ] {
+        let mut system = ActorSystem::new().with_id_generator(ids);
+        let names: HashSet<String> = (0..100).map(|_| system.add_actor_auto(Worker)).collect();
+        assert_eq!(names.len(), 100);
+        assert_eq!(system.actor_names().len(), 100);
+        for name in &names {
+            system.send_message(name, "hello".to_string()).await?;
+        }
+        system.shutdown().await;
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_auto_names_skip_names_already_taken() {
+    let mut system = ActorSystem::new();
+    system.add_actor("actor-1".to_string(), Worker);
+    assert_eq!(system.add_actor_auto(Worker), "actor-2");
+    system.shutdown().await;
+}
+
+#[test]
+fn test_uuids_are_version_4() {
+    let id = UuidGen.next();
+    let groups: Vec<&str> = id.split('-').collect();
+    assert_eq!(
+        groups.iter().map(|g| g.len()).collect::<Vec<_>>(),
+        [8, 4, 4, 4, 12]
+    );
+    assert!(groups[2].starts_with('4'));
+    assert!(matches!(groups[3].as_bytes()[0], b'8' | b'9' | b'a' | b'b'));
+    assert_ne!(id, UuidGen.next());
+}
@@ -0,0 +1,49 @@
+use astra::actor_system::{Actor, ActorSystem, Debuggable, Message};
+use async_trait::async_trait;
+use std::error::Error;
+
+#[derive(Default)]
+struct Counter {
+    count: u32,
+}
+
+#[async_trait]
+impl Actor for Counter {
+    type Message = String;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error> {
+        if let Message::Regular(_) = message {
+            self.count += 1;
+        }
+        Ok(())
+    }
+}
+
+impl Debuggable for Counter {
+    fn debug_state(&self) -> String {
+        format!("count={}", self.count)
+    }
+}
+
+#[tokio::test]
+async fn test_inspect_reports_counter() -> Result<(), Box<dyn Error>> {
+    let mut system = ActorSystem::new();
+    system.add_debuggable_actor("counter".to_string(), Counter::default());
+    system.add_actor("plain".to_string(), Counter::default());
+    assert_eq!(system.inspect("counter").await.as_deref(), Some("count=0"));
+
+    for _ in 0..3 {
+        system.send_message("counter", "tick".to_string()).await?;
+    }
+    system.wait_quiesced().await;
+    assert_eq!(system.inspect("counter").await.as_deref(), Some("count=3"));
+
+    // Actors that didn't opt in, and unknown ones, have nothing to report
+    assert_eq!(system.inspect("plain").await, None);
+    assert_eq!(system.inspect("missing").await, None);
+
+    system.shutdown().await;
+    assert_eq!(system.inspect("counter").await, None);
+    Ok(())
+}
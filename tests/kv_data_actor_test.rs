@@ -0,0 +1,91 @@
+use astra::actor_system::{Actor, Message};
+use astra::backends::file::FileBackend;
+use astra::backends::memory::MemoryBackend;
+use astra::kv_data_actor::{KvDataActor, KvMessage};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Session {
+    user: String,
+    visits: u32,
+}
+
+#[tokio::test]
+async fn test_set_get_remove() -> Result<(), Box<dyn Error>> {
+    let mut actor: KvDataActor<_, Session> = KvDataActor::new(MemoryBackend::new());
+    let alice = Session {
+        user: "alice".to_string(),
+        visits: 1,
+    };
+
+    assert_eq!(actor.get("s1").await?, None);
+    assert_eq!(actor.set("s1", alice.clone()).await?, None);
+    assert_eq!(actor.get("s1").await?, Some(alice.clone()));
+
+    let again = Session {
+        visits: 2,
+        ..alice.clone()
+    };
+    assert_eq!(actor.set("s1", again.clone()).await?, Some(alice));
+    assert_eq!(actor.get("s1").await?, Some(again.clone()));
+
+    assert_eq!(actor.remove("s1").await?, Some(again));
+    assert_eq!(actor.get("s1").await?, None);
+    assert_eq!(actor.remove("s1").await?, None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_keys_and_persistence() -> Result<(), Box<dyn Error>> {
+    let path = std::env::temp_dir().join(format!("astra_{}_kv_keys.txt", std::process::id()));
+    let path = path.to_str().unwrap();
+    let mut actor: KvDataActor<_, u32> = KvDataActor::new(FileBackend::new(path).await?);
+    assert!(actor.keys().await?.is_empty());
+
+    for (key, value) in [("pears", 5), ("apples", 3), ("figs", 8)] {
+        actor.set(key, value).await?;
+    }
+    actor.remove("figs").await?;
+    assert_eq!(actor.keys().await?, vec!["apples", "pears"]);
+
+    // Changes sent as messages land in the same store
+    actor
+        .receive(Message::Regular(KvMessage::Set("plums".to_string(), 2)))
+        .await
+        .map_err(|e| e.to_string())?;
+    actor
+        .receive(Message::Regular(KvMessage::Remove("apples".to_string())))
+        .await
+        .map_err(|e| e.to_string())?;
+    assert_eq!(actor.keys().await?, vec!["pears", "plums"]);
+
+    actor.cleanup_backend().await?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_actors_sharing_a_backend_keep_each_others_keys() -> Result<(), Box<dyn Error>> {
+    let backend = MemoryBackend::new();
+    let mut tasks = Vec::new();
+    for writer in 0..2 {
+        let mut actor: KvDataActor<_, u32> = KvDataActor::new(backend.clone());
+        tasks.push(tokio::spawn(async move {
+            for i in 0..50 {
+                actor
+                    .set(format!("w{}-{:02}", writer, i), i)
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+            Ok::<_, String>(())
+        }));
+    }
+    for task in tasks {
+        task.await??;
+    }
+
+    let mut actor: KvDataActor<_, u32> = KvDataActor::new(backend);
+    assert_eq!(actor.keys().await?.len(), 100);
+    assert_eq!(actor.get("w1-49").await?, Some(49));
+    Ok(())
+}
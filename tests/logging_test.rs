@@ -0,0 +1,199 @@
+use astra::logging::{ConsoleLogger, FallbackLogger, FileLogger, LogLevel, Logger};
+use astra::test_util::CapturingLogger;
+use std::error::Error;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+const TASKS: usize = 32;
+const LINES: usize = 100;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_logging_never_interleaves() -> Result<(), Box<dyn Error>> {
+    let path = std::env::temp_dir().join(format!("astra_{}_logger.txt", std::process::id()));
+    let path = path.to_str().unwrap().to_string();
+    let _ = std::fs::remove_file(&path);
+    let logger = FileLogger::new(path.clone());
+
+    let mut tasks = Vec::new();
+    for task in 0..TASKS {
+        let logger = logger.clone();
+        tasks.push(tokio::spawn(async move {
+            // Long lines make partial writes easy to spot
+            let filler = "x".repeat(512);
+            for line in 0..LINES {
+                let message = format!("task-{} line-{} {}", task, line, filler);
+                logger.log(LogLevel::Info, &message).await;
+            }
+        }));
+    }
+    for task in tasks {
+        task.await?;
+    }
+    logger.flush().await;
+
+    let content = std::fs::read_to_string(&path)?;
+    let lines: Vec<_> = content.lines().collect();
+    assert_eq!(lines.len(), TASKS * LINES);
+    let filler = "x".repeat(512);
+    let mut next_line = vec![0; TASKS];
+    for line in lines {
+        let rest = line.strip_prefix("[Info] task-").expect("intact prefix");
+        let (task, rest) = rest.split_once(" line-").expect("intact line");
+        let (number, tail) = rest.split_once(' ').expect("intact line");
+        assert_eq!(tail, filler, "line was torn");
+        // Each task's lines appear in the order it logged them
+        let task: usize = task.parse()?;
+        assert_eq!(number.parse::<usize>()?, next_line[task]);
+        next_line[task] += 1;
+    }
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_file_logger_appends() -> Result<(), Box<dyn Error>> {
+    let path = std::env::temp_dir().join(format!("astra_{}_append_logger.txt", std::process::id()));
+    let path = path.to_str().unwrap().to_string();
+    std::fs::write(&path, "[Info] earlier run\n")?;
+
+    let logger = FileLogger::new(path.clone());
+    logger.log(LogLevel::Error, "first").await;
+    logger.log(LogLevel::Debug, "second").await;
+    logger.flush().await;
+
+    assert_eq!(
+        std::fs::read_to_string(&path)?,
+        "[Info] earlier run\n[Error] first\n[Debug] second\n"
+    );
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_unwritable_log_file_does_not_panic() -> Result<(), Box<dyn Error>> {
+    // A directory can't be opened for appending
+    let path = std::env::temp_dir().join(format!("astra_{}_log_dir", std::process::id()));
+    std::fs::create_dir_all(&path)?;
+    let logger = FileLogger::new(path.to_str().unwrap().to_string());
+
+    let logging = tokio::spawn(async move {
+        logger.log(LogLevel::Error, "lost line").await;
+        logger.flush().await;
+        logger.log(LogLevel::Info, "after the failure").await;
+        logger.flush().await;
+    });
+    // The logging task survived, and flush still returns
+    logging.await?;
+
+    std::fs::remove_dir(&path)?;
+    Ok(())
+}
+
+// A writer whose output the test can read back
+#[derive(Clone, Default)]
+struct Captured(Arc<Mutex<Vec<u8>>>);
+
+impl Captured {
+    fn text(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+impl Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+async fn log_every_level(logger: &ConsoleLogger) {
+    logger.log(LogLevel::Debug, "debug").await;
+    logger.log(LogLevel::Info, "info").await;
+    logger.log(LogLevel::Warn, "warn").await;
+    logger.log(LogLevel::Error, "error").await;
+}
+
+#[tokio::test]
+async fn test_console_logger_routes_levels_to_streams() {
+    let (stdout, stderr) = (Captured::default(), Captured::default());
+    let logger = ConsoleLogger::new()
+        .with_writers(stdout.clone(), stderr.clone())
+        .with_color(false);
+    log_every_level(&logger).await;
+
+    assert_eq!(stdout.text(), "[Debug] debug\n[Info] info\n");
+    assert_eq!(stderr.text(), "[Warn] warn\n[Error] error\n");
+}
+
+#[tokio::test]
+async fn test_console_logger_without_split_uses_stdout() {
+    let (stdout, stderr) = (Captured::default(), Captured::default());
+    let logger = ConsoleLogger::new()
+        .with_writers(stdout.clone(), stderr.clone())
+        .with_color(false)
+        .with_stderr_split(false);
+    log_every_level(&logger).await;
+
+    assert_eq!(stdout.text().lines().count(), 4);
+    assert_eq!(stderr.text(), "");
+}
+
+#[tokio::test]
+async fn test_console_logger_colors_level_tags() {
+    let (stdout, stderr) = (Captured::default(), Captured::default());
+    let logger = ConsoleLogger::new()
+        .with_writers(stdout.clone(), stderr.clone())
+        .with_color(true);
+    log_every_level(&logger).await;
+
+    assert_eq!(
+        stderr.text(),
+        "[\x1b[33mWarn\x1b[0m] warn\n[\x1b[31mError\x1b[0m] error\n"
+    );
+    assert!(stdout.text().starts_with("[\x1b[34mDebug\x1b[0m] debug\n"));
+}
+
+#[tokio::test]
+async fn test_fallback_logger_routes_failed_lines_to_secondary() -> Result<(), Box<dyn Error>> {
+    let missing = std::env::temp_dir()
+        .join(format!("astra_{}_missing_dir", std::process::id()))
+        .join("app.log");
+    let logger = FallbackLogger::new(
+        FileLogger::new(missing.to_str().unwrap().to_string()),
+        CapturingLogger::new(),
+    );
+
+    logger.log(LogLevel::Error, "disk is gone").await;
+    assert!(logger.secondary().contains(LogLevel::Error, "disk is gone"));
+    assert!(logger
+        .primary()
+        .try_log(LogLevel::Info, "still gone")
+        .await
+        .is_err());
+    assert!(logger.try_log(LogLevel::Info, "rescued").await.is_ok());
+    assert_eq!(logger.secondary().lines().len(), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fallback_logger_leaves_secondary_alone_while_primary_works(
+) -> Result<(), Box<dyn Error>> {
+    let path =
+        std::env::temp_dir().join(format!("astra_{}_fallback_logger.txt", std::process::id()));
+    let path = path.to_str().unwrap().to_string();
+    let _ = std::fs::remove_file(&path);
+    let logger = FallbackLogger::new(FileLogger::new(path.clone()), CapturingLogger::new());
+
+    logger.log(LogLevel::Warn, "written").await;
+    logger.primary().flush().await;
+    assert_eq!(std::fs::read_to_string(&path)?, "[Warn] written\n");
+    assert!(logger.secondary().lines().is_empty());
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
@@ -0,0 +1,150 @@
+use astra::actor_system::{Actor, ActorOptions, ActorSystem, Message, OverflowPolicy, SendError};
+use async_trait::async_trait;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, Semaphore};
+
+// Records readings, each only once the test hands out a permit
+struct Telemetry {
+    gate: Arc<Semaphore>,
+    seen: mpsc::UnboundedSender<u32>,
+}
+
+#[async_trait]
+impl Actor for Telemetry {
+    type Message = u32;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error> {
+        if let Message::Regular(reading) = message {
+            self.gate.acquire().await.unwrap().forget();
+            let _ = self.seen.send(reading);
+        }
+        Ok(())
+    }
+}
+
+fn telemetry_system(
+    policy: OverflowPolicy,
+    gate: &Arc<Semaphore>,
+) -> (ActorSystem<u32>, mpsc::UnboundedReceiver<u32>) {
+    let (seen_tx, seen) = mpsc::unbounded_channel();
+    let mut system = ActorSystem::new();
+    system.add_actor_with_options(
+        "telemetry".to_string(),
+        Telemetry {
+            gate: Arc::clone(gate),
+            seen: seen_tx,
+        },
+        ActorOptions::new()
+            .with_mailbox_capacity(3)
+            .with_overflow_policy(policy),
+    );
+    (system, seen)
+}
+
+#[tokio::test]
+async fn test_drop_oldest_keeps_the_newest_messages() -> Result<(), Box<dyn Error>> {
+    let gate = Arc::new(Semaphore::new(0));
+    let (system, mut seen) = telemetry_system(OverflowPolicy::DropOldest, &gate);
+    let actor = system.actor_ref("telemetry").unwrap();
+
+    // The first reading is taken off the mailbox and holds the actor up
+    system.send_message("telemetry", 0).await?;
+    while actor.mailbox_depth() > 0 {
+        tokio::task::yield_now().await;
+    }
+
+    // Neither kind of send waits or fails on a full mailbox
+    for reading in 1..=5 {
+        system.try_send_message("telemetry", reading)?;
+    }
+    for reading in 6..=10 {
+        tokio::time::timeout(
+            Duration::from_secs(1),
+            system.send_message("telemetry", reading),
+        )
+        .await??;
+    }
+    assert_eq!(actor.mailbox_depth(), 3);
+    assert_eq!(actor.dropped_messages(), 7);
+
+    gate.add_permits(4);
+    let mut handled = Vec::new();
+    for _ in 0..4 {
+        handled.push(seen.recv().await.unwrap());
+    }
+    assert_eq!(handled, vec![0, 8, 9, 10]);
+
+    // Dropped readings don't count as pending work
+    system.wait_quiesced().await;
+    system.shutdown().await;
+    assert!(seen.recv().await.is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_block_policy_refuses_when_full() -> Result<(), Box<dyn Error>> {
+    let gate = Arc::new(Semaphore::new(0));
+    let (system, _seen) = telemetry_system(OverflowPolicy::Block, &gate);
+    let actor = system.actor_ref("telemetry").unwrap();
+
+    system.send_message("telemetry", 0).await?;
+    while actor.mailbox_depth() > 0 {
+        tokio::task::yield_now().await;
+    }
+    for reading in 1..=3 {
+        system.try_send_message("telemetry", reading)?;
+    }
+    assert_eq!(
+        system.try_send_message("telemetry", 4),
+        Err(SendError::MailboxFull("telemetry".to_string()))
+    );
+    assert_eq!(actor.dropped_messages(), 0);
+
+    gate.add_permits(4);
+    system.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_overflow_hook_sees_messages_sent_to_a_full_mailbox() -> Result<(), Box<dyn Error>> {
+    for policy in [OverflowPolicy::Block, OverflowPolicy::DropOldest] {
+        let gate = Arc::new(Semaphore::new(0));
+        let (system, _seen) = telemetry_system(policy, &gate);
+        let overflowed = Arc::new(Mutex::new(Vec::new()));
+        system.on_overflow("telemetry", {
+            let overflowed = Arc::clone(&overflowed);
+            move |actor: &str, reading: &u32| {
+                overflowed
+                    .lock()
+                    .unwrap()
+                    .push((actor.to_string(), *reading));
+            }
+        })?;
+        let actor = system.actor_ref("telemetry").unwrap();
+
+        system.send_message("telemetry", 0).await?;
+        while actor.mailbox_depth() > 0 {
+            tokio::task::yield_now().await;
+        }
+        // Fill the mailbox, then overflow it
+        for reading in 1..=3 {
+            system.try_send_message("telemetry", reading)?;
+        }
+        assert!(overflowed.lock().unwrap().is_empty());
+        let sent = system.try_send_message("telemetry", 4);
+        assert_eq!(*overflowed.lock().unwrap(), [("telemetry".to_string(), 4)]);
+        match policy {
+            OverflowPolicy::Block => {
+                assert_eq!(sent, Err(SendError::MailboxFull("telemetry".to_string())))
+            }
+            OverflowPolicy::DropOldest => assert_eq!(sent, Ok(())),
+        }
+
+        gate.add_permits(100);
+        system.shutdown().await;
+    }
+    Ok(())
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use astra::network::http::{CommunicationProtocol, HttpServer};
+use astra::network::tcp::{TcpProtocol, TcpServer};
+use common::recording_system;
+use hyper::body::to_bytes;
+use hyper::{Body, Client, Request, Response, StatusCode};
+use serde::Deserialize;
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+// What the actors expect to receive
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct Order {
+    id: u64,
+    item: String,
+}
+
+async fn post(addr: SocketAddr, path: &str, body: &str) -> Response<Body> {
+    let req = Request::post(format!("http://{}{}", addr, path))
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    Client::new().request(req).await.unwrap()
+}
+
+async fn body_text(response: Response<Body>) -> String {
+    String::from_utf8(to_bytes(response.into_body()).await.unwrap().to_vec()).unwrap()
+}
+
+#[tokio::test]
+async fn test_http_server_answers_each_bad_request_with_its_status() -> Result<(), Box<dyn Error>> {
+    let (system, mut seen) = recording_system("orders");
+    let server = HttpServer::new(Arc::new(system))
+        .with_message_type::<Order>()
+        .with_max_message_size(64)
+        .start("127.0.0.1:0".parse()?)
+        .await?;
+    let addr = server.local_addr();
+    let order = r#"{"id": 1, "item": "book"}"#;
+
+    let response = post(addr, "/actors/orders", r#"{"id": "one"}"#).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let error = body_text(response).await;
+    assert!(
+        error.starts_with("Malformed message: invalid type"),
+        "{}",
+        error
+    );
+
+    let response = post(addr, "/actors/missing", order).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = post(addr, "/actors/orders", &" ".repeat(65)).await;
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    // Only the well-formed order got through
+    let response = post(addr, "/actors/orders", order).await;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    assert_eq!(seen.recv().await.unwrap(), order);
+    assert!(seen.try_recv().is_err());
+
+    server.stop();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tcp_server_refuses_malformed_payloads() -> Result<(), Box<dyn Error>> {
+    let (system, mut seen) = recording_system("orders");
+    let server = TcpServer::new(Arc::new(system))
+        .with_message_type::<Order>()
+        .start("127.0.0.1:0".parse()?)
+        .await?;
+    let address = format!("{}/orders", server.local_addr());
+
+    let tcp = TcpProtocol::new();
+    let err = tcp.send_message(&address, "not json").await.unwrap_err();
+    assert_eq!(err, "Server could not parse the message");
+
+    // The connection stays usable after a refused frame
+    let order = r#"{"id": 2, "item": "pen"}"#;
+    tcp.send_message(&address, order).await?;
+    assert_eq!(seen.recv().await.unwrap(), order);
+
+    server.stop();
+    Ok(())
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use astra::actor_system::SendError;
+use astra::network::http::{CommunicationProtocol, HttpProtocol, HttpServer};
+use astra::network::tcp::{TcpProtocol, TcpServer};
+use common::recording_system;
+use hyper::{Body, Client, Request, StatusCode};
+use std::error::Error;
+use std::sync::Arc;
+
+#[tokio::test]
+async fn test_actor_system_rejects_oversized_message() -> Result<(), Box<dyn Error>> {
+    let (system, mut seen) = recording_system("recorder");
+    let system = system.with_max_message_size(8);
+
+    // Exactly at the limit is fine, one byte more is not
+    system
+        .send_message("recorder", "12345678".to_string())
+        .await?;
+    let err = system
+        .try_send_message("recorder", "123456789".to_string())
+        .unwrap_err();
+    assert_eq!(
+        err,
+        SendError::TooLarge {
+            actor: "recorder".to_string(),
+            size: 9,
+            max: 8
+        }
+    );
+    assert!(system
+        .send_message("recorder", "123456789".to_string())
+        .await
+        .is_err());
+
+    assert_eq!(seen.recv().await.unwrap(), "12345678");
+    assert!(seen.try_recv().is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_protocols_reject_oversized_before_sending() {
+    // Nothing listens on these addresses: the size check must fail first
+    let http = HttpProtocol::new().with_max_message_size(4);
+    let err = http
+        .send_message("http://127.0.0.1:1/actors/recorder", "too long")
+        .await
+        .unwrap_err();
+    assert!(err.contains("exceeding the 4 byte limit"), "{}", err);
+
+    let tcp = TcpProtocol::new().with_max_message_size(4);
+    let err = tcp
+        .send_message("127.0.0.1:1/recorder", "too long")
+        .await
+        .unwrap_err();
+    assert!(err.contains("exceeding the 4 byte limit"), "{}", err);
+}
+
+#[tokio::test]
+async fn test_tcp_server_enforces_limit() -> Result<(), Box<dyn Error>> {
+    let (system, mut seen) = recording_system("recorder");
+    let server = TcpServer::new(Arc::new(system))
+        .with_max_message_size(8)
+        .start("127.0.0.1:0".parse()?)
+        .await?;
+    let address = format!("{}/recorder", server.local_addr());
+
+    let tcp = TcpProtocol::new();
+    tcp.send_message(&address, "small").await?;
+    assert_eq!(seen.recv().await.unwrap(), "small");
+
+    let err = tcp
+        .send_message(&address, "much too large")
+        .await
+        .unwrap_err();
+    assert!(err.contains("too large"), "{}", err);
+
+    // The server skipped the rejected frame, so its connection went back to the
+    // pool and carries the next message
+    let host = server.local_addr().to_string();
+    assert_eq!(tcp.pooled_connections(&host), 1);
+    tcp.send_message(&address, "again").await?;
+    assert_eq!(seen.recv().await.unwrap(), "again");
+    assert_eq!(tcp.pooled_connections(&host), 1);
+
+    server.stop();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_http_server_enforces_limit() -> Result<(), Box<dyn Error>> {
+    let (system, _seen) = recording_system("recorder");
+    let server = HttpServer::new(Arc::new(system))
+        .with_max_message_size(8)
+        .start("127.0.0.1:0".parse()?)
+        .await?;
+
+    let req = Request::post(format!("http://{}/actors/recorder", server.local_addr()))
+        .body(Body::from("much too large"))?;
+    let response = Client::new().request(req).await?;
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    server.stop();
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use astra::backends::memory::MemoryBackend;
+use astra::backends::metered::{BackendOperation, MeteredBackend};
+use astra::backends::storage::StorageBackend;
+use async_trait::async_trait;
+use std::error::Error;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+// Every tenth read is slow, everything else is instant
+#[derive(Clone, Default)]
+struct SleepyBackend {
+    inner: MemoryBackend,
+    reads: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl StorageBackend for SleepyBackend {
+    async fn write(&mut self, data: &str) -> Result<(), Box<dyn Error>> {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        self.inner.write(data).await
+    }
+
+    async fn read(&mut self) -> Result<String, Box<dyn Error>> {
+        if self.reads.fetch_add(1, Ordering::SeqCst) % 10 == 9 {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        self.inner.read().await
+    }
+
+    async fn cleanup(&mut self) -> Result<(), Box<dyn Error>> {
+        self.inner.cleanup().await
+    }
+}
+
+#[tokio::test]
+async fn test_percentiles_reflect_known_latencies() -> Result<(), Box<dyn Error>> {
+    let mut backend = MeteredBackend::new(SleepyBackend::default());
+    for _ in 0..100 {
+        backend.read().await?;
+    }
+
+    let reads = backend.operation_stats(BackendOperation::Read);
+    assert_eq!(reads.count, 100);
+    // 90% of reads are instant, the slowest 10% take at least 50ms
+    assert!(
+        reads.p50 < Duration::from_millis(10),
+        "p50 was {:?}",
+        reads.p50
+    );
+    assert!(
+        reads.p95 >= Duration::from_millis(50),
+        "p95 was {:?}",
+        reads.p95
+    );
+    assert!(
+        reads.p99 >= Duration::from_millis(50),
+        "p99 was {:?}",
+        reads.p99
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_operations_are_tracked_separately() -> Result<(), Box<dyn Error>> {
+    let mut backend = MeteredBackend::new(SleepyBackend::default());
+    // Clones share the histograms
+    let observer = backend.clone();
+
+    backend.write("data").await?;
+    backend.write_bytes(b"bytes").await?;
+    backend.read().await?;
+
+    let writes = observer.operation_stats(BackendOperation::Write);
+    assert_eq!(writes.count, 2);
+    assert!(writes.p50 >= Duration::from_millis(20));
+    assert_eq!(observer.operation_stats(BackendOperation::Read).count, 1);
+    assert_eq!(observer.operation_stats(BackendOperation::Cleanup).count, 0);
+    assert_eq!(observer.stats().count, 3);
+
+    observer.reset();
+    assert_eq!(backend.stats().count, 0);
+    Ok(())
+}
@@ -0,0 +1,135 @@
+use astra::actor_system::{Actor, ActorSystem, Message};
+use astra::network::http::CommunicationProtocol;
+use astra::network::migration::{migrate_actor, Migratable, MigratableActor, MigrationError};
+use astra::network::registry::{ActorRegistry, LocalRegistry};
+use astra::network::remote::RemoteActorRef;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[derive(Default)]
+struct Counter {
+    count: u64,
+}
+
+#[async_trait]
+impl Actor for Counter {
+    type Message = String;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<String>) -> Result<(), String> {
+        if let Message::Regular(_) = message {
+            self.count += 1;
+        }
+        Ok(())
+    }
+}
+
+impl Migratable for Counter {
+    fn snapshot(&self) -> String {
+        self.count.to_string()
+    }
+
+    fn restore(&mut self, snapshot: &str) -> Result<(), String> {
+        self.count = snapshot.parse().map_err(|_| "Bad count".to_string())?;
+        Ok(())
+    }
+}
+
+// Delivers `node/actor` addresses straight to the actor systems of the nodes
+#[derive(Clone, Default)]
+struct InMemoryNodes {
+    nodes: HashMap<String, ActorSystem<String>>,
+}
+
+#[async_trait]
+impl CommunicationProtocol for InMemoryNodes {
+    async fn send_message(&self, address: &str, message: &str) -> Result<(), String> {
+        let (node, actor) = address
+            .split_once('/')
+            .ok_or_else(|| format!("Bad address {}", address))?;
+        let system = self
+            .nodes
+            .get(node)
+            .ok_or_else(|| format!("Node {} is unreachable", node))?;
+        system.send_message(actor, message.to_string()).await
+    }
+}
+
+// Node a runs the counter, node b has it in standby
+async fn two_nodes() -> (InMemoryNodes, Arc<LocalRegistry>) {
+    let mut a = ActorSystem::new();
+    a.add_actor(
+        "counter".to_string(),
+        MigratableActor::new(Counter::default()),
+    );
+    let mut b = ActorSystem::new();
+    b.add_actor(
+        "counter".to_string(),
+        MigratableActor::standby(Counter::default()),
+    );
+    let registry = Arc::new(LocalRegistry::new());
+    registry
+        .register_actor("counter", "node-a/counter")
+        .await
+        .unwrap();
+    let nodes = HashMap::from([("node-a".to_string(), a), ("node-b".to_string(), b)]);
+    (InMemoryNodes { nodes }, registry)
+}
+
+#[tokio::test]
+async fn test_migrate_actor_moves_state_and_redirects_senders() -> Result<(), String> {
+    let (protocol, registry) = two_nodes().await;
+    let (a, b) = (&protocol.nodes["node-a"], &protocol.nodes["node-b"]);
+    let counter = RemoteActorRef::new("counter", registry.clone(), protocol.clone());
+    for _ in 0..3 {
+        counter.send("tick").await?;
+    }
+    // Reaches the standby early: held until the snapshot arrives
+    b.send_message("counter", "early".to_string()).await?;
+
+    migrate_actor(a, "counter", "node-b/counter", &protocol, registry.as_ref())
+        .await
+        .map_err(|e| e.to_string())?;
+    assert_eq!(registry.lookup_actor("counter").await?, "node-b/counter");
+    assert!(a.send_message("counter", "late".to_string()).await.is_err());
+
+    // The cached address is refused, after which the actor is looked up again
+    assert!(counter.send("tick").await.is_err());
+    counter.send("tick").await?;
+    b.wait_quiesced().await;
+    assert_eq!(b.inspect("counter").await.as_deref(), Some("5"));
+
+    assert_eq!(a.shutdown().await.stopped_cleanly, vec!["counter"]);
+    b.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_failed_migration_keeps_the_actor_running() -> Result<(), String> {
+    let (protocol, registry) = two_nodes().await;
+    let a = &protocol.nodes["node-a"];
+    a.send_message("counter", "tick".to_string()).await?;
+
+    let failed = migrate_actor(a, "counter", "node-c/counter", &protocol, registry.as_ref()).await;
+    assert!(matches!(failed, Err(MigrationError::Transfer { .. })));
+    assert_eq!(registry.lookup_actor("counter").await?, "node-a/counter");
+
+    // Still live here, and taking messages again
+    a.send_message("counter", "tick".to_string()).await?;
+    a.wait_quiesced().await;
+    assert_eq!(a.inspect("counter").await.as_deref(), Some("2"));
+
+    // A standby has no state of its own to migrate
+    let b = &protocol.nodes["node-b"];
+    let failed = migrate_actor(b, "counter", "node-a/counter", &protocol, registry.as_ref()).await;
+    assert_eq!(
+        failed,
+        Err(MigrationError::NotMigratable("counter".to_string()))
+    );
+    assert!(!b.actor_ref("counter").unwrap().is_closing());
+
+    a.shutdown().await;
+    b.shutdown().await;
+    Ok(())
+}
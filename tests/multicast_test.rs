@@ -0,0 +1,103 @@
+use astra::network::http::CommunicationProtocol;
+use astra::network::multicast::MulticastSender;
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// Endpoints in memory: "down" refuses messages, "slow" takes a minute, the rest
+// record what they received after a short delay
+#[derive(Clone, Default)]
+struct MockEndpoints(Arc<Recorded>);
+
+#[derive(Default)]
+struct Recorded {
+    received: Mutex<Vec<(String, String)>>,
+    in_flight: AtomicUsize,
+    max_in_flight: AtomicUsize,
+}
+
+impl std::ops::Deref for MockEndpoints {
+    type Target = Recorded;
+
+    fn deref(&self) -> &Recorded {
+        &self.0
+    }
+}
+
+#[async_trait]
+impl CommunicationProtocol for MockEndpoints {
+    async fn send_message(&self, address: &str, message: &str) -> Result<(), String> {
+        let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_in_flight.fetch_max(now, Ordering::SeqCst);
+        let delay = if address.contains("slow") {
+            Duration::from_secs(60)
+        } else {
+            Duration::from_millis(20)
+        };
+        tokio::time::sleep(delay).await;
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        if address.contains("down") {
+            return Err(format!("{} refused the connection", address));
+        }
+        self.received
+            .lock()
+            .unwrap()
+            .push((address.to_string(), message.to_string()));
+        Ok(())
+    }
+}
+
+fn nodes(count: usize) -> Vec<String> {
+    (0..count).map(|i| format!("node{}", i)).collect()
+}
+
+#[tokio::test]
+async fn test_multicast_reaches_every_node() {
+    let endpoints = MockEndpoints::default();
+    let multicast = MulticastSender::new(nodes(5), endpoints.clone());
+
+    let results = multicast.send("invalidate users").await;
+    let addresses: Vec<_> = results.iter().map(|(address, _)| address.clone()).collect();
+    assert_eq!(addresses, nodes(5), "results follow the addresses");
+    assert!(results.iter().all(|(_, result)| result.is_ok()));
+
+    let mut received = endpoints.received.lock().unwrap().clone();
+    received.sort();
+    let expected: Vec<_> = nodes(5)
+        .into_iter()
+        .map(|node| (node, "invalidate users".to_string()))
+        .collect();
+    assert_eq!(received, expected);
+}
+
+#[tokio::test]
+async fn test_multicast_limits_concurrency() {
+    let endpoints = MockEndpoints::default();
+    let multicast = MulticastSender::new(nodes(12), endpoints.clone()).with_concurrency(3);
+
+    let results = multicast.send("ping").await;
+    assert_eq!(results.len(), 12);
+    assert_eq!(endpoints.max_in_flight.load(Ordering::SeqCst), 3);
+    assert_eq!(endpoints.received.lock().unwrap().len(), 12);
+}
+
+#[tokio::test]
+async fn test_failed_and_slow_nodes_are_reported_per_address() {
+    let endpoints = MockEndpoints::default();
+    let addresses = vec![
+        "node0".to_string(),
+        "down".to_string(),
+        "slow".to_string(),
+        "node1".to_string(),
+    ];
+    let multicast =
+        MulticastSender::new(addresses, endpoints.clone()).with_timeout(Duration::from_millis(200));
+
+    let results = multicast.send("ping").await;
+    assert!(results[0].1.is_ok());
+    assert!(results[1].1.as_ref().unwrap_err().contains("refused"));
+    assert!(results[2].1.as_ref().unwrap_err().contains("timed out"));
+    assert!(results[3].1.is_ok());
+    assert_eq!(endpoints.received.lock().unwrap().len(), 2);
+}
@@ -0,0 +1,177 @@
+use astra::actor_system::{Actor, ActorSystem, Message, PersistentScheduler, TimerKind};
+use astra::backends::memory::MemoryBackend;
+use astra::clock::MockClock;
+use async_trait::async_trait;
+use std::error::Error;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc;
+use tokio::time::timeout;
+
+const HOUR: Duration = Duration::from_secs(3600);
+
+// Forwards what it receives to the test
+struct Inbox {
+    received: mpsc::UnboundedSender<String>,
+}
+
+#[async_trait]
+impl Actor for Inbox {
+    type Message = String;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<String>) -> Result<(), String> {
+        if let Message::Regular(message) = message {
+            let _ = self.received.send(message);
+        }
+        Ok(())
+    }
+}
+
+// A fresh "process": a system with the inbox actor on `clock`, if given
+fn start(clock: Option<&MockClock>) -> (ActorSystem<String>, mpsc::UnboundedReceiver<String>) {
+    let (received, inbox) = mpsc::unbounded_channel();
+    let mut system = ActorSystem::new();
+    if let Some(clock) = clock {
+        system = system.with_clock(Arc::new(clock.clone()));
+    }
+    system.add_actor("inbox".to_string(), Inbox { received });
+    (system, inbox)
+}
+
+#[tokio::test]
+async fn test_scheduled_message_fires_after_restart() -> Result<(), Box<dyn Error>> {
+    let store = MemoryBackend::new();
+    let (system, _) = start(Some(&MockClock::new()));
+    let scheduler = PersistentScheduler::open(&system, store.clone()).await?;
+    scheduler
+        .send_after("reminder", "inbox", "wake up".to_string(), HOUR)
+        .await?;
+    // The process goes away before the timer fires
+    system.shutdown().await;
+
+    let clock = MockClock::new();
+    let (system, mut inbox) = start(Some(&clock));
+    let scheduler = PersistentScheduler::open(&system, store).await?;
+    let pending = scheduler.pending().await;
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].key, "reminder");
+    assert_eq!(pending[0].kind, TimerKind::Once);
+
+    clock.wait_for_sleepers(1).await;
+    clock.advance(HOUR);
+    assert_eq!(inbox.recv().await.as_deref(), Some("wake up"));
+    // Fired timers are forgotten, so a further restart doesn't fire it again
+    timeout(Duration::from_secs(5), async {
+        while !scheduler.pending().await.is_empty() {
+            tokio::task::yield_now().await;
+        }
+    })
+    .await?;
+    system.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_overdue_timer_fires_on_startup() -> Result<(), Box<dyn Error>> {
+    let store = MemoryBackend::new();
+    let (system, _) = start(None);
+    let scheduler = PersistentScheduler::open(&system, store.clone()).await?;
+    scheduler
+        .send_after(
+            "late",
+            "inbox",
+            "overdue".to_string(),
+            Duration::from_millis(20),
+        )
+        .await?;
+    system.shutdown().await;
+    // Down for longer than the delay
+    tokio::time::sleep(Duration::from_millis(60)).await;
+
+    let (system, mut inbox) = start(None);
+    let _scheduler = PersistentScheduler::open(&system, store).await?;
+    let fired = timeout(Duration::from_secs(5), inbox.recv()).await?;
+    assert_eq!(fired.as_deref(), Some("overdue"));
+    system.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_recurring_timer_fires_once_for_missed_periods() -> Result<(), Box<dyn Error>> {
+    let store = MemoryBackend::new();
+    let (system, _) = start(None);
+    let scheduler = PersistentScheduler::open(&system, store.clone()).await?;
+    let period = Duration::from_millis(20);
+    scheduler
+        .schedule_recurring("tick", "inbox", period, "tick".to_string())
+        .await?;
+    system.shutdown().await;
+    // Several periods go by while the process is down
+    tokio::time::sleep(Duration::from_millis(150)).await;
+
+    // Time stands still in the new process, so only the catch-up fires
+    let clock = MockClock::new();
+    let (system, mut inbox) = start(Some(&clock));
+    let scheduler = PersistentScheduler::open(&system, store).await?;
+    assert_eq!(inbox.recv().await.as_deref(), Some("tick"));
+    clock.wait_for_sleepers(1).await;
+    assert!(inbox.try_recv().is_err(), "fired more than once");
+
+    // The timer kept its cadence: the next tick is less than a period away
+    let pending = scheduler.pending().await;
+    assert_eq!(pending[0].kind, TimerKind::Recurring(period));
+    let next = pending[0].fire_at.duration_since(SystemTime::now());
+    assert!(
+        next.as_ref().map_or(true, |wait| *wait <= period),
+        "{:?}",
+        next
+    );
+    system.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_scheduling_under_a_pending_key_replaces_the_timer() -> Result<(), Box<dyn Error>> {
+    let clock = MockClock::new();
+    let (system, mut inbox) = start(Some(&clock));
+    let scheduler = PersistentScheduler::open(&system, MemoryBackend::new()).await?;
+    scheduler
+        .send_after("reminder", "inbox", "first".to_string(), HOUR)
+        .await?;
+    scheduler
+        .send_after("reminder", "inbox", "second".to_string(), HOUR)
+        .await?;
+    assert_eq!(scheduler.pending().await.len(), 1);
+
+    clock.wait_for_sleepers(1).await;
+    clock.advance(HOUR);
+    assert_eq!(inbox.recv().await.as_deref(), Some("second"));
+    system.wait_quiesced().await;
+    assert!(inbox.try_recv().is_err());
+    system.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cancelled_timer_is_forgotten() -> Result<(), Box<dyn Error>> {
+    let store = MemoryBackend::new();
+    let (system, _) = start(Some(&MockClock::new()));
+    let scheduler = PersistentScheduler::open(&system, store.clone()).await?;
+    scheduler
+        .send_after("reminder", "inbox", "never".to_string(), HOUR)
+        .await?;
+    assert!(scheduler.cancel("reminder").await?);
+    assert!(!scheduler.cancel("reminder").await?);
+    assert!(scheduler
+        .send_after("other", "nobody", "lost".to_string(), HOUR)
+        .await
+        .is_err());
+    system.shutdown().await;
+
+    let (system, _) = start(None);
+    let scheduler = PersistentScheduler::open(&system, store).await?;
+    assert!(scheduler.pending().await.is_empty());
+    system.shutdown().await;
+    Ok(())
+}
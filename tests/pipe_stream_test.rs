@@ -0,0 +1,64 @@
+use astra::actor_system::{Actor, ActorOptions, ActorSystem, Message};
+use async_trait::async_trait;
+use futures::stream;
+use std::error::Error;
+use tokio::sync::mpsc;
+
+struct Collector {
+    items: mpsc::UnboundedSender<u32>,
+}
+
+#[async_trait]
+impl Actor for Collector {
+    type Message = u32;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error> {
+        if let Message::Regular(item) = message {
+            self.items.send(item).unwrap();
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_pipe_delivers_every_item_in_order() -> Result<(), Box<dyn Error>> {
+    let (items_tx, mut items) = mpsc::unbounded_channel();
+    let mut system = ActorSystem::new();
+    // A tiny mailbox makes the pipe wait on backpressure
+    system.add_actor_with_options(
+        "collector".to_string(),
+        Collector { items: items_tx },
+        ActorOptions::new().with_mailbox_capacity(2),
+    );
+
+    let pipe = system.pipe_stream("collector", stream::iter(1..=50))?;
+    assert_eq!(pipe.join().await?, 50);
+
+    let mut received = Vec::new();
+    for _ in 0..50 {
+        received.push(items.recv().await.unwrap());
+    }
+    assert_eq!(received, (1..=50).collect::<Vec<_>>());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_pipe_to_unknown_actor_fails() {
+    let system: ActorSystem<u32> = ActorSystem::new();
+    let result = system.pipe_stream("missing", stream::iter(1..=3));
+    assert_eq!(result.unwrap_err(), "Actor missing not found");
+}
+
+#[tokio::test]
+async fn test_cancelled_pipe_stops() -> Result<(), Box<dyn Error>> {
+    let (items_tx, _items) = mpsc::unbounded_channel();
+    let mut system = ActorSystem::new();
+    system.add_actor("collector".to_string(), Collector { items: items_tx });
+
+    // A stream that never ends
+    let pipe = system.pipe_stream("collector", stream::pending())?;
+    pipe.cancel();
+    assert!(pipe.join().await.is_err());
+    Ok(())
+}
@@ -0,0 +1,115 @@
+mod common;
+
+use astra::actor_system::{ActorOptions, ActorSystem, SendError};
+use common::gated_system;
+use std::error::Error;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+async fn wait_for_depth(system: &ActorSystem<String>, depth: usize) {
+    let actor = system.actor_ref("worker").unwrap();
+    while actor.mailbox_depth() != depth {
+        tokio::task::yield_now().await;
+    }
+}
+
+#[tokio::test]
+async fn test_aged_message_is_handled_despite_priority_flood() -> Result<(), Box<dyn Error>> {
+    let gate = Arc::new(Semaphore::new(0));
+    let options = ActorOptions::new().with_priority_lane(Duration::from_millis(300));
+    let (system, mut seen) = gated_system(options, &gate);
+
+    // The first message holds the actor up while the others queue
+    system
+        .send_priority_message("worker", "p0".to_string())
+        .await?;
+    wait_for_depth(&system, 0).await;
+    system.send_message("worker", "regular".to_string()).await?;
+    for n in 1..=5 {
+        system
+            .send_priority_message("worker", format!("p{}", n))
+            .await?;
+    }
+
+    // Priority messages overtake the regular one while it is fresh
+    gate.add_permits(3);
+    for n in 0..3 {
+        assert_eq!(seen.recv().await.unwrap(), format!("p{}", n));
+    }
+    // p3 is being handled, "regular", p4 and p5 wait
+    wait_for_depth(&system, 3).await;
+
+    // Priority messages keep coming, but the regular one has waited too long
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    for n in 6..=8 {
+        system
+            .send_priority_message("worker", format!("p{}", n))
+            .await?;
+    }
+    gate.add_permits(7);
+    let mut handled = Vec::new();
+    for _ in 0..7 {
+        handled.push(seen.recv().await.unwrap());
+    }
+    assert_eq!(
+        handled,
+        ["p3", "regular", "p4", "p5", "p6", "p7", "p8"].map(String::from)
+    );
+    system.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_lanes_share_the_mailbox_capacity() -> Result<(), Box<dyn Error>> {
+    let gate = Arc::new(Semaphore::new(0));
+    let options = ActorOptions::new()
+        .with_mailbox_capacity(2)
+        .with_priority_lane(Duration::from_secs(60));
+    let (system, mut seen) = gated_system(options, &gate);
+
+    system.send_message("worker", "first".to_string()).await?;
+    wait_for_depth(&system, 0).await;
+    system.send_message("worker", "regular".to_string()).await?;
+    system
+        .send_priority_message("worker", "urgent".to_string())
+        .await?;
+    assert_eq!(
+        system.try_send_message("worker", "more".to_string()),
+        Err(SendError::MailboxFull("worker".to_string()))
+    );
+
+    // A priority send waits for room like any other
+    let waiting = tokio::spawn({
+        let system = system.clone();
+        async move {
+            system
+                .send_priority_message("worker", "late".to_string())
+                .await
+        }
+    });
+    // Handling the first message makes room for it, while the actor holds on
+    // to the next
+    gate.add_permits(1);
+    waiting.await??;
+    gate.add_permits(3);
+    let mut handled = Vec::new();
+    for _ in 0..4 {
+        handled.push(seen.recv().await.unwrap());
+    }
+    assert_eq!(
+        handled,
+        ["first", "urgent", "late", "regular"].map(String::from)
+    );
+
+    // An actor without a priority lane takes priority sends in order
+    let (plain, mut plain_seen) = gated_system(ActorOptions::new(), &gate);
+    plain
+        .send_priority_message("worker", "a".to_string())
+        .await?;
+    plain.send_message("worker", "b".to_string()).await?;
+    gate.add_permits(2);
+    assert_eq!(plain_seen.recv().await.unwrap(), "a");
+    assert_eq!(plain_seen.recv().await.unwrap(), "b");
+    Ok(())
+}
@@ -0,0 +1,57 @@
+mod common;
+
+use astra::actor_system::{ActorOptions, SendError};
+use common::gated_system;
+use std::error::Error;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::time::{timeout, Duration};
+
+#[tokio::test]
+async fn test_quiesce_rejects_new_messages_and_drains_queued_ones() -> Result<(), Box<dyn Error>> {
+    let gate = Arc::new(Semaphore::new(0));
+    let (system, mut seen) = gated_system(ActorOptions::new(), &gate);
+    for message in ["a", "b", "c"] {
+        system.send_message("worker", message.to_string()).await?;
+    }
+    assert!(!system.quiesced());
+
+    system.quiesce();
+    assert!(system.is_quiescing());
+    assert_eq!(
+        system.try_send_message("worker", "d".to_string()),
+        Err(SendError::Quiescing("worker".to_string()))
+    );
+    assert!(system
+        .send_message("worker", "e".to_string())
+        .await
+        .unwrap_err()
+        .contains("quiescing"));
+    // Actors talking to each other are not affected
+    system
+        .actor_ref("worker")
+        .unwrap()
+        .send("f".to_string())
+        .await?;
+
+    // Nothing drains while the actor is held up
+    let waiting = system.clone();
+    let drained = tokio::spawn(async move { waiting.wait_quiesced().await });
+    tokio::task::yield_now().await;
+    assert!(!drained.is_finished());
+
+    gate.add_permits(4);
+    timeout(Duration::from_secs(5), drained).await??;
+    assert!(system.quiesced());
+    for expected in ["a", "b", "c", "f"] {
+        assert_eq!(seen.recv().await.as_deref(), Some(expected));
+    }
+
+    // Resuming lets messages in again
+    system.resume();
+    gate.add_permits(1);
+    system.send_message("worker", "g".to_string()).await?;
+    assert_eq!(seen.recv().await.as_deref(), Some("g"));
+    system.shutdown().await;
+    Ok(())
+}
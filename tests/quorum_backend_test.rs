@@ -0,0 +1,93 @@
+use astra::backends::boxed::BoxedBackend;
+use astra::backends::memory::MemoryBackend;
+use astra::backends::quorum::{QuorumBackend, QuorumError};
+use astra::backends::read_only::ReadOnlyBackend;
+use astra::backends::storage::StorageBackend;
+use async_trait::async_trait;
+use std::error::Error;
+use std::time::Duration;
+
+fn replicas(count: usize) -> Vec<MemoryBackend> {
+    (0..count).map(|_| MemoryBackend::new()).collect()
+}
+
+fn boxed(replicas: &[MemoryBackend]) -> Vec<BoxedBackend> {
+    replicas.iter().cloned().map(BoxedBackend::new).collect()
+}
+
+#[tokio::test]
+async fn test_write_and_read_meet_their_quorums() -> Result<(), Box<dyn Error>> {
+    let stores = replicas(3);
+    // One replica refuses writes, two acknowledgements are enough
+    let mut backends = boxed(&stores[..2]);
+    backends.push(BoxedBackend::new(ReadOnlyBackend::new(stores[2].clone())));
+    let mut backend = QuorumBackend::new(backends, 2, 2)?;
+
+    backend.write("replicated").await?;
+    assert_eq!(stores[0].clone().read().await?, "replicated");
+    assert_eq!(stores[1].clone().read().await?, "replicated");
+    assert_eq!(stores[2].clone().read().await?, "");
+
+    // Two of the three answers agree
+    let mut backend = QuorumBackend::new(boxed(&stores), 1, 3)?;
+    assert_eq!(backend.read().await?, "replicated");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_write_fails_when_the_quorum_is_not_met() -> Result<(), Box<dyn Error>> {
+    let stores = replicas(3);
+    let mut backends = boxed(&stores[..1]);
+    for store in &stores[1..] {
+        backends.push(BoxedBackend::new(ReadOnlyBackend::new(store.clone())));
+    }
+    let mut backend = QuorumBackend::new(backends, 2, 1)?;
+
+    let error = backend.write("lost").await.unwrap_err();
+    let error = error.downcast_ref::<QuorumError>().expect("a quorum error");
+    assert_eq!(error.operation, "write");
+    assert_eq!(error.succeeded, 1);
+    assert_eq!(error.quorum, 2);
+    assert_eq!(error.errors.len(), 2);
+    Ok(())
+}
+
+// Never answers, like a replica behind a dead link
+#[derive(Clone)]
+struct Hung;
+
+#[async_trait]
+impl StorageBackend for Hung {
+    async fn write(&mut self, _data: &str) -> Result<(), Box<dyn Error>> {
+        std::future::pending().await
+    }
+
+    async fn read(&mut self) -> Result<String, Box<dyn Error>> {
+        std::future::pending().await
+    }
+
+    async fn cleanup(&mut self) -> Result<(), Box<dyn Error>> {
+        std::future::pending().await
+    }
+}
+
+#[tokio::test]
+async fn test_a_hung_replica_does_not_hold_up_the_quorum() -> Result<(), Box<dyn Error>> {
+    let stores = replicas(2);
+    let mut backends = boxed(&stores);
+    backends.push(BoxedBackend::new(Hung));
+    let mut backend = QuorumBackend::new(backends, 2, 2)?;
+
+    let within = Duration::from_secs(5);
+    tokio::time::timeout(within, backend.write("replicated")).await??;
+    assert_eq!(stores[1].clone().read().await?, "replicated");
+    tokio::time::timeout(within, backend.cleanup()).await??;
+    assert_eq!(stores[0].clone().read().await?, "");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_impossible_quorums_are_refused() {
+    assert!(QuorumBackend::new(boxed(&replicas(2)), 3, 1).is_err());
+    assert!(QuorumBackend::new(boxed(&replicas(2)), 1, 0).is_err());
+}
@@ -0,0 +1,119 @@
+mod common;
+
+use astra::actor_system::{Actor, ActorOptions, ActorSystem, Message, RateLimitPolicy, SendError};
+use async_trait::async_trait;
+use common::Gated;
+use std::error::Error;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, Semaphore};
+
+// Reports when each message was handled
+struct Stamper {
+    handled: mpsc::UnboundedSender<Instant>,
+}
+
+#[async_trait]
+impl Actor for Stamper {
+    type Message = String;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error> {
+        if let Message::Regular(_) = message {
+            self.handled.send(Instant::now()).unwrap();
+        }
+        Ok(())
+    }
+}
+
+fn system_with_limit(
+    options: ActorOptions,
+) -> (ActorSystem<String>, mpsc::UnboundedReceiver<Instant>) {
+    let (handled_tx, handled_rx) = mpsc::unbounded_channel();
+    let mut system = ActorSystem::new();
+    system.add_actor_with_options(
+        "limited".to_string(),
+        Stamper {
+            handled: handled_tx,
+        },
+        options,
+    );
+    (system, handled_rx)
+}
+
+#[tokio::test]
+async fn test_excess_messages_are_rejected() -> Result<(), Box<dyn Error>> {
+    let options = ActorOptions::new().with_rate_limit(5, Duration::from_secs(60));
+    let (system, _handled) = system_with_limit(options);
+
+    let results: Vec<_> = (0..10)
+        .map(|n| system.try_send_message("limited", n.to_string()))
+        .collect();
+
+    assert!(results[..5].iter().all(Result::is_ok));
+    assert!(results[5..]
+        .iter()
+        .all(|r| *r == Err(SendError::RateLimited("limited".to_string()))));
+    // Sending through an ActorRef draws from the same budget
+    let actor = system.actor_ref("limited").unwrap();
+    assert!(actor.send("more".to_string()).await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_excess_messages_are_delayed() -> Result<(), Box<dyn Error>> {
+    let options = ActorOptions::new()
+        .with_rate_limit(5, Duration::from_millis(200))
+        .with_rate_limit_policy(RateLimitPolicy::Delay);
+    let (system, mut handled) = system_with_limit(options);
+
+    // All messages are accepted...
+    for n in 0..10 {
+        system.try_send_message("limited", n.to_string())?;
+    }
+
+    // ...but only the first five are handled as a burst, the rest are paced
+    let mut stamps = Vec::new();
+    for _ in 0..10 {
+        stamps.push(handled.recv().await.unwrap());
+    }
+    assert!(stamps[4] - stamps[0] < Duration::from_millis(100));
+    assert!(stamps[9] - stamps[0] >= Duration::from_millis(150));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_failed_send_does_not_use_up_the_rate_limit() -> Result<(), Box<dyn Error>> {
+    let gate = Arc::new(Semaphore::new(0));
+    let mut system = ActorSystem::new();
+    system.add_actor_with_options(
+        "limited".to_string(),
+        Gated::new(&gate).0,
+        ActorOptions::new()
+            .with_mailbox_capacity(1)
+            .with_rate_limit(3, Duration::from_secs(60)),
+    );
+    let actor = system.actor_ref("limited").unwrap();
+
+    // The first message keeps the actor busy and the second fills its mailbox
+    actor.try_send("one".to_string())?;
+    while actor.mailbox_depth() > 0 {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+    actor.try_send("two".to_string())?;
+    assert_eq!(
+        actor.try_send("three".to_string()),
+        Err(SendError::MailboxFull("limited".to_string()))
+    );
+
+    // The rejected message gave its token back
+    gate.add_permits(10);
+    system.wait_quiesced().await;
+    assert_eq!(actor.try_send("four".to_string()), Ok(()));
+    assert_eq!(
+        actor.try_send("five".to_string()),
+        Err(SendError::RateLimited("limited".to_string()))
+    );
+    system.shutdown().await;
+    Ok(())
+}
@@ -0,0 +1,51 @@
+use astra::backends::memory::MemoryBackend;
+use astra::backends::read_only::{ReadOnlyBackend, ReadOnlyError};
+use astra::backends::storage::StorageBackend;
+use astra::data_actor::DataActor;
+use std::error::Error;
+
+#[tokio::test]
+async fn test_writes_are_refused() -> Result<(), Box<dyn Error>> {
+    let mut shared = MemoryBackend::new();
+    shared.write("replicated").await?;
+    let mut backend = ReadOnlyBackend::new(shared.clone());
+
+    let error = backend.write("overwritten").await.unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<ReadOnlyError>(),
+        Some(&ReadOnlyError { operation: "write" })
+    );
+    assert_eq!(error.to_string(), "Backend is read-only, write refused");
+    assert!(backend.extend_bytes(b" more").await.is_err());
+    assert!(backend
+        .compare_and_swap("replicated", "swapped")
+        .await
+        .is_err());
+    let error = backend.cleanup().await.unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<ReadOnlyError>(),
+        Some(&ReadOnlyError {
+            operation: "cleanup"
+        })
+    );
+
+    assert_eq!(backend.read().await?, "replicated");
+    assert_eq!(shared.read().await?, "replicated");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_read_only_data_actor_reads_but_cannot_write() -> Result<(), Box<dyn Error>> {
+    let mut writer = DataActor::new(MemoryBackend::new());
+    writer.write_to_backend("v1").await?;
+
+    let mut reader = writer.clone().read_only();
+    assert_eq!(reader.read_from_backend().await?, "v1");
+    assert!(reader.write_to_backend("v2").await.is_err());
+    assert!(reader.cleanup_backend().await.is_err());
+
+    // The view follows what the writer stores
+    writer.write_to_backend("v3").await?;
+    assert_eq!(reader.read_from_backend().await?, "v3");
+    Ok(())
+}
@@ -0,0 +1,186 @@
+use astra::actor_system::{Actor, ActorOptions, ActorSystem, Message, RestartPolicy};
+use astra::backends::memory::MemoryBackend;
+use astra::backends::storage::StorageBackend;
+use astra::supervision::{SupervisionEventKind, SupervisionStrategy, Supervisor};
+use async_trait::async_trait;
+use std::error::Error;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio::time::{timeout, Duration};
+
+// Hangs on "stuck", records everything else
+struct Sleepy {
+    handled: mpsc::UnboundedSender<String>,
+}
+
+#[async_trait]
+impl Actor for Sleepy {
+    type Message = String;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error> {
+        if let Message::Regular(msg) = message {
+            if msg == "stuck" {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+            }
+            let _ = self.handled.send(msg);
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_stuck_receive_times_out_and_notifies_supervisor() -> Result<(), Box<dyn Error>> {
+    let supervisor = Arc::new(Supervisor::new(SupervisionStrategy::Restart));
+    let mut events = supervisor.events();
+    let (handled_tx, mut handled) = mpsc::unbounded_channel();
+    let mut system = ActorSystem::new().with_supervisor(Arc::clone(&supervisor));
+    system.add_actor_with_options(
+        "sleepy".to_string(),
+        Sleepy {
+            handled: handled_tx,
+        },
+        ActorOptions::new().with_receive_timeout(Duration::from_millis(50)),
+    );
+
+    system.send_message("sleepy", "stuck".to_string()).await?;
+    system.send_message("sleepy", "next".to_string()).await?;
+
+    let failed = timeout(Duration::from_secs(5), events.recv()).await??;
+    assert_eq!(failed.kind, SupervisionEventKind::Failed);
+    assert_eq!(failed.actor, "sleepy");
+    assert!(failed.error.contains("timed out"), "{}", failed.error);
+
+    // The stuck call was abandoned and the actor carried on; without a factory
+    // to rebuild it, it was not restarted
+    assert_eq!(handled.recv().await.as_deref(), Some("next"));
+    assert!(events.try_recv().is_err());
+    system.shutdown().await;
+    Ok(())
+}
+
+// Records which instance handled each message; hangs on "stuck" until dropped
+struct Numbered {
+    instance: usize,
+    handled: mpsc::UnboundedSender<(usize, String)>,
+}
+
+#[async_trait]
+impl Actor for Numbered {
+    type Message = String;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error> {
+        if let Message::Regular(msg) = message {
+            if msg == "stuck" {
+                futures::future::pending::<()>().await;
+            }
+            let _ = self.handled.send((self.instance, msg));
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_timed_out_actor_is_restarted_by_its_supervisor() -> Result<(), Box<dyn Error>> {
+    let supervisor = Arc::new(Supervisor::new(SupervisionStrategy::Restart));
+    let mut events = supervisor.events();
+    let (handled_tx, mut handled) = mpsc::unbounded_channel();
+    let instances = Arc::new(AtomicUsize::new(0));
+    let mut system = ActorSystem::new().with_supervisor(Arc::clone(&supervisor));
+    let built = Arc::clone(&instances);
+    system.add_restartable_actor_with_options(
+        "numbered".to_string(),
+        move || Numbered {
+            instance: built.fetch_add(1, Ordering::SeqCst) + 1,
+            handled: handled_tx.clone(),
+        },
+        ActorOptions::new().with_receive_timeout(Duration::from_millis(50)),
+    );
+
+    system.send_message("numbered", "first".to_string()).await?;
+    system.send_message("numbered", "stuck".to_string()).await?;
+    system.send_message("numbered", "next".to_string()).await?;
+
+    assert_eq!(handled.recv().await, Some((1, "first".to_string())));
+    let failed = timeout(Duration::from_secs(5), events.recv()).await??;
+    assert_eq!(failed.error, "receive timed out after 50ms");
+    assert_eq!(events.recv().await?.kind, SupervisionEventKind::Restarted);
+
+    // A fresh instance took over, and the stuck message was never handled
+    assert_eq!(handled.recv().await, Some((2, "next".to_string())));
+    system.shutdown().await;
+    assert!(handled.try_recv().is_err());
+    assert_eq!(instances.load(Ordering::SeqCst), 2);
+    Ok(())
+}
+
+// Counts messages and saves the count after each; hangs on "stuck"
+struct Tally {
+    count: u64,
+    backend: MemoryBackend,
+}
+
+impl Tally {
+    async fn load_state(&mut self) -> Result<(), String> {
+        let saved = self.backend.read().await.map_err(|e| e.to_string())?;
+        self.count = saved
+            .parse()
+            .map_err(|_| format!("Bad count {:?}", saved))?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Actor for Tally {
+    type Message = String;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error> {
+        if let Message::Regular(msg) = message {
+            if msg == "stuck" {
+                futures::future::pending::<()>().await;
+            }
+            self.count += 1;
+            let saved = self.backend.write(&self.count.to_string()).await;
+            saved.map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    fn inspect_state(&self) -> Option<String> {
+        Some(self.count.to_string())
+    }
+}
+
+#[tokio::test]
+async fn test_restarted_actor_reloads_its_saved_state() -> Result<(), Box<dyn Error>> {
+    let supervisor = Arc::new(Supervisor::new(SupervisionStrategy::Restart));
+    let mut events = supervisor.events();
+    let backend = MemoryBackend::new();
+    let mut system = ActorSystem::new().with_supervisor(supervisor);
+    system.add_supervised_actor_with_options(
+        "tally".to_string(),
+        move || Tally {
+            count: 0,
+            backend: backend.clone(),
+        },
+        RestartPolicy { reload_state: true },
+        |tally: &mut Tally| Box::pin(tally.load_state()),
+        ActorOptions::new().with_receive_timeout(Duration::from_millis(50)),
+    );
+
+    for msg in ["one", "two", "stuck"] {
+        system.send_message("tally", msg.to_string()).await?;
+    }
+    timeout(Duration::from_secs(5), events.recv()).await??;
+    assert_eq!(events.recv().await?.kind, SupervisionEventKind::Restarted);
+
+    // The fresh instance counts on from the saved state, not from zero
+    system.send_message("tally", "three".to_string()).await?;
+    system.wait_quiesced().await;
+    assert_eq!(system.inspect("tally").await.as_deref(), Some("3"));
+    system.shutdown().await;
+    Ok(())
+}
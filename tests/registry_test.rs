@@ -1,5 +1,15 @@
-use astra::network::registry::DistributedRegistry;
+use astra::network::registry::{
+    ActorRegistry, DistributedRegistry, LocalRegistry, RegistryError, ACTOR_NOT_FOUND,
+};
+use async_trait::async_trait;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Response, Server};
+use std::collections::HashMap;
+use std::convert::Infallible;
 use std::env;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
 use tokio::time::{timeout, Duration};
 
 #[tokio::test]
@@ -35,3 +45,239 @@ async fn test_registry_with_mock_or_timeout() -> Result<(), Box<dyn std::error::
         }
     }
 }
+
+#[tokio::test]
+async fn test_optional_registry_degrades_to_local() -> Result<(), Box<dyn std::error::Error>> {
+    // Nothing listens on port 1, so the connection fails and we fall back
+    let registry = DistributedRegistry::new_optional(&["http://127.0.0.1:1"]).await;
+
+    registry
+        .register_actor("actor1", "http://127.0.0.1:8080")
+        .await?;
+    assert_eq!(
+        registry.lookup_actor("actor1").await?,
+        "http://127.0.0.1:8080"
+    );
+    assert!(registry.lookup_actor("actor2").await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_unique_registration_conflicts() -> Result<(), Box<dyn std::error::Error>> {
+    // Skip test execution unless TEST_ENV is set
+    if env::var("TEST_ENV").is_err() {
+        return Ok(());
+    }
+
+    let registry = DistributedRegistry::new(&["http://etcd1:2379", "http://etcd2:2379"]).await?;
+    // Make the key unique per run so earlier runs don't interfere
+    let actor_id = format!("unique_actor_{}", std::process::id());
+
+    registry
+        .register_actor_unique(&actor_id, "http://node1:8080")
+        .await?;
+    // Registering the same address again is fine
+    registry
+        .register_actor_unique(&actor_id, "http://node1:8080")
+        .await?;
+    assert_eq!(
+        registry
+            .register_actor_unique(&actor_id, "http://node2:8080")
+            .await,
+        Err(RegistryError::Conflict {
+            actor_id: actor_id.clone(),
+            existing_address: "http://node1:8080".to_string(),
+        })
+    );
+    assert_eq!(registry.lookup_actor(&actor_id).await?, "http://node1:8080");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_local_unique_registration_conflicts() -> Result<(), Box<dyn std::error::Error>> {
+    let registry = LocalRegistry::new();
+
+    registry
+        .register_actor_unique("actor1", "http://node1:8080")
+        .await?;
+    let err = registry
+        .register_actor_unique("actor1", "http://node2:8080")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Actor actor1 is already registered at http://node1:8080"
+    );
+    assert_eq!(registry.lookup_actor("actor1").await?, "http://node1:8080");
+    Ok(())
+}
+
+// A registry implementing only what `ActorRegistry` requires
+#[derive(Default)]
+struct MinimalRegistry {
+    actors: std::sync::Mutex<HashMap<String, String>>,
+}
+
+#[async_trait]
+impl ActorRegistry for MinimalRegistry {
+    async fn register_actor(&self, actor_id: &str, node_address: &str) -> Result<(), String> {
+        let mut actors = self.actors.lock().unwrap();
+        actors.insert(actor_id.to_string(), node_address.to_string());
+        Ok(())
+    }
+
+    async fn lookup_actor(&self, actor_id: &str) -> Result<String, String> {
+        let actors = self.actors.lock().unwrap();
+        actors
+            .get(actor_id)
+            .cloned()
+            .ok_or_else(|| ACTOR_NOT_FOUND.to_string())
+    }
+}
+
+#[tokio::test]
+async fn test_default_unique_registration_conflicts() -> Result<(), Box<dyn std::error::Error>> {
+    let registry = MinimalRegistry::default();
+
+    registry
+        .register_actor_unique("actor1", "http://node1:8080")
+        .await?;
+    // The same address again is fine
+    registry
+        .register_actor_unique("actor1", "http://node1:8080")
+        .await?;
+    let err = registry
+        .register_actor_unique("actor1", "http://node2:8080")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err,
+        RegistryError::Conflict {
+            actor_id: "actor1".to_string(),
+            existing_address: "http://node1:8080".to_string(),
+        }
+    );
+    assert_eq!(registry.lookup_actor("actor1").await?, "http://node1:8080");
+    Ok(())
+}
+
+// An etcd stand-in that answers every request with `grpc_status` after `delay`
+struct StubEtcd {
+    address: String,
+    requests: Arc<AtomicUsize>,
+    // The most requests it was handling at once
+    max_in_flight: Arc<AtomicUsize>,
+}
+
+async fn stub_etcd(delay: Duration, grpc_status: &'static str) -> StubEtcd {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    let max_in_flight = Arc::new(AtomicUsize::new(0));
+    let (counted, max) = (Arc::clone(&requests), Arc::clone(&max_in_flight));
+    let make_service = make_service_fn(move |_| {
+        let (counted, in_flight, max) = (
+            Arc::clone(&counted),
+            Arc::clone(&in_flight),
+            Arc::clone(&max),
+        );
+        async move {
+            Ok::<_, Infallible>(service_fn(move |_request| {
+                let (counted, in_flight, max) = (
+                    Arc::clone(&counted),
+                    Arc::clone(&in_flight),
+                    Arc::clone(&max),
+                );
+                async move {
+                    counted.fetch_add(1, Ordering::SeqCst);
+                    let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    max.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(delay).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    Response::builder()
+                        .header("content-type", "application/grpc")
+                        .header("grpc-status", grpc_status)
+                        .body(Body::empty())
+                }
+            }))
+        }
+    });
+    let server = Server::bind(&([127, 0, 0, 1], 0).into())
+        .http2_only(true)
+        .serve(make_service);
+    let address = format!("http://{}", server.local_addr());
+    tokio::spawn(server);
+    StubEtcd {
+        address,
+        requests,
+        max_in_flight,
+    }
+}
+
+// gRPC status codes the stubs answer with
+const NOT_FOUND: &str = "5";
+const UNAVAILABLE: &str = "14";
+
+#[tokio::test]
+async fn test_concurrent_lookups_do_not_serialize() -> Result<(), Box<dyn std::error::Error>> {
+    let etcd = stub_etcd(Duration::from_millis(200), NOT_FOUND).await;
+    let registry = Arc::new(DistributedRegistry::new(&[etcd.address.as_str()]).await?);
+
+    let started = Instant::now();
+    let lookups: Vec<_> = (0..16)
+        .map(|i| {
+            let registry = Arc::clone(&registry);
+            tokio::spawn(async move { registry.lookup_actor(&format!("actor{}", i)).await })
+        })
+        .collect();
+    for lookup in lookups {
+        assert!(lookup.await?.is_err());
+    }
+
+    // One at a time, 16 lookups would take 3.2 seconds
+    assert!(etcd.max_in_flight.load(Ordering::SeqCst) > 1);
+    assert!(started.elapsed() < Duration::from_secs(2));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fails_over_to_the_next_endpoint() -> Result<(), Box<dyn std::error::Error>> {
+    let degraded = stub_etcd(Duration::ZERO, UNAVAILABLE).await;
+    let healthy = stub_etcd(Duration::ZERO, NOT_FOUND).await;
+    let registry =
+        DistributedRegistry::new(&[degraded.address.as_str(), healthy.address.as_str()]).await?;
+    assert_eq!(registry.active_endpoint(), degraded.address);
+
+    // The healthy endpoint answered: the actor just isn't registered there
+    let error = registry.lookup_actor("actor1").await.unwrap_err();
+    assert!(!error.contains("Unavailable"), "{}", error);
+    assert_eq!(degraded.requests.load(Ordering::SeqCst), 1);
+    assert_eq!(healthy.requests.load(Ordering::SeqCst), 1);
+    assert_eq!(registry.active_endpoint(), healthy.address);
+
+    let health = registry.endpoint_health();
+    assert!(!health[0].is_healthy() && !health[0].active);
+    assert!(health[1].is_healthy() && health[1].active);
+
+    // Later calls go straight to the endpoint that answered
+    let _ = registry.lookup_actor("actor2").await;
+    assert_eq!(degraded.requests.load(Ordering::SeqCst), 1);
+    assert_eq!(healthy.requests.load(Ordering::SeqCst), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_every_endpoint_unavailable_fails() -> Result<(), Box<dyn std::error::Error>> {
+    let first = stub_etcd(Duration::ZERO, UNAVAILABLE).await;
+    let second = stub_etcd(Duration::ZERO, UNAVAILABLE).await;
+    let registry =
+        DistributedRegistry::new(&[first.address.as_str(), second.address.as_str()]).await?;
+
+    assert!(registry.lookup_actor("actor1").await.is_err());
+    let failures: Vec<_> = registry
+        .endpoint_health()
+        .iter()
+        .map(|health| health.consecutive_failures)
+        .collect();
+    assert_eq!(failures, [1, 1]);
+    Ok(())
+}
@@ -0,0 +1,179 @@
+use astra::clock::MockClock;
+use astra::network::http::CommunicationProtocol;
+use astra::network::registry::{ActorRegistry, LocalRegistry, RegistryError};
+use astra::network::remote::{RefreshReport, RemoteActorRef, RemoteRefRegistry};
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// A local registry counting its lookups
+#[derive(Default)]
+struct CountingRegistry {
+    inner: LocalRegistry,
+    lookups: AtomicUsize,
+}
+
+#[async_trait]
+impl ActorRegistry for CountingRegistry {
+    async fn register_actor(&self, actor_id: &str, node_address: &str) -> Result<(), String> {
+        self.inner.register_actor(actor_id, node_address).await
+    }
+
+    async fn register_actor_unique(
+        &self,
+        actor_id: &str,
+        node_address: &str,
+    ) -> Result<(), RegistryError> {
+        self.inner
+            .register_actor_unique(actor_id, node_address)
+            .await
+    }
+
+    async fn lookup_actor(&self, actor_id: &str) -> Result<String, String> {
+        self.lookups.fetch_add(1, Ordering::SeqCst);
+        self.inner.lookup_actor(actor_id).await
+    }
+}
+
+// Records where messages went, failing for addresses marked as down
+#[derive(Clone, Default)]
+struct RecordingProtocol {
+    sent: Arc<Mutex<Vec<(String, String)>>>,
+    down: Arc<Mutex<Vec<String>>>,
+}
+
+#[async_trait]
+impl CommunicationProtocol for RecordingProtocol {
+    async fn send_message(&self, address: &str, message: &str) -> Result<(), String> {
+        if self.down.lock().unwrap().iter().any(|down| down == address) {
+            return Err(format!("{} is unreachable", address));
+        }
+        self.sent
+            .lock()
+            .unwrap()
+            .push((address.to_string(), message.to_string()));
+        Ok(())
+    }
+}
+
+async fn setup() -> (Arc<CountingRegistry>, RecordingProtocol) {
+    let registry = Arc::new(CountingRegistry::default());
+    registry
+        .register_actor("worker", "node-a/worker")
+        .await
+        .unwrap();
+    (registry, RecordingProtocol::default())
+}
+
+#[tokio::test]
+async fn test_sends_within_refresh_interval_look_up_once() -> Result<(), String> {
+    let (registry, protocol) = setup().await;
+    let clock = MockClock::new();
+    let worker = RemoteActorRef::new("worker", registry.clone(), protocol.clone())
+        .with_refresh_interval(Duration::from_secs(10))
+        .with_clock(Arc::new(clock.clone()));
+
+    for i in 0..5 {
+        worker.send(&format!("job-{}", i)).await?;
+    }
+    assert_eq!(registry.lookups.load(Ordering::SeqCst), 1);
+    assert_eq!(protocol.sent.lock().unwrap().len(), 5);
+
+    // Once stale, the address is looked up again
+    clock.advance(Duration::from_secs(11));
+    assert_eq!(worker.cached_address(), None);
+    worker.send("job-5").await?;
+    assert_eq!(registry.lookups.load(Ordering::SeqCst), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_failed_send_resolves_again() -> Result<(), String> {
+    let (registry, protocol) = setup().await;
+    let worker = RemoteActorRef::new("worker", registry.clone(), protocol.clone());
+    worker.send("first").await?;
+
+    // The actor moves and its old node goes away
+    registry.register_actor("worker", "node-b/worker").await?;
+    protocol
+        .down
+        .lock()
+        .unwrap()
+        .push("node-a/worker".to_string());
+    assert!(worker.send("lost").await.is_err());
+    assert_eq!(worker.cached_address(), None);
+
+    worker.send("second").await?;
+    assert_eq!(registry.lookups.load(Ordering::SeqCst), 2);
+    assert_eq!(
+        *protocol.sent.lock().unwrap(),
+        vec![
+            ("node-a/worker".to_string(), "first".to_string()),
+            ("node-b/worker".to_string(), "second".to_string()),
+        ]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_refresh_all_picks_up_moved_actors_and_marks_missing_ones_dead() -> Result<(), String>
+{
+    let (registry, protocol) = setup().await;
+    let refs = RemoteRefRegistry::new(registry.clone());
+    let workers: Vec<_> = (0..2)
+        .map(|_| RemoteActorRef::new("worker", registry.clone(), protocol.clone()))
+        .collect();
+    let ghost = RemoteActorRef::new("ghost", registry.clone(), protocol.clone());
+    for remote in workers.iter().chain([&ghost]) {
+        refs.track(remote);
+    }
+    for worker in &workers {
+        worker.send("before").await?;
+    }
+
+    // The partition heals with the worker on another node
+    registry.register_actor("worker", "node-b/worker").await?;
+    let report = refs.refresh_all().await;
+    assert_eq!(
+        report,
+        RefreshReport {
+            refreshed: vec!["worker".to_string()],
+            dead: vec!["ghost".to_string()],
+            failed: vec![],
+        }
+    );
+    for worker in &workers {
+        assert_eq!(worker.cached_address().as_deref(), Some("node-b/worker"));
+    }
+    assert!(ghost.is_dead());
+
+    // Dropped references are no longer tracked
+    drop(workers);
+    assert_eq!(refs.len(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_follow_applies_registrations_as_they_happen() -> Result<(), String> {
+    let registry = Arc::new(LocalRegistry::new());
+    registry.register_actor("worker", "node-a/worker").await?;
+    let refs = RemoteRefRegistry::new(registry.clone());
+    let worker = RemoteActorRef::new("worker", registry.clone(), RecordingProtocol::default());
+    refs.track(&worker);
+    worker.resolve().await?;
+    let follower = refs
+        .follow()
+        .expect("a local registry reports registrations");
+
+    registry.register_actor("worker", "node-b/worker").await?;
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while worker.cached_address().as_deref() != Some("node-b/worker") {
+            tokio::task::yield_now().await;
+        }
+    })
+    .await
+    .map_err(|_| "The registration never reached the reference".to_string())?;
+    follower.abort();
+    Ok(())
+}
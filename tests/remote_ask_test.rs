@@ -0,0 +1,119 @@
+use astra::actor_system::{Actor, ActorSystem, Message};
+use astra::network::ask::{Ask, Replies, CORRELATION_ID_HEADER};
+use astra::network::http::{CommunicationProtocol, HttpProtocol, HttpServer, HttpServerHandle};
+use astra::network::registry::{ActorRegistry, LocalRegistry};
+use astra::network::remote::RemoteActorRef;
+use async_trait::async_trait;
+use hyper::{Body, Client, Request, StatusCode};
+use std::error::Error;
+use std::sync::Arc;
+use std::time::Duration;
+
+// Answers asks with the message reversed, after `delay`; plain messages get no reply
+struct Reverser {
+    replies: Arc<Replies>,
+    delay: Duration,
+    late_replies: Arc<std::sync::Mutex<Vec<bool>>>,
+}
+
+#[async_trait]
+impl Actor for Reverser {
+    type Message = String;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<String>) -> Result<(), String> {
+        if let Message::Regular(message) = message {
+            if let Some(ask) = Ask::parse(&message) {
+                tokio::time::sleep(self.delay).await;
+                let reversed: String = ask.message.chars().rev().collect();
+                let delivered = self.replies.reply(&ask.correlation_id, reversed);
+                self.late_replies.lock().unwrap().push(!delivered);
+            }
+        }
+        Ok(())
+    }
+}
+
+async fn start_node(
+    delay: Duration,
+    ask_timeout: Duration,
+) -> Result<(HttpServerHandle, Arc<std::sync::Mutex<Vec<bool>>>), Box<dyn Error>> {
+    let replies = Arc::new(Replies::new());
+    let late_replies = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let mut system = ActorSystem::new();
+    system.add_actor(
+        "reverser".to_string(),
+        Reverser {
+            replies: Arc::clone(&replies),
+            delay,
+            late_replies: Arc::clone(&late_replies),
+        },
+    );
+    let server = HttpServer::new(Arc::new(system))
+        .with_replies(replies)
+        .with_ask_timeout(ask_timeout)
+        .start("127.0.0.1:0".parse()?)
+        .await?;
+    Ok((server, late_replies))
+}
+
+#[tokio::test]
+async fn test_remote_ask_over_loopback() -> Result<(), Box<dyn Error>> {
+    let (server, _) = start_node(Duration::ZERO, Duration::from_secs(5)).await?;
+    let registry = Arc::new(LocalRegistry::new());
+    registry
+        .register_actor(
+            "reverser",
+            &format!("http://{}/actors/reverser", server.local_addr()),
+        )
+        .await?;
+
+    let reverser = RemoteActorRef::new("reverser", registry, HttpProtocol::new());
+    let timeout = Duration::from_secs(5);
+    assert_eq!(reverser.ask("stressed", timeout).await?, "desserts");
+    assert_eq!(reverser.ask("drawer", timeout).await?, "reward");
+
+    server.stop();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_ask_times_out_and_late_reply_is_dropped() -> Result<(), Box<dyn Error>> {
+    let (server, late_replies) =
+        start_node(Duration::from_millis(300), Duration::from_millis(100)).await?;
+    let address = format!("http://{}/actors/reverser", server.local_addr());
+
+    // The server gives up before the actor answers
+    let err = HttpProtocol::new()
+        .ask(&address, "slow", Duration::from_secs(5))
+        .await
+        .unwrap_err();
+    assert!(err.contains("504"), "{}", err);
+
+    // The asker can give up first, too
+    let err = HttpProtocol::new()
+        .ask(&address, "slower", Duration::from_millis(50))
+        .await
+        .unwrap_err();
+    assert!(err.contains("No reply"), "{}", err);
+
+    // Both replies came too late and went nowhere
+    tokio::time::sleep(Duration::from_millis(700)).await;
+    assert_eq!(*late_replies.lock().unwrap(), vec![true, true]);
+
+    server.stop();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_malformed_correlation_id_is_refused() -> Result<(), Box<dyn Error>> {
+    let (server, _) = start_node(Duration::ZERO, Duration::from_secs(5)).await?;
+    let req = Request::post(format!("http://{}/actors/reverser", server.local_addr()))
+        .header(CORRELATION_ID_HEADER, "not a valid id!")
+        .body(Body::from("hello"))?;
+    let response = Client::new().request(req).await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    server.stop();
+    Ok(())
+}
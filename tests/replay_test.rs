@@ -0,0 +1,120 @@
+use astra::actor_system::{Actor, ActorSystem, Message};
+use astra::backends::file::FileBackend;
+use astra::backends::memory::MemoryBackend;
+use astra::backends::storage::StorageBackend;
+use async_trait::async_trait;
+use std::error::Error;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+
+// The commands of an event-sourced counter, logged as `add 5` or `sub 2`
+#[derive(Debug)]
+enum Command {
+    Add(i64),
+    Sub(i64),
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (verb, amount) = s
+            .split_once(' ')
+            .ok_or_else(|| format!("bad command {:?}", s))?;
+        let amount = amount.parse().map_err(|e| format!("bad amount: {}", e))?;
+        match verb {
+            "add" => Ok(Command::Add(amount)),
+            "sub" => Ok(Command::Sub(amount)),
+            _ => Err(format!("unknown verb {:?}", verb)),
+        }
+    }
+}
+
+struct Counter {
+    total: Arc<AtomicI64>,
+}
+
+#[async_trait]
+impl Actor for Counter {
+    type Message = Command;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error> {
+        match message {
+            Message::Regular(Command::Add(n)) => self.total.fetch_add(n, Ordering::SeqCst),
+            Message::Regular(Command::Sub(n)) => self.total.fetch_sub(n, Ordering::SeqCst),
+            _ => 0,
+        };
+        Ok(())
+    }
+}
+
+fn counter_system() -> (ActorSystem<Command>, Arc<AtomicI64>) {
+    let total = Arc::new(AtomicI64::new(0));
+    let mut system = ActorSystem::new();
+    system.add_actor(
+        "counter".to_string(),
+        Counter {
+            total: Arc::clone(&total),
+        },
+    );
+    (system, total)
+}
+
+#[tokio::test]
+async fn test_replay_rebuilds_state_from_append_log() -> Result<(), Box<dyn Error>> {
+    let path = std::env::temp_dir().join(format!("astra_{}_replay_log.txt", std::process::id()));
+    let path = path.to_str().unwrap();
+    let mut log = FileBackend::new_append(path).await?;
+    log.append("add 5\n").await?;
+    log.append("add 3\n").await?;
+    log.append("sub 2\n").await?;
+
+    // A fresh actor, as after a restart
+    let (system, total) = counter_system();
+    let replayed = system
+        .replay_from_backend("counter", FileBackend::new_append(path).await?)
+        .await?;
+    assert_eq!(replayed, 3);
+    assert_eq!(total.load(Ordering::SeqCst), 6);
+
+    system.shutdown().await;
+    log.cleanup().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_empty_log_replays_nothing() -> Result<(), Box<dyn Error>> {
+    let (system, total) = counter_system();
+    assert_eq!(
+        system
+            .replay_from_backend("counter", MemoryBackend::new())
+            .await?,
+        0
+    );
+    assert_eq!(total.load(Ordering::SeqCst), 0);
+    system.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_replay_stops_at_a_bad_record() -> Result<(), Box<dyn Error>> {
+    let mut log = MemoryBackend::new();
+    log.write("add 1\nmultiply 2\nadd 4\n").await?;
+
+    let (system, total) = counter_system();
+    let error = system
+        .replay_from_backend("counter", log)
+        .await
+        .unwrap_err();
+    assert!(error.contains("record 2"), "{}", error);
+    assert!(system
+        .replay_from_backend("missing", MemoryBackend::new())
+        .await
+        .is_err());
+
+    system.shutdown().await;
+    assert_eq!(total.load(Ordering::SeqCst), 1);
+    Ok(())
+}
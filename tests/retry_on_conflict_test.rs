@@ -0,0 +1,38 @@
+use astra::util::{retry_on_conflict, ConflictError};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[tokio::test]
+async fn test_conflicts_are_retried_until_the_write_goes_through() {
+    let attempts = AtomicUsize::new(0);
+    let result: Result<&str, ConflictError<String>> = retry_on_conflict(5, || async {
+        match attempts.fetch_add(1, Ordering::SeqCst) {
+            0 | 1 => Err(ConflictError::Conflict),
+            _ => Ok("written"),
+        }
+    })
+    .await;
+
+    assert_eq!(result, Ok("written"));
+    assert_eq!(attempts.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn test_retrying_stops_on_failure_or_after_max_attempts() {
+    let attempts = AtomicUsize::new(0);
+    let result: Result<(), _> = retry_on_conflict(5, || async {
+        attempts.fetch_add(1, Ordering::SeqCst);
+        Err(ConflictError::Failed("backend unavailable"))
+    })
+    .await;
+    assert_eq!(result, Err(ConflictError::Failed("backend unavailable")));
+    assert_eq!(attempts.load(Ordering::SeqCst), 1);
+
+    let attempts = AtomicUsize::new(0);
+    let result: Result<(), ConflictError<String>> = retry_on_conflict(3, || async {
+        attempts.fetch_add(1, Ordering::SeqCst);
+        Err(ConflictError::Conflict)
+    })
+    .await;
+    assert_eq!(result, Err(ConflictError::Conflict));
+    assert_eq!(attempts.load(Ordering::SeqCst), 3);
+}
@@ -0,0 +1,161 @@
+use astra::actor_system::{Actor, ActorSystem, ConsistentHashRouter, Message, Router};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+// Records the numbers it receives
+struct Collector {
+    received: Arc<Mutex<Vec<u32>>>,
+}
+
+#[async_trait]
+impl Actor for Collector {
+    type Message = u32;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<u32>) -> Result<(), String> {
+        if let Message::Regular(n) = message {
+            self.received.lock().unwrap().push(n);
+        }
+        Ok(())
+    }
+}
+
+// Content-based routing: even and odd numbers go to different actors, and
+// zero goes nowhere
+struct ParityRouter;
+
+impl Router<u32> for ParityRouter {
+    fn route(&self, message: &u32) -> Option<String> {
+        match message {
+            0 => None,
+            n if n % 2 == 0 => Some("even".to_string()),
+            _ => Some("odd".to_string()),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_dispatch_routes_by_content() -> Result<(), String> {
+    let even = Arc::new(Mutex::new(Vec::new()));
+    let odd = Arc::new(Mutex::new(Vec::new()));
+    let mut system = ActorSystem::new().with_router(Arc::new(ParityRouter));
+    system.add_actor(
+        "even".to_string(),
+        Collector {
+            received: Arc::clone(&even),
+        },
+    );
+    system.add_actor(
+        "odd".to_string(),
+        Collector {
+            received: Arc::clone(&odd),
+        },
+    );
+
+    for n in 1..=6 {
+        system.dispatch(n).await?;
+    }
+    let err = system.dispatch(0).await.unwrap_err();
+    assert!(err.contains("No route"), "{}", err);
+
+    system.wait_quiesced().await;
+    assert_eq!(*even.lock().unwrap(), vec![2, 4, 6]);
+    assert_eq!(*odd.lock().unwrap(), vec![1, 3, 5]);
+    system.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_dispatch_needs_a_router_and_a_target() {
+    let system: ActorSystem<u32> = ActorSystem::new();
+    assert_eq!(system.dispatch(1).await.unwrap_err(), "No router installed");
+
+    // A route to an actor that doesn't exist fails like send_message
+    let system: ActorSystem<u32> =
+        ActorSystem::new().with_router(Arc::new(|_: &u32| Some("missing".to_string())));
+    let err = system.dispatch(1).await.unwrap_err();
+    assert!(err.contains("missing"), "{}", err);
+}
+
+#[test]
+fn test_consistent_hash_keeps_keys_and_moves_only_removed_workers_keys() {
+    // Routes each number by its remainder modulo 1000
+    let router = ConsistentHashRouter::new(|n: &u32| n % 1000);
+    for worker in ["w0", "w1", "w2", "w3"] {
+        router.add_worker(worker);
+    }
+    let routes: HashMap<u32, String> = (0..1000)
+        .map(|key| (key, router.route(&key).unwrap()))
+        .collect();
+
+    // The same key always lands on the same worker
+    for key in 0..1000 {
+        assert_eq!(router.route(&(key + 1000)).unwrap(), routes[&key]);
+        assert_eq!(router.worker_for(&key).unwrap(), routes[&key]);
+    }
+    // Every worker gets a share
+    for worker in router.workers() {
+        assert!(
+            routes.values().any(|w| *w == worker),
+            "{} got no keys",
+            worker
+        );
+    }
+
+    assert!(router.remove_worker("w2"));
+    assert!(!router.remove_worker("w2"));
+    assert_eq!(router.workers(), vec!["w0", "w1", "w3"]);
+    for (key, before) in &routes {
+        let after = router.route(key).unwrap();
+        if before == "w2" {
+            assert_ne!(after, "w2");
+        } else {
+            assert_eq!(&after, before, "key {} moved off {}", key, before);
+        }
+    }
+
+    // Adding it back restores the original routes
+    router.add_worker("w2");
+    for (key, before) in &routes {
+        assert_eq!(&router.route(key).unwrap(), before);
+    }
+}
+
+#[tokio::test]
+async fn test_dispatch_through_consistent_hash_router() -> Result<(), String> {
+    let router = Arc::new(ConsistentHashRouter::new(|n: &u32| n % 10).with_worker("a"));
+    let mut system = ActorSystem::new().with_router(router.clone());
+    let mut received = HashMap::new();
+    for name in ["a", "b"] {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        system.add_actor(
+            name.to_string(),
+            Collector {
+                received: Arc::clone(&log),
+            },
+        );
+        received.insert(name, log);
+    }
+    router.add_worker("b");
+
+    for n in 0..40 {
+        system.dispatch(n).await?;
+    }
+    system.wait_quiesced().await;
+    // Each key's messages all went to the worker the router picks for it
+    for (name, log) in &received {
+        for n in log.lock().unwrap().iter() {
+            assert_eq!(router.worker_for(&(n % 10)).unwrap(), *name);
+        }
+    }
+    let total: usize = received.values().map(|log| log.lock().unwrap().len()).sum();
+    assert_eq!(total, 40);
+
+    // An empty pool routes nowhere
+    router.remove_worker("a");
+    router.remove_worker("b");
+    assert!(system.dispatch(1).await.unwrap_err().contains("No route"));
+    system.shutdown().await;
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use astra::actor_system::{Actor, ActorSystem, Message, RuntimeConfig};
+use async_trait::async_trait;
+use std::error::Error;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+// Reports the name of the thread each message is handled on
+struct ThreadReporter {
+    threads: mpsc::UnboundedSender<Option<String>>,
+}
+
+#[async_trait]
+impl Actor for ThreadReporter {
+    type Message = u32;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error> {
+        if let Message::Regular(_) = message {
+            let name = std::thread::current().name().map(str::to_string);
+            let _ = self.threads.send(name);
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_actors_run_on_the_dedicated_runtime() -> Result<(), Box<dyn Error>> {
+    let config = RuntimeConfig::new()
+        .with_worker_threads(2)
+        .with_thread_name("astra-test-worker");
+    let mut system = ActorSystem::new().with_runtime(config)?;
+    let (threads, mut seen) = mpsc::unbounded_channel();
+    system.add_actor("reporter".to_string(), ThreadReporter { threads });
+
+    system.send_message("reporter", 1).await?;
+    let _timer = system.send_after("reporter", 2, Duration::from_millis(10))?;
+    for _ in 0..2 {
+        let name = seen.recv().await.unwrap();
+        assert_eq!(name.as_deref(), Some("astra-test-worker"));
+    }
+
+    system.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_ambient_runtime_by_default() -> Result<(), Box<dyn Error>> {
+    let mut system = ActorSystem::new();
+    let (threads, mut seen) = mpsc::unbounded_channel();
+    system.add_actor("reporter".to_string(), ThreadReporter { threads });
+
+    system.send_message("reporter", 1).await?;
+    let name = seen.recv().await.unwrap();
+    assert_eq!(name, std::thread::current().name().map(str::to_string));
+
+    system.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_shutdown_stops_the_runtime() -> Result<(), Box<dyn Error>> {
+    let mut system =
+        ActorSystem::new().with_runtime(RuntimeConfig::new().with_worker_threads(1))?;
+    let (threads, _seen) = mpsc::unbounded_channel();
+    system.add_actor("reporter".to_string(), ThreadReporter { threads });
+    system.pipe_stream("reporter", futures::stream::pending())?;
+    assert_eq!(system.running_tasks(), 2);
+
+    // The pipe never ends by itself, but goes down with the runtime
+    system.shutdown().await;
+    tokio::time::timeout(Duration::from_secs(5), system.wait_until_stopped()).await?;
+    assert_eq!(system.running_tasks(), 0);
+    Ok(())
+}
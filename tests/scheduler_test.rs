@@ -0,0 +1,105 @@
+use astra::actor_system::{Actor, ActorOptions, ActorSystem, Message};
+use async_trait::async_trait;
+use std::error::Error;
+use tokio::sync::mpsc;
+
+const MESSAGES: usize = 400;
+
+// Reports its name for every message it handles
+struct Tagger {
+    name: &'static str,
+    handled: mpsc::UnboundedSender<&'static str>,
+}
+
+#[async_trait]
+impl Actor for Tagger {
+    type Message = u32;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error> {
+        if let Message::Regular(_) = message {
+            // Give other tasks a chance to run, as real work would
+            tokio::task::yield_now().await;
+            let _ = self.handled.send(self.name);
+        }
+        Ok(())
+    }
+}
+
+fn options(weight: u32) -> ActorOptions {
+    ActorOptions::new()
+        .with_mailbox_capacity(MESSAGES)
+        .with_weight(weight)
+}
+
+#[tokio::test]
+async fn test_turns_follow_actor_weights() -> Result<(), Box<dyn Error>> {
+    let (handled_tx, mut handled) = mpsc::unbounded_channel();
+    let mut system = ActorSystem::new().with_pooled_scheduler(1);
+    for (name, weight) in [("heavy", 3), ("light", 1)] {
+        system.add_actor_with_options(
+            name.to_string(),
+            Tagger {
+                name,
+                handled: handled_tx.clone(),
+            },
+            options(weight),
+        );
+    }
+
+    // Saturate both actors before either gets to run
+    for n in 0..MESSAGES as u32 {
+        system.try_send_message("heavy", n)?;
+        system.try_send_message("light", n)?;
+    }
+
+    let mut heavy = 0;
+    for _ in 0..200 {
+        if handled.recv().await == Some("heavy") {
+            heavy += 1;
+        }
+    }
+    let light = 200 - heavy;
+    // The weights are 3:1, so close to 150 of the first 200 messages are heavy's
+    assert!(
+        (140..=160).contains(&heavy),
+        "heavy {} light {}",
+        heavy,
+        light
+    );
+    assert!(light > 0, "the light actor was starved");
+
+    system.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_equal_weights_share_turns_evenly() -> Result<(), Box<dyn Error>> {
+    let (handled_tx, mut handled) = mpsc::unbounded_channel();
+    let mut system = ActorSystem::new().with_pooled_scheduler(1);
+    for name in ["first", "second"] {
+        system.add_actor_with_options(
+            name.to_string(),
+            Tagger {
+                name,
+                handled: handled_tx.clone(),
+            },
+            options(1),
+        );
+    }
+    for n in 0..10 {
+        system.try_send_message("first", n)?;
+        system.try_send_message("second", n)?;
+    }
+
+    let mut order = Vec::new();
+    for _ in 0..6 {
+        order.push(handled.recv().await.unwrap());
+    }
+    assert_eq!(
+        order,
+        vec!["first", "second", "first", "second", "first", "second"]
+    );
+    system.shutdown().await;
+    Ok(())
+}
@@ -0,0 +1,151 @@
+use astra::actor_system::{Actor, ActorOptions, ActorSystem, Message, SendError};
+use async_trait::async_trait;
+use std::error::Error;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Semaphore};
+
+// Tags every message with the actor's name
+struct Named {
+    name: &'static str,
+    seen: mpsc::UnboundedSender<String>,
+}
+
+#[async_trait]
+impl Actor for Named {
+    type Message = String;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error> {
+        if let Message::Regular(msg) = message {
+            let _ = self.seen.send(format!("{}:{}", self.name, msg));
+        }
+        Ok(())
+    }
+}
+
+fn system(names: &[&'static str]) -> (ActorSystem<String>, mpsc::UnboundedReceiver<String>) {
+    let (seen_tx, seen) = mpsc::unbounded_channel();
+    let mut system = ActorSystem::new();
+    for &name in names {
+        system.add_actor(
+            name.to_string(),
+            Named {
+                name,
+                seen: seen_tx.clone(),
+            },
+        );
+    }
+    (system, seen)
+}
+
+#[tokio::test]
+async fn test_prefix_pattern_hits_only_matching_actors() -> Result<(), Box<dyn Error>> {
+    let (system, mut seen) = system(&["worker-1", "logger", "worker-2"]);
+    assert_eq!(system.actor_names(), vec!["worker-1", "logger", "worker-2"]);
+
+    let results = system.send_matching("worker-*", "drain".to_string()).await;
+    let names: Vec<_> = results.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, vec!["worker-1", "worker-2"]);
+    assert!(results.iter().all(|(_, result)| result.is_ok()));
+
+    system.shutdown().await;
+    let mut delivered = Vec::new();
+    while let Some(message) = seen.recv().await {
+        delivered.push(message);
+    }
+    delivered.sort();
+    assert_eq!(delivered, vec!["worker-1:drain", "worker-2:drain"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_glob_wildcards() {
+    let (system, _seen) = system(&["eu-db-1", "eu-web-1", "us-db-1", "eu-db-12"]);
+    let matched = |pattern: &'static str| {
+        let system = system.clone();
+        async move {
+            system
+                .send_matching(pattern, "ping".to_string())
+                .await
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>()
+        }
+    };
+
+    assert_eq!(matched("eu-*-1").await, vec!["eu-db-1", "eu-web-1"]);
+    assert_eq!(matched("*-db-?").await, vec!["eu-db-1", "us-db-1"]);
+    assert_eq!(matched("us-db-1").await, vec!["us-db-1"]);
+    assert_eq!(matched("*").await.len(), 4);
+    assert!(matched("eu-db").await.is_empty());
+    system.shutdown().await;
+}
+
+// Holds each message until the test hands out a permit
+struct Stuck {
+    permits: Arc<Semaphore>,
+}
+
+#[async_trait]
+impl Actor for Stuck {
+    type Message = String;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error> {
+        if let Message::Regular(_) = message {
+            self.permits.acquire().await.unwrap().forget();
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_broadcast_try_skips_saturated_actors() -> Result<(), Box<dyn Error>> {
+    let (mut system, mut seen) = system(&["worker-1", "worker-2"]);
+    let permits = Arc::new(Semaphore::new(0));
+    system.add_actor_with_options(
+        "stuck".to_string(),
+        Stuck {
+            permits: Arc::clone(&permits),
+        },
+        ActorOptions::new().with_mailbox_capacity(1),
+    );
+    // One message being handled, one filling the mailbox
+    system.send_message("stuck", "busy".to_string()).await?;
+    while system
+        .try_send_message("stuck", "queued".to_string())
+        .is_err()
+    {
+        tokio::task::yield_now().await;
+    }
+
+    let results = system.broadcast_try("reload".to_string());
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0], ("worker-1".to_string(), Ok(())));
+    assert_eq!(results[1], ("worker-2".to_string(), Ok(())));
+    assert_eq!(
+        results[2],
+        (
+            "stuck".to_string(),
+            Err(SendError::MailboxFull("stuck".to_string()))
+        )
+    );
+
+    permits.add_permits(2);
+    system.shutdown().await;
+    let mut delivered = Vec::new();
+    while let Some(message) = seen.recv().await {
+        delivered.push(message);
+    }
+    delivered.sort();
+    assert_eq!(delivered, vec!["worker-1:reload", "worker-2:reload"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_try_send_matching_filters_by_pattern() {
+    let (system, _seen) = system(&["worker-1", "logger"]);
+    let results = system.try_send_matching("log*", "rotate".to_string());
+    assert_eq!(results, vec![("logger".to_string(), Ok(()))]);
+    system.shutdown().await;
+}
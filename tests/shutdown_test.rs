@@ -0,0 +1,347 @@
+use astra::actor_system::{
+    Actor, ActorOptions, ActorRef, ActorSystem, Message, SendError, StopMode,
+};
+use async_trait::async_trait;
+use std::error::Error;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::sync::{mpsc, Semaphore};
+
+// Records messages; with a gate it waits for a permit before each one
+struct Recorder {
+    gate: Option<Arc<Semaphore>>,
+    seen: mpsc::UnboundedSender<String>,
+}
+
+#[async_trait]
+impl Actor for Recorder {
+    type Message = String;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error> {
+        if let Message::Regular(msg) = message {
+            if let Some(gate) = &self.gate {
+                gate.acquire().await.unwrap().forget();
+            }
+            let _ = self.seen.send(msg);
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_send_after_shutdown_is_refused() -> Result<(), Box<dyn Error>> {
+    let (seen_tx, mut seen) = mpsc::unbounded_channel();
+    let mut system = ActorSystem::new().with_dead_letters(10);
+    system.add_actor(
+        "worker".to_string(),
+        Recorder {
+            gate: None,
+            seen: seen_tx,
+        },
+    );
+    system.shutdown().await;
+
+    assert_eq!(
+        system.try_send_message("worker", "late".to_string()),
+        Err(SendError::Closing("worker".to_string()))
+    );
+    let err = system
+        .send_message("worker", "later".to_string())
+        .await
+        .unwrap_err();
+    assert_eq!(err, "Actor worker is shutting down");
+    assert_eq!(system.dead_letter_count(), 2);
+    assert!(seen.recv().await.is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_closing_actor_still_drains_its_mailbox() -> Result<(), Box<dyn Error>> {
+    let (seen_tx, mut seen) = mpsc::unbounded_channel();
+    let gate = Arc::new(Semaphore::new(0));
+    let mut system = ActorSystem::new();
+    system.add_actor(
+        "worker".to_string(),
+        Recorder {
+            gate: Some(Arc::clone(&gate)),
+            seen: seen_tx,
+        },
+    );
+    let worker = system.actor_ref("worker").unwrap();
+    worker.send("queued".to_string()).await?;
+
+    // The actor is stuck on its first message, so shutdown can't finish yet
+    let stopping = system.clone();
+    let shutdown = tokio::spawn(async move { stopping.shutdown().await });
+    while !worker.is_closing() {
+        tokio::task::yield_now().await;
+    }
+    assert_eq!(
+        worker.try_send("rejected".to_string()),
+        Err(SendError::Closing("worker".to_string()))
+    );
+
+    // What was queued before the shutdown is still handled
+    gate.add_permits(1);
+    shutdown.await?;
+    assert_eq!(seen.recv().await.as_deref(), Some("queued"));
+    assert!(seen.recv().await.is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sender_waiting_on_a_full_mailbox_is_refused_by_shutdown() -> Result<(), Box<dyn Error>>
+{
+    let (seen_tx, mut seen) = mpsc::unbounded_channel();
+    let gate = Arc::new(Semaphore::new(0));
+    let mut system = ActorSystem::new().with_dead_letters(10);
+    system.add_actor_with_options(
+        "worker".to_string(),
+        Recorder {
+            gate: Some(Arc::clone(&gate)),
+            seen: seen_tx,
+        },
+        ActorOptions::new().with_mailbox_capacity(1),
+    );
+    let worker = system.actor_ref("worker").unwrap();
+
+    // The actor is stuck on its first message and its mailbox is full
+    worker.send("handling".to_string()).await?;
+    while worker.mailbox_depth() > 0 {
+        tokio::task::yield_now().await;
+    }
+    worker.send("queued".to_string()).await?;
+    let waiting = tokio::spawn({
+        let worker = worker.clone();
+        async move { worker.send("waiting".to_string()).await }
+    });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    // Shutdown refuses the waiting sender instead of queueing its message
+    // behind `Shutdown`
+    let stopping = system.clone();
+    let shutdown = tokio::spawn(async move { stopping.shutdown().await });
+    assert_eq!(waiting.await?.unwrap_err(), "Actor worker is shutting down");
+    assert_eq!(system.dead_letter_count(), 1);
+
+    gate.add_permits(2);
+    shutdown.await?;
+    assert_eq!(seen.recv().await.as_deref(), Some("handling"));
+    assert_eq!(seen.recv().await.as_deref(), Some("queued"));
+    assert!(seen.recv().await.is_none());
+    Ok(())
+}
+
+// Forwards everything downstream, flushing a last message on its way out. The
+// downstream actor is filled in once it is registered.
+struct Stage {
+    name: &'static str,
+    downstream: Arc<OnceLock<ActorRef<String>>>,
+    events: mpsc::UnboundedSender<String>,
+}
+
+#[async_trait]
+impl Actor for Stage {
+    type Message = String;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error> {
+        match (message, self.downstream.get()) {
+            (Message::Regular(msg), Some(downstream)) => downstream.send(msg).await,
+            (Message::Regular(msg), None) => {
+                let _ = self.events.send(format!("{} got {}", self.name, msg));
+                Ok(())
+            }
+            (Message::Shutdown, Some(downstream)) => {
+                downstream.send(format!("{} final", self.name)).await
+            }
+            (Message::Shutdown, None) | (Message::System(_), _) => Ok(()),
+        }
+    }
+
+    async fn cleanup(&mut self) {
+        let _ = self.events.send(format!("{} stopped", self.name));
+    }
+}
+
+fn stage(name: &'static str, events: &mpsc::UnboundedSender<String>) -> Stage {
+    Stage {
+        name,
+        downstream: Arc::new(OnceLock::new()),
+        events: events.clone(),
+    }
+}
+
+#[tokio::test]
+async fn test_dependent_actor_stops_first() -> Result<(), Box<dyn Error>> {
+    let (events_tx, mut events) = mpsc::unbounded_channel();
+    let mut system = ActorSystem::new();
+    // "a" writes through "b" but is registered first, so reverse registration
+    // order alone would stop "b" before it
+    let a = stage("a", &events_tx);
+    let downstream = Arc::clone(&a.downstream);
+    system.add_actor("a".to_string(), a);
+    system.add_actor("b".to_string(), stage("b", &events_tx));
+    downstream.set(system.actor_ref("b").unwrap()).unwrap();
+    assert_eq!(system.shutdown_order(), vec!["b", "a"]);
+
+    system.depends_on("a", "b")?;
+    assert_eq!(system.shutdown_order(), vec!["a", "b"]);
+    system.send_message("a", "write".to_string()).await?;
+    system.shutdown().await;
+
+    drop(events_tx);
+    let mut log = Vec::new();
+    while let Some(event) = events.recv().await {
+        log.push(event);
+    }
+    // "b" handled everything "a" sent, including its final flush, and only
+    // stopped after "a" had
+    let at = |event: &str| log.iter().position(|e| e == event).unwrap();
+    assert_eq!(log.len(), 4);
+    assert!(at("b got write") < at("b got a final"));
+    assert!(at("a stopped") < at("b stopped"));
+    assert!(at("b got a final") < at("b stopped"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_dependency_cycles_are_refused() -> Result<(), Box<dyn Error>> {
+    let (events_tx, _events) = mpsc::unbounded_channel();
+    let mut system = ActorSystem::new();
+    for name in ["a", "b", "c", "d"] {
+        system.add_actor(name.to_string(), stage(name, &events_tx));
+    }
+    system.depends_on("a", "b")?;
+    system.depends_on("b", "c")?;
+    system.depends_on("d", "c")?;
+
+    let err = system.depends_on("c", "a").unwrap_err();
+    assert!(err.contains("cycle"), "{}", err);
+    assert!(system.depends_on("a", "a").is_err());
+    assert!(system.depends_on("a", "missing").is_err());
+
+    // Dependents first, ties broken by reverse registration order
+    assert_eq!(system.shutdown_order(), vec!["d", "a", "b", "c"]);
+    system.shutdown().await;
+    Ok(())
+}
+
+// Fails to clean up, e.g. a connection that won't close
+struct Leaky;
+
+#[async_trait]
+impl Actor for Leaky {
+    type Message = String;
+    type Error = String;
+
+    async fn receive(&mut self, _message: Message<Self::Message>) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    async fn try_cleanup(&mut self) -> Result<(), Self::Error> {
+        Err("connection refused to close".to_string())
+    }
+}
+
+#[tokio::test]
+async fn test_shutdown_reports_how_each_actor_stopped() -> Result<(), Box<dyn Error>> {
+    let (seen_tx, _seen) = mpsc::unbounded_channel();
+    let mut system = ActorSystem::new();
+    system.add_actor(
+        "clean".to_string(),
+        Recorder {
+            gate: None,
+            seen: seen_tx.clone(),
+        },
+    );
+    system.add_actor("leaky".to_string(), Leaky);
+    system.add_actor_with_options(
+        "cut-short".to_string(),
+        Recorder {
+            gate: None,
+            seen: seen_tx,
+        },
+        ActorOptions::new().with_stop_mode(StopMode::Immediate),
+    );
+
+    let report = system.shutdown().await;
+    assert_eq!(report.stopped_cleanly, ["clean"]);
+    assert_eq!(
+        report.cleanup_failed,
+        [(
+            "leaky".to_string(),
+            "\"connection refused to close\"".to_string()
+        )]
+    );
+    assert_eq!(report.aborted, ["cut-short"]);
+    assert!(!report.is_clean());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_shutdown_timeout_reports_undrained_actors_as_aborted() -> Result<(), Box<dyn Error>> {
+    let (seen_tx, _seen) = mpsc::unbounded_channel();
+    let gate = Arc::new(Semaphore::new(0));
+    let mut system = ActorSystem::new();
+    system.add_actor(
+        "idle".to_string(),
+        Recorder {
+            gate: None,
+            seen: seen_tx.clone(),
+        },
+    );
+    // Stopped first, and held up past the timeout
+    system.add_actor(
+        "slow".to_string(),
+        Recorder {
+            gate: Some(Arc::clone(&gate)),
+            seen: seen_tx,
+        },
+    );
+    system.send_message("slow", "late".to_string()).await?;
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        gate.add_permits(1);
+    });
+
+    // The idle actor never got its turn and is cancelled, the slow one still
+    // drains before the second timeout
+    let report = system.shutdown_timeout(Duration::from_millis(100)).await?;
+    assert_eq!(report.stopped_cleanly, ["slow"]);
+    assert_eq!(report.aborted, ["idle"]);
+    assert!(report.cleanup_failed.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_stuck_actor_does_not_hold_up_shutdown() -> Result<(), Box<dyn Error>> {
+    let (seen_tx, _seen) = mpsc::unbounded_channel();
+    let gate = Arc::new(Semaphore::new(0));
+    let mut system = ActorSystem::new().with_shutdown_timeout(Duration::from_millis(50));
+    system.add_actor(
+        "idle".to_string(),
+        Recorder {
+            gate: None,
+            seen: seen_tx.clone(),
+        },
+    );
+    // Stopped first, and stuck on its message until the gate opens
+    system.add_actor(
+        "stuck".to_string(),
+        Recorder {
+            gate: Some(Arc::clone(&gate)),
+            seen: seen_tx,
+        },
+    );
+    system.send_message("stuck", "never".to_string()).await?;
+
+    let report = tokio::time::timeout(Duration::from_secs(5), system.shutdown()).await?;
+    assert_eq!(report.aborted, ["stuck"]);
+    assert_eq!(report.stopped_cleanly, ["idle"]);
+
+    // The stuck message was cut short, and no task is left behind
+    assert_eq!(system.running_tasks(), 0);
+    Ok(())
+}
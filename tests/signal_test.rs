@@ -0,0 +1,65 @@
+#![cfg(all(unix, feature = "signal"))]
+
+use astra::actor_system::{Actor, ActorSystem, Message};
+use async_trait::async_trait;
+use std::error::Error;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::time::{timeout, Duration};
+
+struct Flagged {
+    cleaned_up: Arc<AtomicBool>,
+}
+
+#[async_trait]
+impl Actor for Flagged {
+    type Message = String;
+    type Error = String;
+
+    async fn receive(&mut self, _message: Message<Self::Message>) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    async fn cleanup(&mut self) {
+        self.cleaned_up.store(true, Ordering::SeqCst);
+    }
+}
+
+#[tokio::test]
+async fn test_sigterm_shuts_system_down() -> Result<(), Box<dyn Error>> {
+    // Install a SIGTERM handler up front so the signal never kills the test binary
+    let mut guard = signal(SignalKind::terminate())?;
+
+    let cleaned_up = Arc::new(AtomicBool::new(false));
+    let mut system = ActorSystem::new().with_shutdown_timeout(Duration::from_secs(2));
+    system.add_actor(
+        "worker".to_string(),
+        Flagged {
+            cleaned_up: Arc::clone(&cleaned_up),
+        },
+    );
+    let token = system.cancellation_token();
+    let mut running = tokio::spawn(system.run_until_signal());
+
+    // `run_until_signal` misses a signal sent before it starts listening, so
+    // keep signalling until it returns
+    let stopped = timeout(Duration::from_secs(5), async {
+        loop {
+            let status = Command::new("kill")
+                .args(["-TERM", &std::process::id().to_string()])
+                .status()?;
+            assert!(status.success());
+            tokio::select! {
+                stopped = &mut running => return Ok::<_, Box<dyn Error>>(stopped?),
+                _ = guard.recv() => {}
+            }
+        }
+    })
+    .await??;
+    stopped?;
+    assert!(cleaned_up.load(Ordering::SeqCst));
+    assert!(token.is_cancelled());
+    Ok(())
+}
@@ -1,12 +1,29 @@
+use astra::actor_system::ActorSystem;
 use astra::backends::file::FileBackend;
-use astra::snapshot_actor::SnapshotActor;
+use astra::backends::memory::MemoryBackend;
+use astra::backends::storage::StorageBackend;
+use astra::clock::MockClock;
+use astra::logging::ConsoleLogger;
+use astra::snapshot_actor::{
+    LoadOutcome, ShutdownMode, SnapshotActor, SnapshotConflict, SnapshotFormat, StaleSnapshot,
+};
+use astra::supervision::{SupervisionEventKind, SupervisionStrategy, Supervisor};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::error::Error;
-use tokio::time::{sleep, Duration};
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::SystemTime;
 
 #[tokio::test]
 async fn test_snapshot_actor_lifecycle() -> Result<(), Box<dyn Error>> {
     let file_backend = FileBackend::new("snapshot_test.txt").await?;
-    let mut actor = SnapshotActor::new("actor1".to_string(), file_backend);
+    let clock = MockClock::new();
+    let mut actor =
+        SnapshotActor::new("actor1".to_string(), file_backend).with_clock(Arc::new(clock.clone()));
 
     // Set the actor's state to a specific value
     actor.set_state("expected_state".to_string());
@@ -21,7 +38,8 @@ async fn test_snapshot_actor_lifecycle() -> Result<(), Box<dyn Error>> {
     });
 
     // Simulate some work
-    sleep(Duration::from_secs(5)).await;
+    clock.wait_for_sleepers(1).await;
+    clock.advance(Duration::from_secs(5));
 
     // Shutdown the snapshot task
     actor.shutdown();
@@ -36,3 +54,589 @@ async fn test_snapshot_actor_lifecycle() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_load_state_tells_whether_anything_was_restored() -> Result<(), Box<dyn Error>> {
+    let backend = MemoryBackend::new();
+    let mut actor = SnapshotActor::new("counter".to_string(), backend.clone());
+    actor.set_state("defaults".to_string());
+    assert_eq!(actor.load_state().await?, LoadOutcome::NoPriorState);
+    assert_eq!(actor.get_state(), "defaults");
+
+    actor.set_state("saved".to_string());
+    actor.save_state().await?;
+    let mut restarted: SnapshotActor<_> =
+        SnapshotActor::new("counter".to_string(), backend.clone());
+    assert_eq!(restarted.load_state().await?, LoadOutcome::Loaded);
+    assert_eq!(restarted.get_state(), "saved");
+
+    // The snapshot of another actor is nothing to restore for this one
+    let mut other: SnapshotActor<_> = SnapshotActor::new("other".to_string(), backend);
+    assert_eq!(other.load_state().await?, LoadOutcome::NoPriorState);
+    assert_eq!(other.get_state(), "");
+    Ok(())
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct Inventory {
+    owner: String,
+    items: Vec<(String, u32)>,
+    tags: BTreeMap<String, Vec<String>>,
+    note: Option<String>,
+}
+
+fn sample_inventory() -> Inventory {
+    let mut tags = BTreeMap::new();
+    tags.insert(
+        "fruit".to_string(),
+        vec!["apple".to_string(), "pear".to_string()],
+    );
+    Inventory {
+        owner: "warehouse: east".to_string(),
+        items: vec![("apple".to_string(), 3), ("pear".to_string(), 7)],
+        tags,
+        note: Some("restock friday".to_string()),
+    }
+}
+
+async fn round_trip(format: SnapshotFormat) -> Result<(), Box<dyn Error>> {
+    let backend = MemoryBackend::new();
+    let mut actor: SnapshotActor<_, Inventory> =
+        SnapshotActor::new("inventory".to_string(), backend.clone()).with_format(format);
+    actor.set_state(sample_inventory());
+    actor.save_state().await?;
+
+    let mut restored: SnapshotActor<_, Inventory> =
+        SnapshotActor::new("inventory".to_string(), backend).with_format(format);
+    restored.load_state().await?;
+    assert_eq!(restored.get_state(), sample_inventory());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_json_round_trip() -> Result<(), Box<dyn Error>> {
+    round_trip(SnapshotFormat::Json).await
+}
+
+#[cfg(feature = "bincode")]
+#[tokio::test]
+async fn test_bincode_round_trip() -> Result<(), Box<dyn Error>> {
+    round_trip(SnapshotFormat::Bincode).await
+}
+
+#[cfg(feature = "msgpack")]
+#[tokio::test]
+async fn test_msgpack_round_trip() -> Result<(), Box<dyn Error>> {
+    round_trip(SnapshotFormat::MessagePack).await
+}
+
+#[cfg(feature = "bincode")]
+#[tokio::test]
+async fn test_load_rejects_other_format() -> Result<(), Box<dyn Error>> {
+    let backend = MemoryBackend::new();
+    let mut writer: SnapshotActor<_, Inventory> =
+        SnapshotActor::new("inventory".to_string(), backend.clone())
+            .with_format(SnapshotFormat::Bincode);
+    writer.set_state(sample_inventory());
+    writer.save_state().await?;
+
+    let mut reader: SnapshotActor<_, Inventory> =
+        SnapshotActor::new("inventory".to_string(), backend);
+    let err = reader.load_state().await.unwrap_err();
+    assert!(err.to_string().contains("stored as bincode"), "{}", err);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_load_reads_untagged_snapshots() -> Result<(), Box<dyn Error>> {
+    // A snapshot file as written before formats were tagged
+    let path = std::env::temp_dir().join(format!("astra_{}_untagged.txt", std::process::id()));
+    let path = path.to_str().unwrap();
+    let backend = FileBackend::new(path).await?;
+    std::fs::write(path, "actor1:left: 3, right: 4")?;
+
+    let mut actor: SnapshotActor<_, String> =
+        SnapshotActor::new("actor1".to_string(), backend.clone());
+    assert_eq!(actor.load_state().await?, LoadOutcome::Loaded);
+    assert_eq!(actor.get_state(), "left: 3, right: 4");
+    assert_eq!(actor.last_sequence(), Some(0));
+
+    // Saving moves it to the current layout
+    actor.save_state().await?;
+    let mut reloaded: SnapshotActor<_, String> =
+        SnapshotActor::new("actor1".to_string(), backend.clone());
+    reloaded.load_state().await?;
+    assert_eq!(reloaded.get_state(), "left: 3, right: 4");
+    assert_eq!(reloaded.last_sequence(), Some(1));
+
+    // Only a String state can come from an untagged snapshot
+    std::fs::write(path, "inventory:42 widgets")?;
+    let mut inventory: SnapshotActor<_, Inventory> =
+        SnapshotActor::new("inventory".to_string(), backend);
+    let err = inventory.load_state().await.unwrap_err();
+    assert!(err.to_string().contains("untagged format"), "{}", err);
+
+    std::fs::remove_file(path)?;
+    Ok(())
+}
+
+// Run the snapshot task until shut down, with `state` set, in the given mode
+async fn run_and_stop(path: &str, mode: ShutdownMode) -> Result<(), Box<dyn Error>> {
+    let mut actor = SnapshotActor::new("actor1".to_string(), FileBackend::new(path).await?)
+        .with_shutdown_mode(mode);
+    actor.set_state("final_state".to_string());
+
+    let mut actor_clone = actor.clone();
+    let snapshot_task = tokio::spawn(async move {
+        actor_clone.start_snapshot_task().await;
+    });
+    actor.shutdown();
+    snapshot_task.await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_persist_mode_keeps_state_across_restarts() -> Result<(), Box<dyn Error>> {
+    let path = std::env::temp_dir().join(format!("astra_{}_persist.txt", std::process::id()));
+    let path = path.to_str().unwrap();
+    run_and_stop(path, ShutdownMode::Persist).await?;
+
+    // A restarted actor picks up the state saved on shutdown
+    let backend = FileBackend::new_append(path).await?;
+    let mut restarted: SnapshotActor<_, String> = SnapshotActor::new("actor1".to_string(), backend);
+    restarted.load_state().await?;
+    assert_eq!(restarted.get_state(), "final_state");
+
+    std::fs::remove_file(path)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cleanup_mode_removes_snapshot() -> Result<(), Box<dyn Error>> {
+    let path = std::env::temp_dir().join(format!("astra_{}_cleanup.txt", std::process::id()));
+    let path = path.to_str().unwrap();
+    run_and_stop(path, ShutdownMode::Cleanup).await?;
+
+    assert!(!std::path::Path::new(path).exists());
+    Ok(())
+}
+
+// Save `inventory` after each of a few successive changes
+async fn save_changes(
+    actor: &mut SnapshotActor<MemoryBackend, Inventory>,
+) -> Result<Inventory, Box<dyn Error>> {
+    let mut inventory = sample_inventory();
+    actor.set_state(inventory.clone());
+    actor.save_state().await?;
+
+    inventory.items[0].1 = 4;
+    actor.set_state(inventory.clone());
+    actor.save_state().await?;
+
+    inventory.note = None;
+    inventory
+        .tags
+        .insert("veg".to_string(), vec!["leek".to_string()]);
+    actor.set_state(inventory.clone());
+    actor.save_state().await?;
+
+    inventory.tags.remove("fruit");
+    inventory.owner = "warehouse: west".to_string();
+    actor.set_state(inventory.clone());
+    actor.save_state().await?;
+    Ok(inventory)
+}
+
+#[tokio::test]
+async fn test_delta_snapshots_rebuild_the_latest_state() -> Result<(), Box<dyn Error>> {
+    let (backend, log) = (MemoryBackend::new(), MemoryBackend::new());
+    let mut actor = SnapshotActor::new("inventory".to_string(), backend.clone())
+        .with_delta_snapshots(log.clone(), 10);
+    let expected = save_changes(&mut actor).await?;
+
+    // One full snapshot of the first state, then a delta per change
+    let mut full = backend.clone();
+    assert!(full.read().await?.contains("restock friday"));
+    let deltas = log.clone().read().await?;
+    assert_eq!(deltas.lines().count(), 3, "{}", deltas);
+
+    // Saving an unchanged state writes nothing
+    actor.save_state().await?;
+    assert_eq!(log.clone().read().await?, deltas);
+
+    let mut restored: SnapshotActor<_, Inventory> =
+        SnapshotActor::new("inventory".to_string(), backend).with_delta_snapshots(log, 10);
+    restored.load_state().await?;
+    assert_eq!(restored.get_state(), expected);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_delta_snapshots_take_periodic_full_snapshots() -> Result<(), Box<dyn Error>> {
+    let (backend, log) = (MemoryBackend::new(), MemoryBackend::new());
+    let mut actor = SnapshotActor::new("inventory".to_string(), backend.clone())
+        .with_delta_snapshots(log.clone(), 3);
+    let expected = save_changes(&mut actor).await?;
+
+    // The fourth save started a new baseline and emptied the log
+    assert!(backend.clone().read().await?.contains("warehouse: west"));
+    assert_eq!(log.clone().read().await?, "");
+
+    let mut restored: SnapshotActor<_, Inventory> =
+        SnapshotActor::new("inventory".to_string(), backend).with_delta_snapshots(log, 3);
+    restored.load_state().await?;
+    assert_eq!(restored.get_state(), expected);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_corrupted_delta_log_falls_back_to_full_snapshot() -> Result<(), Box<dyn Error>> {
+    let (backend, log) = (MemoryBackend::new(), MemoryBackend::new());
+    let mut actor = SnapshotActor::new("inventory".to_string(), backend.clone())
+        .with_delta_snapshots(log.clone(), 10);
+    save_changes(&mut actor).await?;
+
+    // A write torn in a crash leaves half a delta at the end of the log
+    log.clone().extend_bytes(b"4:{\"merge\":{\"se").await?;
+
+    let mut restored: SnapshotActor<_, Inventory> =
+        SnapshotActor::new("inventory".to_string(), backend.clone())
+            .with_delta_snapshots(log.clone(), 10);
+    restored.load_state().await?;
+    assert_eq!(restored.get_state(), sample_inventory());
+
+    // The next save replaces the broken chain with a fresh baseline
+    restored.save_state().await?;
+    assert_eq!(log.clone().read().await?, "");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_manifest_describes_the_saved_snapshot() -> Result<(), Box<dyn Error>> {
+    let (backend, manifest) = (MemoryBackend::new(), MemoryBackend::new());
+    let mut actor = SnapshotActor::new("inventory".to_string(), backend.clone())
+        .with_manifest(manifest.clone())
+        .with_schema_version(3);
+    assert!(actor.snapshot_info().await.is_err(), "nothing saved yet");
+
+    let before = SystemTime::now();
+    actor.set_state(sample_inventory());
+    actor.save_state().await?;
+
+    let info = actor.snapshot_info().await?;
+    let blob = backend.clone().read_bytes().await?;
+    assert_eq!(info.actor_id, "inventory");
+    assert_eq!(info.format, "json");
+    assert_eq!(info.schema_version, 3);
+    assert_eq!(info.writer_version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(info.len, blob.len());
+    assert!(info.matches(&blob));
+    assert!(info.saved_at >= before && info.saved_at <= SystemTime::now());
+
+    // Another actor reads the same manifest without loading the state
+    let mut reader: SnapshotActor<_, Inventory> =
+        SnapshotActor::new("inventory".to_string(), MemoryBackend::new()).with_manifest(manifest);
+    assert_eq!(reader.snapshot_info().await?, info);
+    assert_eq!(reader.get_state(), Inventory::default());
+
+    // A blob changed behind the manifest's back no longer matches it
+    let mut tampered = blob.clone();
+    tampered[blob.len() - 2] ^= 1;
+    assert!(!info.matches(&tampered));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_export_and_import_move_state_between_backends() -> Result<(), Box<dyn Error>> {
+    let mut source: SnapshotActor<_, Inventory> = SnapshotActor::new(
+        "inventory".to_string(),
+        FileBackend::new("snapshot_export_test.txt").await?,
+    );
+    source.set_state(sample_inventory());
+    let archive = source.export().await?;
+    source.close().await?;
+    tokio::fs::remove_file("snapshot_export_test.txt").await?;
+
+    let target = MemoryBackend::new();
+    let mut imported: SnapshotActor<_, Inventory> =
+        SnapshotActor::new("inventory".to_string(), target.clone());
+    imported.import(&archive).await?;
+    assert_eq!(imported.get_state(), sample_inventory());
+
+    // The import was saved to the new backend
+    let mut restored: SnapshotActor<_, Inventory> =
+        SnapshotActor::new("inventory".to_string(), target);
+    restored.load_state().await?;
+    assert_eq!(restored.get_state(), sample_inventory());
+
+    // Another actor's archive, or a corrupted one, is refused
+    let mut other: SnapshotActor<_, Inventory> =
+        SnapshotActor::new("orders".to_string(), MemoryBackend::new());
+    let refused = other.import(&archive).await.unwrap_err().to_string();
+    assert_eq!(
+        refused,
+        "Snapshot archive is for actor inventory, not orders"
+    );
+    assert_eq!(other.get_state(), Inventory::default());
+
+    let tampered = String::from_utf8(archive)?.replace("pear", "plum");
+    assert!(restored.import(tampered.as_bytes()).await.is_err());
+    assert!(restored.import(b"not an archive").await.is_err());
+    assert_eq!(restored.get_state(), sample_inventory());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_snapshot_task_saves_state_set_after_it_started() -> Result<(), Box<dyn Error>> {
+    let backend = MemoryBackend::new();
+    let clock = MockClock::new();
+    let mut actor = SnapshotActor::new("actor1".to_string(), backend.clone())
+        .with_snapshot_interval(Duration::from_secs(10))
+        .with_clock(Arc::new(clock.clone()));
+
+    let mut snapshotter = actor.clone();
+    let snapshot_task = tokio::spawn(async move {
+        snapshotter.start_snapshot_task().await;
+    });
+    // The first save is done and the task waits for the next tick
+    clock.wait_for_sleepers(1).await;
+    assert_eq!(backend.clone().read().await?, "actor1:json:1:\"\"");
+
+    actor.set_state("live".to_string());
+    clock.advance(Duration::from_secs(10));
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while backend.clone().read().await.unwrap() != "actor1:json:2:\"live\"" {
+            tokio::task::yield_now().await;
+        }
+    })
+    .await?;
+
+    actor.set_state("final".to_string());
+    actor.shutdown();
+    snapshot_task.await?;
+    assert_eq!(backend.clone().read().await?, "actor1:json:3:\"final\"");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_optimistic_save_rejects_stale_writer() -> Result<(), Box<dyn Error>> {
+    let backend = MemoryBackend::new();
+    let mut first: SnapshotActor<_> =
+        SnapshotActor::new("shared".to_string(), backend.clone()).with_optimistic_saves();
+    let mut second: SnapshotActor<_> =
+        SnapshotActor::new("shared".to_string(), backend.clone()).with_optimistic_saves();
+
+    first.set_state("v1".to_string());
+    first.save_state().await?;
+    first.load_state().await?;
+    second.load_state().await?;
+
+    // The first process moves on, leaving the second one's copy stale
+    first.set_state("v2".to_string());
+    first.save_state().await?;
+    second.set_state("stale".to_string());
+    let error = second.save_state().await.unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<SnapshotConflict>(),
+        Some(&SnapshotConflict {
+            actor_id: "shared".to_string()
+        })
+    );
+
+    // The newer snapshot survived, and a reload lets the second one save again
+    second.load_state().await?;
+    assert_eq!(second.get_state(), "v2");
+    second.set_state("v3".to_string());
+    second.save_state().await?;
+    first.load_state().await?;
+    assert_eq!(first.get_state(), "v3");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sequence_numbers_order_saves_and_refuse_stale_ones() -> Result<(), Box<dyn Error>> {
+    let mut backend = MemoryBackend::new();
+    // Written before snapshots had sequence numbers
+    backend.write("shared:json:\"old\"").await?;
+    let mut first: SnapshotActor<_> = SnapshotActor::new("shared".to_string(), backend.clone());
+    let mut second: SnapshotActor<_> = SnapshotActor::new("shared".to_string(), backend.clone());
+    first.load_state().await?;
+    assert_eq!(first.get_state(), "old");
+    assert_eq!(first.last_sequence(), Some(0));
+
+    for expected in 1..=3 {
+        first.save_state().await?;
+        assert_eq!(first.current_sequence().await?, expected);
+        assert_eq!(first.last_sequence(), Some(expected));
+    }
+    assert_eq!(backend.read().await?, "shared:json:3:\"old\"");
+
+    // The second one loaded before the first saved again, so its save is stale
+    second.load_state().await?;
+    first.save_state().await?;
+    second.set_state("stale".to_string());
+    let error = second.save_state().await.unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<StaleSnapshot>(),
+        Some(&StaleSnapshot {
+            actor_id: "shared".to_string(),
+            sequence: 3,
+            stored: 4,
+        })
+    );
+    assert_eq!(second.current_sequence().await?, 4);
+
+    // Once reloaded it carries on from the stored snapshot
+    second.load_state().await?;
+    second.save_state().await?;
+    assert_eq!(second.current_sequence().await?, 5);
+    Ok(())
+}
+
+// A backend whose writes always fail, counting the attempts. It reads as empty,
+// so saves get as far as writing
+#[derive(Debug, Clone, Default)]
+struct Unavailable {
+    writes: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl StorageBackend for Unavailable {
+    async fn write(&mut self, _data: &str) -> Result<(), Box<dyn Error>> {
+        self.writes.fetch_add(1, Ordering::SeqCst);
+        Err("backend unavailable".into())
+    }
+
+    async fn read(&mut self) -> Result<String, Box<dyn Error>> {
+        Ok(String::new())
+    }
+
+    async fn cleanup(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_snapshot_task_backs_off_a_failing_backend() -> Result<(), Box<dyn Error>> {
+    let backend = Unavailable::default();
+    let clock = MockClock::new();
+    let supervisor = Arc::new(Supervisor::new(SupervisionStrategy::Ignore));
+    let mut events = supervisor.events();
+    let actor = SnapshotActor::<_, String>::new("actor1".to_string(), backend.clone())
+        .with_snapshot_interval(Duration::from_secs(10))
+        .with_clock(Arc::new(clock.clone()))
+        .with_logger(Arc::new(
+            ConsoleLogger::new().with_writers(io::sink(), io::sink()),
+        ))
+        .with_supervisor(supervisor, 3);
+
+    let mut snapshotter = actor.clone();
+    let snapshot_task = tokio::spawn(async move {
+        snapshotter.start_snapshot_task().await;
+    });
+    // The first tick is immediate, then one tick per advance. The third failure
+    // skips one tick and the fourth two, so of 7 more ticks only 4 save.
+    clock.wait_for_sleepers(1).await;
+    for _ in 0..7 {
+        clock.advance(Duration::from_secs(10));
+        clock.wait_for_sleepers(1).await;
+    }
+    assert_eq!(backend.writes.load(Ordering::SeqCst), 5);
+    assert_eq!(actor.consecutive_failures(), 5);
+
+    // Reported to the supervisor once, on the third failure
+    let event = events.try_recv()?;
+    assert_eq!(event.actor, "actor1");
+    assert_eq!(event.kind, SupervisionEventKind::Failed);
+    assert_eq!(event.error, "backend unavailable");
+    assert!(events.try_recv().is_err());
+
+    actor.shutdown();
+    snapshot_task.await?;
+    Ok(())
+}
+
+// Writes the first half of the data, then the rest after a pause, so a read in
+// between sees a torn snapshot
+#[derive(Debug, Clone, Default)]
+struct TornWrites {
+    data: Arc<std::sync::Mutex<Vec<u8>>>,
+}
+
+#[async_trait]
+impl StorageBackend for TornWrites {
+    async fn write(&mut self, data: &str) -> Result<(), Box<dyn Error>> {
+        self.write_bytes(data.as_bytes()).await
+    }
+
+    async fn read(&mut self) -> Result<String, Box<dyn Error>> {
+        Ok(String::from_utf8(self.read_bytes().await?)?)
+    }
+
+    async fn cleanup(&mut self) -> Result<(), Box<dyn Error>> {
+        self.data.lock().unwrap().clear();
+        Ok(())
+    }
+
+    async fn write_bytes(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        *self.data.lock().unwrap() = data[..data.len() / 2].to_vec();
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        *self.data.lock().unwrap() = data.to_vec();
+        Ok(())
+    }
+
+    async fn read_bytes(&mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+        Ok(self.data.lock().unwrap().clone())
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_loads_never_see_a_save_halfway_through() -> Result<(), Box<dyn Error>> {
+    let mut actor: SnapshotActor<_, Inventory> =
+        SnapshotActor::new("inventory".to_string(), TornWrites::default());
+    actor.set_state(sample_inventory());
+    actor.save_state().await?;
+
+    let mut saver = actor.clone();
+    let saves = tokio::spawn(async move {
+        for count in 0..50 {
+            let mut state = sample_inventory();
+            state.items[0].1 = count;
+            saver.set_state(state);
+            saver.save_state().await.map_err(|e| e.to_string())?;
+        }
+        Ok::<(), String>(())
+    });
+    while !saves.is_finished() {
+        // Every load finds a whole snapshot, one of those saved
+        actor.load_state().await?;
+        let loaded = actor.get_state();
+        assert_eq!(loaded.items[1], sample_inventory().items[1]);
+        assert!(loaded.items[0].1 < 50);
+        tokio::task::yield_now().await;
+    }
+    saves.await??;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_snapshot_task_stops_with_its_system() -> Result<(), Box<dyn Error>> {
+    let backend = MemoryBackend::new();
+    let system: ActorSystem<String> = ActorSystem::new();
+    let mut actor: SnapshotActor<_> =
+        SnapshotActor::new("tracked".to_string(), backend.clone()).with_system(&system);
+    let mut snapshotter = actor.clone();
+    let task = tokio::spawn(async move { snapshotter.start_snapshot_task().await });
+    actor.set_state("left by the actors".to_string());
+
+    system.shutdown().await;
+    tokio::time::timeout(Duration::from_secs(5), task)
+        .await
+        .expect("the snapshot task stops with the system")?;
+
+    // Its final save went through
+    let mut restored: SnapshotActor<_> = SnapshotActor::new("tracked".to_string(), backend);
+    restored.load_state().await?;
+    assert_eq!(restored.get_state(), "left by the actors");
+    Ok(())
+}
@@ -0,0 +1,73 @@
+mod common;
+
+use astra::actor_system::{ActorOptions, ActorSystem, StopMode};
+use common::{handled_so_far, Gated};
+use std::error::Error;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::{mpsc, Semaphore};
+
+struct Counters {
+    handled: mpsc::UnboundedReceiver<u32>,
+    cleaned_up: Arc<AtomicUsize>,
+}
+
+fn add_gated(
+    system: &mut ActorSystem<u32>,
+    name: &str,
+    permits: &Arc<Semaphore>,
+    stop_mode: StopMode,
+) -> Counters {
+    let (gated, handled) = Gated::new(permits);
+    let cleaned_up = Arc::clone(&gated.cleaned_up);
+    system.add_actor_with_options(
+        name.to_string(),
+        gated,
+        ActorOptions::new().with_stop_mode(stop_mode),
+    );
+    Counters {
+        handled,
+        cleaned_up,
+    }
+}
+
+#[tokio::test]
+async fn test_drain_handles_backlog_and_immediate_discards_it() -> Result<(), Box<dyn Error>> {
+    let mut system = ActorSystem::new();
+    let writer_permits = Arc::new(Semaphore::new(0));
+    let transformer_permits = Arc::new(Semaphore::new(0));
+    let mut writer = add_gated(&mut system, "writer", &writer_permits, StopMode::Drain);
+    let mut transformer = add_gated(
+        &mut system,
+        "transformer",
+        &transformer_permits,
+        StopMode::Immediate,
+    );
+    for n in 0..5 {
+        system.send_message("writer", n).await?;
+        system.send_message("transformer", n).await?;
+    }
+
+    // The writer works through its backlog while shutting down; the transformer
+    // is stuck on its first message and never gets a permit
+    writer_permits.add_permits(5);
+    system.shutdown().await;
+
+    assert_eq!(handled_so_far(&mut writer.handled).len(), 5);
+    assert!(handled_so_far(&mut transformer.handled).is_empty());
+    assert_eq!(writer.cleaned_up.load(Ordering::SeqCst), 1);
+    assert_eq!(transformer.cleaned_up.load(Ordering::SeqCst), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_immediate_actor_refuses_messages_once_stopped() -> Result<(), Box<dyn Error>> {
+    let mut system = ActorSystem::new();
+    let permits = Arc::new(Semaphore::new(0));
+    add_gated(&mut system, "transformer", &permits, StopMode::Immediate);
+    system.send_message("transformer", 1).await?;
+
+    system.shutdown().await;
+    assert!(system.send_message("transformer", 2).await.is_err());
+    Ok(())
+}
@@ -0,0 +1,341 @@
+use astra::actor_system::{Actor, ActorOptions, ActorSystem, Message};
+use astra::logging::{LogLevel, Logger};
+use astra::supervision::{
+    EscalationPolicy, SupervisionEventKind, SupervisionOutcome, SupervisionStrategy, Supervisor,
+    SupervisorTree,
+};
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+fn counter() -> Arc<AtomicUsize> {
+    Arc::new(AtomicUsize::new(0))
+}
+
+fn supervise_counted(tree: &SupervisorTree, name: &str, restarts: &Arc<AtomicUsize>) {
+    let restarts = Arc::clone(restarts);
+    tree.supervise(name, move || {
+        restarts.fetch_add(1, Ordering::SeqCst);
+    });
+}
+
+#[test]
+fn test_leaf_escalation_restarts_branch() {
+    let root = SupervisorTree::root("root", SupervisionStrategy::Restart);
+    let branch = root.child("branch", SupervisionStrategy::Escalate);
+    let sibling = root.child("sibling", SupervisionStrategy::Restart);
+
+    let (a, b, c) = (counter(), counter(), counter());
+    supervise_counted(&branch, "a", &a);
+    supervise_counted(&branch, "b", &b);
+    supervise_counted(&sibling, "c", &c);
+
+    let outcome = branch.handle_failure("a", "boom");
+
+    assert_eq!(
+        outcome,
+        SupervisionOutcome::Restarted {
+            supervisor: "root".to_string(),
+            actors: vec!["a".to_string(), "b".to_string()],
+        }
+    );
+    assert_eq!(a.load(Ordering::SeqCst), 1);
+    assert_eq!(b.load(Ordering::SeqCst), 1);
+    // The sibling branch is left alone
+    assert_eq!(c.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn test_restart_strategy_restarts_only_failed_actor() {
+    let root = SupervisorTree::root("root", SupervisionStrategy::Restart);
+    let (a, b) = (counter(), counter());
+    supervise_counted(&root, "a", &a);
+    supervise_counted(&root, "b", &b);
+
+    root.handle_failure("b", "boom");
+
+    assert_eq!(a.load(Ordering::SeqCst), 0);
+    assert_eq!(b.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn test_escalation_through_multiple_levels() {
+    let root = SupervisorTree::root("root", SupervisionStrategy::Ignore);
+    let middle = root.child("middle", SupervisionStrategy::Escalate);
+    let leaf = middle.child("leaf", SupervisionStrategy::Escalate);
+    leaf.supervise("a", || {});
+
+    assert_eq!(leaf.parent().unwrap().name(), "middle");
+    assert_eq!(
+        leaf.handle_failure("a", "boom"),
+        SupervisionOutcome::Ignored {
+            supervisor: "root".to_string()
+        }
+    );
+}
+
+#[test]
+fn test_escalation_past_root_is_unhandled() {
+    let root = SupervisorTree::root("root", SupervisionStrategy::Escalate);
+    root.supervise("a", || {});
+
+    assert!(root.parent().is_none());
+    assert_eq!(
+        root.handle_failure("a", "boom"),
+        SupervisionOutcome::Unhandled
+    );
+}
+
+#[tokio::test]
+async fn test_restart_emits_restarted_event_once_restarted() {
+    let supervisor = Supervisor::new(SupervisionStrategy::Restart);
+    let mut events = supervisor.events();
+
+    // Deciding to restart is not a restart yet
+    supervisor.handle_failure("worker", "boom");
+    let failed = events.recv().await.unwrap();
+    assert_eq!(failed.kind, SupervisionEventKind::Failed);
+    assert!(events.try_recv().is_err());
+
+    supervisor.restarted("worker", "boom");
+    let restarted = events.recv().await.unwrap();
+    assert_eq!(restarted.kind, SupervisionEventKind::Restarted);
+    assert_eq!(restarted.actor, "worker");
+    assert_eq!(restarted.error, "boom");
+    assert!(restarted.timestamp >= failed.timestamp);
+}
+
+#[test]
+fn test_tree_events_cover_escalation_path() {
+    let root = SupervisorTree::root("root", SupervisionStrategy::Escalate);
+    let leaf = root.child("leaf", SupervisionStrategy::Escalate);
+    leaf.supervise("a", || {});
+    let mut events = root.events();
+
+    leaf.handle_failure("a", "boom");
+
+    let kinds: Vec<_> = std::iter::from_fn(|| events.try_recv().ok())
+        .map(|event| event.kind)
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            SupervisionEventKind::Failed,
+            SupervisionEventKind::Escalated,
+            SupervisionEventKind::GaveUp
+        ]
+    );
+}
+
+// Keeps every line logged through it
+#[derive(Default)]
+struct CapturingLogger {
+    lines: Mutex<Vec<(LogLevel, String)>>,
+}
+
+#[async_trait]
+impl Logger for CapturingLogger {
+    async fn log(&self, level: LogLevel, message: &str) {
+        self.lines
+            .lock()
+            .unwrap()
+            .push((level, message.to_string()));
+    }
+}
+
+fn logged_failure(strategy: SupervisionStrategy) -> Vec<(LogLevel, String)> {
+    let logger = Arc::new(CapturingLogger::default());
+    let supervisor = Supervisor::new(strategy).with_logger(logger.clone());
+    supervisor.handle_failure("worker-1", "boom");
+    let lines = logger.lines.lock().unwrap().clone();
+    lines
+}
+
+#[test]
+fn test_supervisor_logs_each_outcome_at_its_level() {
+    let failed = (LogLevel::Error, "Actor worker-1 failed: boom".to_string());
+    assert_eq!(
+        logged_failure(SupervisionStrategy::Restart),
+        vec![
+            failed.clone(),
+            (
+                LogLevel::Warn,
+                "Restarting actor worker-1 due to error: boom".to_string()
+            ),
+        ]
+    );
+    assert_eq!(
+        logged_failure(SupervisionStrategy::Ignore),
+        vec![
+            failed.clone(),
+            (
+                LogLevel::Info,
+                "Ignoring error for actor worker-1: boom".to_string()
+            ),
+        ]
+    );
+    assert_eq!(
+        logged_failure(SupervisionStrategy::Escalate),
+        vec![
+            failed,
+            (
+                LogLevel::Warn,
+                "Escalating error for actor worker-1: boom".to_string()
+            ),
+        ]
+    );
+}
+
+// Writes each line from a task of its own and waits for it, as loggers doing
+// I/O do
+struct HandingOffLogger {
+    lines: mpsc::UnboundedSender<(LogLevel, String)>,
+}
+
+#[async_trait]
+impl Logger for HandingOffLogger {
+    async fn log(&self, level: LogLevel, message: &str) {
+        let lines = self.lines.clone();
+        let line = (level, message.to_string());
+        tokio::spawn(async move { lines.send(line) })
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_supervisor_logs_from_async_code() {
+    let (lines_tx, mut lines) = mpsc::unbounded_channel();
+    let supervisor = Supervisor::new(SupervisionStrategy::Restart)
+        .with_logger(Arc::new(HandingOffLogger { lines: lines_tx }));
+    // On this single-threaded runtime, waiting on the logger right here would
+    // never let its task run
+    supervisor.handle_failure("worker-2", "timeout");
+    assert_eq!(
+        lines.recv().await.unwrap(),
+        (
+            LogLevel::Error,
+            "Actor worker-2 failed: timeout".to_string()
+        )
+    );
+    assert_eq!(
+        lines.recv().await.unwrap(),
+        (
+            LogLevel::Warn,
+            "Restarting actor worker-2 due to error: timeout".to_string()
+        )
+    );
+}
+
+#[test]
+fn test_tree_logs_through_its_logger() {
+    let logger = Arc::new(CapturingLogger::default());
+    let root =
+        SupervisorTree::root("root", SupervisionStrategy::Escalate).with_logger(logger.clone());
+    // Created afterwards, so it inherits the logger
+    let branch = root.child("branch", SupervisionStrategy::Escalate);
+    branch.handle_failure("a", "boom");
+    assert_eq!(
+        *logger.lines.lock().unwrap(),
+        vec![
+            (
+                LogLevel::Warn,
+                "Supervisor branch escalating error for actor a to root: boom".to_string()
+            ),
+            (
+                LogLevel::Error,
+                "Error for actor a escalated past root supervisor root: boom".to_string()
+            ),
+        ]
+    );
+}
+
+// Hangs on every message, so each one is a failure once its receive times out
+struct Hanging;
+
+#[async_trait]
+impl Actor for Hanging {
+    type Message = String;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<String>) -> Result<(), String> {
+        if let Message::Regular(_) = message {
+            futures::future::pending::<()>().await;
+        }
+        Ok(())
+    }
+}
+
+// Counts the messages it handles
+struct Counting(Arc<AtomicUsize>);
+
+#[async_trait]
+impl Actor for Counting {
+    type Message = String;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<String>) -> Result<(), String> {
+        if let Message::Regular(_) = message {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_escalation_to_shutdown_stops_the_system() -> Result<(), String> {
+    let supervisor = Arc::new(
+        Supervisor::new(SupervisionStrategy::Escalate).with_escalation(EscalationPolicy::Shutdown),
+    );
+    let mut events = supervisor.events();
+    let mut system = ActorSystem::new().with_supervisor(supervisor);
+    system.add_actor_with_options(
+        "hanging".to_string(),
+        Hanging,
+        ActorOptions::new().with_receive_timeout(Duration::from_millis(20)),
+    );
+    let handled = counter();
+    system.add_actor("bystander".to_string(), Counting(Arc::clone(&handled)));
+    let running = tokio::spawn(system.clone().run_until_shutdown());
+
+    system.send_message("bystander", "work".to_string()).await?;
+    for _ in 0..3 {
+        system.send_message("hanging", "work".to_string()).await?;
+    }
+    let report = tokio::time::timeout(Duration::from_secs(5), running)
+        .await
+        .expect("the escalation shut the system down")
+        .unwrap()?;
+
+    assert!(system.is_shutdown_requested());
+    assert!(report.is_clean(), "{:?}", report);
+    assert_eq!(report.stopped_cleanly.len(), 2);
+    assert_eq!(handled.load(Ordering::SeqCst), 1);
+    let kinds: Vec<_> = std::iter::from_fn(|| events.try_recv().ok())
+        .map(|event| event.kind)
+        .collect();
+    assert_eq!(
+        &kinds[..2],
+        [
+            SupervisionEventKind::Failed,
+            SupervisionEventKind::Escalated
+        ]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_escalation_to_parent_leaves_the_system_running() -> Result<(), String> {
+    let supervisor = Arc::new(Supervisor::new(SupervisionStrategy::Escalate));
+    let mut system = ActorSystem::new().with_supervisor(Arc::clone(&supervisor));
+    system.add_actor("bystander".to_string(), Counting(counter()));
+
+    supervisor.handle_failure("bystander", "boom");
+    assert!(!system.is_shutdown_requested());
+    system.send_message("bystander", "work".to_string()).await?;
+    system.shutdown().await;
+    Ok(())
+}
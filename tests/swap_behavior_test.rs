@@ -0,0 +1,86 @@
+use astra::actor_system::{Actor, ActorSystem, Message};
+use async_trait::async_trait;
+use std::error::Error;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Semaphore};
+
+// Tags every message with its version. With a gate, it announces each message and
+// waits for a permit before handling it
+struct Versioned {
+    version: &'static str,
+    gate: Option<Arc<Semaphore>>,
+    out: mpsc::UnboundedSender<String>,
+}
+
+#[async_trait]
+impl Actor for Versioned {
+    type Message = String;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error> {
+        if let Message::Regular(message) = message {
+            if let Some(gate) = &self.gate {
+                self.out.send(format!("{}:waiting", self.version)).unwrap();
+                gate.acquire().await.unwrap().forget();
+            }
+            self.out
+                .send(format!("{}:{}", self.version, message))
+                .unwrap();
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_queued_messages_use_new_behavior() -> Result<(), Box<dyn Error>> {
+    let (out_tx, mut out) = mpsc::unbounded_channel();
+    let gate = Arc::new(Semaphore::new(1));
+    let mut system = ActorSystem::new();
+    system.add_actor(
+        "worker".to_string(),
+        Versioned {
+            version: "v1",
+            gate: Some(Arc::clone(&gate)),
+            out: out_tx.clone(),
+        },
+    );
+
+    system.send_message("worker", "a".to_string()).await?;
+    assert_eq!(out.recv().await.unwrap(), "v1:waiting");
+    assert_eq!(out.recv().await.unwrap(), "v1:a");
+
+    // v1 blocks on "b" while "c" waits in the mailbox
+    system.send_message("worker", "b".to_string()).await?;
+    assert_eq!(out.recv().await.unwrap(), "v1:waiting");
+    system.send_message("worker", "c".to_string()).await?;
+    system.swap_behavior(
+        "worker",
+        Versioned {
+            version: "v2",
+            gate: None,
+            out: out_tx,
+        },
+    )?;
+    gate.add_permits(1);
+
+    assert_eq!(out.recv().await.unwrap(), "v1:b");
+    assert_eq!(out.recv().await.unwrap(), "v2:c");
+    system.send_message("worker", "d".to_string()).await?;
+    assert_eq!(out.recv().await.unwrap(), "v2:d");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_swap_unknown_actor_fails() {
+    let (out_tx, _out) = mpsc::unbounded_channel();
+    let system: ActorSystem<String> = ActorSystem::new();
+    let result = system.swap_behavior(
+        "missing",
+        Versioned {
+            version: "v2",
+            gate: None,
+            out: out_tx,
+        },
+    );
+    assert_eq!(result.unwrap_err(), "Actor missing not found");
+}
@@ -1,6 +1,8 @@
-use astra::actor_system::{Actor, ActorSystem, Message};
+use astra::actor_system::{Actor, ActorSystem, AnyMessage, Message};
+use astra::test_util::TestHarness;
 use async_trait::async_trait;
 use std::error::Error;
+use tokio::sync::mpsc;
 
 struct SimpleActor;
 
@@ -19,23 +21,139 @@ impl Actor for SimpleActor {
                 println!("Shutting down SimpleActor.");
                 Ok(())
             }
+            Message::System(_) => Ok(()),
         }
     }
 }
 
 #[tokio::test]
 async fn test_actor_system() -> Result<(), Box<dyn Error>> {
-    // Initialize the actor system and add a SimpleActor
-    let mut system = ActorSystem::new();
-    system.add_actor("simple_actor".to_string(), SimpleActor);
+    // Initialize a harness around the actor system and add a SimpleActor
+    let mut harness = TestHarness::new();
+    harness.add_actor("simple_actor", SimpleActor);
 
-    // Send a message to the actor and verify it processes correctly
-    system
-        .send_message("simple_actor", "Hello, actor!".to_string())
+    // Send a message and wait until the actor has processed it
+    harness
+        .send_and_settle("simple_actor", "Hello, actor!".to_string())
         .await?;
+    harness.assert_processed("simple_actor", 1);
 
     // Shutdown the system and ensure proper cleanup
+    harness.shutdown().await;
+
+    Ok(())
+}
+
+struct Recorder<T> {
+    seen: mpsc::UnboundedSender<String>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+#[async_trait]
+impl<T: std::fmt::Debug + Send + 'static> Actor for Recorder<T> {
+    type Message = T;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error> {
+        if let Message::Regular(msg) = message {
+            self.seen.send(format!("{:?}", msg)).unwrap();
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_mixed_message_types() -> Result<(), Box<dyn Error>> {
+    let (numbers_tx, mut numbers_rx) = mpsc::unbounded_channel();
+    let (words_tx, mut words_rx) = mpsc::unbounded_channel();
+
+    let mut system: ActorSystem<AnyMessage> = ActorSystem::new();
+    system.add_typed_actor(
+        "numbers".to_string(),
+        Recorder::<u32> {
+            seen: numbers_tx,
+            _marker: std::marker::PhantomData,
+        },
+    );
+    system.add_typed_actor(
+        "words".to_string(),
+        Recorder::<String> {
+            seen: words_tx,
+            _marker: std::marker::PhantomData,
+        },
+    );
+
+    system
+        .send_message("numbers", AnyMessage::new(42u32))
+        .await?;
+    system
+        .send_message("words", AnyMessage::new("hello".to_string()))
+        .await?;
+    assert_eq!(numbers_rx.recv().await.unwrap(), "42");
+    assert_eq!(words_rx.recv().await.unwrap(), "\"hello\"");
+
+    // Sending the wrong payload type is rejected before it is enqueued
+    let err = system
+        .send_message("numbers", AnyMessage::new("oops".to_string()))
+        .await
+        .unwrap_err();
+    assert!(err.contains("expected message of type u32"), "{}", err);
+
     system.shutdown().await;
+    Ok(())
+}
+
+#[derive(Debug, PartialEq)]
+enum LedgerError {
+    Negative(i64),
+    Overflow,
+}
+
+// Keeps a running balance, refusing deposits that are negative
+struct Ledger {
+    balances: mpsc::UnboundedSender<i64>,
+    balance: i64,
+}
+
+#[async_trait]
+impl Actor for Ledger {
+    type Message = i64;
+    type Error = LedgerError;
 
+    async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error> {
+        if let Message::Regular(amount) = message {
+            if amount < 0 {
+                return Err(LedgerError::Negative(amount));
+            }
+            self.balance = self
+                .balance
+                .checked_add(amount)
+                .ok_or(LedgerError::Overflow)?;
+            self.balances.send(self.balance).unwrap();
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_system_with_custom_error_type() -> Result<(), Box<dyn Error>> {
+    let (balances_tx, mut balances) = mpsc::unbounded_channel();
+    let mut system: ActorSystem<i64, LedgerError> = ActorSystem::new();
+    system.add_actor(
+        "ledger".to_string(),
+        Ledger {
+            balances: balances_tx,
+            balance: 0,
+        },
+    );
+
+    system.send_message("ledger", 10).await?;
+    // A failing message is reported by the task and the actor keeps going
+    system.send_message("ledger", -5).await?;
+    system.send_message("ledger", 15).await?;
+
+    assert_eq!(balances.recv().await, Some(10));
+    assert_eq!(balances.recv().await, Some(25));
+    system.shutdown().await;
     Ok(())
 }
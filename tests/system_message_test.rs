@@ -0,0 +1,90 @@
+use astra::actor_system::{Actor, ActorSystem, Message, SendError, SystemMessage};
+use async_trait::async_trait;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+
+type Seen = Arc<Mutex<Vec<String>>>;
+
+// Records what reaches `receive`, optionally opting in to system messages
+struct Recorder {
+    seen: Seen,
+    system_messages: bool,
+}
+
+#[async_trait]
+impl Actor for Recorder {
+    type Message = String;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error> {
+        let seen = match message {
+            Message::Regular(msg) => msg,
+            Message::Shutdown => "shutdown".to_string(),
+            Message::System(SystemMessage::Ping(_)) => "ping".to_string(),
+            Message::System(other) => format!("{:?}", other),
+        };
+        self.seen.lock().unwrap().push(seen);
+        Ok(())
+    }
+
+    fn receives_system_messages(&self) -> bool {
+        self.system_messages
+    }
+}
+
+fn system(system_messages: bool) -> (ActorSystem<String>, Seen) {
+    let seen = Seen::default();
+    let mut system = ActorSystem::new();
+    system.add_actor(
+        "recorder".to_string(),
+        Recorder {
+            seen: Arc::clone(&seen),
+            system_messages,
+        },
+    );
+    (system, seen)
+}
+
+#[tokio::test]
+async fn test_ping_is_answered_without_invoking_receive() -> Result<(), Box<dyn Error>> {
+    let (system, seen) = system(false);
+
+    system.ping("recorder").await?;
+    assert!(seen.lock().unwrap().is_empty());
+
+    // The answer comes once what was queued before the ping is handled
+    system.send_message("recorder", "first".to_string()).await?;
+    system.ping("recorder").await?;
+    assert_eq!(*seen.lock().unwrap(), vec!["first"]);
+
+    system.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_actors_can_opt_in_to_system_messages() -> Result<(), Box<dyn Error>> {
+    let (system, seen) = system(true);
+
+    let recorder = system.actor_ref("recorder").unwrap();
+    recorder.ping().await?;
+    recorder.send("after".to_string()).await?;
+    system.shutdown().await;
+    assert_eq!(*seen.lock().unwrap(), vec!["ping", "after", "shutdown"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_ping_fails_for_unknown_and_stopped_actors() {
+    let (system, _seen) = system(false);
+    assert_eq!(
+        system.ping("missing").await,
+        Err(SendError::NotFound("missing".to_string()))
+    );
+
+    let recorder = system.actor_ref("recorder").unwrap();
+    system.shutdown().await;
+    assert_eq!(
+        recorder.ping().await,
+        Err(SendError::Closed("recorder".to_string()))
+    );
+}
@@ -0,0 +1,214 @@
+use astra::clock::MockClock;
+use astra::network::http::CommunicationProtocol;
+use astra::network::tcp::{ConnectionState, ReconnectPolicy, TcpProtocol};
+use std::error::Error;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+// Read one frame (actor name, payload) and acknowledge it
+async fn serve_frame(stream: &mut TcpStream) -> std::io::Result<String> {
+    let name_len = stream.read_u32().await? as usize;
+    let mut name = vec![0; name_len];
+    stream.read_exact(&mut name).await?;
+    let payload_len = stream.read_u32().await? as usize;
+    let mut payload = vec![0; payload_len];
+    stream.read_exact(&mut payload).await?;
+    stream.write_u8(0).await?;
+    Ok(String::from_utf8(payload).unwrap())
+}
+
+#[tokio::test]
+async fn test_dead_pooled_connection_is_replaced() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let host = listener.local_addr()?.to_string();
+    let (received_tx, mut received_rx) = mpsc::unbounded_channel();
+    let (closed_tx, mut closed_rx) = mpsc::unbounded_channel();
+
+    // A server that kills the first connection right after serving one frame
+    tokio::spawn(async move {
+        let (mut first, _) = listener.accept().await.unwrap();
+        received_tx
+            .send(serve_frame(&mut first).await.unwrap())
+            .unwrap();
+        drop(first);
+        closed_tx.send(()).unwrap();
+
+        let (mut second, _) = listener.accept().await.unwrap();
+        while let Ok(payload) = serve_frame(&mut second).await {
+            received_tx.send(payload).unwrap();
+        }
+    });
+
+    let tcp = TcpProtocol::new();
+    let address = format!("{}/worker", host);
+
+    tcp.send_message(&address, "one").await?;
+    assert_eq!(received_rx.recv().await.unwrap(), "one");
+    assert_eq!(tcp.pooled_connections(&host), 1);
+
+    // The pooled connection is now dead, the send reconnects transparently
+    closed_rx.recv().await.unwrap();
+    tcp.send_message(&address, "two").await?;
+    assert_eq!(received_rx.recv().await.unwrap(), "two");
+
+    // And the fresh connection is pooled and reused from here on
+    tcp.send_message(&address, "three").await?;
+    assert_eq!(received_rx.recv().await.unwrap(), "three");
+    assert_eq!(tcp.pooled_connections(&host), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_unanswered_frame_is_not_sent_again() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let host = listener.local_addr()?.to_string();
+    let (received_tx, mut received_rx) = mpsc::unbounded_channel();
+
+    // A server that takes the second frame but drops the connection before
+    // answering it
+    tokio::spawn(async move {
+        let (mut first, _) = listener.accept().await.unwrap();
+        received_tx
+            .send(serve_frame(&mut first).await.unwrap())
+            .unwrap();
+        let name_len = first.read_u32().await.unwrap() as usize;
+        let mut frame = vec![0; name_len];
+        first.read_exact(&mut frame).await.unwrap();
+        let payload_len = first.read_u32().await.unwrap() as usize;
+        let mut payload = vec![0; payload_len];
+        first.read_exact(&mut payload).await.unwrap();
+        received_tx
+            .send(String::from_utf8(payload).unwrap())
+            .unwrap();
+        drop(first);
+
+        // A retry would be the first frame of the next connection
+        let (mut second, _) = listener.accept().await.unwrap();
+        while let Ok(payload) = serve_frame(&mut second).await {
+            received_tx.send(payload).unwrap();
+        }
+    });
+
+    let tcp = TcpProtocol::new();
+    let address = format!("{}/worker", host);
+    tcp.send_message(&address, "one").await?;
+    assert_eq!(received_rx.recv().await.unwrap(), "one");
+
+    // The frame went out on the pooled connection, so the lost answer fails the
+    // send rather than delivering the message twice
+    let err = tcp.send_message(&address, "two").await.unwrap_err();
+    assert!(
+        err.contains("may or may not have been delivered"),
+        "{}",
+        err
+    );
+    assert_eq!(received_rx.recv().await.unwrap(), "two");
+    tcp.send_message(&address, "three").await?;
+    assert_eq!(received_rx.recv().await.unwrap(), "three");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_lost_address_reconnects_with_backoff() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let host = listener.local_addr()?.to_string();
+    let clock = MockClock::new();
+    let tcp = TcpProtocol::new()
+        .with_clock(Arc::new(clock.clone()))
+        .with_reconnect_policy(ReconnectPolicy::new(
+            Duration::from_millis(100),
+            Duration::from_secs(1),
+            2.0,
+        ));
+    let address = format!("{}/worker", host);
+
+    // Serve one frame, then take the whole server down
+    let server = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        serve_frame(&mut stream).await.unwrap()
+    });
+    tcp.send_message(&address, "one").await?;
+    assert_eq!(server.await?, "one");
+    assert_eq!(
+        tcp.connection_state(&host),
+        Some(ConnectionState::Connected)
+    );
+
+    // The pooled connection is dead and nothing listens any more
+    assert!(tcp.send_message(&address, "two").await.is_err());
+    let reconnecting = |attempt, millis| ConnectionState::Reconnecting {
+        attempt,
+        next_delay: Duration::from_millis(millis),
+    };
+    clock.wait_for_sleepers(1).await;
+    assert_eq!(tcp.connection_state(&host), Some(reconnecting(1, 100)));
+    // Meanwhile sends fail fast
+    let err = tcp.send_message(&address, "three").await.unwrap_err();
+    assert!(err.contains("Reconnecting"), "{}", err);
+
+    // Each failed attempt doubles the delay before the next
+    for (attempt, millis) in [(2, 200), (3, 400), (4, 800), (5, 1000)] {
+        clock.advance(Duration::from_secs(1));
+        clock.wait_for_sleepers(1).await;
+        assert_eq!(
+            tcp.connection_state(&host),
+            Some(reconnecting(attempt, millis))
+        );
+    }
+
+    // The server comes back and the next attempt reconnects
+    let listener = TcpListener::bind(&host).await?;
+    clock.advance(Duration::from_secs(1));
+    let (mut stream, _) = listener.accept().await?;
+    while tcp.connection_state(&host) != Some(ConnectionState::Connected) {
+        tokio::task::yield_now().await;
+    }
+    assert_eq!(tcp.pooled_connections(&host), 1);
+    let served = tokio::spawn(async move { serve_frame(&mut stream).await.unwrap() });
+    tcp.send_message(&address, "four").await?;
+    assert_eq!(served.await?, "four");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_reconnecting_stops_with_the_token() -> Result<(), Box<dyn Error>> {
+    // An address nothing listens on
+    let host = TcpListener::bind("127.0.0.1:0")
+        .await?
+        .local_addr()?
+        .to_string();
+    let clock = MockClock::new();
+    let shutdown = CancellationToken::new();
+    let tcp = TcpProtocol::new()
+        .with_clock(Arc::new(clock.clone()))
+        .with_cancellation_token(&shutdown);
+
+    assert!(tcp
+        .send_message(&format!("{}/worker", host), "one")
+        .await
+        .is_err());
+    clock.wait_for_sleepers(1).await;
+    assert!(matches!(
+        tcp.connection_state(&host),
+        Some(ConnectionState::Reconnecting { attempt: 1, .. })
+    ));
+
+    shutdown.cancel();
+    tokio::time::timeout(Duration::from_secs(5), tcp.wait_until_stopped()).await?;
+    assert_eq!(tcp.connection_state(&host), Some(ConnectionState::Failed));
+    Ok(())
+}
+
+#[test]
+fn test_reconnect_policy_delays() {
+    let policy = ReconnectPolicy::new(Duration::from_millis(50), Duration::from_millis(300), 3.0);
+    let delays: Vec<_> = (1..=4).map(|attempt| policy.delay(attempt)).collect();
+    assert_eq!(
+        delays,
+        [50, 150, 300, 300].map(Duration::from_millis).to_vec()
+    );
+}
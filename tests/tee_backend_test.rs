@@ -0,0 +1,73 @@
+use astra::backends::memory::MemoryBackend;
+use astra::backends::read_only::ReadOnlyBackend;
+use astra::backends::storage::StorageBackend;
+use astra::backends::tee::TeeBackend;
+use astra::logging::{LogLevel, Logger};
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
+
+// Keeps every line logged through it
+#[derive(Default)]
+struct CapturingLogger {
+    lines: Mutex<Vec<(LogLevel, String)>>,
+}
+
+#[async_trait]
+impl Logger for CapturingLogger {
+    async fn log(&self, level: LogLevel, message: &str) {
+        self.lines
+            .lock()
+            .unwrap()
+            .push((level, message.to_string()));
+    }
+}
+
+#[tokio::test]
+async fn test_tee_writes_to_both_and_reads_the_primary() -> Result<(), Box<dyn std::error::Error>> {
+    let (mut primary, mut secondary) = (MemoryBackend::new(), MemoryBackend::new());
+    let mut backend = TeeBackend::new(primary.clone(), secondary.clone());
+
+    backend.write("first").await?;
+    assert_eq!(primary.read().await?, "first");
+    assert_eq!(secondary.read().await?, "first");
+
+    primary.write("changed").await?;
+    assert_eq!(backend.read().await?, "changed");
+
+    // Cleaning up leaves the secondary alone
+    backend.cleanup().await?;
+    assert_eq!(primary.read().await?, "");
+    assert_eq!(secondary.read().await?, "first");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tee_secondary_failure_is_only_a_warning() -> Result<(), Box<dyn std::error::Error>> {
+    let mut primary = MemoryBackend::new();
+    let mut secondary = MemoryBackend::new();
+    let logger = Arc::new(CapturingLogger::default());
+    let mut backend = TeeBackend::new(primary.clone(), ReadOnlyBackend::new(secondary.clone()))
+        .with_logger(logger.clone());
+
+    backend.write("kept").await?;
+    assert_eq!(primary.read().await?, "kept");
+    assert_eq!(secondary.read().await?, "");
+
+    let lines = logger.lines.lock().unwrap().clone();
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0].0, LogLevel::Warn);
+    assert!(lines[0].1.starts_with("Secondary backend failed to write"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tee_primary_failure_fails_and_skips_the_secondary() {
+    let mut secondary = MemoryBackend::new();
+    let mut backend = TeeBackend::new(
+        ReadOnlyBackend::new(MemoryBackend::new()),
+        secondary.clone(),
+    );
+
+    assert!(backend.write("refused").await.is_err());
+    assert_eq!(secondary.read().await.unwrap(), "");
+}
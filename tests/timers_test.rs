@@ -0,0 +1,85 @@
+mod common;
+
+use astra::actor_system::{ActorSystem, TimerKind};
+use astra::clock::{Clock, MockClock};
+use common::Recorder;
+use std::error::Error;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+fn system_with_recorder(
+    clock: &MockClock,
+) -> (ActorSystem<String>, mpsc::UnboundedReceiver<String>) {
+    let (seen_tx, seen) = mpsc::unbounded_channel();
+    let mut system = ActorSystem::new().with_clock(Arc::new(clock.clone()));
+    system.add_actor("recorder".to_string(), Recorder { seen: seen_tx });
+    (system, seen)
+}
+
+// Let the timer tasks notice a clock change or a cancellation
+async fn settle() {
+    for _ in 0..10 {
+        tokio::task::yield_now().await;
+    }
+}
+
+#[tokio::test]
+async fn test_cancel_all_timers_stops_them() -> Result<(), Box<dyn Error>> {
+    let clock = MockClock::new();
+    let (system, mut seen) = system_with_recorder(&clock);
+    let start = clock.now();
+
+    let first = system.send_after("recorder", "once-1".to_string(), Duration::from_secs(10))?;
+    system.send_after("recorder", "once-2".to_string(), Duration::from_secs(20))?;
+    system.schedule_recurring("recorder", Duration::from_secs(5), || "tick".to_string())?;
+    assert!(system
+        .send_after("missing", "lost".to_string(), Duration::from_secs(1))
+        .is_err());
+
+    let timers = system.scheduled_timers();
+    assert_eq!(timers.len(), 3);
+    assert_eq!(timers[0].id, first.id());
+    assert_eq!(timers[0].actor, "recorder");
+    assert_eq!(timers[0].next_fire, start + Duration::from_secs(10));
+    assert_eq!(timers[2].kind, TimerKind::Recurring(Duration::from_secs(5)));
+
+    // The recurring timer fires and stays scheduled
+    clock.wait_for_sleepers(3).await;
+    clock.advance(Duration::from_secs(5));
+    assert_eq!(seen.recv().await.as_deref(), Some("tick"));
+    clock.wait_for_sleepers(3).await;
+    assert_eq!(
+        system.scheduled_timers()[2].next_fire,
+        start + Duration::from_secs(10)
+    );
+
+    system.cancel_all_timers();
+    assert!(system.scheduled_timers().is_empty());
+    assert!(first.is_cancelled());
+    settle().await;
+
+    // Long past every deadline, nothing else is delivered
+    clock.advance(Duration::from_secs(60));
+    settle().await;
+    assert!(seen.try_recv().is_err());
+    system.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_shutdown_cancels_timers() -> Result<(), Box<dyn Error>> {
+    let clock = MockClock::new();
+    let (system, mut seen) = system_with_recorder(&clock);
+    let timer =
+        system.schedule_recurring("recorder", Duration::from_secs(5), || "tick".to_string())?;
+    system.send_after("recorder", "later".to_string(), Duration::from_secs(5))?;
+
+    system.shutdown().await;
+    assert!(timer.is_cancelled());
+    assert!(system.scheduled_timers().is_empty());
+
+    clock.advance(Duration::from_secs(60));
+    assert!(seen.recv().await.is_none());
+    Ok(())
+}
@@ -0,0 +1,130 @@
+use astra::actor_system::{
+    Actor, ActorFactories, ActorOptions, ActorSystem, Message, SystemSnapshot,
+};
+use async_trait::async_trait;
+use std::error::Error;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Semaphore};
+
+// Forwards messages, optionally waiting for a permit first so messages pile up
+struct Forwarder {
+    gate: Option<Arc<Semaphore>>,
+    out: mpsc::UnboundedSender<String>,
+}
+
+#[async_trait]
+impl Actor for Forwarder {
+    type Message = String;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<Self::Message>) -> Result<(), Self::Error> {
+        if let Message::Regular(message) = message {
+            if let Some(gate) = &self.gate {
+                gate.acquire().await.unwrap().forget();
+            }
+            self.out.send(message).unwrap();
+        }
+        Ok(())
+    }
+}
+
+struct Sink;
+
+#[async_trait]
+impl Actor for Sink {
+    type Message = String;
+    type Error = String;
+
+    async fn receive(&mut self, _message: Message<Self::Message>) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_describe_lists_every_actor() -> Result<(), Box<dyn Error>> {
+    let (out_tx, mut out) = mpsc::unbounded_channel();
+    let gate = Arc::new(Semaphore::new(0));
+    let mut system = ActorSystem::new();
+    system.add_actor(
+        "ingest".to_string(),
+        Forwarder {
+            gate: Some(Arc::clone(&gate)),
+            out: out_tx.clone(),
+        },
+    );
+    system.add_actor_with_options(
+        "audit".to_string(),
+        Sink,
+        ActorOptions::new().with_mailbox_capacity(8),
+    );
+    system.add_actor(
+        "report".to_string(),
+        Forwarder {
+            gate: None,
+            out: out_tx,
+        },
+    );
+
+    // The first message blocks the actor on the gate, the other two wait behind it
+    for message in ["a", "b", "c"] {
+        system.send_message("ingest", message.to_string()).await?;
+    }
+    tokio::task::yield_now().await;
+
+    let snapshot = system.describe();
+    let names: Vec<_> = snapshot.actors.iter().map(|a| a.name.as_str()).collect();
+    assert_eq!(names, vec!["ingest", "audit", "report"]);
+    let ingest = snapshot.actor("ingest").unwrap();
+    assert!(ingest.actor_type.ends_with("Forwarder"));
+    assert!(ingest.mailbox_depth >= 2);
+    let audit = snapshot.actor("audit").unwrap();
+    assert!(audit.actor_type.ends_with("Sink"));
+    assert_eq!((audit.mailbox_depth, audit.mailbox_capacity), (0, 8));
+
+    // The snapshot survives a round trip through JSON
+    let json = serde_json::to_string(&snapshot)?;
+    assert_eq!(serde_json::from_str::<SystemSnapshot>(&json)?, snapshot);
+
+    gate.add_permits(3);
+    for expected in ["a", "b", "c"] {
+        assert_eq!(out.recv().await.as_deref(), Some(expected));
+    }
+    system.shutdown().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_restore_recreates_actors_with_factories() -> Result<(), Box<dyn Error>> {
+    let mut original = ActorSystem::new();
+    original.add_actor_with_options(
+        "audit".to_string(),
+        Sink,
+        ActorOptions::new().with_mailbox_capacity(8),
+    );
+    original.add_actor("report".to_string(), Sink);
+    let snapshot = original.describe();
+    original.shutdown().await;
+
+    let (out_tx, mut out) = mpsc::unbounded_channel();
+    let factories = ActorFactories::new().with_factory("report", move || Forwarder {
+        gate: None,
+        out: out_tx.clone(),
+    });
+    let mut restarted = ActorSystem::new();
+    // Only actors with a factory come back
+    assert_eq!(restarted.restore(&snapshot, &factories), vec!["report"]);
+    assert!(restarted.actor_ref("audit").is_none());
+
+    restarted
+        .send_message("report", "restored".to_string())
+        .await?;
+    assert_eq!(out.recv().await.as_deref(), Some("restored"));
+    let report = restarted.describe();
+    assert!(report
+        .actor("report")
+        .unwrap()
+        .actor_type
+        .ends_with("Forwarder"));
+    restarted.shutdown().await;
+    Ok(())
+}
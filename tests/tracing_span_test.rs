@@ -0,0 +1,114 @@
+#![cfg(feature = "tracing")]
+
+use astra::actor_system::{Actor, ActorSystem, Message};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+// The fields of a span or event, as text
+type Fields = HashMap<String, String>;
+// The fields of an event, and the id of the span it happened in
+type Logged = (Fields, Option<u64>);
+
+struct FieldsVisitor<'a>(&'a mut Fields);
+
+impl Visit for FieldsVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+}
+
+// Keeps the fields of every span, and for every event the span it happened in
+#[derive(Clone, Default)]
+struct Recorder {
+    next_id: Arc<AtomicU64>,
+    spans: Arc<Mutex<HashMap<u64, (String, Fields)>>>,
+    current: Arc<Mutex<Vec<u64>>>,
+    events: Arc<Mutex<Vec<Logged>>>,
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+        let mut fields = Fields::new();
+        span.record(&mut FieldsVisitor(&mut fields));
+        let name = span.metadata().name().to_string();
+        self.spans.lock().unwrap().insert(id, (name, fields));
+        Id::from_u64(id)
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields::new();
+        event.record(&mut FieldsVisitor(&mut fields));
+        let span = self.current.lock().unwrap().last().copied();
+        self.events.lock().unwrap().push((fields, span));
+    }
+
+    fn enter(&self, span: &Id) {
+        self.current.lock().unwrap().push(span.into_u64());
+    }
+
+    fn exit(&self, _: &Id) {
+        self.current.lock().unwrap().pop();
+    }
+}
+
+struct LoggingActor;
+
+#[async_trait]
+impl Actor for LoggingActor {
+    type Message = String;
+    type Error = String;
+
+    async fn receive(&mut self, message: Message<String>) -> Result<(), String> {
+        if let Message::Regular(msg) = message {
+            tracing::info!(handled = %msg);
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_receive_runs_inside_a_span_of_the_message() -> Result<(), String> {
+    let recorder = Recorder::default();
+    // The test runtime has one thread, so the actor's task sees the subscriber
+    let _guard = tracing::subscriber::set_default(recorder.clone());
+
+    let mut system = ActorSystem::new();
+    system.add_actor("logger".to_string(), LoggingActor);
+    system.send_message("logger", "first".to_string()).await?;
+    system.send_message("logger", "second".to_string()).await?;
+    system.wait_quiesced().await;
+
+    let spans = recorder.spans.lock().unwrap().clone();
+    let events = recorder.events.lock().unwrap().clone();
+    assert_eq!(events.len(), 2);
+    for (event, expected_id) in events.iter().zip(["1", "2"]) {
+        let (name, fields) = &spans[&event.1.expect("logged inside a span")];
+        assert_eq!(name, "message");
+        assert_eq!(fields["actor"], "logger");
+        assert_eq!(fields["message_id"], expected_id);
+    }
+    assert_eq!(events[0].0["handled"], "first");
+    assert_eq!(events[1].0["handled"], "second");
+    system.shutdown().await;
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use astra::backends::builder::BackendBuilder;
+use astra::backends::memory::MemoryBackend;
+use astra::backends::storage::StorageBackend;
+use astra::backends::transform::TransformBackend;
+use std::error::Error;
+use std::time::Duration;
+
+fn case_folding(inner: MemoryBackend) -> TransformBackend<MemoryBackend> {
+    TransformBackend::new(
+        inner,
+        |data: &str| data.to_uppercase(),
+        |data: String| data.to_lowercase(),
+    )
+}
+
+#[tokio::test]
+async fn test_transforms_apply_around_the_inner_backend() -> Result<(), Box<dyn Error>> {
+    let stored = MemoryBackend::new();
+    let mut backend = case_folding(stored.clone());
+
+    backend.write("Hello, World").await?;
+    assert_eq!(stored.clone().read().await?, "HELLO, WORLD");
+    assert_eq!(backend.read().await?, "hello, world");
+
+    // Byte operations go through the same transforms
+    backend.extend_bytes(b"!").await?;
+    assert_eq!(stored.clone().read().await?, "HELLO, WORLD!");
+    assert_eq!(backend.read_bytes().await?, b"hello, world!");
+    assert_eq!(backend.read_range(7, 5).await?, "world");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_compare_and_swap_sees_transformed_data() -> Result<(), Box<dyn Error>> {
+    let stored = MemoryBackend::new();
+    let mut backend = case_folding(stored.clone());
+    backend.write("v1").await?;
+
+    assert!(!backend.compare_and_swap("V1", "v2").await?);
+    assert!(backend.compare_and_swap("v1", "v2").await?);
+    assert_eq!(stored.clone().read().await?, "V2");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_composes_with_other_decorators() -> Result<(), Box<dyn Error>> {
+    let stored = MemoryBackend::new();
+    let cached = BackendBuilder::new(stored.clone())
+        .with_cache(Duration::from_secs(60))
+        .build();
+    let mut backend = TransformBackend::new(
+        cached,
+        |data: &str| data.to_uppercase(),
+        |data: String| data.to_lowercase(),
+    );
+
+    backend.write("Cached").await?;
+    assert_eq!(backend.read().await?, "cached");
+    assert_eq!(stored.clone().read().await?, "CACHED");
+    Ok(())
+}
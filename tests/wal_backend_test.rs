@@ -0,0 +1,167 @@
+use astra::backends::memory::MemoryBackend;
+use astra::backends::storage::StorageBackend;
+use astra::backends::wal::WalBackend;
+use async_trait::async_trait;
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::timeout;
+
+// Never finishes a write, like a process that dies in the middle of one
+#[derive(Debug, Clone)]
+struct Hanging(MemoryBackend);
+
+#[async_trait]
+impl StorageBackend for Hanging {
+    async fn write(&mut self, _data: &str) -> Result<(), Box<dyn Error>> {
+        std::future::pending().await
+    }
+
+    async fn read(&mut self) -> Result<String, Box<dyn Error>> {
+        self.0.read().await
+    }
+
+    async fn cleanup(&mut self) -> Result<(), Box<dyn Error>> {
+        self.0.cleanup().await
+    }
+}
+
+fn wal_path(name: &str) -> String {
+    let path = std::env::temp_dir().join(format!("astra_{}_{}.wal", std::process::id(), name));
+    path.to_str().unwrap().to_string()
+}
+
+// Start a write through a backend that never finishes it, then give up on it
+async fn crash_during<F>(data: MemoryBackend, path: &str, write: F) -> Result<(), Box<dyn Error>>
+where
+    F: for<'a> FnOnce(
+        &'a mut WalBackend<Hanging>,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<(), Box<dyn Error>>> + 'a>,
+    >,
+{
+    let mut crashing = WalBackend::open(Hanging(data), path).await?;
+    assert!(timeout(Duration::from_millis(50), write(&mut crashing))
+        .await
+        .is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_uncommitted_write_is_replayed_on_open() -> Result<(), Box<dyn Error>> {
+    let path = wal_path("replay");
+    let mut data = MemoryBackend::new();
+    data.write("v1").await?;
+
+    crash_during(data.clone(), &path, |wal| Box::pin(wal.write("v2"))).await?;
+    assert_eq!(data.read().await?, "v1");
+
+    // The next open completes the interrupted write
+    let mut reopened = WalBackend::open(data.clone(), &path).await?;
+    assert_eq!(reopened.replayed(), 1);
+    assert_eq!(reopened.read().await?, "v2");
+
+    // ...and only once
+    let reopened = WalBackend::open(data.clone(), &path).await?;
+    assert_eq!(reopened.replayed(), 0);
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_torn_append_is_redone_over_its_prior_data() -> Result<(), Box<dyn Error>> {
+    let path = wal_path("append");
+    let mut data = MemoryBackend::new();
+    data.write("1:a\n").await?;
+
+    crash_during(data.clone(), &path, |wal| {
+        Box::pin(wal.extend_bytes(b"2:bb\n"))
+    })
+    .await?;
+    // Half of the append made it out before the crash
+    data.write("1:a\n2:").await?;
+
+    let mut reopened = WalBackend::open(data.clone(), &path).await?;
+    assert_eq!(reopened.replayed(), 1);
+    assert_eq!(reopened.read().await?, "1:a\n2:bb\n");
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_committed_writes_are_compacted_away() -> Result<(), Box<dyn Error>> {
+    let path = wal_path("compact");
+    let data = MemoryBackend::new();
+    let mut backend = WalBackend::open(data.clone(), &path)
+        .await?
+        .with_compact_after(2);
+
+    backend.write("a").await?;
+    backend.write("b").await?;
+    // Two finished operations truncated the log
+    assert_eq!(std::fs::read_to_string(&path)?, "");
+    backend.write("c").await?;
+    assert_eq!(std::fs::read_to_string(&path)?.lines().count(), 2);
+
+    let mut reopened = WalBackend::open(data, &path).await?;
+    assert_eq!(reopened.replayed(), 0);
+    assert_eq!(reopened.read().await?, "c");
+    assert_eq!(std::fs::read_to_string(&path)?, "");
+
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+// Hangs on the first write only, then writes normally
+#[derive(Debug, Clone)]
+struct HangsOnce {
+    data: MemoryBackend,
+    hung: Arc<AtomicBool>,
+}
+
+#[async_trait]
+impl StorageBackend for HangsOnce {
+    async fn write(&mut self, data: &str) -> Result<(), Box<dyn Error>> {
+        if !self.hung.swap(true, Ordering::SeqCst) {
+            std::future::pending::<()>().await;
+        }
+        self.data.write(data).await
+    }
+
+    async fn read(&mut self) -> Result<String, Box<dyn Error>> {
+        self.data.read().await
+    }
+
+    async fn cleanup(&mut self) -> Result<(), Box<dyn Error>> {
+        self.data.cleanup().await
+    }
+}
+
+#[tokio::test]
+async fn test_abandoned_write_is_not_replayed_over_later_ones() -> Result<(), Box<dyn Error>> {
+    let path = wal_path("abandoned");
+    let data = MemoryBackend::new();
+    let mut backend = WalBackend::open(
+        HangsOnce {
+            data: data.clone(),
+            hung: Arc::new(AtomicBool::new(false)),
+        },
+        &path,
+    )
+    .await?;
+
+    // A write that is given up on, followed by one that completes
+    assert!(timeout(Duration::from_millis(50), backend.write("stale"))
+        .await
+        .is_err());
+    backend.write("new").await?;
+
+    let mut reopened = WalBackend::open(data, &path).await?;
+    assert_eq!(reopened.replayed(), 0);
+    assert_eq!(reopened.read().await?, "new");
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
@@ -0,0 +1,153 @@
+#![cfg(feature = "websocket")]
+
+use astra::clock::MockClock;
+use astra::network::http::CommunicationProtocol;
+use astra::network::tcp::{ConnectionState, ReconnectPolicy};
+use astra::network::ws::{IncomingMessage, WebSocketProtocol};
+use futures_util::{SinkExt, StreamExt};
+use std::error::Error;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_util::sync::CancellationToken;
+
+// Accepts WebSocket connections and pushes every text frame back with a prefix
+async fn start_echo_server() -> Result<(String, Arc<AtomicUsize>), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let address = format!("ws://{}/control", listener.local_addr()?);
+    let accepted = Arc::new(AtomicUsize::new(0));
+
+    let counter = Arc::clone(&accepted);
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            counter.fetch_add(1, Ordering::SeqCst);
+            tokio::spawn(async move {
+                let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+                while let Some(Ok(Message::Text(text))) = socket.next().await {
+                    let reply = Message::Text(format!("echo: {}", text));
+                    if socket.send(reply).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+
+    Ok((address, accepted))
+}
+
+#[tokio::test]
+async fn test_loopback_reuses_connection_and_receives_pushes() -> Result<(), Box<dyn Error>> {
+    let (address, accepted) = start_echo_server().await?;
+    let protocol = WebSocketProtocol::new();
+    let mut incoming = protocol.take_incoming().unwrap();
+    assert!(protocol.take_incoming().is_none());
+
+    for n in 0..3 {
+        protocol
+            .send_message(&address, &format!("ping {}", n))
+            .await?;
+    }
+
+    for n in 0..3 {
+        assert_eq!(
+            incoming.recv().await,
+            Some(IncomingMessage {
+                address: address.clone(),
+                message: format!("echo: ping {}", n),
+            })
+        );
+    }
+    assert_eq!(accepted.load(Ordering::SeqCst), 1);
+    assert_eq!(protocol.open_connections(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_oversized_message_is_refused() {
+    let protocol = WebSocketProtocol::new().with_max_message_size(4);
+    let result = protocol
+        .send_message("ws://127.0.0.1:1/control", "too long")
+        .await;
+    assert!(result.unwrap_err().contains("exceeding"));
+}
+
+#[tokio::test]
+async fn test_lost_address_reconnects_with_backoff() -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let host = listener.local_addr()?;
+    let address = format!("ws://{}/control", host);
+    let clock = MockClock::new();
+    let shutdown = CancellationToken::new();
+    let protocol = WebSocketProtocol::new()
+        .with_clock(Arc::new(clock.clone()))
+        .with_reconnect_policy(ReconnectPolicy::new(
+            Duration::from_millis(100),
+            Duration::from_secs(1),
+            2.0,
+        ))
+        .with_cancellation_token(&shutdown);
+
+    // Accept one connection and read one message, then take the whole server
+    // down
+    let server = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+        socket.next().await.unwrap().unwrap()
+    });
+    protocol.send_message(&address, "one").await?;
+    assert_eq!(server.await?, Message::Text("one".to_string()));
+    assert_eq!(
+        protocol.connection_state(&address),
+        Some(ConnectionState::Connected)
+    );
+    while protocol.open_connections() > 0 {
+        tokio::task::yield_now().await;
+    }
+
+    // Nothing listens any more
+    assert!(protocol.send_message(&address, "two").await.is_err());
+    let reconnecting = |attempt, millis| ConnectionState::Reconnecting {
+        attempt,
+        next_delay: Duration::from_millis(millis),
+    };
+    clock.wait_for_sleepers(1).await;
+    assert_eq!(
+        protocol.connection_state(&address),
+        Some(reconnecting(1, 100))
+    );
+    // Meanwhile sends fail fast
+    let err = protocol.send_message(&address, "three").await.unwrap_err();
+    assert!(err.contains("Reconnecting"), "{}", err);
+
+    // Each failed attempt doubles the delay before the next
+    for (attempt, millis) in [(2, 200), (3, 400)] {
+        clock.advance(Duration::from_secs(1));
+        clock.wait_for_sleepers(1).await;
+        assert_eq!(
+            protocol.connection_state(&address),
+            Some(reconnecting(attempt, millis))
+        );
+    }
+
+    // The server comes back and the next attempt reconnects
+    let listener = TcpListener::bind(host).await?;
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+        while socket.next().await.is_some() {}
+    });
+    clock.advance(Duration::from_secs(1));
+    while protocol.connection_state(&address) != Some(ConnectionState::Connected) {
+        tokio::task::yield_now().await;
+    }
+    assert_eq!(protocol.open_connections(), 1);
+    protocol.send_message(&address, "four").await?;
+
+    // The connection's task stops with the token
+    shutdown.cancel();
+    tokio::time::timeout(Duration::from_secs(5), protocol.wait_until_stopped()).await?;
+    Ok(())
+}